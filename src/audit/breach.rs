@@ -0,0 +1,330 @@
+use super::AuditError;
+use crate::cryptography::{HashAlgorithm, HashStruct};
+use crate::storage::Vault;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Length of hash prefix used to name range files.
+const PREFIX_LENGTH: usize = 5;
+const HASH_LENGTH: usize = 40;
+
+/// Local copy of Have I Been Pwned SHA-1 password hashes. Lines have format
+/// `HASH:COUNT` (full file) or `SUFFIX:COUNT` (range files).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreachDatabase {
+    /// Single file with all hashes, sorted by hash.
+    SortedFile(PathBuf),
+    /// Directory of range files named after first five characters of hash
+    /// (e.g. `5BAA6` or `5BAA6.txt`), as served by range API.
+    RangeDirectory(PathBuf),
+}
+
+/// Entry whose password was found in the breach database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreachedEntry {
+    pub path: String,
+    /// Number of times password appeared in known breaches.
+    pub occurrences: u64,
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> AuditError {
+    AuditError::IoError {
+        description: format!("Unable to read {}. Error = {}", path.display(), err),
+    }
+}
+
+/// Splits line into hash (or its suffix) and number of occurrences.
+fn parse_line(line: &str) -> Option<(&str, u64)> {
+    let (hash, count) = line.trim_end().split_once(':')?;
+    Some((hash, count.trim().parse().ok()?))
+}
+
+impl BreachDatabase {
+    /// Opens database at `path` - directories are treated as range files, regular
+    /// files as single sorted file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AuditError> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path).map_err(|err| io_error(path, err))?;
+
+        match metadata.is_dir() {
+            true => Ok(Self::RangeDirectory(path.into())),
+            false => Ok(Self::SortedFile(path.into())),
+        }
+    }
+
+    /// Returns number of times password with given SHA-1 hash (hexadecimal, any case)
+    /// appeared in breaches, 0 if it was not found.
+    pub fn occurrences(&self, hash: &str) -> Result<u64, AuditError> {
+        if hash.len() != HASH_LENGTH || !hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(AuditError::InvalidDatabase {
+                description: "Hash must consist of 40 hexadecimal digits.".into(),
+            });
+        }
+
+        let hash = Zeroizing::new(hash.to_ascii_uppercase());
+        match self {
+            Self::SortedFile(path) => search_sorted_file(path, &hash),
+            Self::RangeDirectory(directory) => search_range_file(directory, &hash),
+        }
+    }
+
+    /// Hashes `password` in memory and looks it up in the database.
+    pub fn password_occurrences(&self, password: &[u8]) -> Result<u64, AuditError> {
+        let mut hasher = HashStruct::new(HashAlgorithm::Sha1);
+        hasher.update(password);
+        let digest = Zeroizing::new(hasher.finalize());
+
+        let mut hash = Zeroizing::new(String::with_capacity(HASH_LENGTH));
+        for byte in digest.iter() {
+            hash.push_str(&format!("{:02X}", byte));
+        }
+
+        self.occurrences(&hash)
+    }
+}
+
+/// Returns first complete line starting at or after `offset`, together with its position.
+fn line_at(
+    reader: &mut BufReader<File>,
+    offset: u64,
+    line: &mut String,
+) -> std::io::Result<Option<u64>> {
+    let mut start = offset;
+    if offset > 0 {
+        reader.seek(SeekFrom::Start(offset - 1))?;
+        let mut skipped = Vec::new();
+        start += reader.read_until(b'\n', &mut skipped)? as u64 - 1;
+    } else {
+        reader.seek(SeekFrom::Start(0))?;
+    }
+
+    line.clear();
+    match reader.read_line(line)? {
+        0 => Ok(None),
+        _ => Ok(Some(start)),
+    }
+}
+
+/// Binary search over byte offsets of the sorted file - only a few dozen lines are
+/// read even for full dataset of hundreds of millions of hashes.
+fn search_sorted_file(path: &Path, hash: &str) -> Result<u64, AuditError> {
+    let file = File::open(path).map_err(|err| io_error(path, err))?;
+    let size = file.metadata().map_err(|err| io_error(path, err))?.len();
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+
+    let (mut low, mut high) = (0, size);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let start =
+            match line_at(&mut reader, middle, &mut line).map_err(|err| io_error(path, err))? {
+                Some(start) => start,
+                None => {
+                    high = middle;
+                    continue;
+                }
+            };
+
+        let (candidate, count) = parse_line(&line).ok_or_else(|| AuditError::InvalidDatabase {
+            description: format!("Line at byte {} of {} is malformed.", start, path.display()),
+        })?;
+
+        match candidate.to_ascii_uppercase().as_str().cmp(hash) {
+            std::cmp::Ordering::Equal => return Ok(count),
+            std::cmp::Ordering::Less => low = start + line.len() as u64,
+            std::cmp::Ordering::Greater => high = middle,
+        }
+    }
+
+    Ok(0)
+}
+
+fn search_range_file(directory: &Path, hash: &str) -> Result<u64, AuditError> {
+    let (prefix, suffix) = hash.split_at(PREFIX_LENGTH);
+
+    let candidates = [
+        directory.join(prefix),
+        directory.join(format!("{}.txt", prefix)),
+        directory.join(prefix.to_ascii_lowercase()),
+        directory.join(format!("{}.txt", prefix.to_ascii_lowercase())),
+    ];
+    let path = candidates
+        .iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| AuditError::InvalidDatabase {
+            description: format!(
+                "Range file {} is missing in {}.",
+                prefix,
+                directory.display()
+            ),
+        })?;
+
+    let reader = BufReader::new(File::open(path).map_err(|err| io_error(path, err))?);
+    for line in reader.lines() {
+        let line = line.map_err(|err| io_error(path, err))?;
+        if let Some((candidate, count)) = parse_line(&line) {
+            if candidate.eq_ignore_ascii_case(suffix) {
+                return Ok(count);
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+/// Checks password of every entry against the breach database. Passwords are hashed
+/// in memory only. Returns compromised entries, most frequently breached first.
+pub fn check_breaches(
+    vault: &Vault,
+    database: &BreachDatabase,
+) -> Result<Vec<BreachedEntry>, AuditError> {
+    let mut breached = Vec::new();
+
+    for (path, entry) in vault.entries() {
+        if entry.password().is_empty() {
+            continue;
+        }
+
+        let occurrences = database.password_occurrences(entry.password().as_ref())?;
+        if occurrences > 0 {
+            breached.push(BreachedEntry {
+                path: path.clone(),
+                occurrences,
+            });
+        }
+    }
+
+    breached.sort_by_key(|entry| std::cmp::Reverse(entry.occurrences));
+    Ok(breached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::{AuditOptions, Finding};
+    use crate::storage::{Entry, SafeBuffer};
+
+    const SORTED_HASHES: &'static str = "\
+        0000000A0E3B9F25FF41DE4B5AC238C2D545C7A8:15\n\
+        36CB589F74157B45811A690C58B434095F48E9FA:2\n\
+        5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\n\
+        7C4A8D09CA3762AF61E59520943DC26494F8941B:37359195\n\
+        B1B3773A05C0ED0176787A4F1574FF0075F7521E:3946737\n\
+        FFFFFFF8A0382AA9C8D9536EFBA77F261815334D:12\n";
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rustypass-{}-{}", name, std::process::id()))
+    }
+
+    fn vault() -> Vault {
+        let mut vault = Vault::new();
+        for (path, password) in [
+            ("mail", "password"),
+            ("bank", "123456"),
+            ("forum", "letmein"),
+            ("work", "k8#Tq!vR2m$Lw9zX"),
+            ("empty", ""),
+        ] {
+            let mut entry = Entry::new();
+            entry.set_password(SafeBuffer::from(String::from(password)));
+            vault.add_entry(path, entry).unwrap();
+        }
+        vault
+    }
+
+    #[test]
+    fn sorted_file_is_searched() {
+        let path = temporary_path("hibp-sorted");
+        std::fs::write(&path, SORTED_HASHES).unwrap();
+        let database = BreachDatabase::open(&path).unwrap();
+        assert_eq!(database, BreachDatabase::SortedFile(path.clone()));
+
+        for line in SORTED_HASHES.lines() {
+            let (hash, count) = parse_line(line).unwrap();
+            assert_eq!(database.occurrences(hash).unwrap(), count);
+        }
+        assert_eq!(
+            database
+                .occurrences("0000000000000000000000000000000000000000")
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            database
+                .occurrences("FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF")
+                .unwrap(),
+            0
+        );
+        assert!(database.occurrences("5BAA6").is_err());
+
+        let breached = check_breaches(&vault(), &database).unwrap();
+        assert_eq!(
+            breached,
+            [
+                BreachedEntry {
+                    path: "bank".into(),
+                    occurrences: 37359195
+                },
+                BreachedEntry {
+                    path: "mail".into(),
+                    occurrences: 9545824
+                },
+            ]
+        );
+
+        let report = vault()
+            .audit(&AuditOptions::default())
+            .with_breaches(breached);
+        assert_eq!(report.summary.breached, 2);
+        assert!(report.findings.contains(&Finding::BreachedPassword {
+            path: "mail".into(),
+            occurrences: 9545824
+        }));
+        assert_eq!(report.summary.healthy, 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn range_directory_is_searched() {
+        let directory = temporary_path("hibp-ranges");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("5BAA6"),
+            "1D2E3F4A5B6C7D8E9F0A1B2C3D4E5F6A7B8:1\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824\r\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("7c4a8.txt"),
+            "D09CA3762AF61E59520943DC26494F8941B:37359195\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("B7A87"),
+            "0000000000000000000000000000000000:3\n",
+        )
+        .unwrap();
+
+        let database = BreachDatabase::open(&directory).unwrap();
+        assert_eq!(database.password_occurrences(b"password").unwrap(), 9545824);
+
+        let mut vault = vault();
+        vault.remove_entry("work").unwrap();
+        let breached = check_breaches(&vault, &database).unwrap();
+        assert_eq!(breached.len(), 2);
+        assert_eq!(breached[0].path, "bank");
+
+        vault
+            .entry_mut("forum")
+            .unwrap()
+            .set_password(SafeBuffer::from(String::from("qwerty")));
+        assert!(matches!(
+            check_breaches(&vault, &database),
+            Err(AuditError::InvalidDatabase { .. })
+        ));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,459 @@
+use super::BreachedEntry;
+use crate::cryptography::KeyDerivationAlgorithm;
+use crate::get_translation;
+use crate::storage::{timestamp, Entry, Vault};
+use crate::strength::{self, Estimate, Score};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// Used when speed of attack cannot be derived from key derivation settings.
+const FALLBACK_GUESSES_PER_SECOND: f64 = 1e4;
+
+/// Settings of [`Vault::audit`].
+#[derive(Debug, Clone)]
+pub struct AuditOptions {
+    /// Passwords scored below this are reported as weak.
+    pub minimum_score: Score,
+    /// Passwords older than this many days are reported.
+    pub max_age_days: u64,
+    /// Speed of offline attack, see [`DynPasswordHasher::guesses_per_second`].
+    ///
+    /// [`DynPasswordHasher::guesses_per_second`]: crate::cryptography::DynPasswordHasher::guesses_per_second
+    pub guesses_per_second: f64,
+    /// Time (seconds since UNIX epoch) ages are computed against.
+    pub now: u64,
+}
+
+impl Default for AuditOptions {
+    /// Default settings assume vault protected by Argon2id with default options.
+    fn default() -> Self {
+        let guesses_per_second = KeyDerivationAlgorithm::Argon2id
+            .builder()
+            .build()
+            .map(|hasher| hasher.guesses_per_second())
+            .unwrap_or(FALLBACK_GUESSES_PER_SECOND);
+
+        Self {
+            minimum_score: Score::Strong,
+            max_age_days: 365,
+            guesses_per_second,
+            now: timestamp(),
+        }
+    }
+}
+
+/// Single problem found by the audit. Passwords themselves are never included.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    /// The same password is used by all listed entries.
+    ReusedPassword {
+        paths: Vec<String>,
+    },
+    WeakPassword {
+        path: String,
+        score: Score,
+        guesses_log10: f64,
+        warning: Option<&'static str>,
+    },
+    OldPassword {
+        path: String,
+        age_days: u64,
+    },
+    /// Entries share both address and username.
+    DuplicateCredentials {
+        url: String,
+        username: String,
+        paths: Vec<String>,
+    },
+    MissingPassword {
+        path: String,
+    },
+    /// Password was found in breach database, see [`check_breaches`].
+    ///
+    /// [`check_breaches`]: super::check_breaches
+    BreachedPassword {
+        path: String,
+        occurrences: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Finding {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::ReusedPassword { .. }
+            | Self::MissingPassword { .. }
+            | Self::BreachedPassword { .. } => Severity::High,
+            Self::WeakPassword { score, .. } if *score <= Score::Weak => Severity::High,
+            Self::WeakPassword { .. } | Self::DuplicateCredentials { .. } => Severity::Medium,
+            Self::OldPassword { .. } => Severity::Low,
+        }
+    }
+
+    /// Paths of all entries affected by the finding.
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            Self::ReusedPassword { paths } | Self::DuplicateCredentials { paths, .. } => {
+                paths.iter().map(String::as_str).collect()
+            }
+            Self::WeakPassword { path, .. }
+            | Self::OldPassword { path, .. }
+            | Self::MissingPassword { path }
+            | Self::BreachedPassword { path, .. } => vec![path.as_str()],
+        }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    /// Describes the problem without paths of affected entries, see [`Finding::paths`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReusedPassword { .. } => {
+                write!(f, "{}", get_translation!("AUDIT_REUSED_PASSWORD"))
+            }
+            Self::WeakPassword { score, warning, .. } => {
+                write!(f, "{} ({})", get_translation!("AUDIT_WEAK_PASSWORD"), score)?;
+                match warning {
+                    Some(warning) => write!(f, ": {}", warning),
+                    None => Ok(()),
+                }
+            }
+            Self::OldPassword { age_days, .. } => write!(
+                f,
+                "{} ({} {})",
+                get_translation!("AUDIT_OLD_PASSWORD"),
+                age_days,
+                get_translation!("AUDIT_DAYS")
+            ),
+            Self::DuplicateCredentials { url, username, .. } => write!(
+                f,
+                "{} ({}, {})",
+                get_translation!("AUDIT_DUPLICATE_CREDENTIALS"),
+                username,
+                url
+            ),
+            Self::MissingPassword { .. } => {
+                write!(f, "{}", get_translation!("AUDIT_MISSING_PASSWORD"))
+            }
+            Self::BreachedPassword { occurrences, .. } => write!(
+                f,
+                "{} ({} {})",
+                get_translation!("AUDIT_BREACHED_PASSWORD"),
+                occurrences,
+                get_translation!("AUDIT_TIMES")
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::Low => get_translation!("AUDIT_SEVERITY_LOW"),
+            Self::Medium => get_translation!("AUDIT_SEVERITY_MEDIUM"),
+            Self::High => get_translation!("AUDIT_SEVERITY_HIGH"),
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Number of findings of every kind - enough for a health dashboard.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditSummary {
+    pub entries: usize,
+    /// Entries without any finding.
+    pub healthy: usize,
+    pub reused: usize,
+    pub weak: usize,
+    pub old: usize,
+    pub duplicate: usize,
+    pub missing: usize,
+    pub breached: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub summary: AuditSummary,
+    /// Findings ordered from the most severe.
+    pub findings: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// Sorts findings and counts them for vault with `entries` entries.
+    fn new(entries: usize, mut findings: Vec<Finding>) -> Self {
+        let mut summary = AuditSummary {
+            entries,
+            ..AuditSummary::default()
+        };
+        let mut affected: Vec<&str> = Vec::new();
+        for finding in findings.iter() {
+            match finding {
+                Finding::ReusedPassword { .. } => summary.reused += 1,
+                Finding::WeakPassword { .. } => summary.weak += 1,
+                Finding::OldPassword { .. } => summary.old += 1,
+                Finding::DuplicateCredentials { .. } => summary.duplicate += 1,
+                Finding::MissingPassword { .. } => summary.missing += 1,
+                Finding::BreachedPassword { .. } => summary.breached += 1,
+            }
+            affected.extend(finding.paths());
+        }
+        affected.sort();
+        affected.dedup();
+        summary.healthy = summary.entries - affected.len();
+
+        findings.sort_by(|a, b| {
+            b.severity()
+                .cmp(&a.severity())
+                .then_with(|| a.paths().cmp(&b.paths()))
+        });
+
+        Self { summary, findings }
+    }
+
+    /// Adds entries found in breach database to the report.
+    pub fn with_breaches(self, breached: Vec<BreachedEntry>) -> Self {
+        let mut findings = self.findings;
+        findings.extend(breached.into_iter().map(|entry| Finding::BreachedPassword {
+            path: entry.path,
+            occurrences: entry.occurrences,
+        }));
+
+        Self::new(self.summary.entries, findings)
+    }
+}
+
+/// Reduces address to host and path, so `https://www.example.com/` and
+/// `example.com` are treated as the same site.
+fn normalize_url(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let url = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    let url = url.strip_prefix("www.").unwrap_or(url);
+
+    url.trim_end_matches('/').to_string()
+}
+
+/// Data attacker could guess from the entry itself.
+fn user_inputs(path: &str, entry: &Entry) -> Vec<String> {
+    let mut inputs = vec![path.to_string(), entry.username().to_string()];
+    inputs.extend(entry.urls().iter().map(|url| normalize_url(url)));
+    inputs
+}
+
+/// Estimates strength of password of the entry. Its path, user name and addresses count
+/// as data related to the user.
+pub fn estimate_password(path: &str, entry: &Entry, guesses_per_second: f64) -> Estimate {
+    let password = String::from_utf8_lossy(entry.password().as_ref());
+    let inputs = user_inputs(path, entry);
+    let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+    strength::estimate(&password, &inputs, guesses_per_second)
+}
+
+impl Vault {
+    /// Checks every entry for reused, weak, old, duplicate and missing credentials.
+    pub fn audit(&self, options: &AuditOptions) -> AuditReport {
+        let mut findings = Vec::new();
+        let mut entries = 0;
+        let mut reused: HashMap<&[u8], Vec<String>> = HashMap::new();
+        let mut duplicates: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+
+        for (path, entry) in self.entries() {
+            entries += 1;
+
+            for url in entry.urls() {
+                let key = (normalize_url(url), entry.username().to_lowercase());
+                let paths = duplicates.entry(key).or_default();
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+
+            if entry.password().is_empty() {
+                findings.push(Finding::MissingPassword { path: path.clone() });
+                continue;
+            }
+            reused
+                .entry(entry.password().as_ref())
+                .or_default()
+                .push(path.clone());
+
+            let age_days = options.now.saturating_sub(entry.password_changed()) / SECONDS_PER_DAY;
+            if age_days > options.max_age_days {
+                findings.push(Finding::OldPassword {
+                    path: path.clone(),
+                    age_days,
+                });
+            }
+
+            let estimate = estimate_password(path, entry, options.guesses_per_second);
+            if estimate.score() < options.minimum_score {
+                findings.push(Finding::WeakPassword {
+                    path: path.clone(),
+                    score: estimate.score(),
+                    guesses_log10: estimate.guesses_log10(),
+                    warning: estimate.feedback().warning,
+                });
+            }
+        }
+
+        findings.extend(
+            reused
+                .into_values()
+                .filter(|paths| paths.len() > 1)
+                .map(|mut paths| {
+                    paths.sort();
+                    Finding::ReusedPassword { paths }
+                }),
+        );
+        findings.extend(
+            duplicates
+                .into_iter()
+                .filter(|(_, paths)| paths.len() > 1)
+                .map(|((url, username), paths)| Finding::DuplicateCredentials {
+                    url,
+                    username,
+                    paths,
+                }),
+        );
+
+        AuditReport::new(entries, findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SafeBuffer;
+
+    fn entry(username: &str, password: &str, url: Option<&str>) -> Entry {
+        let mut entry = Entry::new();
+        entry.set_username(username);
+        entry.set_password(SafeBuffer::from(String::from(password)));
+        if let Some(url) = url {
+            entry.add_url(url);
+        }
+        entry
+    }
+
+    fn options() -> AuditOptions {
+        AuditOptions {
+            guesses_per_second: 1e4,
+            ..AuditOptions::default()
+        }
+    }
+
+    #[test]
+    fn problems_are_found() {
+        let mut vault = Vault::new();
+        let strong = "k8#Tq!vR2m$Lw9zX";
+        vault
+            .add_entry(
+                "mail",
+                entry("alice", strong, Some("https://mail.example.com")),
+            )
+            .unwrap();
+        vault
+            .add_entry(
+                "mail-old",
+                entry("Alice", "Gx7!pQ2#vN9@", Some("mail.example.com/")),
+            )
+            .unwrap();
+        vault
+            .add_entry("bank", entry("alice", strong, None))
+            .unwrap();
+        vault
+            .add_entry(
+                "forum",
+                entry("alice", "alice1990", Some("forum.example.org")),
+            )
+            .unwrap();
+        vault.add_entry("router", entry("admin", "", None)).unwrap();
+        vault
+            .add_entry("vpn", entry("alice", "W3-ultra!Random#Zz", None))
+            .unwrap();
+
+        let report = vault.audit(&options());
+
+        assert!(report.findings.contains(&Finding::ReusedPassword {
+            paths: vec!["bank".into(), "mail".into()]
+        }));
+        assert!(report.findings.contains(&Finding::DuplicateCredentials {
+            url: "mail.example.com".into(),
+            username: "alice".into(),
+            paths: vec!["mail".into(), "mail-old".into()]
+        }));
+        assert!(report.findings.contains(&Finding::MissingPassword {
+            path: "router".into()
+        }));
+        assert!(report.findings.iter().any(|finding| matches!(
+            finding,
+            Finding::WeakPassword { path, .. } if path == "forum"
+        )));
+        assert!(!report
+            .findings
+            .iter()
+            .any(|finding| matches!(finding, Finding::OldPassword { .. })));
+
+        assert_eq!(
+            report.summary,
+            AuditSummary {
+                entries: 6,
+                healthy: 1,
+                reused: 1,
+                weak: 1,
+                old: 0,
+                duplicate: 1,
+                missing: 1,
+                breached: 0,
+            }
+        );
+        assert_eq!(report.findings[0].severity(), Severity::High);
+        assert_eq!(report.findings.last().unwrap().severity(), Severity::Medium);
+    }
+
+    #[test]
+    fn old_passwords_are_reported() {
+        let mut vault = Vault::new();
+        vault
+            .add_entry("vpn", entry("alice", "W3-ultra!Random#Zz", None))
+            .unwrap();
+        let options = AuditOptions {
+            max_age_days: 90,
+            now: timestamp() + 100 * SECONDS_PER_DAY,
+            ..options()
+        };
+
+        let report = vault.audit(&options);
+        assert_eq!(
+            report.findings,
+            [Finding::OldPassword {
+                path: "vpn".into(),
+                age_days: 100
+            }]
+        );
+        assert_eq!(report.findings[0].severity(), Severity::Low);
+        assert_eq!(
+            report.findings[0].to_string(),
+            "Password was not changed for a long time (100 days)"
+        );
+    }
+
+    #[test]
+    fn report_serializes_to_json() {
+        let mut vault = Vault::new();
+        vault.add_entry("router", entry("admin", "", None)).unwrap();
+
+        let json = serde_json::to_value(vault.audit(&options())).unwrap();
+        assert_eq!(json["summary"]["missing"], 1);
+        assert_eq!(json["findings"][0]["kind"], "missing_password");
+        assert_eq!(json["findings"][0]["path"], "router");
+    }
+}
@@ -0,0 +1,13 @@
+mod breach;
+mod credentials;
+
+pub use breach::{check_breaches, BreachDatabase, BreachedEntry};
+pub use credentials::{
+    estimate_password, AuditOptions, AuditReport, AuditSummary, Finding, Severity,
+};
+
+#[derive(Debug)]
+pub enum AuditError {
+    IoError { description: String },
+    InvalidDatabase { description: String },
+}
@@ -0,0 +1,154 @@
+use super::output::{print_json, Format};
+use super::{CliError, Session};
+use crate::audit::{check_breaches, AuditOptions, AuditReport, BreachDatabase};
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+/// Formats findings as table with columns severity, entries and problem, followed by
+/// number of healthy entries.
+fn table(report: &AuditReport) -> Vec<String> {
+    let mut rows = vec![[
+        "SEVERITY".to_string(),
+        "ENTRIES".to_string(),
+        "PROBLEM".to_string(),
+    ]];
+    rows.extend(report.findings.iter().map(|finding| {
+        [
+            finding.severity().to_string(),
+            finding.paths().join(", "),
+            finding.to_string(),
+        ]
+    }));
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let (severity_width, entries_width) = (width(0), width(1));
+
+    let mut lines = Vec::new();
+    if !report.findings.is_empty() {
+        lines.extend(rows.iter().map(|[severity, entries, problem]| {
+            format!("{severity:<severity_width$}  {entries:<entries_width$}  {problem}")
+        }));
+        lines.push(String::new());
+    }
+    lines.push(format!(
+        "{} of {} entries have no problems",
+        report.summary.healthy, report.summary.entries
+    ));
+
+    lines
+}
+
+pub(super) fn audit(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let mut options = AuditOptions::default();
+    if let Some(days) = matches.get_one::<u64>("max-age") {
+        options.max_age_days = *days;
+    }
+
+    // Missing database is reported before the slow key derivation.
+    let database = matches
+        .get_one::<PathBuf>("hibp")
+        .map(BreachDatabase::open)
+        .transpose()?;
+
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault();
+    let mut report = vault.audit(&options);
+    if let Some(database) = &database {
+        report = report.with_breaches(check_breaches(vault, database)?);
+    }
+    match Format::of(matches) {
+        Format::Json => print_json(&report)?,
+        Format::Text => table(&report).iter().for_each(|line| println!("{line}")),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::BreachedEntry;
+    use crate::storage::{Entry, SafeBuffer, Vault};
+
+    fn entry(password: &str) -> Entry {
+        let mut entry = Entry::new();
+        entry.set_username("alice");
+        entry.set_password(SafeBuffer::from(password.to_string()));
+        entry
+    }
+
+    #[test]
+    fn findings_are_printed_as_table() {
+        let mut vault = Vault::new();
+        vault.add_entry("mail", entry("k8#Tq!vR2m$Lw9zX")).unwrap();
+        vault.add_entry("bank", entry("k8#Tq!vR2m$Lw9zX")).unwrap();
+        vault.add_entry("router", entry("")).unwrap();
+        vault.add_entry("vpn", entry("W3-ultra!Random#Zz")).unwrap();
+
+        let report = vault.audit(&AuditOptions::default());
+        assert_eq!(
+            table(&report),
+            [
+                "SEVERITY  ENTRIES     PROBLEM",
+                "high      bank, mail  Password is used by more than one entry",
+                "high      router      Entry has no password",
+                "",
+                "1 of 4 entries have no problems",
+            ]
+        );
+
+        let report = report.with_breaches(vec![BreachedEntry {
+            path: "vpn".into(),
+            occurrences: 3,
+        }]);
+        assert_eq!(
+            table(&report)[1..],
+            [
+                "high      bank, mail  Password is used by more than one entry",
+                "high      router      Entry has no password",
+                "high      vpn         Password appeared in known data breaches (3 times)",
+                "",
+                "0 of 4 entries have no problems",
+            ]
+        );
+
+        let healthy = Vault::new().audit(&AuditOptions::default());
+        assert_eq!(table(&healthy), ["0 of 0 entries have no problems"]);
+    }
+
+    #[test]
+    fn report_is_printed_as_json() {
+        let matches = super::super::command()
+            .try_get_matches_from([
+                "rustypass",
+                "--format",
+                "json",
+                "audit",
+                "-v",
+                "vault.rp",
+                "--max-age",
+                "90",
+                "--hibp",
+                "pwned-passwords",
+            ])
+            .unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        assert_eq!(Format::of(matches), Format::Json);
+        assert_eq!(matches.get_one::<u64>("max-age"), Some(&90));
+        assert_eq!(
+            matches.get_one::<PathBuf>("hibp"),
+            Some(&PathBuf::from("pwned-passwords"))
+        );
+
+        let mut vault = Vault::new();
+        vault.add_entry("router", entry("")).unwrap();
+        let json = serde_json::to_value(vault.audit(&AuditOptions::default())).unwrap();
+        assert_eq!(json["summary"]["entries"], 1);
+        assert_eq!(json["findings"][0]["kind"], "missing_password");
+    }
+}
@@ -0,0 +1,406 @@
+use super::output::{print_json, BulkChangeOutput, Format};
+use super::search::parse_query;
+use super::{CliError, Session};
+use crate::search::{matches_glob, Query};
+use crate::storage::{normalize_path, Vault};
+use clap::ArgMatches;
+
+/// Entry changed by a bulk command, `new_path` is set for moved entries.
+#[derive(Debug, PartialEq, Eq)]
+struct Change {
+    path: String,
+    new_path: Option<String>,
+}
+
+impl Change {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            new_path: None,
+        }
+    }
+}
+
+/// Paths of entries matching every selector given in arguments - the query, saved
+/// search and path globs, of which any one has to match. Free text is not matched
+/// fuzzily, it has to be found in the entry as it is.
+fn select(
+    vault: &Vault,
+    query: Option<&Query>,
+    matches: &ArgMatches,
+) -> Result<Vec<String>, CliError> {
+    let saved = matches
+        .get_one::<String>("saved")
+        .map(|name| vault.saved_search(name))
+        .transpose()?;
+    let globs: Vec<&String> = matches
+        .get_many::<String>("glob")
+        .unwrap_or_default()
+        .collect();
+
+    Ok(vault
+        .entries()
+        .filter(|(path, entry)| {
+            query.is_none_or(|query| query.matches_exactly(path, entry))
+                && saved
+                    .as_ref()
+                    .is_none_or(|saved| saved.matches_exactly(path, entry))
+                && (globs.is_empty() || globs.iter().any(|glob| matches_glob(glob, path)))
+        })
+        .map(|(path, _)| path.clone())
+        .collect())
+}
+
+/// Deepest group containing all `paths`, empty if they have none in common.
+fn common_group(paths: &[String]) -> String {
+    let mut common: Option<Vec<&str>> = None;
+    for path in paths {
+        let mut groups: Vec<&str> = path.split('/').collect();
+        groups.pop();
+        common = Some(match common {
+            Some(mut common) => {
+                let shared = common
+                    .iter()
+                    .zip(&groups)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                common.truncate(shared);
+                common
+            }
+            None => groups,
+        });
+    }
+
+    common.unwrap_or_default().join("/")
+}
+
+/// Moves entries into `group`, they keep their path below the deepest group shared by
+/// all of them. Entries are taken out first, so they may take each other's places.
+fn move_entries(vault: &mut Vault, paths: &[String], group: &str) -> Result<Vec<Change>, CliError> {
+    let common = common_group(paths);
+    let mut moved = Vec::new();
+    for path in paths {
+        let relative = match common.is_empty() {
+            true => path.as_str(),
+            false => &path[common.len() + 1..],
+        };
+        let new_path = normalize_path(&format!("{group}/{relative}"))?;
+        moved.push((path.clone(), new_path, vault.remove_entry(path)?));
+    }
+
+    let mut changes = Vec::new();
+    for (path, new_path, entry) in moved {
+        vault.add_entry(&new_path, entry)?;
+        if new_path != path {
+            changes.push(Change {
+                path,
+                new_path: Some(new_path),
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Adds and removes tags, only entries which did not have them already are changed.
+fn tag_entries(
+    vault: &mut Vault,
+    paths: &[String],
+    matches: &ArgMatches,
+) -> Result<Vec<Change>, CliError> {
+    let added: Vec<&String> = matches.get_many("add").unwrap_or_default().collect();
+    let removed: Vec<&String> = matches.get_many("remove").unwrap_or_default().collect();
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let entry = vault.entry_mut(path)?;
+        let mut changed = false;
+        for tag in &added {
+            if !entry.tags().contains(*tag) {
+                entry.add_tag(tag.as_str());
+                changed = true;
+            }
+        }
+        for tag in &removed {
+            changed |= entry.remove_tag(tag);
+        }
+        if changed {
+            changes.push(Change::new(path));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Sets fields given in arguments. Addresses are added only where missing and removed
+/// only where present. Only entries where some value differs are changed and reported.
+fn set_fields(
+    vault: &mut Vault,
+    paths: &[String],
+    matches: &ArgMatches,
+) -> Result<Vec<Change>, CliError> {
+    let profile = match matches.get_one::<String>("profile") {
+        Some(profile) => Some(Some(profile.as_str())),
+        None => matches.get_flag("no-profile").then_some(None),
+    };
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let entry = vault.entry_mut(path)?;
+        let mut changed = false;
+        if let Some(username) = matches.get_one::<String>("username") {
+            if entry.username() != username {
+                entry.set_username(username.as_str());
+                changed = true;
+            }
+        }
+        if let Some(notes) = matches.get_one::<String>("notes") {
+            if entry.notes() != notes {
+                entry.set_notes(notes.as_str());
+                changed = true;
+            }
+        }
+        for url in matches.get_many::<String>("url").unwrap_or_default() {
+            if !entry.urls().contains(url) {
+                entry.add_url(url.as_str());
+                changed = true;
+            }
+        }
+        for url in matches.get_many::<String>("remove-url").unwrap_or_default() {
+            changed |= entry.remove_url(url);
+        }
+
+        if let Some(profile) = profile {
+            if vault.entry(path)?.profile() != profile {
+                vault.attach_profile(path, profile)?;
+                changed = true;
+            }
+        }
+        if matches.get_flag("generate") {
+            vault.regenerate_password(path)?;
+            changed = true;
+        }
+        if changed {
+            changes.push(Change::new(path));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Changes all entries chosen by the arguments. Changes are made on a copy of the vault,
+/// which replaces it and is saved only after all of them succeed.
+pub(super) fn bulk(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let (action, matches) = matches.subcommand().expect("action is required");
+    // Query is checked before the slow key derivation.
+    let query = matches
+        .get_one::<String>("query")
+        .map(|text| parse_query(text))
+        .transpose()?;
+    let dry_run = matches.get_flag("dry-run");
+
+    let open_vault = session.vault(matches)?;
+    let paths = select(open_vault.vault(), query.as_ref(), matches)?;
+    let mut vault = open_vault.vault().clone();
+    let (changes, done) = match action {
+        "move" => {
+            let group = matches
+                .get_one::<String>("group")
+                .expect("group is required");
+            (move_entries(&mut vault, &paths, group)?, "moved")
+        }
+        "tag" => (tag_entries(&mut vault, &paths, matches)?, "tagged"),
+        "set" => (set_fields(&mut vault, &paths, matches)?, "changed"),
+        "rm" => {
+            for path in &paths {
+                vault.remove_entry(path)?;
+            }
+            (
+                paths.iter().map(|path| Change::new(path)).collect(),
+                "removed",
+            )
+        }
+        _ => unreachable!("bulk action `{action}` is not defined"),
+    };
+
+    match Format::of(matches) {
+        Format::Json => print_json(
+            &changes
+                .iter()
+                .map(|change| BulkChangeOutput {
+                    path: &change.path,
+                    new_path: change.new_path.as_deref(),
+                })
+                .collect::<Vec<_>>(),
+        )?,
+        Format::Text => changes.iter().for_each(|change| match &change.new_path {
+            Some(new_path) => println!("{} -> {new_path}", change.path),
+            None => println!("{}", change.path),
+        }),
+    }
+
+    if dry_run {
+        eprintln!(
+            "{} entries would be {done}, vault was not changed (dry run)",
+            changes.len()
+        );
+        return Ok(());
+    }
+    eprintln!("{} entries {done}", changes.len());
+    if !changes.is_empty() {
+        *open_vault.vault_mut() = vault;
+        open_vault.save()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Entry, VaultError};
+
+    fn vault() -> Vault {
+        let mut vault = Vault::new();
+        for path in [
+            "team-a/db/prod",
+            "team-a/web/prod",
+            "team-a/wiki",
+            "team-b/wiki",
+        ] {
+            vault.add_entry(path, Entry::new()).unwrap();
+        }
+        vault.entry_mut("team-a/wiki").unwrap().add_tag("docs");
+        vault.entry_mut("team-b/wiki").unwrap().add_tag("docs");
+
+        vault
+    }
+
+    fn arguments(action: &[&str]) -> ArgMatches {
+        let matches = super::super::command()
+            .try_get_matches_from(
+                ["rustypass", "bulk"]
+                    .iter()
+                    .chain(action)
+                    .chain(&["-v", "vault.rp"]),
+            )
+            .unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        matches.subcommand().unwrap().1.clone()
+    }
+
+    fn selected(vault: &Vault, action: &[&str]) -> Vec<String> {
+        let matches = arguments(action);
+        let query = matches
+            .get_one::<String>("query")
+            .map(|text| Query::parse(text).unwrap());
+        select(vault, query.as_ref(), &matches).unwrap()
+    }
+
+    #[test]
+    fn entries_are_selected_by_query_and_glob() {
+        let vault = vault();
+        assert_eq!(
+            selected(&vault, &["rm", "--glob", "team-a/*/prod"]),
+            ["team-a/db/prod", "team-a/web/prod"]
+        );
+        assert_eq!(
+            selected(&vault, &["rm", "--glob", "*/db/*", "--glob", "TEAM-B/*"]),
+            ["team-a/db/prod", "team-b/wiki"]
+        );
+        assert_eq!(
+            selected(&vault, &["rm", "-q", "tag:docs", "--glob", "team-a/*"]),
+            ["team-a/wiki"]
+        );
+        assert!(selected(&vault, &["rm", "--glob", "team-a"]).is_empty());
+        // Free text is not matched fuzzily, `tmwk` would select both wikis.
+        assert_eq!(
+            selected(&vault, &["rm", "-q", "wiki"]),
+            ["team-a/wiki", "team-b/wiki"]
+        );
+        assert!(selected(&vault, &["rm", "-q", "tmwk"]).is_empty());
+        assert_eq!(
+            selected(&vault, &["tag", "-q", "DB/PROD", "--add", "x"]),
+            ["team-a/db/prod"]
+        );
+        // Some selection is required.
+        assert!(super::super::command()
+            .try_get_matches_from(["rustypass", "bulk", "rm", "-v", "vault.rp"])
+            .is_err());
+    }
+
+    #[test]
+    fn moved_entries_keep_structure() {
+        assert_eq!(
+            common_group(&["team-a/db/prod".into(), "team-a/web/prod".into()]),
+            "team-a"
+        );
+        assert_eq!(common_group(&["team-a/wiki".into()]), "team-a");
+        assert_eq!(common_group(&["dns".into(), "team-a/wiki".into()]), "");
+        assert_eq!(common_group(&[]), "");
+
+        let mut vault = vault();
+        let paths = ["team-a/db/prod".to_string(), "team-a/web/prod".to_string()];
+        let changes = move_entries(&mut vault, &paths, "/ops/").unwrap();
+        assert_eq!(changes[0].new_path.as_deref(), Some("ops/db/prod"));
+        let paths: Vec<&String> = vault.entries().map(|(path, _)| path).collect();
+        assert_eq!(
+            paths,
+            ["ops/db/prod", "ops/web/prod", "team-a/wiki", "team-b/wiki"]
+        );
+
+        // Entries already in place are not reported.
+        let paths = ["ops/db/prod".to_string(), "ops/web/prod".to_string()];
+        assert!(move_entries(&mut vault, &paths, "ops").unwrap().is_empty());
+        assert!(vault.entry("ops/db/prod").is_ok());
+
+        let paths = ["team-a/wiki".to_string()];
+        assert!(matches!(
+            move_entries(&mut vault, &paths, "team-b"),
+            Err(CliError::VaultError(VaultError::EntryExists { .. }))
+        ));
+    }
+
+    #[test]
+    fn only_changed_entries_are_tagged() {
+        let mut vault = vault();
+        let paths = ["team-a/wiki".to_string(), "team-a/db/prod".to_string()];
+        let matches = arguments(&["tag", "--glob", "*", "--add", "docs", "--remove", "old"]);
+        assert_eq!(
+            tag_entries(&mut vault, &paths, &matches).unwrap(),
+            [Change::new("team-a/db/prod")]
+        );
+        assert!(vault
+            .entry("team-a/db/prod")
+            .unwrap()
+            .tags()
+            .contains("docs"));
+
+        let matches = arguments(&["set", "--glob", "*", "-u", "deploy", "--url", "a.example"]);
+        assert_eq!(set_fields(&mut vault, &paths, &matches).unwrap().len(), 2);
+        assert!(set_fields(&mut vault, &paths, &matches).unwrap().is_empty());
+        let entry = vault.entry("team-a/wiki").unwrap();
+        assert_eq!(entry.username(), "deploy");
+        assert_eq!(entry.urls(), ["a.example"]);
+
+        vault
+            .entry_mut("team-a/wiki")
+            .unwrap()
+            .set_username("admin");
+        let matches = arguments(&[
+            "set",
+            "--glob",
+            "*",
+            "-u",
+            "deploy",
+            "--remove-url",
+            "b.example",
+        ]);
+        assert_eq!(
+            set_fields(&mut vault, &paths, &matches).unwrap(),
+            [Change::new("team-a/wiki")]
+        );
+        let matches = arguments(&["set", "--glob", "*", "--remove-url", "a.example"]);
+        assert_eq!(set_fields(&mut vault, &paths, &matches).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,135 @@
+use super::reference::{self, Field};
+use super::CliError;
+use crate::clipboard::{clear_if_unchanged, fingerprint, Clipboard, CommandClipboard};
+use crate::configuration::ProgramConfiguration;
+use crate::storage::Vault;
+use clap::ArgMatches;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Copies field of the entry to the clipboard, returns [`fingerprint`] of the value.
+fn copy_field(
+    clipboard: &mut dyn Clipboard,
+    vault: &mut Vault,
+    path: &str,
+    field_name: &str,
+) -> Result<Vec<u8>, CliError> {
+    let field = Field::parse(field_name).ok_or_else(|| CliError::InvalidArgument {
+        description: format!(
+            "field `{field_name}` cannot be copied, use {}",
+            Field::NAMES
+        ),
+    })?;
+    let value = reference::resolve(vault, path, field)?;
+    clipboard.set(value.as_bytes())?;
+
+    Ok(fingerprint(value.as_bytes()))
+}
+
+/// Copies field of the entry to the clipboard. Unless disabled in configuration, helper
+/// process is started which clears the clipboard later, if it still holds the value.
+pub(super) fn copy(
+    vault: &mut Vault,
+    path: &str,
+    field_name: &str,
+    configuration: &ProgramConfiguration,
+) -> Result<(), CliError> {
+    let copied = copy_field(&mut CommandClipboard::detect()?, vault, path, field_name)?;
+
+    match clear_after_timeout(&copied, configuration)? {
+        Some(timeout) => {
+            eprintln!(
+                "Copied {field_name} of {path}, clipboard will be cleared in {} s",
+                timeout.as_secs()
+            );
+        }
+        None => eprintln!("Copied {field_name} of {path}"),
+    }
+
+    Ok(())
+}
+
+/// Copies the value to the clipboard, it is cleared later like in [`copy`].
+/// Returns time after which it is cleared.
+pub(crate) fn copy_value(
+    value: &[u8],
+    configuration: &ProgramConfiguration,
+) -> Result<Option<Duration>, CliError> {
+    CommandClipboard::detect()?.set(value)?;
+
+    clear_after_timeout(&fingerprint(value), configuration)
+}
+
+/// Starts [`clear_later`] unless clearing is disabled in configuration.
+fn clear_after_timeout(
+    copied: &[u8],
+    configuration: &ProgramConfiguration,
+) -> Result<Option<Duration>, CliError> {
+    let timeout = configuration.get_clipboard_timeout();
+    if let Some(timeout) = timeout {
+        clear_later(copied, timeout)?;
+    }
+
+    Ok(timeout)
+}
+
+/// Starts `clear-clipboard` command in background. Only fingerprint of the copied value
+/// is passed to it, through its standard input.
+fn clear_later(copied: &[u8], timeout: Duration) -> Result<(), CliError> {
+    let mut helper = Command::new(std::env::current_exe()?)
+        .args(["clear-clipboard", "--after", &timeout.as_secs().to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    helper
+        .stdin
+        .take()
+        .expect("standard input is piped")
+        .write_all(copied)?;
+
+    Ok(())
+}
+
+/// Hidden command started by [`clear_later`].
+pub(super) fn clear(matches: &ArgMatches) -> Result<(), CliError> {
+    let after = matches.get_one::<u64>("after").expect("after is required");
+    let mut copied = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut copied)?;
+
+    std::thread::sleep(Duration::from_secs(*after));
+    clear_if_unchanged(&mut CommandClipboard::detect()?, &copied)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::MemoryClipboard;
+    use crate::storage::{Entry, SafeBuffer};
+
+    #[test]
+    fn fields_are_copied() {
+        let mut vault = Vault::new();
+        let mut entry = Entry::new();
+        entry.set_username("alice");
+        entry.set_password(SafeBuffer::from("hunter2".to_string()));
+        vault.add_entry("mail", entry).unwrap();
+        let mut clipboard = MemoryClipboard::default();
+
+        let copied = copy_field(&mut clipboard, &mut vault, "mail", "username").unwrap();
+        assert_eq!(clipboard.get().unwrap().as_slice(), b"alice");
+        assert_eq!(copied, fingerprint(b"alice"));
+        assert!(vault.entry("mail").unwrap().last_used().is_none());
+
+        // Use is recorded by the command, only if enabled in configuration.
+        copy_field(&mut clipboard, &mut vault, "mail", "password").unwrap();
+        assert_eq!(clipboard.get().unwrap().as_slice(), b"hunter2");
+        assert!(vault.entry("mail").unwrap().last_used().is_none());
+
+        assert!(copy_field(&mut clipboard, &mut vault, "mail", "tags").is_err());
+        assert_eq!(clipboard.get().unwrap().as_slice(), b"hunter2");
+    }
+}
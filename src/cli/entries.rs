@@ -0,0 +1,317 @@
+use super::output::{print_json, EntryOutput, FieldOutput, Format, OtpCodeOutput};
+use super::reference::{self, Field};
+use super::{clipboard, prompt_new_password, CliError, Session};
+use crate::audit::{estimate_password, AuditOptions};
+use crate::otp::OtpKind;
+use crate::storage::{Entry, OpenVault, Vault};
+use clap::ArgMatches;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+fn path(matches: &ArgMatches) -> &str {
+    matches.get_one::<String>("path").expect("path is required")
+}
+
+/// Formats time as `YYYY-MM-DD` (UTC).
+fn format_date(timestamp: u64) -> String {
+    // Civil from days algorithm, years are counted from March so leap day is the last one.
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = (month_index + 2) % 12 + 1;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Sets fields given by `username`, `url`, `tag` and `notes` arguments.
+fn apply_fields(entry: &mut Entry, matches: &ArgMatches) {
+    if let Some(username) = matches.get_one::<String>("username") {
+        entry.set_username(username.as_str());
+    }
+    for url in matches.get_many::<String>("url").unwrap_or_default() {
+        entry.add_url(url.as_str());
+    }
+    for tag in matches.get_many::<String>("tag").unwrap_or_default() {
+        entry.add_tag(tag.as_str());
+    }
+    if let Some(notes) = matches.get_one::<String>("notes") {
+        entry.set_notes(notes.as_str());
+    }
+}
+
+/// Prints strength of typed password of the entry to standard error, with hints how
+/// to improve it, and warns when it does not follow password profile of the entry.
+pub(super) fn report_strength(vault: &Vault, path: &str) -> Result<(), CliError> {
+    let entry = vault.entry(path)?;
+    let estimate = estimate_password(path, entry, AuditOptions::default().guesses_per_second);
+    eprintln!("Strength of password of {path}: {}", estimate.score());
+    let feedback = estimate.feedback();
+    for line in feedback.warning.iter().chain(feedback.suggestions.iter()) {
+        eprintln!("{line}");
+    }
+
+    if let Some(name) = entry.profile() {
+        let password = entry.password().as_str().unwrap_or_default();
+        if let Err(error) = vault.profile(name)?.validate(password) {
+            eprintln!(
+                "warning: password does not follow profile `{name}`: {}",
+                CliError::from(error)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces password of the entry with generated one or asks for it.
+fn set_password(vault: &mut Vault, path: &str, generate: bool) -> Result<(), CliError> {
+    match generate {
+        true => {
+            vault.regenerate_password(path)?;
+        }
+        false => {
+            let password = prompt_new_password(&format!("Password of {path}: "))?;
+            vault.entry_mut(path)?.set_password(password);
+            report_strength(vault, path)?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn add(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let path = path(matches);
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault_mut();
+
+    let mut entry = Entry::new();
+    apply_fields(&mut entry, matches);
+    vault.add_entry(path, entry)?;
+    if let Some(profile) = matches.get_one::<String>("profile") {
+        vault.attach_profile(path, Some(profile))?;
+    }
+    set_password(vault, path, matches.get_flag("generate"))?;
+
+    Ok(open_vault.save()?)
+}
+
+fn print_entry(path: &str, entry: &Entry) {
+    println!("path: {path}");
+    println!("username: {}", entry.username());
+    for url in entry.urls() {
+        println!("url: {url}");
+    }
+    if !entry.tags().is_empty() {
+        let tags: Vec<&str> = entry.tags().iter().map(String::as_str).collect();
+        println!("tags: {}", tags.join(", "));
+    }
+    if let Some(profile) = entry.profile() {
+        println!("profile: {profile}");
+    }
+    if let Some(otp) = entry.otp() {
+        match otp.kind() {
+            OtpKind::Totp { period } => println!("otp: TOTP, {period} s"),
+            OtpKind::Hotp { counter } => println!("otp: HOTP, counter {counter}"),
+        }
+    }
+    println!("created: {}", format_date(entry.created()));
+    println!("modified: {}", format_date(entry.modified()));
+    println!(
+        "password changed: {}",
+        format_date(entry.password_changed())
+    );
+    if let Some(last_used) = entry.last_used() {
+        println!("used: {}", format_date(last_used));
+    }
+    if !entry.notes().is_empty() {
+        println!("notes:\n{}", entry.notes());
+    }
+}
+
+/// Prints requested field or copies it to clipboard. Reading password or one-time code
+/// counts as use of the entry.
+pub(super) fn get(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let path = path(matches);
+    let field = matches
+        .get_one::<String>("field")
+        .expect("field has default value");
+    let configuration = session.configuration().read().unwrap();
+    let open_vault = session.vault(matches)?;
+    if matches.get_flag("clip") {
+        clipboard::copy(open_vault.vault_mut(), path, field, &configuration)?;
+    } else if Format::of(matches) == Format::Json {
+        get_json(open_vault, path, field)?;
+    } else {
+        get_text(open_vault, path, field)?;
+    }
+
+    match Field::parse(field) {
+        Some(field) => reference::record_use(open_vault, [(path, field)], &configuration),
+        None => Ok(()),
+    }
+}
+
+fn get_text(open_vault: &mut OpenVault, path: &str, field: &str) -> Result<(), CliError> {
+    let vault = open_vault.vault_mut();
+    let entry = vault.entry(path)?;
+
+    match field {
+        "password" => println!("{}", String::from_utf8_lossy(entry.password().as_ref())),
+        "otp" => println!("{}", vault.otp_code(path)?.code),
+        "username" => println!("{}", entry.username()),
+        "url" => entry.urls().iter().for_each(|url| println!("{url}")),
+        "tags" => entry.tags().iter().for_each(|tag| println!("{tag}")),
+        "notes" => println!("{}", entry.notes()),
+        _ => print_entry(path, entry),
+    }
+
+    Ok(())
+}
+
+/// Prints requested field as JSON, see [`super::output::JSON_SCHEMA`].
+fn get_json(open_vault: &mut OpenVault, path: &str, field: &str) -> Result<(), CliError> {
+    let vault = open_vault.vault_mut();
+    let entry = vault.entry(path)?;
+    let name = field;
+
+    match field {
+        "password" => print_json(&FieldOutput {
+            path,
+            name,
+            value: String::from_utf8_lossy(entry.password().as_ref()),
+        })?,
+        "otp" => {
+            let code = vault.otp_code(path)?;
+            print_json(&OtpCodeOutput {
+                path,
+                otp: &code.code,
+                valid_for: code.valid_for,
+            })?;
+        }
+        "username" => print_json(&FieldOutput {
+            path,
+            name,
+            value: entry.username(),
+        })?,
+        "url" => print_json(&FieldOutput {
+            path,
+            name,
+            value: entry.urls(),
+        })?,
+        "tags" => print_json(&FieldOutput {
+            path,
+            name,
+            value: entry.tags(),
+        })?,
+        "notes" => print_json(&FieldOutput {
+            path,
+            name,
+            value: entry.notes(),
+        })?,
+        _ => print_json(&EntryOutput::new(path, entry))?,
+    }
+
+    Ok(())
+}
+
+pub(super) fn edit(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let path = path(matches);
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault_mut();
+
+    let entry = vault.entry_mut(path)?;
+    apply_fields(entry, matches);
+    for url in matches.get_many::<String>("remove-url").unwrap_or_default() {
+        if !entry.remove_url(url) {
+            return Err(CliError::InvalidArgument {
+                description: format!("entry `{path}` has no address `{url}`"),
+            });
+        }
+    }
+    for tag in matches.get_many::<String>("remove-tag").unwrap_or_default() {
+        if !entry.remove_tag(tag) {
+            return Err(CliError::InvalidArgument {
+                description: format!("entry `{path}` has no tag `{tag}`"),
+            });
+        }
+    }
+
+    if let Some(profile) = matches.get_one::<String>("profile") {
+        vault.attach_profile(path, Some(profile))?;
+    } else if matches.get_flag("no-profile") {
+        vault.attach_profile(path, None)?;
+    }
+    if matches.get_flag("password") || matches.get_flag("generate") {
+        set_password(vault, path, matches.get_flag("generate"))?;
+    }
+
+    Ok(open_vault.save()?)
+}
+
+pub(super) fn remove(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let open_vault = session.vault(matches)?;
+    open_vault.vault_mut().remove_entry(path(matches))?;
+
+    Ok(open_vault.save()?)
+}
+
+pub(super) fn list(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let prefix = matches
+        .get_one::<String>("group")
+        .map(|group| format!("{}/", group.trim_matches('/')));
+    let open_vault = session.vault(matches)?;
+    let entries = open_vault.vault().entries().filter(|(path, _)| {
+        prefix
+            .as_ref()
+            .is_none_or(|prefix| path.starts_with(prefix))
+    });
+
+    match Format::of(matches) {
+        Format::Json => print_json(
+            &entries
+                .map(|(path, entry)| EntryOutput::new(path, entry))
+                .collect::<Vec<_>>(),
+        )?,
+        Format::Text => entries.for_each(|(path, _)| println!("{path}")),
+    }
+
+    Ok(())
+}
+
+pub(super) fn move_entry(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let from = matches.get_one::<String>("from").expect("from is required");
+    let to = matches.get_one::<String>("to").expect("to is required");
+    let to = match to.ends_with('/') {
+        true => format!(
+            "{to}{}",
+            from.trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or(from)
+        ),
+        false => to.clone(),
+    };
+
+    let open_vault = session.vault(matches)?;
+    open_vault.vault_mut().move_entry(from, &to)?;
+
+    Ok(open_vault.save()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_are_formatted() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_868_800), "2000-03-01");
+        assert_eq!(format_date(1_709_164_800 + 3600), "2024-02-29");
+        assert_eq!(format_date(1_735_689_599), "2024-12-31");
+    }
+}
@@ -0,0 +1,396 @@
+use super::reference::{self, Field};
+use super::{CliError, Session};
+use crate::storage::Vault;
+use clap::ArgMatches;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use zeroize::Zeroizing;
+
+/// Written in place of secret values when output of the command is masked.
+const MASK: &[u8] = b"*****";
+
+/// Value of environment variable taken from the vault.
+struct Variable {
+    name: String,
+    value: Zeroizing<String>,
+    secret: bool,
+}
+
+/// Environment variable filled from an entry, written as `NAME=PATH[:FIELD]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mapping {
+    variable: String,
+    path: String,
+    field: Field,
+}
+
+impl Mapping {
+    fn parse(text: &str) -> Result<Self, CliError> {
+        let invalid = |reason: &str| CliError::InvalidArgument {
+            description: format!("invalid mapping `{text}`, {reason}"),
+        };
+
+        let (variable, reference) = text
+            .split_once('=')
+            .ok_or_else(|| invalid("it should have form NAME=PATH[:FIELD]"))?;
+        let variable = variable.trim();
+        if variable.is_empty() || variable.contains(|c: char| c.is_whitespace() || c == '\0') {
+            return Err(invalid("name of the variable is not valid"));
+        }
+
+        // Paths may contain `:` too, so only known field names are split off.
+        let reference = reference.trim();
+        let (path, field) = reference
+            .rsplit_once(':')
+            .and_then(|(path, field)| Some((path, Field::parse(field)?)))
+            .unwrap_or((reference, Field::Password));
+        if path.is_empty() {
+            return Err(invalid("path of the entry is missing"));
+        }
+
+        Ok(Self {
+            variable: variable.to_string(),
+            path: path.to_string(),
+            field,
+        })
+    }
+}
+
+/// Reads mappings from the file, one per line. Empty lines and lines starting with `#` are skipped.
+fn read_mapping_file(path: &Path) -> Result<Vec<Mapping>, CliError> {
+    std::fs::read_to_string(path)
+        .map_err(|error| CliError::IoError {
+            description: format!("cannot read {}: {error}", path.display()),
+        })?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Mapping::parse)
+        .collect()
+}
+
+/// Returns values of mapped variables. Using password or one-time code counts as use of the entry.
+fn resolve(vault: &mut Vault, mappings: &[Mapping]) -> Result<Vec<Variable>, CliError> {
+    mappings
+        .iter()
+        .map(|mapping| {
+            Ok(Variable {
+                name: mapping.variable.clone(),
+                value: reference::resolve(vault, &mapping.path, mapping.field)?,
+                secret: mapping.field.is_secret(),
+            })
+        })
+        .collect()
+}
+
+/// Replaces secrets in a stream of bytes with [`MASK`]. Bytes which may be the beginning
+/// of a secret are held back until the rest of the stream shows whether it follows.
+struct Masker {
+    secrets: Vec<Zeroizing<Vec<u8>>>,
+    pending: Zeroizing<Vec<u8>>,
+}
+
+impl Masker {
+    fn new<'a>(secrets: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut secrets: Vec<Zeroizing<Vec<u8>>> = secrets
+            .into_iter()
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| Zeroizing::new(secret.to_vec()))
+            .collect();
+        // Longer secrets first, so secret containing another one is masked whole.
+        secrets.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        secrets.dedup();
+
+        Self {
+            secrets,
+            pending: Zeroizing::new(Vec::new()),
+        }
+    }
+
+    /// Returns part of the stream which can be written after `data` arrived.
+    fn feed(&mut self, data: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(data);
+        let mut output = Vec::with_capacity(self.pending.len());
+        let mut position = 0;
+
+        while position < self.pending.len() {
+            let rest = &self.pending[position..];
+            if self
+                .secrets
+                .iter()
+                .any(|secret| secret.len() > rest.len() && secret.starts_with(rest))
+            {
+                break;
+            }
+            match self.secrets.iter().find(|secret| rest.starts_with(secret)) {
+                Some(secret) => {
+                    output.extend_from_slice(MASK);
+                    position += secret.len();
+                }
+                None => {
+                    output.push(rest[0]);
+                    position += 1;
+                }
+            }
+        }
+        self.pending.drain(..position);
+
+        output
+    }
+
+    /// Returns bytes held back at the end of the stream, they are not a whole secret.
+    fn finish(&mut self) -> Vec<u8> {
+        std::mem::take(&mut *self.pending)
+    }
+}
+
+/// Copies output of the command, masking secrets in it.
+async fn copy_masked(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    mut masker: Masker,
+) -> std::io::Result<()> {
+    let mut buffer = [0; 4096];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&masker.feed(&buffer[..read])).await?;
+        writer.flush().await?;
+    }
+    writer.write_all(&masker.finish()).await?;
+    writer.flush().await
+}
+
+/// Waits for the command, passing signals sent to this process on to it. Interrupt and quit
+/// are not passed on, terminal sends them to the command directly and it would get them twice.
+#[cfg(unix)]
+async fn wait(child: &mut Child) -> std::io::Result<ExitStatus> {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut user_defined1 = signal(SignalKind::user_defined1())?;
+    let mut user_defined2 = signal(SignalKind::user_defined2())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut quit = signal(SignalKind::quit())?;
+
+    loop {
+        let forwarded = tokio::select! {
+            status = child.wait() => return status,
+            _ = terminate.recv() => Signal::SIGTERM,
+            _ = hangup.recv() => Signal::SIGHUP,
+            _ = user_defined1.recv() => Signal::SIGUSR1,
+            _ = user_defined2.recv() => Signal::SIGUSR2,
+            _ = interrupt.recv() => continue,
+            _ = quit.recv() => continue,
+        };
+        if let Some(id) = child.id() {
+            // Command may have just finished, its status is collected in the next iteration.
+            let _ = kill(Pid::from_raw(id as i32), forwarded);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait(child: &mut Child) -> std::io::Result<ExitStatus> {
+    child.wait().await
+}
+
+/// Returns exit status of the command, shell convention 128 + signal is used if it was killed.
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    status.code().unwrap_or(super::EXIT_FAILURE)
+}
+
+/// Runs the command, when `mask` is set its output is copied to `stdout` and `stderr`
+/// with secrets masked.
+async fn supervise(
+    command: &[String],
+    variables: &[Variable],
+    mask: bool,
+    stdout: impl AsyncWrite + Unpin + Send + 'static,
+    stderr: impl AsyncWrite + Unpin + Send + 'static,
+) -> Result<i32, CliError> {
+    let mut child = Command::new(&command[0]);
+    child.args(&command[1..]).envs(
+        variables
+            .iter()
+            .map(|variable| (&variable.name, variable.value.as_str())),
+    );
+    if mask {
+        child.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut child = child.spawn().map_err(|error| CliError::IoError {
+        description: format!("cannot run `{}`: {error}", command[0]),
+    })?;
+
+    let masker = || {
+        Masker::new(
+            variables
+                .iter()
+                .filter(|variable| variable.secret)
+                .map(|variable| variable.value.as_bytes()),
+        )
+    };
+    let stdout = child
+        .stdout
+        .take()
+        .map(|output| tokio::spawn(copy_masked(output, stdout, masker())));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|output| tokio::spawn(copy_masked(output, stderr, masker())));
+
+    let status = wait(&mut child).await?;
+    for copy in stdout.into_iter().chain(stderr) {
+        copy.await.map_err(|error| CliError::IoError {
+            description: error.to_string(),
+        })??;
+    }
+
+    Ok(exit_code(status))
+}
+
+/// Runs the command with entries of the vault in its environment, returns its exit status.
+pub(super) fn exec(matches: &ArgMatches, session: &mut Session) -> Result<i32, CliError> {
+    let mut mappings = Vec::new();
+    if let Some(path) = matches.get_one::<std::path::PathBuf>("env-file") {
+        mappings.extend(read_mapping_file(path)?);
+    }
+    for mapping in matches.get_many::<String>("env").unwrap_or_default() {
+        mappings.push(Mapping::parse(mapping)?);
+    }
+    let command: Vec<String> = matches
+        .get_many::<String>("command")
+        .expect("command is required")
+        .cloned()
+        .collect();
+
+    let configuration = session.configuration();
+    let open_vault = session.vault(matches)?;
+    let variables = resolve(open_vault.vault_mut(), &mappings)?;
+    reference::record_use(
+        open_vault,
+        mappings
+            .iter()
+            .map(|mapping| (mapping.path.as_str(), mapping.field)),
+        &configuration.read().unwrap(),
+    )?;
+    // Vault is closed before the command starts, only the mapped values are kept.
+    session.release();
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(supervise(
+            &command,
+            &variables,
+            matches.get_flag("mask"),
+            tokio::io::stdout(),
+            tokio::io::stderr(),
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mappings_are_parsed() {
+        assert_eq!(
+            Mapping::parse("DATABASE_PASSWORD=db/prod").unwrap(),
+            Mapping {
+                variable: "DATABASE_PASSWORD".to_string(),
+                path: "db/prod".to_string(),
+                field: Field::Password,
+            }
+        );
+        let mapping = Mapping::parse(" DB_USER = db/prod:username ").unwrap();
+        assert_eq!(mapping.variable, "DB_USER");
+        assert_eq!(mapping.path, "db/prod");
+        assert_eq!(mapping.field, Field::Username);
+
+        // Unknown field is part of the path.
+        let mapping = Mapping::parse("HOST=hosts/db:5432").unwrap();
+        assert_eq!(mapping.path, "hosts/db:5432");
+        assert_eq!(mapping.field, Field::Password);
+
+        assert!(Mapping::parse("db/prod").is_err());
+        assert!(Mapping::parse("=db/prod").is_err());
+        assert!(Mapping::parse("MY VAR=db/prod").is_err());
+        assert!(Mapping::parse("TOKEN=:otp").is_err());
+    }
+
+    #[test]
+    fn secrets_are_masked_across_chunks() {
+        let mut masker = Masker::new([&b"hunter2"[..], b"hunter", b""]);
+        let mut output = masker.feed(b"password is hun");
+        assert_eq!(output, b"password is ");
+        output.extend(masker.feed(b"ter2, ok"));
+        output.extend(masker.feed(b" hunte"));
+        output.extend(masker.feed(b"r!"));
+        output.extend(masker.feed(b" hun"));
+        output.extend(masker.finish());
+        assert_eq!(output, b"password is *****, ok *****! hun");
+
+        let mut masker = Masker::new([&b"aab"[..]]);
+        assert_eq!(masker.feed(b"aaab"), b"a*****");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exit_status_of_command_is_returned() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let command = |script: &str| ["sh".to_string(), "-c".to_string(), script.to_string()];
+        let variables = [
+            Variable {
+                name: "SECRET".to_string(),
+                value: Zeroizing::new("hunter2".to_string()),
+                secret: true,
+            },
+            Variable {
+                name: "NOTES".to_string(),
+                value: Zeroizing::new("e".to_string()),
+                secret: Field::Notes.is_secret(),
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("rustypass-exec-{}", std::process::id()));
+        let output = || tokio::fs::File::from_std(std::fs::File::create(&path).unwrap());
+
+        let code = runtime.block_on(supervise(
+            &command("test \"$SECRET\" = hunter2 && echo secret $SECRET && exit 7"),
+            &variables,
+            true,
+            output(),
+            tokio::io::sink(),
+        ));
+        assert_eq!(code.unwrap(), 7);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "secret *****\n");
+        std::fs::remove_file(&path).unwrap();
+
+        let code = runtime.block_on(supervise(
+            &command("kill -TERM $$"),
+            &[],
+            false,
+            tokio::io::sink(),
+            tokio::io::sink(),
+        ));
+        assert_eq!(code.unwrap(), 128 + 15);
+    }
+}
@@ -0,0 +1,172 @@
+use super::reference::{self, Field};
+use super::{clipboard, CliError, Session};
+use crate::clipboard::{has_session, is_installed, WAYLAND_SESSION, X11_SESSION};
+use clap::ArgMatches;
+use std::cmp::Reverse;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Pickers tried when none is configured, the first installed one is used. Graphical
+/// ones need a graphical session, `fzf` works in terminal.
+const PICKERS: &[(bool, &str)] = &[
+    (true, "rofi -dmenu -i -p rustypass"),
+    (true, "wofi --dmenu -i -p rustypass"),
+    (true, "dmenu -i -p rustypass"),
+    (false, "fzf --prompt=rustypass>"),
+];
+
+/// Programs typing text read from standard input into the focused window.
+const WAYLAND_TYPER: &[&str] = &["wtype", "-"];
+const X11_TYPER: &[&str] = &["xdotool", "type", "--clearmodifiers", "--file", "-"];
+
+fn detect_picker() -> Result<String, CliError> {
+    let graphical = has_session(WAYLAND_SESSION) || has_session(X11_SESSION);
+    PICKERS
+        .iter()
+        .filter(|(needs_session, _)| graphical || !needs_session)
+        .map(|(_, picker)| *picker)
+        .find(|picker| is_installed(picker.split(' ').next().unwrap_or_default()))
+        .map(String::from)
+        .ok_or_else(|| CliError::InvalidArgument {
+            description: "no picker found, install rofi, wofi, dmenu or fzf, or use --picker"
+                .to_string(),
+        })
+}
+
+fn start(arguments: &[&str], stdout: Stdio) -> Result<std::process::Child, CliError> {
+    Command::new(arguments[0])
+        .args(&arguments[1..])
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .spawn()
+        .map_err(|error| CliError::IoError {
+            description: format!("cannot start {}: {error}", arguments[0]),
+        })
+}
+
+/// Writes `input` to standard input of the child. Programs may exit before reading
+/// all of it, e.g. when the choice is made early.
+fn feed(child: &mut std::process::Child, input: &[u8]) -> Result<(), CliError> {
+    let mut stdin = child.stdin.take().expect("standard input is piped");
+    match stdin.write_all(input) {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => Err(error.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Lets the user choose one of `paths` with the picker, given as a command line which
+/// reads choices from standard input and prints the chosen one. Returns `None` if the
+/// picker was closed without a choice.
+fn pick(picker: &str, paths: &[&String]) -> Result<Option<String>, CliError> {
+    let arguments = shlex::split(picker)
+        .filter(|arguments| !arguments.is_empty())
+        .ok_or_else(|| CliError::InvalidArgument {
+            description: format!("picker `{picker}` is not a valid command line"),
+        })?;
+    let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    let mut child = start(&arguments, Stdio::piped())?;
+    let input: String = paths.iter().map(|path| format!("{path}\n")).collect();
+    feed(&mut child, input.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let chosen = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    Ok((!chosen.is_empty()).then_some(chosen))
+}
+
+/// Types text into the focused window.
+fn type_text(text: &str) -> Result<(), CliError> {
+    let typer = [
+        (has_session(WAYLAND_SESSION), WAYLAND_TYPER),
+        (has_session(X11_SESSION), X11_TYPER),
+    ]
+    .into_iter()
+    .find(|(available, typer)| *available && is_installed(typer[0]))
+    .map(|(_, typer)| typer)
+    .ok_or_else(|| CliError::InvalidArgument {
+        description: "no program to type text found, install wtype (Wayland) or xdotool (X11)"
+            .to_string(),
+    })?;
+
+    let mut child = start(typer, Stdio::null())?;
+    feed(&mut child, text.as_bytes())?;
+    match child.wait()? {
+        status if status.success() => Ok(()),
+        status => Err(CliError::IoError {
+            description: format!("{} failed: {status}", typer[0]),
+        }),
+    }
+}
+
+/// Lets the user choose entry with a picker, then copies or types its field.
+pub(super) fn menu(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let configuration = session.configuration();
+    let picker = match matches.get_one::<String>("picker") {
+        Some(picker) => picker.clone(),
+        None => match configuration.read().unwrap().get_picker() {
+            Some(picker) => picker.to_string(),
+            None => detect_picker()?,
+        },
+    };
+    let field_name = matches
+        .get_one::<String>("field")
+        .expect("field has default value");
+    let open_vault = session.vault(matches)?;
+
+    // Recently used entries are offered first.
+    let mut entries: Vec<_> = open_vault.vault().entries().collect();
+    entries.sort_by_key(|(path, entry)| (Reverse(entry.last_used()), *path));
+    let paths: Vec<&String> = entries.into_iter().map(|(path, _)| path).collect();
+    let path = pick(&picker, &paths)?.ok_or(CliError::Cancelled)?;
+
+    let configuration = configuration.read().unwrap();
+    let field = Field::parse(field_name).expect("field is validated by clap");
+    match matches.get_flag("type") {
+        true => {
+            let value = reference::resolve(open_vault.vault_mut(), &path, field)?;
+            type_text(&value)?;
+        }
+        false => clipboard::copy(open_vault.vault_mut(), &path, field_name, &configuration)?,
+    }
+
+    reference::record_use(open_vault, [(path.as_str(), field)], &configuration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn entry_is_chosen_with_picker() {
+        let paths = ["db/prod".to_string(), "mail/work".to_string()];
+        let paths: Vec<&String> = paths.iter().collect();
+
+        assert_eq!(
+            pick("sh -c 'grep mail'", &paths).unwrap().as_deref(),
+            Some("mail/work")
+        );
+        assert_eq!(
+            pick("head -n 1", &paths).unwrap().as_deref(),
+            Some("db/prod")
+        );
+        // Closed picker exits with failure, like dmenu and rofi after Escape.
+        assert_eq!(
+            pick("sh -c 'cat >/dev/null; exit 1'", &paths).unwrap(),
+            None
+        );
+        assert_eq!(pick("true", &paths).unwrap(), None);
+
+        assert!(pick("rustypass-missing-picker", &paths).is_err());
+        assert!(pick("'unclosed", &paths).is_err());
+        assert!(pick("", &paths).is_err());
+    }
+}
@@ -0,0 +1,1327 @@
+mod audit;
+mod bulk;
+mod clipboard;
+mod entries;
+mod exec;
+mod menu;
+mod otp;
+mod output;
+mod pass;
+mod profile;
+mod reference;
+mod search;
+mod shell;
+mod template;
+mod vault;
+
+pub(crate) use clipboard::copy_value;
+
+use crate::audit::AuditError;
+use crate::clipboard::ClipboardError;
+use crate::configuration::ProgramConfiguration;
+use crate::generator::GeneratorError;
+use crate::get_translation;
+use crate::search::QueryError;
+use crate::storage::{OpenVault, SafeBuffer, StorageError, VaultError};
+use clap::{value_parser, Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use std::ffi::OsString;
+use std::io::{BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Exit status for errors without more specific code.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit status for invalid arguments, including queries. Clap uses it for usage errors too.
+pub const EXIT_USAGE: i32 = 2;
+/// Exit status when vault, entry, profile or saved search does not exist.
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_WRONG_PASSWORD: i32 = 4;
+pub const EXIT_CORRUPTED: i32 = 5;
+
+/// Exit statuses shown in `--help`, scripts may rely on them.
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  success
+  1  other error
+  2  invalid arguments or query
+  3  vault, entry, password profile, saved search or entry referenced by template
+     not found
+  4  wrong master password
+  5  vault is corrupted";
+
+#[derive(Debug)]
+pub enum CliError {
+    /// Vault file does not exist.
+    VaultNotFound {
+        path: PathBuf,
+    },
+    StorageError(StorageError),
+    VaultError(VaultError),
+    GeneratorError(GeneratorError),
+    ClipboardError(ClipboardError),
+    QueryError {
+        query: String,
+        error: QueryError,
+    },
+    /// Repeated password differs from the first one.
+    PasswordMismatch,
+    /// User did not confirm overwriting or removing an entry.
+    Cancelled,
+    InvalidArgument {
+        description: String,
+    },
+    /// Syntax errors in template, one per line.
+    TemplateError {
+        description: String,
+    },
+    /// References in template which cannot be resolved, one per line.
+    UnresolvedReference {
+        description: String,
+    },
+    IoError {
+        description: String,
+    },
+}
+
+impl CliError {
+    /// Returns exit status of the program for this error, see [`EXIT_STATUS_HELP`].
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::VaultNotFound { .. }
+            | Self::VaultError(
+                VaultError::EntryNotFound { .. }
+                | VaultError::ProfileNotFound { .. }
+                | VaultError::SavedSearchNotFound { .. }
+                | VaultError::OtpNotConfigured { .. },
+            )
+            | Self::UnresolvedReference { .. } => EXIT_NOT_FOUND,
+            Self::StorageError(StorageError::WrongPassword) => EXIT_WRONG_PASSWORD,
+            Self::StorageError(StorageError::CorruptedVault { .. }) => EXIT_CORRUPTED,
+            Self::QueryError { .. }
+            | Self::VaultError(VaultError::QueryError(_) | VaultError::InvalidPath { .. })
+            | Self::InvalidArgument { .. }
+            | Self::TemplateError { .. } => EXIT_USAGE,
+            _ => EXIT_FAILURE,
+        }
+    }
+}
+
+impl From<StorageError> for CliError {
+    fn from(error: StorageError) -> Self {
+        Self::StorageError(error)
+    }
+}
+
+impl From<VaultError> for CliError {
+    fn from(error: VaultError) -> Self {
+        Self::VaultError(error)
+    }
+}
+
+impl From<GeneratorError> for CliError {
+    fn from(error: GeneratorError) -> Self {
+        Self::GeneratorError(error)
+    }
+}
+
+impl From<ClipboardError> for CliError {
+    fn from(error: ClipboardError) -> Self {
+        Self::ClipboardError(error)
+    }
+}
+
+impl From<AuditError> for CliError {
+    fn from(error: AuditError) -> Self {
+        match error {
+            AuditError::IoError { description } => Self::IoError { description },
+            AuditError::InvalidDatabase { description } => Self::IoError {
+                description: format!("breach database is invalid: {description}"),
+            },
+        }
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IoError {
+            description: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::VaultNotFound { path } => write!(f, "vault {} not found", path.display()),
+            // Query is repeated with the error position marked below it.
+            Self::QueryError { query, error } => write!(
+                f,
+                "{query}\n{:>width$}\n{error}",
+                "^",
+                width = error.position() + 1
+            ),
+            Self::StorageError(StorageError::WrongPassword) => write!(f, "wrong master password"),
+            Self::StorageError(StorageError::CorruptedVault { description }) => {
+                write!(f, "vault is corrupted: {description}")
+            }
+            Self::StorageError(StorageError::WeakPassword {
+                score,
+                minimum,
+                feedback,
+            }) => {
+                write!(
+                    f,
+                    "master password is too weak ({score}, at least {minimum} is required)"
+                )?;
+                for line in feedback.warning.iter().chain(feedback.suggestions.iter()) {
+                    write!(f, "\n{line}")?;
+                }
+                Ok(())
+            }
+            Self::StorageError(StorageError::IoError { description }) => {
+                write!(f, "{description}")
+            }
+            Self::StorageError(StorageError::UnsupportedFormat { version }) => write!(
+                f,
+                "vault was saved by newer version of the program (format {version})"
+            ),
+            Self::StorageError(StorageError::UnsupportedHash { algorithm }) => {
+                write!(f, "{algorithm} cannot protect integrity of the vault")
+            }
+            Self::StorageError(StorageError::KeyDerivationError(error)) => write!(f, "{error}"),
+            Self::StorageError(StorageError::EncryptionError(error)) => write!(f, "{error}"),
+            Self::StorageError(StorageError::SerializationError { description }) => {
+                write!(f, "vault cannot be serialized: {description}")
+            }
+            Self::VaultError(VaultError::InvalidPath { path }) => {
+                write!(f, "invalid entry path `{path}`")
+            }
+            Self::VaultError(VaultError::EntryNotFound { path }) => {
+                write!(f, "entry `{path}` not found")
+            }
+            Self::VaultError(VaultError::EntryExists { path }) => {
+                write!(f, "entry `{path}` already exists")
+            }
+            Self::VaultError(VaultError::ProfileNotFound { name }) => {
+                write!(f, "password profile `{name}` not found")
+            }
+            Self::VaultError(VaultError::OtpNotConfigured { path }) => {
+                write!(f, "entry `{path}` has no one-time password")
+            }
+            Self::VaultError(VaultError::SavedSearchNotFound { name }) => {
+                write!(f, "saved search `{name}` not found")
+            }
+            Self::VaultError(VaultError::QueryError(error)) => write!(f, "{error}"),
+            Self::VaultError(VaultError::GeneratorError(error)) | Self::GeneratorError(error) => {
+                match error {
+                    GeneratorError::InvalidLength { description }
+                    | GeneratorError::EmptyCharacterSet { description }
+                    | GeneratorError::UnsatisfiableRequirements { description }
+                    | GeneratorError::InvalidTemplate { description }
+                    | GeneratorError::PolicyViolation { description } => {
+                        write!(f, "{description}")
+                    }
+                }
+            }
+            Self::ClipboardError(error) => write!(f, "{error}"),
+            Self::PasswordMismatch => write!(f, "passwords do not match"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::InvalidArgument { description }
+            | Self::TemplateError { description }
+            | Self::UnresolvedReference { description }
+            | Self::IoError { description } => {
+                write!(f, "{description}")
+            }
+        }
+    }
+}
+
+fn vault_argument() -> Arg {
+    Arg::new("vault")
+        .short('v')
+        .long("vault")
+        .required(true)
+        .value_parser(value_parser!(PathBuf))
+        .help("Vault file")
+}
+
+fn path_argument() -> Arg {
+    Arg::new("path")
+        .required(true)
+        .help("Path of the entry, groups are separated with `/`")
+}
+
+fn query_argument() -> Arg {
+    Arg::new("query")
+        .num_args(1..)
+        .allow_hyphen_values(true)
+        .help("Free text and conditions like tag:prod, -tag:old or used:<30d")
+}
+
+fn profile_argument() -> Arg {
+    Arg::new("profile")
+        .long("profile")
+        .help("Password profile saved in the vault")
+}
+
+fn generate_argument() -> Arg {
+    Arg::new("generate")
+        .short('g')
+        .long("generate")
+        .action(ArgAction::SetTrue)
+        .help("Generate password following profile of the entry")
+}
+
+/// Arguments setting content of the entry, shared by `add` and `edit`.
+fn entry_arguments(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("username")
+                .short('u')
+                .long("username")
+                .help("User name"),
+        )
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .action(ArgAction::Append)
+                .help("Address of the service, may be repeated"),
+        )
+        .arg(
+            Arg::new("tag")
+                .short('t')
+                .long("tag")
+                .action(ArgAction::Append)
+                .help("Tag, may be repeated"),
+        )
+        .arg(Arg::new("notes").long("notes").help("Notes"))
+}
+
+/// Adds arguments choosing entries changed by `bulk` commands.
+fn bulk_arguments(command: Command) -> Command {
+    command
+        .arg(vault_argument())
+        .arg(
+            Arg::new("query")
+                .short('q')
+                .long("query")
+                .allow_hyphen_values(true)
+                .help("Change entries matching the query, e.g. 'tag:team-a -tag:old', free text has to match exactly"),
+        )
+        .arg(
+            Arg::new("glob")
+                .long("glob")
+                .action(ArgAction::Append)
+                .help("Change entries with path matching glob like 'team-a/*', may be repeated"),
+        )
+        .arg(
+            Arg::new("saved")
+                .short('s')
+                .long("saved")
+                .help("Change entries of the saved search"),
+        )
+        .group(
+            ArgGroup::new("selection")
+                .args(["query", "glob", "saved"])
+                .required(true)
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .short('n')
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .help("Only list entries which would be changed, the vault is not saved"),
+        )
+}
+
+pub fn command() -> Command {
+    Command::new("rustypass")
+        .about("Password manager")
+        .long_about(
+            "Password manager. Without a command the graphical interface is started.\n\n\
+             Master password is taken from --password-fd, --password-file or \
+             --password-env if given, otherwise it is asked for in the terminal. When \
+             standard input is not a terminal, it is read from its first line. Passwords \
+             of entries are read from the following lines.",
+        )
+        .after_long_help(format!("{}\n\n{EXIT_STATUS_HELP}", output::JSON_SCHEMA))
+        .arg(
+            Arg::new("gui")
+                .long("gui")
+                .action(ArgAction::SetTrue)
+                .help("Start the graphical interface"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .global(true)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Output format of commands which print data"),
+        )
+        .arg(
+            Arg::new("password-fd")
+                .long("password-fd")
+                .global(true)
+                .value_name("FD")
+                .value_parser(value_parser!(u32))
+                .conflicts_with_all(["password-file", "password-env"])
+                .help("Read master password from the first line of open file descriptor"),
+        )
+        .arg(
+            Arg::new("password-file")
+                .long("password-file")
+                .global(true)
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("password-env")
+                .help("Read master password from the first line of the file"),
+        )
+        .arg(
+            Arg::new("password-env")
+                .long("password-env")
+                .global(true)
+                .value_name("VARIABLE")
+                .help("Read master password from the environment variable"),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Creates new vault with recommended settings")
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("option")
+                        .short('o')
+                        .long("option")
+                        .action(ArgAction::Append)
+                        .value_name("NAME=VALUE")
+                        .help("Key derivation option, e.g. 'memory size=262144'"),
+                )
+                .arg(
+                    Arg::new("allow-weak")
+                        .long("allow-weak")
+                        .action(ArgAction::SetTrue)
+                        .help("Accept master password weaker than recommended"),
+                ),
+        )
+        .subcommand(
+            Command::new("open")
+                .visible_alias("unlock")
+                .about("Checks master password and shows state of the vault")
+                .arg(vault_argument()),
+        )
+        .subcommand(
+            Command::new("upgrade")
+                .about("Re-encrypts vault with recommended settings, fixing its health issues")
+                .arg(vault_argument()),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Finds reused, weak, old, duplicate and missing passwords of entries")
+                .long_about(
+                    "Finds passwords used by more than one entry, weak passwords, passwords \
+                     not changed for --max-age days, entries with the same address and user \
+                     name and entries without password. Findings are printed as a table, \
+                     the most severe first.\n\n\
+                     With --hibp passwords are also looked up in a local copy of Have I Been \
+                     Pwned SHA-1 hashes - a file of HASH:COUNT lines sorted by hash, or \
+                     a directory of range files named after first five characters of hash. \
+                     Passwords are hashed in memory only, nothing is sent over network.",
+                )
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("hibp")
+                        .long("hibp")
+                        .value_name("PATH")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("Report passwords found in Have I Been Pwned file or directory"),
+                )
+                .arg(
+                    Arg::new("max-age")
+                        .long("max-age")
+                        .value_name("DAYS")
+                        .value_parser(value_parser!(u64))
+                        .help("Report passwords older than this many days, 365 by default"),
+                ),
+        )
+        .subcommand(
+            Command::new("rekey")
+                .about("Re-encrypts vault with new salt, cipher, hash and key derivation settings")
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("encryption")
+                        .long("encryption")
+                        .value_name("ALGORITHM")
+                        .help("Encryption algorithm, e.g. ChaCha20Poly1305, current one is kept by default"),
+                )
+                .arg(
+                    Arg::new("hash")
+                        .long("hash")
+                        .value_name("ALGORITHM")
+                        .help("Hash algorithm protecting integrity, e.g. Sha512, current one is kept by default"),
+                )
+                .arg(
+                    Arg::new("key-derivation")
+                        .long("key-derivation")
+                        .value_name("ALGORITHM")
+                        .help("Key derivation algorithm, e.g. Argon2id, current one and its settings are kept by default"),
+                )
+                .arg(
+                    Arg::new("option")
+                        .short('o')
+                        .long("option")
+                        .action(ArgAction::Append)
+                        .value_name("NAME=VALUE")
+                        .help("Key derivation option, e.g. 'memory size=262144'"),
+                ),
+        )
+        .subcommand(
+            entry_arguments(Command::new("add"))
+                .about("Adds entry, its password is asked for unless it is generated")
+                .arg(vault_argument())
+                .arg(path_argument())
+                .arg(profile_argument())
+                .arg(generate_argument()),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Prints field of the entry, its password by default")
+                .arg(vault_argument())
+                .arg(path_argument())
+                .arg(
+                    Arg::new("field")
+                        .value_parser([
+                            "password", "username", "url", "notes", "tags", "otp", "all",
+                        ])
+                        .default_value("password")
+                        .help("Field to print, `all` prints every field except password"),
+                )
+                .arg(
+                    Arg::new("clip")
+                        .short('c')
+                        .long("clip")
+                        .action(ArgAction::SetTrue)
+                        .help("Copy the field to clipboard, it is cleared after a timeout"),
+                ),
+        )
+        .subcommand(
+            entry_arguments(Command::new("edit"))
+                .about("Changes fields of the entry, `--url` and `--tag` add values")
+                .arg(vault_argument())
+                .arg(path_argument())
+                .arg(
+                    Arg::new("remove-url")
+                        .long("remove-url")
+                        .action(ArgAction::Append)
+                        .help("Address to remove, may be repeated"),
+                )
+                .arg(
+                    Arg::new("remove-tag")
+                        .long("remove-tag")
+                        .action(ArgAction::Append)
+                        .help("Tag to remove, may be repeated"),
+                )
+                .arg(profile_argument().conflicts_with("no-profile"))
+                .arg(
+                    Arg::new("no-profile")
+                        .long("no-profile")
+                        .action(ArgAction::SetTrue)
+                        .help("Detach password profile"),
+                )
+                .arg(
+                    Arg::new("password")
+                        .short('p')
+                        .long("password")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("generate")
+                        .help("Ask for new password"),
+                )
+                .arg(generate_argument()),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Removes entry")
+                .arg(vault_argument())
+                .arg(path_argument()),
+        )
+        .subcommand(
+            Command::new("ls")
+                .about("Lists paths of entries")
+                .arg(vault_argument())
+                .arg(Arg::new("group").help("List only entries in this group")),
+        )
+        .subcommand(
+            Command::new("mv")
+                .about("Moves entry, target ending with `/` is a group")
+                .arg(vault_argument())
+                .arg(Arg::new("from").required(true).help("Path of the entry"))
+                .arg(Arg::new("to").required(true).help("New path of the entry")),
+        )
+        .subcommand(
+            Command::new("bulk")
+                .about("Moves, tags, changes or removes all entries matching a query or path glob")
+                .long_about(
+                    "Moves, tags, changes or removes all entries matching a query or path \
+                     glob. Entries are chosen with --query, --saved and --glob, when more \
+                     of them are given entries have to match all of them. Changed entries \
+                     are listed.\n\n\
+                     Changes are made together and the vault is saved once at the end, \
+                     nothing is changed if any of them fails. --dry-run only lists entries \
+                     which would be changed.\n\n\
+                     Example: rustypass bulk move -v vault.rp --glob 'team-a/*' team-b",
+                )
+                .subcommand_required(true)
+                .subcommand(
+                    bulk_arguments(Command::new("move"))
+                        .visible_alias("mv")
+                        .about(
+                            "Moves entries into the group, keeping their path below the \
+                             deepest group they share",
+                        )
+                        .arg(
+                            Arg::new("group")
+                                .required(true)
+                                .help("Target group, `/` for the top level"),
+                        ),
+                )
+                .subcommand(
+                    bulk_arguments(Command::new("tag"))
+                        .about("Adds and removes tags of entries")
+                        .arg(
+                            Arg::new("add")
+                                .long("add")
+                                .action(ArgAction::Append)
+                                .help("Tag to add, may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("remove")
+                                .long("remove")
+                                .action(ArgAction::Append)
+                                .help("Tag to remove, may be repeated"),
+                        )
+                        .group(
+                            ArgGroup::new("tags")
+                                .args(["add", "remove"])
+                                .required(true)
+                                .multiple(true),
+                        ),
+                )
+                .subcommand(
+                    bulk_arguments(Command::new("set"))
+                        .about("Sets fields of entries, `--url` adds an address")
+                        .arg(
+                            Arg::new("username")
+                                .short('u')
+                                .long("username")
+                                .help("User name"),
+                        )
+                        .arg(Arg::new("notes").long("notes").help("Notes"))
+                        .arg(
+                            Arg::new("url")
+                                .long("url")
+                                .action(ArgAction::Append)
+                                .help("Address of the service to add, may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("remove-url")
+                                .long("remove-url")
+                                .action(ArgAction::Append)
+                                .help("Address to remove, may be repeated"),
+                        )
+                        .arg(profile_argument().conflicts_with("no-profile"))
+                        .arg(
+                            Arg::new("no-profile")
+                                .long("no-profile")
+                                .action(ArgAction::SetTrue)
+                                .help("Detach password profile"),
+                        )
+                        .arg(generate_argument())
+                        .group(
+                            ArgGroup::new("fields")
+                                .args([
+                                    "username",
+                                    "notes",
+                                    "url",
+                                    "remove-url",
+                                    "profile",
+                                    "no-profile",
+                                    "generate",
+                                ])
+                                .required(true)
+                                .multiple(true),
+                        ),
+                )
+                .subcommand(
+                    bulk_arguments(Command::new("rm"))
+                        .visible_alias("remove")
+                        .about("Removes entries"),
+                ),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Prints random password or passphrase")
+                .arg(
+                    Arg::new("length")
+                        .short('l')
+                        .long("length")
+                        .value_parser(value_parser!(usize))
+                        .help("Length of the password"),
+                )
+                .arg(
+                    Arg::new("words")
+                        .short('w')
+                        .long("words")
+                        .value_parser(value_parser!(usize))
+                        .conflicts_with("length")
+                        .help("Generate passphrase with this many words"),
+                )
+                .arg(profile_argument().conflicts_with_all(["length", "words"]))
+                .arg(vault_argument().required(false))
+                .arg(
+                    Arg::new("entropy")
+                        .short('e')
+                        .long("entropy")
+                        .action(ArgAction::SetTrue)
+                        .help("Print entropy of the secret to standard error"),
+                ),
+        )
+        .subcommand(
+            Command::new("find")
+                .about("Lists paths of entries matching the query or saved search")
+                .long_about(
+                    "Lists paths of entries matching the query, best matches first.\n\n\
+                     Example: rustypass find -v vault.rp tag:prod url:*.example.com \
+                     -tag:deprecated 'modified:<90d' has:totp",
+                )
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("limit")
+                        .short('n')
+                        .long("limit")
+                        .value_parser(value_parser!(usize))
+                        .help("Maximum number of listed entries"),
+                )
+                .arg(
+                    Arg::new("saved")
+                        .short('s')
+                        .long("saved")
+                        .conflicts_with("query")
+                        .help("Name of saved search to list"),
+                )
+                .arg(query_argument().required_unless_present("saved")),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Runs command with entries of the vault in environment variables")
+                .long_about(
+                    "Runs command with entries of the vault in environment variables and \
+                     exits with its status. Variables are given as NAME=PATH[:FIELD], \
+                     where FIELD is password (default), username, url, notes or otp.\n\n\
+                     Example: rustypass exec -v vault.rp -e DB_PASSWORD=db/prod \
+                     -e DB_USER=db/prod:username -- ./migrate.sh",
+                )
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("env")
+                        .short('e')
+                        .long("env")
+                        .action(ArgAction::Append)
+                        .value_name("NAME=PATH[:FIELD]")
+                        .help("Variable filled from the entry, may be repeated"),
+                )
+                .arg(
+                    Arg::new("env-file")
+                        .long("env-file")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("File with NAME=PATH[:FIELD] lines, `#` starts a comment"),
+                )
+                .arg(
+                    Arg::new("mask")
+                        .short('m')
+                        .long("mask")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Replace passwords and one-time codes in output of the command",
+                        ),
+                )
+                .arg(
+                    Arg::new("command")
+                        .required(true)
+                        .num_args(1..)
+                        .last(true)
+                        .help("Command and its arguments, given after `--`"),
+                ),
+        )
+        .subcommand(
+            Command::new("render")
+                .visible_alias("inject")
+                .about("Fills template with values from the vault")
+                .long_about(
+                    "Fills template with values from the vault. References have form \
+                     {{ vault \"PATH\" \"FIELD\" }}, where FIELD is password (default), \
+                     username, url, notes or otp. Other {{ ... }} placeholders are kept.\n\n\
+                     Output file is readable only by its owner.",
+                )
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("template")
+                        .required(true)
+                        .value_parser(value_parser!(PathBuf))
+                        .help("Template file"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_parser(value_parser!(PathBuf))
+                        .help("Write result to the file instead of standard output"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("output")
+                        .help("Only check that all references can be resolved"),
+                ),
+        )
+        .subcommand(
+            Command::new("menu")
+                .about("Chooses entry with dmenu, rofi or fzf and copies its password")
+                .long_about(
+                    "Offers paths of entries to a picker program on its standard input, \
+                     recently used first, and copies the field of the chosen entry to \
+                     clipboard or types it into the focused window.\n\n\
+                     Picker is a command line reading choices from standard input and \
+                     printing the chosen one, e.g. 'rofi -dmenu' or 'fzf'. It is taken from \
+                     --picker, `picker` in configuration file, or the first installed of \
+                     rofi, wofi, dmenu and fzf.",
+                )
+                .arg(vault_argument())
+                .arg(
+                    Arg::new("picker")
+                        .short('p')
+                        .long("picker")
+                        .value_name("COMMAND")
+                        .help("Command line of the picker"),
+                )
+                .arg(
+                    Arg::new("field")
+                        .short('f')
+                        .long("field")
+                        .value_parser(["password", "username", "url", "notes", "otp"])
+                        .default_value("password")
+                        .help("Field of the chosen entry"),
+                )
+                .arg(
+                    Arg::new("type")
+                        .short('t')
+                        .long("type")
+                        .action(ArgAction::SetTrue)
+                        .help("Type the field with wtype or xdotool instead of copying it"),
+                ),
+        )
+        .subcommand(
+            Command::new("clear-clipboard")
+                .about(
+                    "Clears clipboard if it holds value with fingerprint given on standard input",
+                )
+                .hide(true)
+                .arg(
+                    Arg::new("after")
+                        .long("after")
+                        .required(true)
+                        .value_parser(value_parser!(u64))
+                        .help("Seconds to wait"),
+                ),
+        )
+        .subcommand(
+            Command::new("shell")
+                .about("Unlocks the vault once and reads commands interactively")
+                .long_about(
+                    "Unlocks the vault once and reads commands interactively, they are \
+                     the same as program commands without --vault. Tab completes commands \
+                     and paths of entries.\n\n\
+                     History is kept only in memory. Lines starting with a space or \
+                     containing --notes are left out of it. Vault is locked after \
+                     `lock_timeout` seconds of inactivity (300 by default) set in \
+                     configuration file, the key is erased from memory then.",
+                )
+                .arg(vault_argument()),
+        )
+        .subcommand(
+            Command::new("tui")
+                .about("Browses and edits the vault in a full-screen terminal interface")
+                .long_about(
+                    "Browses and edits the vault in a full-screen terminal interface, \
+                     e.g. over SSH where the graphical one cannot run. Groups, saved \
+                     searches and entries are listed side by side, secrets are masked \
+                     until revealed. Keys are shown at the bottom of the screen.\n\n\
+                     Vault is locked after `lock_timeout` seconds without a key press \
+                     (300 by default) set in configuration file.",
+                )
+                .arg(vault_argument()),
+        )
+        .subcommand(pass::command())
+        .subcommand(
+            Command::new("otp")
+                .about("Manages one-time passwords of entries")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("import")
+                        .about("Imports accounts exported by authenticator apps as entries")
+                        .long_about(
+                            "Imports accounts exported by authenticator apps. FILE holds one \
+                             otpauth:// URI or otpauth-migration:// export of Google \
+                             Authenticator per line. Every account becomes an entry \
+                             GROUP/ISSUER/ACCOUNT with its one-time password, paths of the \
+                             entries are printed. Accounts which could not be imported or \
+                             were imported only in part are reported as warnings.",
+                        )
+                        .arg(vault_argument())
+                        .arg(
+                            Arg::new("file")
+                                .required(true)
+                                .value_parser(value_parser!(PathBuf))
+                                .help("File with exported accounts"),
+                        )
+                        .arg(
+                            Arg::new("group")
+                                .short('g')
+                                .long("group")
+                                .default_value("otp")
+                                .help("Group of imported entries"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("profile")
+                .about("Manages password profiles saved in the vault and attached to entries")
+                .long_about(
+                    "Manages password profiles saved in the vault. Profile holds rules \
+                     which passwords of a site have to follow, passwords of entries with \
+                     the profile attached are generated following them.\n\n\
+                     Character classes are uppercase, lowercase, digits, symbols or \
+                     characters in brackets like [-_.]. Template is a sequence of tokens \
+                     [...] (any character of the set), . (any allowed character), \\u, \\l, \
+                     \\d, \\s (uppercase letter, lowercase letter, digit, symbol) or a \
+                     literal character, each optionally followed by {n} or {n,m}.\n\n\
+                     Example: rustypass profile set -v vault.rp intranet --min-length 12 \
+                     --max-length 16 --forbid '&' --require digits=2 \
+                     --template '[A-Za-z].{11,15}'",
+                )
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("Lists names and rules of password profiles")
+                        .arg(vault_argument()),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Saves password profile under the name, replacing previous one")
+                        .arg(vault_argument())
+                        .arg(Arg::new("name").required(true).help("Name of the profile"))
+                        .arg(
+                            Arg::new("min-length")
+                                .long("min-length")
+                                .value_parser(value_parser!(usize))
+                                .help("Minimal length of password, maximal one by default"),
+                        )
+                        .arg(
+                            Arg::new("max-length")
+                                .long("max-length")
+                                .required(true)
+                                .value_parser(value_parser!(usize))
+                                .help("Maximal length of password"),
+                        )
+                        .arg(
+                            Arg::new("allow")
+                                .long("allow")
+                                .action(ArgAction::Append)
+                                .value_name("CLASS")
+                                .help("Allowed character class, all but custom ones by default, may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("require")
+                                .long("require")
+                                .action(ArgAction::Append)
+                                .value_name("CLASS=COUNT")
+                                .help("Minimal number of characters of the class, may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("forbid")
+                                .long("forbid")
+                                .action(ArgAction::Append)
+                                .value_name("PATTERN")
+                                .allow_hyphen_values(true)
+                                .help("Forbidden character or text, ignoring case, may be repeated"),
+                        )
+                        .arg(
+                            Arg::new("template")
+                                .long("template")
+                                .allow_hyphen_values(true)
+                                .help("Template which passwords have to match"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Removes password profile, entries using it are detached from it")
+                        .arg(vault_argument())
+                        .arg(Arg::new("name").required(true).help("Name of the profile")),
+                ),
+        )
+        .subcommand(
+            Command::new("saved")
+                .about("Manages searches saved in the vault, shown as smart folders")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("Lists names and queries of saved searches")
+                        .arg(vault_argument()),
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("Saves query under the name, replacing previous one")
+                        .arg(vault_argument())
+                        .arg(Arg::new("name").required(true).help("Name of the search"))
+                        .arg(query_argument().required(true)),
+                )
+                .subcommand(
+                    Command::new("remove")
+                        .about("Removes saved search")
+                        .arg(vault_argument())
+                        .arg(Arg::new("name").required(true).help("Name of the search")),
+                ),
+        )
+}
+
+/// Returns program arguments. When the program is started as `pass`, e.g. through
+/// a symbolic link, they are arguments of `pass` command.
+pub fn arguments() -> Vec<OsString> {
+    let mut arguments: Vec<OsString> = std::env::args_os().collect();
+    let program = arguments
+        .first()
+        .and_then(|program| Path::new(program).file_stem())
+        .and_then(|name| name.to_str());
+    if program == Some(pass::PROGRAM_NAME) {
+        arguments.insert(1, OsString::from(pass::PROGRAM_NAME));
+        return pass::expand_clip_lines(arguments);
+    }
+
+    arguments
+}
+
+/// Reads one line without the line ending.
+fn read_line(reader: &mut impl BufRead) -> Result<SafeBuffer, CliError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let length = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(length);
+
+    Ok(SafeBuffer::from(line))
+}
+
+/// Asks for password without echoing it. When standard input is not a terminal,
+/// its next line is read instead, so passwords can be piped by scripts.
+fn prompt_password(prompt: &str) -> Result<SafeBuffer, CliError> {
+    if std::io::stdin().is_terminal() {
+        return Ok(SafeBuffer::from(rpassword::prompt_password(prompt)?));
+    }
+
+    read_line(&mut std::io::stdin().lock())
+}
+
+/// Asks for new password twice, unless it is read from standard input.
+fn prompt_new_password(prompt: &str) -> Result<SafeBuffer, CliError> {
+    let password = prompt_password(prompt)?;
+    if std::io::stdin().is_terminal()
+        && prompt_password("Repeat password: ")?.as_ref() != password.as_ref()
+    {
+        return Err(CliError::PasswordMismatch);
+    }
+
+    Ok(password)
+}
+
+/// Reads first line of the file, error mentions `source` of the password.
+fn read_password_file(path: &Path, source: &str) -> Result<SafeBuffer, CliError> {
+    let file = std::fs::File::open(path).map_err(|error| CliError::IoError {
+        description: format!("cannot read master password from {source}: {error}"),
+    })?;
+
+    read_line(&mut std::io::BufReader::new(file))
+}
+
+/// Returns master password from the source given in arguments or asks for it,
+/// `new` password is asked for twice.
+fn master_password(matches: &ArgMatches, new: bool) -> Result<SafeBuffer, CliError> {
+    if let Some(fd) = matches.get_one::<u32>("password-fd") {
+        // Descriptor is opened again through /dev/fd, so it stays owned by the parent.
+        read_password_file(
+            &PathBuf::from(format!("/dev/fd/{fd}")),
+            &format!("file descriptor {fd}"),
+        )
+    } else if let Some(path) = matches.get_one::<PathBuf>("password-file") {
+        read_password_file(path, &path.display().to_string())
+    } else if let Some(variable) = matches.get_one::<String>("password-env") {
+        std::env::var(variable)
+            .map(SafeBuffer::from)
+            .map_err(|_| CliError::InvalidArgument {
+                description: format!("environment variable `{variable}` is not set"),
+            })
+    } else if new {
+        prompt_new_password("Master password: ")
+    } else {
+        prompt_password("Master password: ")
+    }
+}
+
+/// Opens vault given in `vault` argument and returns it with the master password,
+/// which is needed again to change encryption of the vault.
+fn unlock_vault(matches: &ArgMatches) -> Result<(OpenVault, SafeBuffer), CliError> {
+    let path = matches
+        .get_one::<PathBuf>("vault")
+        .ok_or_else(|| CliError::InvalidArgument {
+            description: "vault is not given, use --vault".to_string(),
+        })?;
+    if !path.is_file() {
+        return Err(CliError::VaultNotFound { path: path.clone() });
+    }
+
+    let password = master_password(matches, false)?;
+    Ok((OpenVault::open(path, &password)?, password))
+}
+
+/// Prints problems with cryptographic settings of the vault to standard error.
+fn warn_about_health(open_vault: &OpenVault) {
+    for issue in open_vault.health_issues() {
+        eprintln!("warning: {issue}");
+    }
+    if !open_vault.health_issues().is_empty() {
+        eprintln!("warning: {}", get_translation!("HEALTH_UPGRADE_HINT"));
+    }
+}
+
+/// Opens vault given in `vault` argument, warning about its health issues.
+fn open_vault(matches: &ArgMatches) -> Result<OpenVault, CliError> {
+    let (open_vault, _) = unlock_vault(matches)?;
+    warn_about_health(&open_vault);
+
+    Ok(open_vault)
+}
+
+/// Vault and configuration used by commands. Shell keeps the vault unlocked between
+/// commands, otherwise it is opened by the command which needs it.
+pub(super) struct Session<'a> {
+    configuration: &'a RwLock<ProgramConfiguration>,
+    open_vault: Option<OpenVault>,
+    /// Vault stays open after [`Session::release`].
+    persistent: bool,
+}
+
+impl<'a> Session<'a> {
+    fn new(configuration: &'a RwLock<ProgramConfiguration>) -> Self {
+        Self {
+            configuration,
+            open_vault: None,
+            persistent: false,
+        }
+    }
+
+    fn configuration(&self) -> &'a RwLock<ProgramConfiguration> {
+        self.configuration
+    }
+
+    /// Returns unlocked vault, or opens the one given in `vault` argument.
+    fn vault(&mut self, matches: &ArgMatches) -> Result<&mut OpenVault, CliError> {
+        if let (Some(open_vault), Some(path)) =
+            (&self.open_vault, matches.get_one::<PathBuf>("vault"))
+        {
+            if open_vault.path() != path {
+                return Err(CliError::InvalidArgument {
+                    description: format!("vault {} is open", open_vault.path().display()),
+                });
+            }
+        }
+        if self.open_vault.is_none() {
+            self.open_vault = Some(open_vault(matches)?);
+        }
+
+        Ok(self.open_vault.as_mut().expect("vault is open"))
+    }
+
+    /// Closes the vault, unless it is kept open by the shell.
+    fn release(&mut self) {
+        if !self.persistent {
+            self.open_vault = None;
+        }
+    }
+}
+
+/// Runs one command and returns exit status of the program.
+fn execute(name: &str, matches: &ArgMatches, session: &mut Session) -> Result<i32, CliError> {
+    match name {
+        "init" => vault::init(matches)?,
+        "open" => vault::open(matches, session)?,
+        "upgrade" => vault::upgrade(matches)?,
+        "rekey" => vault::rekey(matches)?,
+        "audit" => audit::audit(matches, session)?,
+        "add" => entries::add(matches, session)?,
+        "get" => entries::get(matches, session)?,
+        "edit" => entries::edit(matches, session)?,
+        "rm" => entries::remove(matches, session)?,
+        "ls" => entries::list(matches, session)?,
+        "mv" => entries::move_entry(matches, session)?,
+        "bulk" => bulk::bulk(matches, session)?,
+        "generate" => vault::generate(matches, session)?,
+        "find" => search::find(matches, session)?,
+        "saved" => search::saved(matches, session)?,
+        "profile" => profile::profile(matches, session)?,
+        "otp" => otp::otp(matches, session)?,
+        "menu" => menu::menu(matches, session)?,
+        "clear-clipboard" => clipboard::clear(matches)?,
+        "render" => template::render_template(matches, session)?,
+        "exec" => return exec::exec(matches, session),
+        "shell" => shell::shell(matches, session)?,
+        "tui" => vault::tui(matches, session)?,
+        "pass" => pass::pass(matches, session)?,
+        _ => unreachable!("command `{name}` is not defined"),
+    }
+
+    Ok(0)
+}
+
+/// Runs subcommand given in program arguments and returns exit status of the program.
+/// Returns `None` if there was no subcommand and graphical interface should be started instead.
+pub fn run(
+    matches: &ArgMatches,
+    configuration: &RwLock<ProgramConfiguration>,
+) -> Result<Option<i32>, CliError> {
+    // Conflict is checked here, as clap would reject global arguments given before the command too.
+    if matches.get_flag("gui") && matches.subcommand().is_some() {
+        return Err(CliError::InvalidArgument {
+            description: "--gui cannot be used with a command".to_string(),
+        });
+    }
+
+    match matches.subcommand() {
+        Some((name, matches)) => execute(name, matches, &mut Session::new(configuration)).map(Some),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::KeyDerivationError;
+
+    #[test]
+    fn command_is_valid() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn query_errors_point_at_position() {
+        let matches = command()
+            .try_get_matches_from([
+                "rustypass",
+                "find",
+                "-v",
+                "vault",
+                "tag:a",
+                "-tag:b",
+                "colour:red",
+            ])
+            .unwrap();
+        let error = run(&matches, &RwLock::default()).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("tag:a -tag:b colour:red\n             ^\nunknown field `colour`"));
+    }
+
+    #[test]
+    fn master_password_is_read_from_given_source() {
+        let file = std::env::temp_dir().join("rustypass-cli-password");
+        std::fs::write(&file, "from file\nsecond line\n").unwrap();
+        std::env::set_var("RUSTYPASS_CLI_TEST_PASSWORD", "from environment");
+
+        let matches = |source: &[&str]| {
+            let mut arguments = vec!["rustypass", "ls", "-v", "vault"];
+            arguments.extend_from_slice(source);
+            let matches = command().try_get_matches_from(arguments).unwrap();
+            matches.subcommand_matches("ls").unwrap().clone()
+        };
+        let file_matches = matches(&["--password-file", file.to_str().unwrap()]);
+        assert_eq!(
+            master_password(&file_matches, true).unwrap().as_ref(),
+            b"from file"
+        );
+        let environment_matches = matches(&["--password-env", "RUSTYPASS_CLI_TEST_PASSWORD"]);
+        assert_eq!(
+            master_password(&environment_matches, false)
+                .unwrap()
+                .as_ref(),
+            b"from environment"
+        );
+        let missing_matches = matches(&["--password-env", "RUSTYPASS_CLI_TEST_MISSING"]);
+        assert_eq!(
+            master_password(&missing_matches, false)
+                .unwrap_err()
+                .exit_code(),
+            EXIT_USAGE
+        );
+
+        assert!(command()
+            .try_get_matches_from([
+                "rustypass",
+                "ls",
+                "-v",
+                "vault",
+                "--password-fd",
+                "3",
+                "--password-env",
+                "PASSWORD",
+            ])
+            .is_err());
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn errors_have_stable_exit_codes() {
+        let matches = command()
+            .try_get_matches_from(["rustypass", "--format", "json", "ls", "-v", "missing"])
+            .unwrap();
+        let error = run(&matches, &RwLock::default()).unwrap_err();
+        assert_eq!(error.exit_code(), EXIT_NOT_FOUND);
+
+        let error = CliError::from(VaultError::EntryNotFound {
+            path: "mail".to_string(),
+        });
+        assert_eq!(error.exit_code(), EXIT_NOT_FOUND);
+        assert_eq!(
+            CliError::from(StorageError::WrongPassword).exit_code(),
+            EXIT_WRONG_PASSWORD
+        );
+        let error = CliError::from(StorageError::CorruptedVault {
+            description: String::new(),
+        });
+        assert_eq!(error.exit_code(), EXIT_CORRUPTED);
+        assert_eq!(CliError::PasswordMismatch.exit_code(), EXIT_FAILURE);
+
+        let error = CliError::from(StorageError::from(KeyDerivationError::InvalidConfigFormat));
+        assert_eq!(
+            error.to_string(),
+            "key derivation options cannot be decoded"
+        );
+    }
+
+    #[test]
+    fn gui_is_started_without_command() {
+        let matches = command().try_get_matches_from(["rustypass"]).unwrap();
+        assert_eq!(run(&matches, &RwLock::default()).unwrap(), None);
+        let matches = command()
+            .try_get_matches_from(["rustypass", "--gui", "ls", "-v", "vault"])
+            .unwrap();
+        assert!(run(&matches, &RwLock::default()).is_err());
+    }
+}
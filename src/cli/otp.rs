@@ -0,0 +1,126 @@
+use super::output::{print_json, Format, ImportIssueOutput, ImportOutput};
+use super::{CliError, Session};
+use crate::otp::{import, ImportIssue, ImportReport};
+use clap::ArgMatches;
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+/// Describes issue of the import in one line, e.g.
+/// `line 3 (alice@example.com): Counter of TOTP was ignored.`
+fn issue_text(issue: &ImportIssue) -> String {
+    let mut text = match issue.line {
+        0 => String::new(),
+        line => format!("line {line}"),
+    };
+    if let Some(account) = &issue.account {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&format!("({account})"));
+    }
+    if !text.is_empty() {
+        text.push_str(": ");
+    }
+    text.push_str(&issue.description);
+    if issue.skipped {
+        text.push_str(" Account was not imported.");
+    }
+
+    text
+}
+
+fn print_report(report: &ImportReport, group: &str, format: Format) -> Result<(), CliError> {
+    match format {
+        Format::Json => print_json(&ImportOutput {
+            imported: &report.imported,
+            issues: report
+                .issues
+                .iter()
+                .map(|issue| ImportIssueOutput {
+                    line: (issue.line > 0).then_some(issue.line),
+                    account: issue.account.as_deref(),
+                    description: &issue.description,
+                    skipped: issue.skipped,
+                })
+                .collect(),
+        })?,
+        Format::Text => {
+            report.imported.iter().for_each(|path| println!("{path}"));
+            for issue in &report.issues {
+                eprintln!("warning: {}", issue_text(issue));
+            }
+            eprintln!("{} accounts imported into {group}", report.imported.len());
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn otp(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("import", matches)) => {
+            let path = matches
+                .get_one::<PathBuf>("file")
+                .expect("file is required");
+            let group = matches
+                .get_one::<String>("group")
+                .expect("group has default value");
+            // Secrets of the accounts are read, so the text is zeroized.
+            let input = Zeroizing::new(std::fs::read_to_string(path).map_err(|error| {
+                CliError::IoError {
+                    description: format!("cannot read {}: {error}", path.display()),
+                }
+            })?);
+
+            let open_vault = session.vault(matches)?;
+            let report = import(open_vault.vault_mut(), &input, group.trim_matches('/'));
+            if !report.imported.is_empty() {
+                open_vault.save()?;
+            }
+
+            print_report(&report, group, Format::of(matches))?;
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Vault;
+
+    #[test]
+    fn import_is_reported() {
+        let matches = super::super::command()
+            .try_get_matches_from(["rustypass", "otp", "import", "-v", "vault.rp", "codes.txt"])
+            .unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        let group = matches.get_one::<String>("group").unwrap();
+        assert_eq!(group, "otp");
+
+        let mut vault = Vault::new();
+        let input = "otpauth://totp/Example:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example&image=x\n\
+                     otpauth://totp/Example:alice%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example\n\
+                     https://example.com";
+        let report = import(&mut vault, input, group);
+
+        assert_eq!(report.imported, ["otp/Example/alice@example.com"]);
+        assert!(vault
+            .entry("otp/Example/alice@example.com")
+            .unwrap()
+            .otp()
+            .is_some());
+        assert_eq!(
+            report.issues.iter().map(issue_text).collect::<Vec<_>>(),
+            [
+                "line 1 (alice@example.com): Parameter image is not supported and was ignored.",
+                "line 3: Line is not an otpauth URI. Account was not imported.",
+                "(alice@example.com): Entry otp/Example/alice@example.com already exists. \
+                 Account was not imported.",
+            ]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use super::CliError;
+use crate::otp::OtpKind;
+use crate::storage::{Entry, HealthIssue};
+use clap::ArgMatches;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeSet;
+use zeroize::Zeroizing;
+
+/// Description of `--format json` output, shown in `--help`. It is a stable interface:
+/// fields may be added, but existing ones are never renamed or removed.
+pub(super) const JSON_SCHEMA: &str = "\
+JSON output (--format json) is printed as a single line:
+  get PATH [FIELD]  {\"path\", FIELD: value}, `url` and `tags` are arrays of strings,
+                    `otp` adds \"valid_for\" (seconds, null for HOTP)
+  get PATH all      entry object
+  ls [GROUP]        array of entry objects
+  find QUERY        array of {\"path\", \"score\", \"field\"}, field is title, group,
+                    username, url, tag, notes or null
+  open              {\"path\", \"entries\", \"health_issues\": [{\"kind\", ...}]}
+  audit             {\"summary\": {\"entries\", \"healthy\", \"reused\", \"weak\", \"old\",
+                    \"duplicate\", \"missing\", \"breached\"}, \"findings\": [{\"kind\", ...}]}, kind is
+                    reused_password {\"paths\"}, weak_password {\"path\", \"score\",
+                    \"guesses_log10\", \"warning\"}, old_password {\"path\", \"age_days\"},
+                    duplicate_credentials {\"url\", \"username\", \"paths\"},
+                    missing_password {\"path\"} or breached_password {\"path\",
+                    \"occurrences\"}
+  saved list        array of {\"name\", \"query\"}
+  profile list      array of {\"name\", \"min_length\", \"max_length\", \"allowed\",
+                    \"required\": [{\"class\", \"minimum\"}], \"forbidden\", \"template\"},
+                    classes are written like in `profile set`
+  generate          {\"secret\", \"entropy\"}
+  otp import FILE   {\"imported\": paths, \"issues\": [{\"line\", \"account\",
+                    \"description\", \"skipped\"}]}, line and account may be null
+  bulk ACTION       array of {\"path\", \"new_path\"}, new_path is null unless moved
+Entry object: {\"path\", \"username\", \"urls\", \"tags\", \"notes\", \"profile\", \"otp\",
+  \"created\", \"modified\", \"password_changed\", \"last_used\"}, where otp is null or
+  {\"kind\": \"totp\", \"period\"} or {\"kind\": \"hotp\", \"counter\"} and times are
+  seconds since UNIX epoch. Passwords are never part of it.
+Fields may be added in future versions, existing ones are not renamed or removed.";
+
+/// Output format selected with the global `format` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    pub(super) fn of(matches: &ArgMatches) -> Self {
+        match matches.get_one::<String>("format").map(String::as_str) {
+            Some("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Prints `value` as one line of JSON. The line may contain secrets, so it is zeroized.
+pub(super) fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), CliError> {
+    let json = Zeroizing::new(
+        serde_json::to_string(value).map_err(|error| CliError::IoError {
+            description: error.to_string(),
+        })?,
+    );
+    println!("{}", json.as_str());
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum OtpOutput {
+    Totp { period: u64 },
+    Hotp { counter: u64 },
+}
+
+impl From<OtpKind> for OtpOutput {
+    fn from(kind: OtpKind) -> Self {
+        match kind {
+            OtpKind::Totp { period } => Self::Totp { period },
+            OtpKind::Hotp { counter } => Self::Hotp { counter },
+        }
+    }
+}
+
+/// Entry without password and one-time password secret.
+#[derive(Serialize)]
+pub(super) struct EntryOutput<'a> {
+    path: &'a str,
+    username: &'a str,
+    urls: &'a [String],
+    tags: &'a BTreeSet<String>,
+    notes: &'a str,
+    profile: Option<&'a str>,
+    otp: Option<OtpOutput>,
+    created: u64,
+    modified: u64,
+    password_changed: u64,
+    last_used: Option<u64>,
+}
+
+impl<'a> EntryOutput<'a> {
+    pub(super) fn new(path: &'a str, entry: &'a Entry) -> Self {
+        Self {
+            path,
+            username: entry.username(),
+            urls: entry.urls(),
+            tags: entry.tags(),
+            notes: entry.notes(),
+            profile: entry.profile(),
+            otp: entry.otp().map(|otp| otp.kind().into()),
+            created: entry.created(),
+            modified: entry.modified(),
+            password_changed: entry.password_changed(),
+            last_used: entry.last_used(),
+        }
+    }
+}
+
+/// Single field of an entry, serialized as `{"path": path, name: value}`.
+pub(super) struct FieldOutput<'a, T: Serialize> {
+    pub path: &'a str,
+    pub name: &'a str,
+    pub value: T,
+}
+
+impl<T: Serialize> Serialize for FieldOutput<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("path", self.path)?;
+        map.serialize_entry(self.name, &self.value)?;
+        map.end()
+    }
+}
+
+/// One-time code with number of seconds it stays valid, `None` for HOTP.
+#[derive(Serialize)]
+pub(super) struct OtpCodeOutput<'a> {
+    pub path: &'a str,
+    pub otp: &'a str,
+    pub valid_for: Option<u64>,
+}
+
+/// Output of `open` command.
+#[derive(Serialize)]
+pub(super) struct VaultOutput<'a> {
+    pub path: &'a str,
+    pub entries: usize,
+    pub health_issues: &'a [HealthIssue],
+}
+
+/// Output of `saved list` command.
+#[derive(Serialize)]
+pub(super) struct SavedSearchOutput<'a> {
+    pub name: &'a str,
+    pub query: &'a str,
+}
+
+/// Output of `profile list` command.
+#[derive(Serialize)]
+pub(super) struct ProfileOutput<'a> {
+    pub name: &'a str,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub allowed: Vec<String>,
+    pub required: Vec<RequiredClassOutput>,
+    pub forbidden: &'a [String],
+    pub template: Option<&'a str>,
+}
+
+/// Class required by password profile, see [`ProfileOutput`].
+#[derive(Serialize)]
+pub(super) struct RequiredClassOutput {
+    pub class: String,
+    pub minimum: usize,
+}
+
+/// Entry changed by `bulk` command, `new_path` is `None` unless it was moved.
+#[derive(Serialize)]
+pub(super) struct BulkChangeOutput<'a> {
+    pub path: &'a str,
+    pub new_path: Option<&'a str>,
+}
+
+/// Output of `otp import` command.
+#[derive(Serialize)]
+pub(super) struct ImportOutput<'a> {
+    pub imported: &'a [String],
+    pub issues: Vec<ImportIssueOutput<'a>>,
+}
+
+/// Account which could not be imported or was imported only in part, see [`ImportOutput`].
+#[derive(Serialize)]
+pub(super) struct ImportIssueOutput<'a> {
+    pub line: Option<usize>,
+    pub account: Option<&'a str>,
+    pub description: &'a str,
+    pub skipped: bool,
+}
+
+/// Output of `generate` command.
+#[derive(Serialize)]
+pub(super) struct GeneratedOutput<'a> {
+    pub secret: &'a str,
+    pub entropy: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn entries_are_serialized_without_secrets() {
+        let mut entry = Entry::new();
+        entry.set_username("alice");
+        entry.add_tag("work");
+        entry.set_password("hunter2".to_string().into());
+
+        let value = serde_json::to_value(EntryOutput::new("mail/work", &entry)).unwrap();
+        assert_eq!(value["path"], json!("mail/work"));
+        assert_eq!(value["username"], json!("alice"));
+        assert_eq!(value["tags"], json!(["work"]));
+        assert_eq!(value["otp"], json!(null));
+        assert_eq!(value["last_used"], json!(null));
+        assert!(value["created"].is_u64());
+        assert!(!value.to_string().contains("hunter2"));
+
+        assert_eq!(
+            serde_json::to_value(OtpOutput::from(OtpKind::Totp { period: 30 })).unwrap(),
+            json!({"kind": "totp", "period": 30})
+        );
+    }
+
+    #[test]
+    fn fields_are_keyed_by_name() {
+        let output = FieldOutput {
+            path: "mail/work",
+            name: "tags",
+            value: ["work", "mail"],
+        };
+        assert_eq!(
+            serde_json::to_string(&output).unwrap(),
+            r#"{"path":"mail/work","tags":["work","mail"]}"#
+        );
+    }
+}
@@ -0,0 +1,775 @@
+use super::entries::report_strength;
+use super::reference::{self, Field};
+use super::{clipboard, prompt_new_password, read_line, vault_argument, CliError, Session};
+use crate::generator::{CharacterClass, PasswordGenerator};
+use crate::otp::{otpauth_uri, parse_accounts, OtpSecret};
+use crate::storage::{Entry, SafeBuffer, Vault, VaultError};
+use clap::builder::RangedU64ValueParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::{IsTerminal, Read};
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+/// Program started under this name accepts arguments of `pass` command directly.
+pub(super) const PROGRAM_NAME: &str = "pass";
+
+/// Environment variable with the vault, as scripts written for `pass` do not give it.
+const VAULT_VARIABLE: &str = "RUSTYPASS_VAULT";
+
+/// Length of generated passwords, the same as in `pass`.
+const DEFAULT_GENERATED_LENGTH: usize = 25;
+const GENERATED_LENGTH_VARIABLE: &str = "PASSWORD_STORE_GENERATED_LENGTH";
+
+/// Keys of the line holding user name, the first one is written.
+const LOGIN_KEYS: &[&str] = &["login", "username", "user"];
+const URL_KEY: &str = "url";
+
+fn name_argument() -> Arg {
+    Arg::new("name").required(true).help("Path of the entry")
+}
+
+/// `-c[LINE]` or `--clip[=LINE]`, as in `pass show`.
+fn clip_line_argument() -> Arg {
+    Arg::new("clip")
+        .short('c')
+        .long("clip")
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("1")
+        .value_name("LINE")
+        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+        .help("Copy the line (the password by default) to clipboard instead of printing")
+}
+
+fn clip_argument() -> Arg {
+    Arg::new("clip")
+        .short('c')
+        .long("clip")
+        .action(ArgAction::SetTrue)
+        .help("Copy to clipboard instead of printing")
+}
+
+fn force_argument() -> Arg {
+    Arg::new("force")
+        .short('f')
+        .long("force")
+        .action(ArgAction::SetTrue)
+        .help("Do not ask before overwriting or removing")
+}
+
+/// Rewrites `-cLINE` to `--clip=LINE`. Value of the option can be attached only with `=`,
+/// so `-c NAME` is not mistaken for a line.
+pub(super) fn expand_clip_lines(arguments: Vec<OsString>) -> Vec<OsString> {
+    let mut options = true;
+    arguments
+        .into_iter()
+        .map(|argument| {
+            options &= argument != "--";
+            match argument.to_str().and_then(|text| text.strip_prefix("-c")) {
+                Some(line)
+                    if options && !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit()) =>
+                {
+                    OsString::from(format!("--clip={line}"))
+                }
+                _ => argument,
+            }
+        })
+        .collect()
+}
+
+pub(super) fn command() -> Command {
+    Command::new("pass")
+        .about("Commands compatible with pass, the standard unix password manager")
+        .long_about(
+            "Commands accepting the same arguments and printing the same output as pass, \
+             so its scripts work with the vault. When the program is started as `pass` \
+             (e.g. through a symbolic link), they are accepted without `rustypass pass`.\n\n\
+             Vault is given by --vault or RUSTYPASS_VAULT environment variable. `pass NAME` \
+             shows the entry and `pass` alone lists all of them. Entries are shown with \
+             the password on the first line, followed by `login:`, `url:` and `otpauth://` \
+             lines and notes. Content inserted with --multiline is read the same way.\n\n\
+             Line to copy is given as --clip=LINE, the short form -cLINE is accepted \
+             only when started as `pass`.",
+        )
+        .arg(
+            vault_argument()
+                .required(false)
+                .global(true)
+                .env(VAULT_VARIABLE),
+        )
+        .arg(clip_line_argument())
+        .allow_external_subcommands(true)
+        .external_subcommand_value_parser(value_parser!(String))
+        .subcommand(
+            Command::new("show")
+                .about("Prints the entry or lists the folder")
+                .arg(clip_line_argument())
+                .arg(name_argument()),
+        )
+        .subcommand(
+            Command::new("ls")
+                .visible_alias("list")
+                .about("Lists entries as a tree")
+                .arg(Arg::new("subfolder").help("List only entries in this folder")),
+        )
+        .subcommand(
+            Command::new("insert")
+                .visible_alias("add")
+                .about("Adds entry, or replaces its content")
+                .arg(
+                    Arg::new("echo")
+                        .short('e')
+                        .long("echo")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("multiline")
+                        .help("Show the password while it is typed and ask only once"),
+                )
+                .arg(
+                    Arg::new("multiline")
+                        .short('m')
+                        .long("multiline")
+                        .action(ArgAction::SetTrue)
+                        .help("Read whole content from standard input until end of file"),
+                )
+                .arg(force_argument())
+                .arg(name_argument()),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generates password of the entry and prints it")
+                .arg(
+                    Arg::new("no-symbols")
+                        .short('n')
+                        .long("no-symbols")
+                        .action(ArgAction::SetTrue)
+                        .help("Use only letters and digits"),
+                )
+                .arg(clip_argument())
+                .arg(
+                    Arg::new("in-place")
+                        .short('i')
+                        .long("in-place")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("force")
+                        .help("Replace only the password, keep rest of the content"),
+                )
+                .arg(force_argument())
+                .arg(name_argument())
+                .arg(
+                    Arg::new("length")
+                        .value_parser(value_parser!(usize))
+                        .help("Length of the password, 25 by default"),
+                ),
+        )
+        .subcommand(
+            Command::new("otp")
+                .about("Prints current one-time code of the entry")
+                .arg(clip_argument())
+                .arg(
+                    Arg::new("arguments")
+                        .required(true)
+                        .num_args(1..=2)
+                        .value_name("[code] NAME")
+                        .help("Path of the entry, optionally preceded by `code`"),
+                ),
+        )
+        .subcommand(
+            Command::new("rm")
+                .visible_aliases(["remove", "delete"])
+                .about("Removes entry or folder")
+                .arg(
+                    Arg::new("recursive")
+                        .short('r')
+                        .long("recursive")
+                        .action(ArgAction::SetTrue)
+                        .help("Remove folder with all its entries"),
+                )
+                .arg(force_argument())
+                .arg(name_argument()),
+        )
+}
+
+/// Fields read from content of an entry, see [`content`].
+#[derive(Debug, Default)]
+struct Content {
+    password: SafeBuffer,
+    username: String,
+    urls: Vec<String>,
+    otp: Option<OtpSecret>,
+    notes: Zeroizing<String>,
+}
+
+impl Content {
+    fn password(password: SafeBuffer) -> Self {
+        Self {
+            password,
+            ..Self::default()
+        }
+    }
+
+    /// Replaces content of the entry, its tags and profile are kept.
+    fn apply(self, entry: &mut Entry) {
+        entry.set_password(self.password);
+        entry.set_username(self.username);
+        for url in entry.urls().to_vec() {
+            entry.remove_url(&url);
+        }
+        for url in self.urls {
+            entry.add_url(url);
+        }
+        entry.set_otp(self.otp);
+        entry.set_notes(self.notes.as_str());
+    }
+}
+
+/// Returns content of the entry as `pass` shows it: password on the first line, then
+/// user name, addresses, one-time password URI and notes.
+fn content(entry: &Entry) -> Zeroizing<String> {
+    let mut content =
+        Zeroizing::new(String::from_utf8_lossy(entry.password().as_ref()).into_owned());
+    content.push('\n');
+    if !entry.username().is_empty() {
+        content.push_str(&format!("{}: {}\n", LOGIN_KEYS[0], entry.username()));
+    }
+    for url in entry.urls() {
+        content.push_str(&format!("{URL_KEY}: {url}\n"));
+    }
+    if let Some(otp) = entry.otp() {
+        content.push_str(&otpauth_uri(otp));
+        content.push('\n');
+    }
+    if !entry.notes().is_empty() {
+        content.push_str(entry.notes());
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+    }
+
+    content
+}
+
+/// Reads content written in the format of [`content`]. Other lines become notes.
+fn parse_content(text: &str) -> Result<Content, CliError> {
+    let mut lines = text.lines();
+    let mut content = Content::password(SafeBuffer::from(
+        lines.next().unwrap_or_default().to_string(),
+    ));
+
+    for line in lines {
+        let (key, value) = line
+            .split_once(':')
+            .map_or((String::new(), ""), |(key, value)| {
+                (key.trim().to_ascii_lowercase(), value.trim())
+            });
+
+        if key == "otpauth" && content.otp.is_none() {
+            let (accounts, issues) = parse_accounts(line);
+            let otp = accounts
+                .into_iter()
+                .next()
+                .ok_or_else(|| CliError::InvalidArgument {
+                    description: format!(
+                        "invalid one-time password URI: {}",
+                        issues
+                            .first()
+                            .map_or("", |issue| issue.description.as_str())
+                    ),
+                })?;
+            content.otp = Some(otp);
+        } else if LOGIN_KEYS.contains(&key.as_str()) && content.username.is_empty() {
+            content.username = value.to_string();
+        } else if key == URL_KEY && !value.is_empty() {
+            content.urls.push(value.to_string());
+        } else {
+            content.notes.push_str(line);
+            content.notes.push('\n');
+        }
+    }
+    let length = content.notes.trim_end_matches('\n').len();
+    content.notes.truncate(length);
+
+    Ok(content)
+}
+
+/// Folders and entries, entry may be a folder at the same time.
+#[derive(Debug, Default)]
+struct Tree {
+    children: BTreeMap<String, Tree>,
+}
+
+impl Tree {
+    fn new<'a>(paths: impl Iterator<Item = &'a str>) -> Self {
+        let mut tree = Self::default();
+        for path in paths {
+            path.split('/').fold(&mut tree, |node, name| {
+                node.children.entry(name.to_string()).or_default()
+            });
+        }
+        tree
+    }
+
+    /// Formats children like `tree` program used by `pass ls`.
+    fn format(&self, indent: &str, output: &mut String) {
+        let count = self.children.len();
+        for (index, (name, child)) in self.children.iter().enumerate() {
+            let last = index + 1 == count;
+            output.push_str(indent);
+            output.push_str(if last { "└── " } else { "├── " });
+            output.push_str(name);
+            output.push('\n');
+            child.format(
+                &format!("{indent}{}", if last { "    " } else { "│   " }),
+                output,
+            );
+        }
+    }
+}
+
+/// Returns paths of entries in the folder, relative to it. Empty folder is the root.
+fn folder_entries<'a>(vault: &'a Vault, folder: &str) -> Vec<&'a str> {
+    vault
+        .entries()
+        .filter_map(|(path, _)| match folder.is_empty() {
+            true => Some(path.as_str()),
+            false => path
+                .strip_prefix(folder)
+                .and_then(|rest| rest.strip_prefix('/')),
+        })
+        .collect()
+}
+
+/// Asks for confirmation on standard error and reads answer from standard input.
+fn confirm(question: &str) -> Result<(), CliError> {
+    eprint!("{question} [y/N] ");
+    let answer = read_line(&mut std::io::stdin().lock())?;
+    match answer.as_ref() {
+        b"y" | b"Y" => Ok(()),
+        _ => Err(CliError::Cancelled),
+    }
+}
+
+fn print_copied(what: &str, timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => println!(
+            "Copied {what} to clipboard. Will clear in {} seconds.",
+            timeout.as_secs()
+        ),
+        None => println!("Copied {what} to clipboard."),
+    }
+}
+
+/// Prints the entry, copies its line to clipboard, or lists the folder.
+fn show(
+    matches: &ArgMatches,
+    session: &mut Session,
+    name: Option<&str>,
+    clip: Option<usize>,
+) -> Result<(), CliError> {
+    let configuration = session.configuration();
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault_mut();
+    let name = name.unwrap_or_default().trim_matches('/');
+
+    if let Ok(entry) = vault.entry(name) {
+        let content = content(entry);
+        match clip {
+            Some(line) => {
+                let value =
+                    content
+                        .lines()
+                        .nth(line - 1)
+                        .ok_or_else(|| CliError::InvalidArgument {
+                            description: format!(
+                                "There is no password to put on the clipboard at line {line}."
+                            ),
+                        })?;
+                let timeout =
+                    clipboard::copy_value(value.as_bytes(), &configuration.read().unwrap())?;
+                print_copied(name, timeout);
+            }
+            None => print!("{}", content.as_str()),
+        }
+        if clip.is_none_or(|line| line == 1) {
+            reference::record_use(
+                open_vault,
+                [(name, Field::Password)],
+                &configuration.read().unwrap(),
+            )?;
+        }
+        return Ok(());
+    }
+
+    let entries = folder_entries(vault, name);
+    if entries.is_empty() && !name.is_empty() {
+        return Err(VaultError::EntryNotFound {
+            path: name.to_string(),
+        }
+        .into());
+    }
+    let mut output = match name.is_empty() {
+        true => "Password Store\n".to_string(),
+        false => format!("{name}\n"),
+    };
+    Tree::new(entries.into_iter()).format("", &mut output);
+    print!("{output}");
+
+    Ok(())
+}
+
+/// Reads content of the entry as `pass insert` does.
+fn read_content(name: &str, matches: &ArgMatches) -> Result<Content, CliError> {
+    if matches.get_flag("multiline") {
+        if std::io::stdin().is_terminal() {
+            eprintln!("Enter contents of {name} and press Ctrl+D when finished:\n");
+        }
+        let mut text = Zeroizing::new(String::new());
+        std::io::stdin().lock().read_to_string(&mut text)?;
+        return parse_content(&text);
+    }
+
+    let password = match matches.get_flag("echo") {
+        true => {
+            eprint!("Enter password for {name}: ");
+            read_line(&mut std::io::stdin().lock())?
+        }
+        false => prompt_new_password(&format!("Enter password for {name}: "))?,
+    };
+    Ok(Content::password(password))
+}
+
+/// Sets content of the entry, existing one is replaced after confirmation.
+fn store(vault: &mut Vault, name: &str, content: Content, force: bool) -> Result<(), CliError> {
+    match vault.entry_mut(name) {
+        Ok(entry) => {
+            if !force {
+                confirm(&format!(
+                    "An entry already exists for {name}. Overwrite it?"
+                ))?;
+            }
+            content.apply(entry);
+            Ok(())
+        }
+        Err(VaultError::EntryNotFound { .. }) => {
+            let mut entry = Entry::new();
+            content.apply(&mut entry);
+            Ok(vault.add_entry(name, entry)?)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+fn insert(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let name = matches.get_one::<String>("name").expect("name is required");
+    let open_vault = session.vault(matches)?;
+    let exists = open_vault.vault().entry(name).is_ok();
+    if exists && !matches.get_flag("force") {
+        confirm(&format!(
+            "An entry already exists for {name}. Overwrite it?"
+        ))?;
+    }
+
+    let content = read_content(name, matches)?;
+    store(open_vault.vault_mut(), name, content, true)?;
+    if !open_vault.vault().entry(name)?.password().is_empty() {
+        report_strength(open_vault.vault(), name)?;
+    }
+
+    Ok(open_vault.save()?)
+}
+
+fn generate(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let name = matches.get_one::<String>("name").expect("name is required");
+    let length = match matches.get_one::<usize>("length") {
+        Some(length) => *length,
+        None => std::env::var(GENERATED_LENGTH_VARIABLE)
+            .ok()
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(DEFAULT_GENERATED_LENGTH),
+    };
+    let generator = match matches.get_flag("no-symbols") {
+        true => PasswordGenerator::new(length)
+            .with_class(CharacterClass::Uppercase, 1)
+            .with_class(CharacterClass::Lowercase, 1)
+            .with_class(CharacterClass::Digits, 1),
+        false => {
+            let mut generator = PasswordGenerator::default();
+            generator.set_length(length);
+            generator
+        }
+    };
+    let password = generator.generate()?.into_secret();
+    let printed = Zeroizing::new(password.as_str().unwrap_or_default().to_string());
+
+    let configuration = session.configuration();
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault_mut();
+    match matches.get_flag("in-place") {
+        true => vault.entry_mut(name)?.set_password(password),
+        false => store(
+            vault,
+            name,
+            Content::password(password),
+            matches.get_flag("force"),
+        )?,
+    }
+    open_vault.save()?;
+
+    if matches.get_flag("clip") {
+        let timeout = clipboard::copy_value(printed.as_bytes(), &configuration.read().unwrap())?;
+        print_copied(name, timeout);
+    } else if std::io::stdout().is_terminal() {
+        println!(
+            "\x1b[1mThe generated password for \x1b[4m{name}\x1b[24m is:\x1b[0m\n\
+             \x1b[1m\x1b[93m{}\x1b[0m",
+            printed.as_str()
+        );
+    } else {
+        println!(
+            "The generated password for {name} is:\n{}",
+            printed.as_str()
+        );
+    }
+
+    Ok(())
+}
+
+fn otp(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let arguments: Vec<&String> = matches
+        .get_many::<String>("arguments")
+        .expect("arguments are required")
+        .collect();
+    let name = match arguments.as_slice() {
+        [name] => *name,
+        [command, name] if command.as_str() == "code" => *name,
+        [command, ..] => {
+            return Err(CliError::InvalidArgument {
+                description: format!("unknown otp command `{command}`"),
+            })
+        }
+        [] => unreachable!("arguments are required"),
+    };
+
+    let configuration = session.configuration();
+    let open_vault = session.vault(matches)?;
+    let code = open_vault.vault_mut().otp_code(name)?;
+    reference::record_use(
+        open_vault,
+        [(name.as_str(), Field::Otp)],
+        &configuration.read().unwrap(),
+    )?;
+
+    match matches.get_flag("clip") {
+        true => {
+            let timeout =
+                clipboard::copy_value(code.code.as_bytes(), &configuration.read().unwrap())?;
+            print_copied(&format!("OTP code for {name}"), timeout);
+        }
+        false => println!("{}", code.code),
+    }
+
+    Ok(())
+}
+
+fn remove(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let name = matches
+        .get_one::<String>("name")
+        .expect("name is required")
+        .trim_matches('/');
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault_mut();
+
+    let paths: Vec<String> = match vault.entry(name) {
+        Ok(_) => vec![name.to_string()],
+        Err(_) => {
+            let entries = folder_entries(vault, name);
+            if entries.is_empty() {
+                return Err(VaultError::EntryNotFound {
+                    path: name.to_string(),
+                }
+                .into());
+            }
+            if !matches.get_flag("recursive") {
+                return Err(CliError::InvalidArgument {
+                    description: format!("{name} is a folder, use --recursive"),
+                });
+            }
+            entries
+                .into_iter()
+                .map(|path| format!("{name}/{path}"))
+                .collect()
+        }
+    };
+    if !matches.get_flag("force") {
+        let kind = if paths.len() == 1 { "" } else { "recursively " };
+        confirm(&format!(
+            "Are you sure you would like to {kind}delete {name}?"
+        ))?;
+    }
+
+    for path in paths {
+        vault.remove_entry(&path)?;
+    }
+
+    Ok(open_vault.save()?)
+}
+
+pub(super) fn pass(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    match matches.subcommand() {
+        None => show(matches, session, None, matches.get_one("clip").copied()),
+        Some(("show", matches)) => show(
+            matches,
+            session,
+            matches.get_one::<String>("name").map(String::as_str),
+            matches.get_one("clip").copied(),
+        ),
+        Some(("ls", matches)) => show(
+            matches,
+            session,
+            matches.get_one::<String>("subfolder").map(String::as_str),
+            None,
+        ),
+        Some(("insert", matches)) => insert(matches, session),
+        Some(("generate", matches)) => generate(matches, session),
+        Some(("otp", matches)) => otp(matches, session),
+        Some(("rm", matches)) => remove(matches, session),
+        // `pass NAME` is the same as `pass show NAME`, options may follow the name.
+        Some((name, external)) => {
+            let options = Command::new("show")
+                .no_binary_name(true)
+                .arg(clip_line_argument())
+                .try_get_matches_from(external.get_many::<String>("").unwrap_or_default())
+                .map_err(|error| CliError::InvalidArgument {
+                    description: error.to_string(),
+                })?;
+            let clip = matches
+                .get_one("clip")
+                .or_else(|| options.get_one("clip"))
+                .copied();
+            show(matches, session, Some(name), clip)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_of_pass_are_accepted() {
+        let command = command();
+        command.clone().debug_assert();
+        let parse = |arguments: &[&str]| {
+            command
+                .clone()
+                .try_get_matches_from(std::iter::once("pass").chain(arguments.iter().copied()))
+        };
+
+        let matches = parse(&["-v", "vault", "show", "--clip=2", "mail/work"]).unwrap();
+        let (_, show) = matches.subcommand().unwrap();
+        assert_eq!(show.get_one::<usize>("clip"), Some(&2));
+        assert_eq!(
+            show.get_one::<std::path::PathBuf>("vault"),
+            Some(&std::path::PathBuf::from("vault"))
+        );
+
+        let matches = parse(&["show", "-c", "mail/work"]).unwrap();
+        let (_, show) = matches.subcommand().unwrap();
+        assert_eq!(show.get_one::<usize>("clip"), Some(&1));
+        assert_eq!(show.get_one::<String>("name").unwrap(), "mail/work");
+
+        let matches = parse(&["-c", "mail/work"]).unwrap();
+        assert_eq!(matches.get_one::<usize>("clip"), Some(&1));
+        assert_eq!(matches.subcommand_name(), Some("mail/work"));
+
+        assert!(parse(&["insert", "-m", "-f", "mail/work"]).is_ok());
+        assert!(parse(&["generate", "-n", "-c", "mail/work", "16"]).is_ok());
+        assert!(parse(&["generate", "-i", "-f", "mail/work"]).is_err());
+        assert!(parse(&["otp", "code", "mail/work"]).is_ok());
+        assert!(parse(&["rm", "-rf", "mail"]).is_ok());
+
+        let expanded = expand_clip_lines(
+            ["pass", "-c2", "mail", "-c", "-cx", "--", "-c3"]
+                .map(OsString::from)
+                .to_vec(),
+        );
+        assert_eq!(
+            expanded,
+            ["pass", "--clip=2", "mail", "-c", "-cx", "--", "-c3"]
+        );
+    }
+
+    #[test]
+    fn content_is_read_back() {
+        let text = "hunter2\n\
+                    Username: alice\n\
+                    url: https://mail.example.com\n\
+                    otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP&issuer=Example\n\
+                    pin: 1234\n\
+                    \n\
+                    recovery codes in the safe\n";
+        let parsed = parse_content(text).unwrap();
+        assert_eq!(parsed.password.as_ref(), b"hunter2");
+        assert_eq!(parsed.username, "alice");
+        assert_eq!(parsed.urls, ["https://mail.example.com"]);
+        assert_eq!(parsed.otp.as_ref().unwrap().issuer(), Some("Example"));
+        assert_eq!(
+            parsed.notes.as_str(),
+            "pin: 1234\n\nrecovery codes in the safe"
+        );
+
+        let mut entry = Entry::new();
+        entry.add_tag("kept");
+        parsed.apply(&mut entry);
+        assert!(entry.tags().contains("kept"));
+        let written = content(&entry);
+        assert!(written.starts_with(
+            "hunter2\nlogin: alice\nurl: https://mail.example.com\notpauth://totp/Example:alice?"
+        ));
+        assert!(written.ends_with("\npin: 1234\n\nrecovery codes in the safe\n"));
+
+        let reparsed = parse_content(&written).unwrap();
+        assert_eq!(reparsed.otp, entry.otp().cloned());
+        assert_eq!(reparsed.notes.as_str(), entry.notes());
+
+        Content::password(SafeBuffer::from("new".to_string())).apply(&mut entry);
+        assert_eq!(content(&entry).as_str(), "new\n");
+        assert!(parse_content("x\notpauth://totp/broken").is_err());
+    }
+
+    #[test]
+    fn entries_are_listed_as_tree() {
+        let tree = Tree::new(
+            [
+                "db/prod",
+                "db/staging",
+                "dns",
+                "mail/work/alice",
+                "mail/home",
+            ]
+            .into_iter(),
+        );
+        let mut output = String::new();
+        tree.format("", &mut output);
+
+        assert_eq!(
+            output,
+            "├── db\n\
+             │   ├── prod\n\
+             │   └── staging\n\
+             ├── dns\n\
+             └── mail\n    \
+                 ├── home\n    \
+                 └── work\n        \
+                     └── alice\n"
+        );
+
+        let mut vault = Vault::new();
+        for path in ["db/prod", "db/staging", "dbx"] {
+            vault.add_entry(path, Entry::new()).unwrap();
+        }
+        assert_eq!(folder_entries(&vault, "db"), ["prod", "staging"]);
+        assert_eq!(folder_entries(&vault, "").len(), 3);
+    }
+}
@@ -0,0 +1,253 @@
+use super::output::{print_json, Format, ProfileOutput, RequiredClassOutput};
+use super::{CliError, Session};
+use crate::generator::{CharacterClass, PasswordPolicy};
+use clap::ArgMatches;
+
+/// Parses class given in arguments - `uppercase`, `lowercase`, `digits`, `symbols` or
+/// characters in brackets like `[-_.]`.
+fn parse_class(text: &str) -> Result<CharacterClass, CliError> {
+    match text {
+        "uppercase" => Ok(CharacterClass::Uppercase),
+        "lowercase" => Ok(CharacterClass::Lowercase),
+        "digits" => Ok(CharacterClass::Digits),
+        "symbols" => Ok(CharacterClass::Symbols),
+        _ => match text
+            .strip_prefix('[')
+            .and_then(|text| text.strip_suffix(']'))
+        {
+            Some(characters) if !characters.is_empty() => {
+                Ok(CharacterClass::Custom(characters.to_string()))
+            }
+            _ => Err(CliError::InvalidArgument {
+                description: format!(
+                    "invalid character class `{text}`, use uppercase, lowercase, digits, \
+                     symbols or characters in brackets like [-_.]"
+                ),
+            }),
+        },
+    }
+}
+
+/// Formats class the way [`parse_class`] reads it.
+fn format_class(class: &CharacterClass) -> String {
+    match class {
+        CharacterClass::Uppercase => "uppercase".to_string(),
+        CharacterClass::Lowercase => "lowercase".to_string(),
+        CharacterClass::Digits => "digits".to_string(),
+        CharacterClass::Symbols => "symbols".to_string(),
+        CharacterClass::Custom(characters) => format!("[{characters}]"),
+    }
+}
+
+/// Builds policy from arguments of `profile set`. It is checked by generating
+/// a password, so profiles which cannot be followed are not saved.
+fn policy(matches: &ArgMatches) -> Result<PasswordPolicy, CliError> {
+    let max_length = *matches
+        .get_one::<usize>("max-length")
+        .expect("maximal length is required");
+    let min_length = matches
+        .get_one::<usize>("min-length")
+        .copied()
+        .unwrap_or(max_length);
+
+    let mut policy = PasswordPolicy::new(min_length, max_length);
+    for class in matches.get_many::<String>("allow").unwrap_or_default() {
+        policy = policy.allow(parse_class(class)?);
+    }
+    for requirement in matches.get_many::<String>("require").unwrap_or_default() {
+        let (class, minimum) = requirement
+            .rsplit_once('=')
+            .and_then(|(class, minimum)| Some((class, minimum.parse().ok()?)))
+            .ok_or_else(|| CliError::InvalidArgument {
+                description: format!("invalid requirement `{requirement}`, use CLASS=COUNT"),
+            })?;
+        policy = policy.require(parse_class(class)?, minimum);
+    }
+    for pattern in matches.get_many::<String>("forbid").unwrap_or_default() {
+        policy = policy.forbid(pattern.as_str());
+    }
+    if let Some(template) = matches.get_one::<String>("template") {
+        policy = policy.template(template.as_str());
+    }
+
+    policy.generate()?;
+    Ok(policy)
+}
+
+/// Arguments of `profile set` which create `policy`.
+fn policy_arguments(policy: &PasswordPolicy) -> Vec<String> {
+    let (min_length, max_length) = policy.length_range();
+    let mut arguments = vec![
+        "--min-length".to_string(),
+        min_length.to_string(),
+        "--max-length".to_string(),
+        max_length.to_string(),
+    ];
+    for class in policy.allowed() {
+        arguments.extend(["--allow".to_string(), format_class(class)]);
+    }
+    for (class, minimum) in policy.required() {
+        arguments.extend([
+            "--require".to_string(),
+            format!("{}={minimum}", format_class(class)),
+        ]);
+    }
+    for pattern in policy.forbidden() {
+        arguments.extend(["--forbid".to_string(), pattern.clone()]);
+    }
+    if let Some(template) = policy.template_pattern() {
+        arguments.extend(["--template".to_string(), template.to_string()]);
+    }
+
+    arguments
+}
+
+pub(super) fn profile(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("list", matches)) => {
+            let open_vault = session.vault(matches)?;
+            let profiles = open_vault.vault().profiles();
+            match Format::of(matches) {
+                Format::Json => print_json(
+                    &profiles
+                        .map(|(name, policy)| ProfileOutput {
+                            name,
+                            min_length: policy.length_range().0,
+                            max_length: policy.length_range().1,
+                            allowed: policy.allowed().iter().map(format_class).collect(),
+                            required: policy
+                                .required()
+                                .iter()
+                                .map(|(class, minimum)| RequiredClassOutput {
+                                    class: format_class(class),
+                                    minimum: *minimum,
+                                })
+                                .collect(),
+                            forbidden: policy.forbidden(),
+                            template: policy.template_pattern(),
+                        })
+                        .collect::<Vec<_>>(),
+                )?,
+                Format::Text => profiles.for_each(|(name, policy)| {
+                    let arguments = policy_arguments(policy);
+                    let arguments = shlex::try_join(arguments.iter().map(String::as_str))
+                        .unwrap_or_else(|_| arguments.join(" "));
+                    println!("{name}\t{arguments}");
+                }),
+            }
+        }
+        Some(("set", matches)) => {
+            let policy = policy(matches)?;
+            let name = matches.get_one::<String>("name").expect("name is required");
+
+            let open_vault = session.vault(matches)?;
+            open_vault.vault_mut().set_profile(name.as_str(), policy);
+            open_vault.save()?;
+        }
+        Some(("remove", matches)) => {
+            let name = matches.get_one::<String>("name").expect("name is required");
+
+            let open_vault = session.vault(matches)?;
+            open_vault.vault_mut().remove_profile(name)?;
+            open_vault.save()?;
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::GeneratorError;
+
+    fn arguments(arguments: &[&str]) -> ArgMatches {
+        let matches = super::super::command()
+            .try_get_matches_from(
+                ["rustypass", "profile", "set", "-v", "vault.rp", "intranet"]
+                    .iter()
+                    .chain(arguments),
+            )
+            .unwrap();
+        let (_, matches) = matches.subcommand().unwrap();
+        matches.subcommand().unwrap().1.clone()
+    }
+
+    #[test]
+    fn policy_is_built_from_arguments() {
+        let matches = arguments(&[
+            "--min-length",
+            "12",
+            "--max-length",
+            "16",
+            "--forbid",
+            "&",
+            "--require",
+            "digits=2",
+            "--template",
+            "[A-Za-z].{11,15}",
+        ]);
+        let policy = policy(&matches).unwrap();
+        assert_eq!(
+            policy,
+            PasswordPolicy::new(12, 16)
+                .forbid("&")
+                .require(CharacterClass::Digits, 2)
+                .template("[A-Za-z].{11,15}")
+        );
+
+        // Listed arguments create the same policy.
+        let listed = policy_arguments(&policy);
+        let listed: Vec<&str> = listed.iter().map(String::as_str).collect();
+        assert_eq!(super::policy(&arguments(&listed)).unwrap(), policy);
+
+        let policy = super::policy(&arguments(&[
+            "--max-length",
+            "8",
+            "--allow",
+            "lowercase",
+            "--allow",
+            "[-_]",
+            "--require",
+            "[-_]=1",
+        ]))
+        .unwrap();
+        assert_eq!(policy.length_range(), (8, 8));
+        assert_eq!(
+            policy.allowed(),
+            [
+                CharacterClass::Lowercase,
+                CharacterClass::Custom("-_".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        for invalid in [
+            &["--max-length", "8", "--allow", "letters"][..],
+            &["--max-length", "8", "--allow", "[]"],
+            &["--max-length", "8", "--require", "digits"],
+            &["--max-length", "8", "--require", "digits=two"],
+        ] {
+            assert!(matches!(
+                policy(&arguments(invalid)),
+                Err(CliError::InvalidArgument { .. })
+            ));
+        }
+
+        assert!(matches!(
+            policy(&arguments(&["--max-length", "4", "--require", "digits=5"])),
+            Err(CliError::GeneratorError(
+                GeneratorError::UnsatisfiableRequirements { .. }
+            ))
+        ));
+        assert!(matches!(
+            policy(&arguments(&["--max-length", "4", "--template", "[ab"])),
+            Err(CliError::GeneratorError(
+                GeneratorError::InvalidTemplate { .. }
+            ))
+        ));
+    }
+}
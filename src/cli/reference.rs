@@ -0,0 +1,197 @@
+use super::CliError;
+use crate::configuration::ProgramConfiguration;
+use crate::otp::OtpKind;
+use crate::storage::{OpenVault, Vault, VaultError};
+use zeroize::Zeroizing;
+
+/// Field of an entry which can be referenced by `exec` and `render` commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Field {
+    Password,
+    Username,
+    /// First address of the entry.
+    Url,
+    Notes,
+    /// Current one-time code.
+    Otp,
+}
+
+impl Field {
+    /// Names accepted by [`Field::parse`], for error messages.
+    pub(super) const NAMES: &'static str = "password, username, url, notes or otp";
+
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "password" => Some(Self::Password),
+            "username" => Some(Self::Username),
+            "url" => Some(Self::Url),
+            "notes" => Some(Self::Notes),
+            "otp" => Some(Self::Otp),
+            _ => None,
+        }
+    }
+
+    /// Secret values may be masked in output. Notes are not, they usually hold common text
+    /// which would be masked everywhere it appears.
+    pub(super) fn is_secret(&self) -> bool {
+        matches!(self, Self::Password | Self::Otp)
+    }
+}
+
+/// Checks that the field of the entry has a value, without using the entry.
+pub(super) fn check(vault: &Vault, path: &str, field: Field) -> Result<(), CliError> {
+    let entry = vault.entry(path)?;
+    match field {
+        Field::Url if entry.urls().is_empty() => Err(CliError::InvalidArgument {
+            description: format!("entry `{path}` has no address"),
+        }),
+        Field::Otp if entry.otp().is_none() => Err(VaultError::OtpNotConfigured {
+            path: path.to_string(),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Returns value of the field. Reading password or one-time code counts as use of the
+/// entry, it is recorded by [`record_use`].
+pub(super) fn resolve(
+    vault: &mut Vault,
+    path: &str,
+    field: Field,
+) -> Result<Zeroizing<String>, CliError> {
+    check(vault, path, field)?;
+    let entry = vault.entry(path)?;
+    let value = match field {
+        Field::Password => String::from_utf8_lossy(entry.password().as_ref()).into_owned(),
+        Field::Username => entry.username().to_string(),
+        Field::Url => entry.urls()[0].clone(),
+        Field::Notes => entry.notes().to_string(),
+        Field::Otp => vault.otp_code(path)?.code,
+    };
+
+    Ok(Zeroizing::new(value))
+}
+
+/// Records use of entries whose password or one-time code was read and saves the vault,
+/// unless recording is disabled in configuration. Values were already read, so vault which
+/// cannot be written (e.g. read-only shared one) is only reported. Vault is always saved
+/// after HOTP code was read, as its counter must not be reused.
+pub(super) fn record_use<'a>(
+    open_vault: &mut OpenVault,
+    used: impl IntoIterator<Item = (&'a str, Field)>,
+    configuration: &ProgramConfiguration,
+) -> Result<(), CliError> {
+    let vault = open_vault.vault_mut();
+    let mut recorded = false;
+    let mut counter_advanced = false;
+    for (path, field) in used {
+        if !matches!(field, Field::Password | Field::Otp) {
+            continue;
+        }
+        if configuration.get_record_use() {
+            vault.mark_used(path)?;
+            recorded = true;
+        }
+        counter_advanced |= field == Field::Otp
+            && vault
+                .entry(path)?
+                .otp()
+                .is_some_and(|otp| matches!(otp.kind(), OtpKind::Hotp { .. }));
+    }
+
+    if counter_advanced {
+        open_vault.save()?;
+    } else if recorded {
+        if let Err(error) = open_vault.save() {
+            eprintln!(
+                "warning: use of entries was not recorded: {}",
+                CliError::from(error)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::{EncryptionAlgorithm, HashAlgorithm, KeyDerivationAlgorithm};
+    use crate::otp::OtpSecret;
+    use crate::storage::{Entry, SafeBuffer, VaultCreator};
+
+    #[test]
+    fn use_is_recorded_only_when_enabled() {
+        let path = std::env::temp_dir().join(format!("rustypass-use-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        let mut creator = VaultCreator::new(
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            HashAlgorithm::Sha256,
+            KeyDerivationAlgorithm::Argon2id,
+        )
+        .unwrap();
+        creator
+            .key_derivation()
+            .set_option("memory size", "64")
+            .unwrap();
+        creator.create(&path, &password, &[]).unwrap();
+
+        let mut open_vault = OpenVault::open(&path, &password).unwrap();
+        let mut token = Entry::new();
+        token.set_otp(Some(
+            OtpSecret::hotp(b"12345678901234567890".to_vec().into(), 0).unwrap(),
+        ));
+        open_vault.vault_mut().add_entry("token", token).unwrap();
+        open_vault
+            .vault_mut()
+            .add_entry("mail", Entry::new())
+            .unwrap();
+        open_vault.save().unwrap();
+        let saved = std::fs::read(&path).unwrap();
+
+        let disabled: ProgramConfiguration =
+            serde_json::from_str(r#"{"language": "USEnglish", "record_use": false}"#).unwrap();
+        record_use(&mut open_vault, [("mail", Field::Password)], &disabled).unwrap();
+        assert!(open_vault
+            .vault()
+            .entry("mail")
+            .unwrap()
+            .last_used()
+            .is_none());
+        assert_eq!(std::fs::read(&path).unwrap(), saved);
+
+        // Counter of HOTP is saved even if use is not recorded.
+        open_vault.vault_mut().otp_code("token").unwrap();
+        record_use(&mut open_vault, [("token", Field::Otp)], &disabled).unwrap();
+        assert_ne!(std::fs::read(&path).unwrap(), saved);
+        let mut reopened = OpenVault::open(&path, &password).unwrap();
+        assert_eq!(
+            reopened.vault_mut().otp_code("token").unwrap().code,
+            "287082"
+        );
+        assert!(reopened
+            .vault()
+            .entry("token")
+            .unwrap()
+            .last_used()
+            .is_none());
+
+        let enabled = ProgramConfiguration::default();
+        record_use(
+            &mut open_vault,
+            [("mail", Field::Password), ("mail", Field::Username)],
+            &enabled,
+        )
+        .unwrap();
+        let reopened = OpenVault::open(&path, &password).unwrap();
+        assert!(reopened
+            .vault()
+            .entry("mail")
+            .unwrap()
+            .last_used()
+            .is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use super::output::{print_json, Format, SavedSearchOutput};
+use super::{CliError, Session};
+use crate::search::{Query, SearchIndex};
+use clap::ArgMatches;
+
+/// Quotes `word` given as one argument when it is a phrase of plain words, so they
+/// are searched together. Words with conditions, operators or quotes are left as they are.
+fn query_term(word: &str) -> String {
+    let is_plain = |part: &str| {
+        Query::parse(part).is_ok_and(|query| query.free_text() == [part.to_lowercase()])
+    };
+    if word.split_whitespace().count() > 1 && word.split_whitespace().all(is_plain) {
+        format!("\"{}\"", word.replace('\\', "\\\\"))
+    } else {
+        word.to_string()
+    }
+}
+
+/// Joins words of `query` argument, they are usually split by the shell. Each word stays
+/// a separate term, so `find "home router" admin` searches for the phrase and the word.
+fn query_text(matches: &ArgMatches) -> Option<String> {
+    matches.get_many::<String>("query").map(|words| {
+        words
+            .map(|word| query_term(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    })
+}
+
+pub(super) fn parse_query(text: &str) -> Result<Query, CliError> {
+    Query::parse(text).map_err(|error| CliError::QueryError {
+        query: text.to_string(),
+        error,
+    })
+}
+
+pub(super) fn find(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    // Query is checked before the slow key derivation.
+    let query = query_text(matches)
+        .map(|text| parse_query(&text))
+        .transpose()?;
+    let limit = matches
+        .get_one::<usize>("limit")
+        .copied()
+        .unwrap_or(usize::MAX);
+
+    let open_vault = session.vault(matches)?;
+    let vault = open_vault.vault();
+    let query = match (query, matches.get_one::<String>("saved")) {
+        (Some(query), _) => query,
+        (None, Some(name)) => vault.saved_search(name)?,
+        (None, None) => unreachable!("query or saved search is required"),
+    };
+
+    let index = SearchIndex::new(vault);
+    let results = index.find(vault, &query, limit);
+    match Format::of(matches) {
+        Format::Json => print_json(&results)?,
+        Format::Text => results
+            .iter()
+            .for_each(|result| println!("{}", result.path)),
+    }
+
+    Ok(())
+}
+
+pub(super) fn saved(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    match matches.subcommand() {
+        Some(("list", matches)) => {
+            let open_vault = session.vault(matches)?;
+            let searches = open_vault.vault().saved_searches();
+            match Format::of(matches) {
+                Format::Json => print_json(
+                    &searches
+                        .map(|(name, query)| SavedSearchOutput { name, query })
+                        .collect::<Vec<_>>(),
+                )?,
+                Format::Text => {
+                    searches.for_each(|(name, query)| println!("{name}\t{query}"));
+                }
+            }
+        }
+        Some(("set", matches)) => {
+            let text = query_text(matches).expect("query is required");
+            parse_query(&text)?;
+            let name = matches.get_one::<String>("name").expect("name is required");
+
+            let open_vault = session.vault(matches)?;
+            open_vault
+                .vault_mut()
+                .set_saved_search(name.as_str(), &text)?;
+            open_vault.save()?;
+        }
+        Some(("remove", matches)) => {
+            let name = matches.get_one::<String>("name").expect("name is required");
+
+            let open_vault = session.vault(matches)?;
+            open_vault.vault_mut().remove_saved_search(name)?;
+            open_vault.save()?;
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(arguments: &[&str]) -> String {
+        let matches = super::super::command()
+            .try_get_matches_from(
+                ["rustypass", "find", "-v", "vault.rp"]
+                    .iter()
+                    .chain(arguments),
+            )
+            .unwrap();
+        query_text(matches.subcommand().unwrap().1).unwrap()
+    }
+
+    #[test]
+    fn quoted_words_stay_one_term() {
+        let text = query(&["home router", "admin"]);
+        assert_eq!(text, "\"home router\" admin");
+        assert_eq!(
+            parse_query(&text).unwrap().free_text(),
+            ["home router", "admin"]
+        );
+
+        assert_eq!(query(&[r"D\data  backup"]), r#""D\\data  backup""#);
+        assert_eq!(
+            parse_query(&query(&[r"D\data backup"]))
+                .unwrap()
+                .free_text(),
+            [r"d\data backup"]
+        );
+
+        // Arguments which are queries on their own are not changed.
+        for argument in [
+            "tag:prod -tag:old",
+            "tag:prod OR tag:dev",
+            "(mail OR bank)",
+            "title:\"home router\" admin",
+        ] {
+            assert_eq!(query(&[argument]), argument);
+        }
+        assert_eq!(query(&["tag:prod", "-tag:old"]), "tag:prod -tag:old");
+    }
+}
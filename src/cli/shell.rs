@@ -0,0 +1,358 @@
+use super::{command, execute, prompt_password, CliError, Session};
+use crate::storage::OpenVault;
+use clap::{ArgMatches, Command};
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Config, Context, Editor, Helper};
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+/// Commands which do not work with the unlocked vault. `upgrade` and `rekey` re-encrypt
+/// the vault, so the unlocked one would be saved with outdated key.
+const UNAVAILABLE_COMMANDS: &[&str] = &[
+    "init",
+    "shell",
+    "clear-clipboard",
+    "pass",
+    "tui",
+    "upgrade",
+    "rekey",
+];
+
+/// Options with possibly secret values, lines with them are not added to history.
+const SECRET_OPTIONS: &[&str] = &["--notes"];
+
+/// How often [`lock_when_idle`] checks the time of the last command.
+const LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Vault unlocked by the shell. It is taken out while a command runs.
+struct Unlocked {
+    open_vault: Option<OpenVault>,
+    last_used: Instant,
+}
+
+/// Makes `vault` argument of the command and its subcommands optional and hidden,
+/// commands use the vault unlocked by the shell.
+fn without_vault(mut command: Command) -> Command {
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, without_vault);
+    }
+
+    let has_vault = command
+        .get_arguments()
+        .any(|argument| argument.get_id() == "vault");
+    match has_vault {
+        true => command.mut_arg("vault", |argument| argument.required(false).hide(true)),
+        false => command,
+    }
+}
+
+/// Commands accepted by the shell.
+fn shell_command() -> Command {
+    let program = command();
+    let shell = Command::new("rustypass")
+        .no_binary_name(true)
+        .subcommand_required(true)
+        .args(
+            program
+                .get_arguments()
+                .filter(|argument| argument.get_id() == "format")
+                .cloned(),
+        )
+        .subcommand(
+            Command::new("lock")
+                .about("Locks the vault, the next command asks for master password"),
+        )
+        .subcommand(
+            Command::new("exit")
+                .visible_alias("quit")
+                .about("Leaves the shell"),
+        );
+
+    program
+        .get_subcommands()
+        .filter(|subcommand| !UNAVAILABLE_COMMANDS.contains(&subcommand.get_name()))
+        .cloned()
+        .map(without_vault)
+        .fold(shell, Command::subcommand)
+}
+
+/// Checks whether the line may be added to history.
+fn is_recorded(arguments: &[String]) -> bool {
+    !arguments.iter().any(|argument| {
+        SECRET_OPTIONS.iter().any(|option| {
+            argument == option
+                || argument
+                    .strip_prefix(option)
+                    .is_some_and(|rest| rest.starts_with('='))
+        })
+    })
+}
+
+/// Returns paths starting with `prefix`. They are cut after the next group separator,
+/// so groups are completed one level at a time.
+fn complete_path<'a>(paths: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = paths
+        .filter(|path| path.starts_with(prefix))
+        .map(|path| match path[prefix.len()..].find('/') {
+            Some(separator) => path[..prefix.len() + separator + 1].to_string(),
+            None => path.to_string(),
+        })
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    candidates
+}
+
+/// Completes names of commands and paths of entries.
+struct ShellHelper {
+    commands: Vec<String>,
+    unlocked: Arc<Mutex<Unlocked>>,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        position: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..position]
+            .rfind(char::is_whitespace)
+            .map_or(0, |index| index + 1);
+        let word = &line[start..position];
+
+        let candidates = match line[..start].trim().is_empty() {
+            true => self
+                .commands
+                .iter()
+                .filter(|command| command.starts_with(word))
+                .cloned()
+                .collect(),
+            false => match &self.unlocked.lock().unwrap().open_vault {
+                Some(open_vault) => complete_path(
+                    open_vault.vault().entries().map(|(path, _)| path.as_str()),
+                    word,
+                ),
+                None => Vec::new(),
+            },
+        };
+
+        Ok((
+            start,
+            candidates
+                .into_iter()
+                .map(|candidate| Pair {
+                    display: candidate.clone(),
+                    replacement: candidate,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// Locks the vault after `timeout` without commands. Dropping it erases the key and
+/// entries from memory. Ends together with the shell.
+fn lock_when_idle(unlocked: Weak<Mutex<Unlocked>>, timeout: Duration) {
+    while let Some(unlocked) = unlocked.upgrade() {
+        {
+            let mut unlocked = unlocked.lock().unwrap();
+            if unlocked.open_vault.is_some() && unlocked.last_used.elapsed() >= timeout {
+                unlocked.open_vault = None;
+            }
+        }
+        drop(unlocked);
+        std::thread::sleep(LOCK_CHECK_INTERVAL);
+    }
+}
+
+fn unlock(path: &Path) -> Result<OpenVault, CliError> {
+    Ok(OpenVault::open(
+        path,
+        &prompt_password("Master password: ")?,
+    )?)
+}
+
+fn readline_error(error: ReadlineError) -> CliError {
+    CliError::IoError {
+        description: error.to_string(),
+    }
+}
+
+/// Reads commands until `exit` or end of input, the vault is unlocked only once.
+pub(super) fn shell(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let configuration = session.configuration();
+    let path = session.vault(matches)?.path().to_path_buf();
+    let unlocked = Arc::new(Mutex::new(Unlocked {
+        open_vault: session.open_vault.take(),
+        last_used: Instant::now(),
+    }));
+    if let Some(timeout) = configuration.read().unwrap().get_lock_timeout() {
+        let unlocked = Arc::downgrade(&unlocked);
+        std::thread::spawn(move || lock_when_idle(unlocked, timeout));
+    }
+
+    let shell_command = shell_command();
+    let mut commands: Vec<String> = shell_command
+        .get_subcommands()
+        .map(|command| command.get_name().to_string())
+        .collect();
+    commands.push("help".to_string());
+    // History is not saved, it is kept only until the shell ends.
+    let config = Config::builder()
+        .auto_add_history(false)
+        .history_ignore_space(true)
+        .build();
+    let mut editor: Editor<ShellHelper, DefaultHistory> =
+        Editor::with_config(config).map_err(readline_error)?;
+    editor.set_helper(Some(ShellHelper {
+        commands,
+        unlocked: Arc::clone(&unlocked),
+    }));
+    eprintln!(
+        "Vault {} is unlocked, `help` lists commands.",
+        path.display()
+    );
+
+    loop {
+        let prompt = match unlocked.lock().unwrap().open_vault.is_some() {
+            true => "rustypass> ",
+            false => "rustypass (locked)> ",
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => Zeroizing::new(line),
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(readline_error(error)),
+        };
+        let arguments = match shlex::split(&line) {
+            Some(arguments) => Zeroizing::new(arguments),
+            None => {
+                eprintln!("error: quote is not closed");
+                continue;
+            }
+        };
+        if arguments.is_empty() {
+            continue;
+        }
+        if is_recorded(&arguments) {
+            editor
+                .add_history_entry(line.as_str())
+                .map_err(readline_error)?;
+        }
+
+        let matches = match shell_command.clone().try_get_matches_from(arguments.iter()) {
+            Ok(matches) => matches,
+            Err(error) => {
+                let _ = error.print();
+                continue;
+            }
+        };
+        let (name, matches) = matches.subcommand().expect("subcommand is required");
+        match name {
+            "exit" => break,
+            "lock" => {
+                unlocked.lock().unwrap().open_vault = None;
+                continue;
+            }
+            _ => (),
+        }
+
+        let open_vault = unlocked.lock().unwrap().open_vault.take();
+        let open_vault = match open_vault {
+            Some(open_vault) => open_vault,
+            None => match unlock(&path) {
+                Ok(open_vault) => open_vault,
+                Err(error) => {
+                    eprintln!("error: {error}");
+                    continue;
+                }
+            },
+        };
+        let mut command_session = Session {
+            configuration,
+            open_vault: Some(open_vault),
+            persistent: true,
+        };
+        match execute(name, matches, &mut command_session) {
+            Ok(0) => (),
+            Ok(code) => eprintln!("exit status {code}"),
+            Err(error) => eprintln!("error: {error}"),
+        }
+
+        let mut unlocked = unlocked.lock().unwrap();
+        unlocked.open_vault = command_session.open_vault;
+        unlocked.last_used = Instant::now();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_commands_do_not_need_vault() {
+        let command = shell_command();
+        command.clone().debug_assert();
+
+        let matches = command
+            .clone()
+            .try_get_matches_from(["get", "mail/work", "username", "--format", "json"])
+            .unwrap();
+        let (name, matches) = matches.subcommand().unwrap();
+        assert_eq!(name, "get");
+        assert!(matches.get_one::<std::path::PathBuf>("vault").is_none());
+        assert!(command
+            .clone()
+            .try_get_matches_from(["saved", "list"])
+            .is_ok());
+        assert!(command.clone().try_get_matches_from(["lock"]).is_ok());
+        assert!(command.clone().try_get_matches_from(["upgrade"]).is_err());
+        assert!(command.try_get_matches_from(["init"]).is_err());
+    }
+
+    #[test]
+    fn secrets_are_not_recorded() {
+        let arguments = |line: &str| shlex::split(line).unwrap();
+        assert!(is_recorded(&arguments("edit mail -u alice")));
+        assert!(!is_recorded(&arguments("edit mail --notes 'pin 1234'")));
+        assert!(!is_recorded(&arguments("add mail --notes=secret")));
+        assert!(is_recorded(&arguments("find --notes-like x")));
+    }
+
+    #[test]
+    fn paths_are_completed_by_groups() {
+        let paths = ["db/prod", "db/staging", "dns", "mail/work"];
+        assert_eq!(complete_path(paths.into_iter(), "d"), ["db/", "dns"]);
+        assert_eq!(
+            complete_path(paths.into_iter(), "db/"),
+            ["db/prod", "db/staging"]
+        );
+        assert!(complete_path(paths.into_iter(), "x").is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+use super::reference::{self, Field};
+use super::{CliError, Session};
+use crate::storage::Vault;
+use clap::ArgMatches;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Word starting references to the vault. Other `{{ ... }}` placeholders belong to
+/// other tools and are kept as they are.
+const KEYWORD: &str = "vault";
+
+/// Reference to a field of vault entry, written as `{{ vault "PATH" "FIELD" }}`.
+/// Field may be left out, password is used then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reference {
+    path: String,
+    field: Field,
+    /// Byte offset of `{{` in the template.
+    start: usize,
+    /// Byte offset after `}}`.
+    end: usize,
+}
+
+/// Problem with the template, `offset` points at the reference or the invalid part of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Issue {
+    offset: usize,
+    description: String,
+}
+
+impl Issue {
+    fn new(offset: usize, description: impl Into<String>) -> Self {
+        Self {
+            offset,
+            description: description.into(),
+        }
+    }
+}
+
+/// Parses quoted arguments of a reference starting at `offset`. Returns them together
+/// with offset after the closing `}}`.
+fn parse_arguments(template: &str, mut offset: usize) -> Result<(Vec<String>, usize), Issue> {
+    let mut arguments = Vec::new();
+    loop {
+        let rest = &template[offset..];
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+
+        if trimmed.starts_with("}}") {
+            return Ok((arguments, offset + 2));
+        }
+        if !trimmed.starts_with('"') {
+            return Err(match trimmed.is_empty() {
+                true => Issue::new(offset, "reference is not closed with `}}`"),
+                false => Issue::new(offset, "expected quoted argument or `}}`"),
+            });
+        }
+
+        let mut argument = String::new();
+        let mut characters = trimmed.char_indices().skip(1);
+        let end = loop {
+            match characters.next() {
+                Some((index, '"')) => break index,
+                Some((_, '\\')) => match characters.next() {
+                    Some((_, character)) => argument.push(character),
+                    None => return Err(Issue::new(offset, "quoted argument is not closed")),
+                },
+                Some((_, character)) => argument.push(character),
+                None => return Err(Issue::new(offset, "quoted argument is not closed")),
+            }
+        };
+        arguments.push(argument);
+        offset += end + 1;
+    }
+}
+
+fn reference(arguments: Vec<String>, start: usize, end: usize) -> Result<Reference, Issue> {
+    let mut arguments = arguments.into_iter();
+    let path = arguments
+        .next()
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| Issue::new(start, "path of the entry is missing"))?;
+    let field = match arguments.next() {
+        None => Field::Password,
+        Some(name) => Field::parse(&name).ok_or_else(|| {
+            Issue::new(
+                start,
+                format!("unknown field `{name}`, expected {}", Field::NAMES),
+            )
+        })?,
+    };
+    if arguments.next().is_some() {
+        return Err(Issue::new(
+            start,
+            "too many arguments, expected path and optional field",
+        ));
+    }
+
+    Ok(Reference {
+        path,
+        field,
+        start,
+        end,
+    })
+}
+
+/// Returns references to the vault found in the template, or all syntax errors in them.
+fn parse(template: &str) -> Result<Vec<Reference>, Vec<Issue>> {
+    let mut references = Vec::new();
+    let mut issues = Vec::new();
+    let mut position = 0;
+
+    while let Some(found) = template[position..].find("{{") {
+        let start = position + found;
+        position = start + 2;
+        let Some(rest) = template[position..].trim_start().strip_prefix(KEYWORD) else {
+            continue;
+        };
+        if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+
+        match parse_arguments(template, template.len() - rest.len()) {
+            Ok((arguments, end)) => {
+                position = end;
+                match reference(arguments, start, end) {
+                    Ok(reference) => references.push(reference),
+                    Err(issue) => issues.push(issue),
+                }
+            }
+            Err(issue) => {
+                // Rest of the broken reference is skipped, so following ones are checked too.
+                position = template[position..]
+                    .find("}}")
+                    .map_or(template.len(), |end| position + end + 2);
+                issues.push(issue);
+            }
+        }
+    }
+
+    match issues.is_empty() {
+        true => Ok(references),
+        false => Err(issues),
+    }
+}
+
+/// Formats issues as `TEMPLATE:LINE:COLUMN: DESCRIPTION` lines.
+fn describe(name: &Path, template: &str, issues: &[Issue]) -> String {
+    issues
+        .iter()
+        .map(|issue| {
+            let before = &template[..issue.offset];
+            let line = before.matches('\n').count() + 1;
+            let column = before
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+                + 1;
+            format!("{}:{line}:{column}: {}", name.display(), issue.description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks that all references can be resolved, without using the entries.
+fn check(vault: &Vault, references: &[Reference]) -> Vec<Issue> {
+    references
+        .iter()
+        .filter_map(|reference| {
+            reference::check(vault, &reference.path, reference.field)
+                .err()
+                .map(|error| Issue::new(reference.start, error.to_string()))
+        })
+        .collect()
+}
+
+/// Replaces references with values from the vault.
+fn render(
+    template: &str,
+    references: &[Reference],
+    vault: &mut Vault,
+) -> Result<Zeroizing<String>, CliError> {
+    let mut output = Zeroizing::new(String::with_capacity(template.len()));
+    let mut position = 0;
+    for reference in references {
+        output.push_str(&template[position..reference.start]);
+        output.push_str(&reference::resolve(
+            vault,
+            &reference.path,
+            reference.field,
+        )?);
+        position = reference.end;
+    }
+    output.push_str(&template[position..]);
+
+    Ok(output)
+}
+
+/// Writes the file readable only by its owner. Permissions of existing file are changed
+/// before anything is written to it.
+fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+pub(super) fn render_template(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let name = matches
+        .get_one::<PathBuf>("template")
+        .expect("template is required");
+    let template = std::fs::read_to_string(name).map_err(|error| CliError::IoError {
+        description: format!("cannot read {}: {error}", name.display()),
+    })?;
+    // Syntax is checked before the slow key derivation.
+    let references = parse(&template).map_err(|issues| CliError::TemplateError {
+        description: describe(name, &template, &issues),
+    })?;
+
+    let configuration = session.configuration();
+    let open_vault = session.vault(matches)?;
+    let issues = check(open_vault.vault(), &references);
+    if !issues.is_empty() {
+        return Err(CliError::UnresolvedReference {
+            description: describe(name, &template, &issues),
+        });
+    }
+    if matches.get_flag("check") {
+        eprintln!(
+            "{}: all {} references can be resolved",
+            name.display(),
+            references.len()
+        );
+        return Ok(());
+    }
+
+    let output = render(&template, &references, open_vault.vault_mut())?;
+    match matches.get_one::<PathBuf>("output") {
+        Some(path) => {
+            write_private(path, output.as_bytes()).map_err(|error| CliError::IoError {
+                description: format!("cannot write {}: {error}", path.display()),
+            })?
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(output.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+
+    reference::record_use(
+        open_vault,
+        references
+            .iter()
+            .map(|reference| (reference.path.as_str(), reference.field)),
+        &configuration.read().unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Entry, SafeBuffer};
+
+    #[test]
+    fn references_are_parsed() {
+        let template = "user: {{ vault \"db/prod\" \"username\" }}\n\
+                        password: {{vault \"db/prod\"}}\n\
+                        quoted: {{ vault \"say \\\"hi\\\"\" \"notes\" }}\n\
+                        helm: {{ .Values.vaulted }} {{ vaulted }}";
+        let references = parse(template).unwrap();
+
+        assert_eq!(references.len(), 3);
+        assert_eq!(references[0].path, "db/prod");
+        assert_eq!(references[0].field, Field::Username);
+        assert_eq!(
+            &template[references[0].start..references[0].end],
+            "{{ vault \"db/prod\" \"username\" }}"
+        );
+        assert_eq!(references[1].field, Field::Password);
+        assert_eq!(references[2].path, "say \"hi\"");
+    }
+
+    #[test]
+    fn syntax_errors_are_located() {
+        let template = "a: {{ vault \"db\" \"pass\" }}\n\
+                        b: {{ vault db }}\n\
+                        c: {{ vault }}\n\
+                        d: {{ vault \"db\" \"username\" \"x\" }}\n\
+                        e: {{ vault \"db";
+        let issues = parse(template).unwrap_err();
+
+        assert_eq!(
+            describe(Path::new("app.conf"), template, &issues),
+            "app.conf:1:4: unknown field `pass`, expected password, username, url, notes or otp\n\
+             app.conf:2:13: expected quoted argument or `}}`\n\
+             app.conf:3:4: path of the entry is missing\n\
+             app.conf:4:4: too many arguments, expected path and optional field\n\
+             app.conf:5:13: quoted argument is not closed"
+        );
+    }
+
+    #[test]
+    fn template_is_rendered() {
+        let mut vault = Vault::new();
+        let mut entry = Entry::new();
+        entry.set_username("app");
+        entry.set_password(SafeBuffer::from("hunter2".to_string()));
+        vault.add_entry("db/prod", entry).unwrap();
+
+        let template = "{{ vault \"db/prod\" \"username\" }}:{{ vault \"db/prod\" }}@{{ host }}";
+        let references = parse(template).unwrap();
+        assert!(check(&vault, &references).is_empty());
+        assert!(vault.entry("db/prod").unwrap().last_used().is_none());
+
+        let output = render(template, &references, &mut vault).unwrap();
+        assert_eq!(output.as_str(), "app:hunter2@{{ host }}");
+        assert!(vault.entry("db/prod").unwrap().last_used().is_none());
+
+        let template = "{{ vault \"db/prod\" \"url\" }} {{ vault \"db/test\" }}";
+        let issues = check(&vault, &parse(template).unwrap());
+        assert_eq!(
+            describe(Path::new("t"), template, &issues),
+            "t:1:1: entry `db/prod` has no address\nt:1:29: entry `db/test` not found"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join("rustypass-rendered-template");
+        std::fs::write(&path, "previous content").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, b"secret").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "secret");
+        std::fs::remove_file(path).unwrap();
+    }
+}
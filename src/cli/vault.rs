@@ -0,0 +1,284 @@
+use super::output::{print_json, Format, GeneratedOutput, VaultOutput};
+use super::{master_password, unlock_vault, warn_about_health, CliError, Session};
+use crate::cryptography::{
+    EncryptionAlgorithm, HashAlgorithm, KeyDerivationAlgorithm, OptionValue, PasswordHasherBuilder,
+    OUTPUT_LENGTH_OPTION,
+};
+use crate::generator::{PassphraseGenerator, PasswordGenerator};
+use crate::storage::{StorageError, VaultCreator, VaultHeader};
+use crate::strength::Score;
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+/// Sets key derivation options given as `NAME=VALUE` in `option` arguments.
+fn set_options(
+    builder: &mut dyn PasswordHasherBuilder,
+    matches: &ArgMatches,
+) -> Result<(), CliError> {
+    for option in matches.get_many::<String>("option").unwrap_or_default() {
+        let (name, value) = option
+            .split_once('=')
+            .ok_or_else(|| CliError::InvalidArgument {
+                description: format!("option `{option}` should have form NAME=VALUE"),
+            })?;
+        builder
+            .set_option(name.trim(), value.trim())
+            .map_err(StorageError::from)?;
+    }
+
+    Ok(())
+}
+
+/// Finds algorithm named `name` among `algorithms`, `kind` describes them in errors.
+fn parse_algorithm<T: std::fmt::Display + Clone>(
+    kind: &str,
+    name: &str,
+    algorithms: &[T],
+) -> Result<T, CliError> {
+    algorithms
+        .iter()
+        .find(|algorithm| algorithm.to_string().eq_ignore_ascii_case(name))
+        .cloned()
+        .ok_or_else(|| CliError::InvalidArgument {
+            description: format!(
+                "unknown {kind} algorithm `{name}`, use one of: {}",
+                algorithms
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        })
+}
+
+pub(super) fn init(matches: &ArgMatches) -> Result<(), CliError> {
+    let path = matches
+        .get_one::<PathBuf>("vault")
+        .expect("vault is required");
+
+    let mut creator = VaultCreator::recommended()?;
+    set_options(creator.key_derivation(), matches)?;
+    if !matches.get_flag("allow-weak") {
+        creator = creator.minimum_score(Score::Strong);
+    }
+
+    let password = master_password(matches, true)?;
+    let estimate = creator.create(path, &password, &[])?;
+    eprintln!(
+        "Vault {} created, strength of master password: {}",
+        path.display(),
+        estimate.score()
+    );
+
+    Ok(())
+}
+
+pub(super) fn open(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let open_vault = session.vault(matches)?;
+
+    if Format::of(matches) == Format::Json {
+        return print_json(&VaultOutput {
+            path: &open_vault.path().to_string_lossy(),
+            entries: open_vault.vault().entries().count(),
+            health_issues: open_vault.health_issues(),
+        });
+    }
+    println!(
+        "{}: {} entries",
+        open_vault.path().display(),
+        open_vault.vault().entries().count()
+    );
+
+    Ok(())
+}
+
+pub(super) fn upgrade(matches: &ArgMatches) -> Result<(), CliError> {
+    let (mut open_vault, password) = unlock_vault(matches)?;
+    let fixed = open_vault.upgrade(&password)?;
+    if fixed.is_empty() {
+        eprintln!("Vault already uses recommended settings");
+    }
+    for issue in fixed {
+        eprintln!("fixed: {issue}");
+    }
+
+    Ok(())
+}
+
+/// Changes `header` of the vault as `rekey` arguments ask. Settings which are not
+/// given stay as they are.
+fn rekeyed_header(mut header: VaultHeader, matches: &ArgMatches) -> Result<VaultHeader, CliError> {
+    if let Some(name) = matches.get_one::<String>("encryption") {
+        header.encryption_algorithm =
+            parse_algorithm("encryption", name, EncryptionAlgorithm::ALL)?;
+    }
+    if let Some(name) = matches.get_one::<String>("hash") {
+        header.hash_algorithm = parse_algorithm("hash", name, HashAlgorithm::ALL)?;
+    }
+    if let Some(name) = matches.get_one::<String>("key-derivation") {
+        let algorithm: KeyDerivationAlgorithm =
+            name.parse().map_err(|_| CliError::InvalidArgument {
+                description: format!("unknown key derivation algorithm `{name}`"),
+            })?;
+        // Current settings are kept, new algorithm takes those it understands.
+        if algorithm != header.key_derivation_algorithm {
+            let mut key_derivation = algorithm.builder();
+            for descriptor in key_derivation.options() {
+                if let Ok(value) = header.key_derivation.get_value(descriptor.name) {
+                    let _ = key_derivation.set_value(descriptor.name, value);
+                }
+            }
+            header.key_derivation_algorithm = algorithm;
+            header.key_derivation = key_derivation;
+        }
+    }
+    // Derived key has to fit the cipher, which may have changed.
+    header
+        .key_derivation
+        .set_value(
+            OUTPUT_LENGTH_OPTION,
+            OptionValue::Unsigned(header.encryption_algorithm.key_size() as u32),
+        )
+        .map_err(StorageError::from)?;
+    set_options(header.key_derivation.as_mut(), matches)?;
+
+    Ok(header)
+}
+
+pub(super) fn rekey(matches: &ArgMatches) -> Result<(), CliError> {
+    let (mut open_vault, password) = unlock_vault(matches)?;
+    let header = rekeyed_header(open_vault.header()?, matches)?;
+    let backup_path = open_vault.rekey(&password, header)?;
+    eprintln!(
+        "Vault {} re-encrypted, previous version was kept in {}",
+        open_vault.path().display(),
+        backup_path.display()
+    );
+    warn_about_health(&open_vault);
+
+    Ok(())
+}
+
+/// Starts terminal interface, it asks for the master password itself.
+pub(super) fn tui(matches: &ArgMatches, session: &Session) -> Result<(), CliError> {
+    let path = matches
+        .get_one::<PathBuf>("vault")
+        .expect("vault is required");
+    if !path.is_file() {
+        return Err(CliError::VaultNotFound { path: path.clone() });
+    }
+
+    Ok(crate::tui::run(path, session.configuration())?)
+}
+
+pub(super) fn generate(matches: &ArgMatches, session: &mut Session) -> Result<(), CliError> {
+    let generated = if let Some(name) = matches.get_one::<String>("profile") {
+        session.vault(matches)?.vault().profile(name)?.generate()?
+    } else if let Some(words) = matches.get_one::<usize>("words") {
+        let configuration = session.configuration().read().unwrap();
+        PassphraseGenerator::from_configuration(&configuration)
+            .word_count(*words)
+            .generate()?
+    } else {
+        let mut generator = PasswordGenerator::default();
+        if let Some(length) = matches.get_one::<usize>("length") {
+            generator.set_length(*length);
+        }
+        generator.generate()?
+    };
+
+    if Format::of(matches) == Format::Json {
+        return print_json(&GeneratedOutput {
+            secret: generated.secret().as_str().unwrap_or_default(),
+            entropy: generated.entropy(),
+        });
+    }
+    println!("{}", generated.secret().as_str().unwrap_or_default());
+    if matches.get_flag("entropy") {
+        eprintln!("Entropy: {:.1} bits", generated.entropy());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> VaultHeader {
+        let mut key_derivation = KeyDerivationAlgorithm::Argon2id
+            .builder_for(&EncryptionAlgorithm::ChaCha20Poly1305)
+            .unwrap();
+        key_derivation.set_option("memory size", "128").unwrap();
+        key_derivation.set_option("iterations", "5").unwrap();
+        VaultHeader {
+            encryption_algorithm: EncryptionAlgorithm::ChaCha20Poly1305,
+            hash_algorithm: HashAlgorithm::Sha256,
+            key_derivation_algorithm: KeyDerivationAlgorithm::Argon2id,
+            key_derivation,
+        }
+    }
+
+    fn arguments(arguments: &[&str]) -> ArgMatches {
+        let matches = super::super::command()
+            .try_get_matches_from(
+                ["rustypass", "rekey", "-v", "vault.rp"]
+                    .iter()
+                    .chain(arguments),
+            )
+            .unwrap();
+        matches.subcommand().unwrap().1.clone()
+    }
+
+    #[test]
+    fn rekey_keeps_settings_which_are_not_changed() {
+        let header = rekeyed_header(
+            header(),
+            &arguments(&[
+                "--encryption",
+                "aes256gcmsiv",
+                "--hash",
+                "Sha512",
+                "--key-derivation",
+                "Argon2id",
+                "-o",
+                "iterations=6",
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            header.encryption_algorithm,
+            EncryptionAlgorithm::Aes256GcmSiv
+        );
+        assert_eq!(header.hash_algorithm, HashAlgorithm::Sha512);
+        assert_eq!(
+            header.key_derivation.get_option("memory size").unwrap(),
+            "128"
+        );
+        assert_eq!(header.key_derivation.get_option("iterations").unwrap(), "6");
+        assert_eq!(
+            header
+                .key_derivation
+                .get_value(OUTPUT_LENGTH_OPTION)
+                .unwrap(),
+            OptionValue::Unsigned(EncryptionAlgorithm::Aes256GcmSiv.key_size() as u32)
+        );
+
+        // Settings are carried over to another algorithm.
+        let header = rekeyed_header(header, &arguments(&["--key-derivation", "Argon2i"])).unwrap();
+        assert_eq!(
+            header.key_derivation_algorithm,
+            KeyDerivationAlgorithm::Argon2i
+        );
+        assert_eq!(
+            header.key_derivation.get_option("memory size").unwrap(),
+            "128"
+        );
+        assert_eq!(header.key_derivation.get_option("iterations").unwrap(), "6");
+
+        assert!(matches!(
+            rekeyed_header(header, &arguments(&["--hash", "Md5"])),
+            Err(CliError::InvalidArgument { .. })
+        ));
+    }
+}
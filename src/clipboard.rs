@@ -0,0 +1,238 @@
+use crate::cryptography::{HashAlgorithm, HashStruct};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    /// No supported clipboard program was found.
+    Unavailable,
+    CommandFailed {
+        description: String,
+    },
+}
+
+impl std::fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unavailable => write!(
+                f,
+                "no clipboard found, install wl-clipboard (Wayland), xclip or xsel (X11)"
+            ),
+            Self::CommandFailed { description } => write!(f, "{description}"),
+        }
+    }
+}
+
+/// Environment variables set in graphical sessions.
+pub const WAYLAND_SESSION: &str = "WAYLAND_DISPLAY";
+pub const X11_SESSION: &str = "DISPLAY";
+
+/// System clipboard holding text copied by the user.
+pub trait Clipboard {
+    fn set(&mut self, content: &[u8]) -> Result<(), ClipboardError>;
+    /// Returns current content, empty if the clipboard holds nothing.
+    fn get(&mut self) -> Result<Zeroizing<Vec<u8>>, ClipboardError>;
+    fn clear(&mut self) -> Result<(), ClipboardError> {
+        self.set(&[])
+    }
+}
+
+/// Returns digest identifying copied value, so it can be recognized later without keeping it.
+pub fn fingerprint(content: &[u8]) -> Vec<u8> {
+    let mut hash = HashStruct::new(HashAlgorithm::Sha256);
+    hash.update(content);
+    hash.finalize()
+}
+
+/// Clears the clipboard if it still holds value with given [`fingerprint`].
+/// Returns `false` if the user copied something else in the meantime.
+pub fn clear_if_unchanged(
+    clipboard: &mut dyn Clipboard,
+    copied: &[u8],
+) -> Result<bool, ClipboardError> {
+    if fingerprint(&clipboard.get()?) != copied {
+        return Ok(false);
+    }
+    clipboard.clear()?;
+
+    Ok(true)
+}
+
+/// Clipboard kept in memory, for tests and systems without graphical session.
+#[derive(Default)]
+pub struct MemoryClipboard {
+    content: Zeroizing<Vec<u8>>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn set(&mut self, content: &[u8]) -> Result<(), ClipboardError> {
+        self.content = Zeroizing::new(content.to_vec());
+        Ok(())
+    }
+
+    fn get(&mut self) -> Result<Zeroizing<Vec<u8>>, ClipboardError> {
+        Ok(self.content.clone())
+    }
+}
+
+/// Clipboard of graphical session accessed through command line programs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandClipboard {
+    /// Program reading new content from standard input.
+    copy: &'static [&'static str],
+    /// Program writing content to standard output.
+    paste: &'static [&'static str],
+    /// Program emptying the clipboard, empty content is copied if there is none.
+    clear: Option<&'static [&'static str]>,
+}
+
+impl CommandClipboard {
+    pub const WAYLAND: Self = Self {
+        copy: &["wl-copy"],
+        paste: &["wl-paste", "--no-newline"],
+        clear: Some(&["wl-copy", "--clear"]),
+    };
+    pub const XCLIP: Self = Self {
+        copy: &["xclip", "-selection", "clipboard", "-in"],
+        paste: &["xclip", "-selection", "clipboard", "-out"],
+        clear: None,
+    };
+    pub const XSEL: Self = Self {
+        copy: &["xsel", "--clipboard", "--input"],
+        paste: &["xsel", "--clipboard", "--output"],
+        clear: Some(&["xsel", "--clipboard", "--delete"]),
+    };
+    pub const MACOS: Self = Self {
+        copy: &["pbcopy"],
+        paste: &["pbpaste"],
+        clear: None,
+    };
+
+    /// Returns clipboard of the current graphical session, Wayland is preferred over X11.
+    pub fn detect() -> Result<Self, ClipboardError> {
+        let candidates: &[(bool, Self)] = &[
+            (cfg!(target_os = "macos"), Self::MACOS),
+            (has_session(WAYLAND_SESSION), Self::WAYLAND),
+            (has_session(X11_SESSION), Self::XCLIP),
+            (has_session(X11_SESSION), Self::XSEL),
+        ];
+
+        candidates
+            .iter()
+            .find(|(available, clipboard)| *available && is_installed(clipboard.copy[0]))
+            .map(|(_, clipboard)| clipboard.clone())
+            .ok_or(ClipboardError::Unavailable)
+    }
+
+    fn command(arguments: &[&str]) -> Command {
+        let mut command = Command::new(arguments[0]);
+        command.args(&arguments[1..]);
+        command
+    }
+}
+
+/// Checks whether graphical session announced by the environment variable is running.
+pub fn has_session(variable: &str) -> bool {
+    std::env::var_os(variable).is_some_and(|value| !value.is_empty())
+}
+
+/// Checks whether the program is in one of `PATH` directories.
+pub fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|directory| directory.join(program).is_file())
+    })
+}
+
+fn failed(arguments: &[&str], error: impl std::fmt::Display) -> ClipboardError {
+    ClipboardError::CommandFailed {
+        description: format!("{} failed: {error}", arguments[0]),
+    }
+}
+
+impl Clipboard for CommandClipboard {
+    fn set(&mut self, content: &[u8]) -> Result<(), ClipboardError> {
+        // Programs keep running in background to serve the clipboard, so their output
+        // is not captured, waiting for it would never end.
+        let mut child = Self::command(self.copy)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| failed(self.copy, error))?;
+        let mut stdin = child.stdin.take().expect("standard input is piped");
+        stdin
+            .write_all(content)
+            .map_err(|error| failed(self.copy, error))?;
+        drop(stdin);
+
+        match child.wait().map_err(|error| failed(self.copy, error))? {
+            status if status.success() => Ok(()),
+            status => Err(failed(self.copy, status)),
+        }
+    }
+
+    /// Failure of the paste program is treated as empty clipboard, `wl-paste` fails when
+    /// nothing was copied.
+    fn get(&mut self) -> Result<Zeroizing<Vec<u8>>, ClipboardError> {
+        let output = Self::command(self.paste)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|error| failed(self.paste, error))?;
+        let content = Zeroizing::new(output.stdout);
+
+        match output.status.success() {
+            true => Ok(content),
+            false => Ok(Zeroizing::new(Vec::new())),
+        }
+    }
+
+    fn clear(&mut self) -> Result<(), ClipboardError> {
+        let Some(clear) = self.clear else {
+            return self.set(&[]);
+        };
+
+        match Self::command(clear)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|error| failed(clear, error))?
+        {
+            status if status.success() => Ok(()),
+            status => Err(failed(clear, status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clearing_works(clipboard: &mut dyn Clipboard) {
+        clipboard.set(b"hunter2").unwrap();
+        assert_eq!(clipboard.get().unwrap().as_slice(), b"hunter2");
+        let copied = fingerprint(b"hunter2");
+
+        clipboard.set(b"copied by user").unwrap();
+        assert!(!clear_if_unchanged(clipboard, &copied).unwrap());
+        assert_eq!(clipboard.get().unwrap().as_slice(), b"copied by user");
+
+        clipboard.set(b"hunter2").unwrap();
+        assert!(clear_if_unchanged(clipboard, &copied).unwrap());
+        assert!(clipboard.get().unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_copied_value_is_cleared() {
+        clearing_works(&mut MemoryClipboard::default());
+    }
+
+    /// Needs graphical session, e.g. `xvfb-run cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn system_clipboard_is_cleared() {
+        clearing_works(&mut CommandClipboard::detect().unwrap());
+    }
+}
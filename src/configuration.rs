@@ -3,13 +3,64 @@ use serde::{Deserialize, Serialize};
 use crate::language::Language;
 use std::ops::Deref;
 use std::sync::RwLock;
+use std::time::Duration;
 use std::{collections::HashMap, path::PathBuf};
 
 const CONFIGURATION_FOLDER_NAME: &'static str = "rustypass";
 
-#[derive(Default, Serialize, Deserialize)]
+const DEFAULT_CLIPBOARD_TIMEOUT: u64 = 45;
+const DEFAULT_LOCK_TIMEOUT: u64 = 300;
+
+fn default_clipboard_timeout() -> u64 {
+    DEFAULT_CLIPBOARD_TIMEOUT
+}
+
+fn default_lock_timeout() -> u64 {
+    DEFAULT_LOCK_TIMEOUT
+}
+
+fn default_record_use() -> bool {
+    true
+}
+
+/// Seconds to duration, 0 means the action is disabled.
+fn timeout(seconds: u64) -> Option<Duration> {
+    match seconds {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct ProgramConfiguration {
     language: Language,
+    /// Seconds after which copied secrets are removed from the clipboard, 0 keeps them.
+    #[serde(default = "default_clipboard_timeout")]
+    clipboard_timeout: u64,
+    /// Seconds of inactivity after which shell locks the vault, 0 keeps it unlocked.
+    #[serde(default = "default_lock_timeout")]
+    lock_timeout: u64,
+    /// Command line of the program choosing entry in `menu` command, e.g. `rofi -dmenu`.
+    /// Installed one is detected if it is not set.
+    #[serde(default)]
+    picker: Option<String>,
+    /// Reading a password or one-time code records time of use in the vault, so recently
+    /// used entries are found first. It rewrites the vault, which may be unwanted for
+    /// read-only or shared vaults.
+    #[serde(default = "default_record_use")]
+    record_use: bool,
+}
+
+impl Default for ProgramConfiguration {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            clipboard_timeout: DEFAULT_CLIPBOARD_TIMEOUT,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            picker: None,
+            record_use: true,
+        }
+    }
 }
 
 impl ProgramConfiguration {
@@ -17,6 +68,24 @@ impl ProgramConfiguration {
         &self.language
     }
 
+    /// Returns `None` if copied secrets should stay in the clipboard.
+    pub fn get_clipboard_timeout(&self) -> Option<Duration> {
+        timeout(self.clipboard_timeout)
+    }
+
+    /// Returns `None` if shell should keep the vault unlocked.
+    pub fn get_lock_timeout(&self) -> Option<Duration> {
+        timeout(self.lock_timeout)
+    }
+
+    pub fn get_picker(&self) -> Option<&str> {
+        self.picker.as_deref()
+    }
+
+    pub fn get_record_use(&self) -> bool {
+        self.record_use
+    }
+
     const CONFIGURATION_FILE_NAME: &'static str = "config";
     pub fn load() -> Result<RwLock<Self>, ConfigurationError> {
         let mut config_path = configuration_path();
@@ -75,3 +144,28 @@ pub fn read_translation(lang: &Language) -> Result<HashMap<String, String>, Conf
     let file_content = std::fs::read_to_string(path)?;
     serde_json::from_str(&file_content).map_err(|_| ConfigurationError::DeserializationError)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_options_have_defaults() {
+        let configuration: ProgramConfiguration =
+            serde_json::from_str(r#"{"language": "USEnglish"}"#).unwrap();
+        assert_eq!(
+            configuration.get_clipboard_timeout(),
+            Some(Duration::from_secs(DEFAULT_CLIPBOARD_TIMEOUT))
+        );
+        assert_eq!(
+            configuration.get_lock_timeout(),
+            Some(Duration::from_secs(DEFAULT_LOCK_TIMEOUT))
+        );
+
+        let configuration: ProgramConfiguration =
+            serde_json::from_str(r#"{"language": "USEnglish", "clipboard_timeout": 0}"#).unwrap();
+        assert_eq!(configuration.get_clipboard_timeout(), None);
+        assert_eq!(configuration.get_picker(), None);
+        assert!(configuration.get_record_use());
+    }
+}
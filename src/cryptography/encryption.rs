@@ -1,10 +1,28 @@
-use aead::{Aead, AeadCore, KeyInit};
-use generic_array::{ArrayLength, GenericArray};
+use aead::{Aead, AeadCore, KeyInit, KeySizeUser};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug)]
 pub enum EncryptionError {
-    InvalidKeyLength { provided: usize, required: usize },
+    InvalidKeyLength {
+        provided: usize,
+        required: usize,
+    },
+    /// Data was modified or key is wrong.
+    DecryptionFailed,
+}
+
+impl std::fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKeyLength { provided, required } => write!(
+                f,
+                "key has {provided} bytes, but the cipher requires {required}"
+            ),
+            Self::DecryptionFailed => write!(f, "data was modified or the key is wrong"),
+        }
+    }
 }
 
 trait EncryptionCore {
@@ -15,7 +33,11 @@ trait EncryptionCore {
         Self: Sized;
     fn random_nonce() -> GenericArray<u8, Self::NonceSize>;
     fn encrypt<T: AsRef<[u8]>, N: AsRef<[u8]>>(&self, nonce: N, plain_data: T) -> Vec<u8>;
-    fn decrypt<C: AsRef<[u8]>, N: AsRef<[u8]>>(&self, nonce: N, encrypted_data: C) -> Vec<u8>;
+    fn decrypt<C: AsRef<[u8]>, N: AsRef<[u8]>>(
+        &self,
+        nonce: N,
+        encrypted_data: C,
+    ) -> Result<Vec<u8>, EncryptionError>;
 }
 
 impl<E: Aead + KeyInit + AeadCore> EncryptionCore for E {
@@ -38,12 +60,19 @@ impl<E: Aead + KeyInit + AeadCore> EncryptionCore for E {
         nonce
     }
 
-    fn decrypt<C: AsRef<[u8]>, N: AsRef<[u8]>>(&self, nonce: N, encrypted_data: C) -> Vec<u8> {
+    fn decrypt<C: AsRef<[u8]>, N: AsRef<[u8]>>(
+        &self,
+        nonce: N,
+        encrypted_data: C,
+    ) -> Result<Vec<u8>, EncryptionError> {
+        if nonce.as_ref().len() != <Self as AeadCore>::NonceSize::USIZE {
+            return Err(EncryptionError::DecryptionFailed);
+        }
         let nonce: &GenericArray<u8, <Self as AeadCore>::NonceSize> =
             GenericArray::from_slice(nonce.as_ref());
 
         self.decrypt(nonce, encrypted_data.as_ref())
-            .expect("This should never fail.")
+            .map_err(|_| EncryptionError::DecryptionFailed)
     }
 
     fn encrypt<T: AsRef<[u8]>, N: AsRef<[u8]>>(&self, nonce: N, plain_data: T) -> Vec<u8> {
@@ -57,7 +86,7 @@ impl<E: Aead + KeyInit + AeadCore> EncryptionCore for E {
 
 trait DynEncryptionCore {
     fn random_nonce(&self) -> Vec<u8>;
-    fn decrypt(&self, nonce: &[u8], encrypted_data: &[u8]) -> Vec<u8>;
+    fn decrypt(&self, nonce: &[u8], encrypted_data: &[u8]) -> Result<Vec<u8>, EncryptionError>;
     fn encrypt(&self, nonce: &[u8], plain_data: &[u8]) -> Vec<u8>;
 }
 
@@ -66,7 +95,7 @@ impl<T: EncryptionCore> DynEncryptionCore for T {
         Self::random_nonce().to_vec()
     }
 
-    fn decrypt(&self, nonce: &[u8], encrypted_data: &[u8]) -> Vec<u8> {
+    fn decrypt(&self, nonce: &[u8], encrypted_data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
         self.decrypt(nonce, encrypted_data)
     }
 
@@ -79,7 +108,7 @@ macro_rules! encryption_algorithms {
     ($($name:ident),*) => {
         type EncryptionNonce = Vec<u8>;
 
-        #[derive(Serialize, Deserialize, Clone)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
         pub enum EncryptionAlgorithm {
             $(
                 $name,
@@ -90,7 +119,21 @@ macro_rules! encryption_algorithms {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self {
                     $(
-                        $name => write!(f, stringify!($name)),
+                        Self::$name => write!(f, stringify!($name)),
+                    )*
+                }
+            }
+        }
+
+        impl EncryptionAlgorithm {
+            /// Every supported algorithm.
+            pub const ALL: &'static [Self] = &[$(Self::$name),*];
+
+            /// Returns length of key (in bytes) required by the algorithm.
+            pub fn key_size(&self) -> usize {
+                match self {
+                    $(
+                        Self::$name => <$name as KeySizeUser>::key_size(),
                     )*
                 }
             }
@@ -129,11 +172,12 @@ macro_rules! encryption_algorithms {
                 self.algorithm.clone()
             }
 
+            /// Decrypts and authenticates data. Fails if data was modified or key is wrong.
             pub fn decrypt<T, N>(
                 &self,
                 encrypted_data: T,
                 nonce: N
-            ) -> Vec<u8>
+            ) -> Result<Vec<u8>, EncryptionError>
             where
                 T: AsRef<[u8]>,
                 N: AsRef<[u8]>
@@ -148,3 +192,31 @@ macro_rules! encryption_algorithms {
 use aes_gcm_siv::Aes256GcmSiv;
 use chacha20poly1305::ChaCha20Poly1305;
 encryption_algorithms!(Aes256GcmSiv, ChaCha20Poly1305);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tampered_data_is_rejected() {
+        for algorithm in [
+            EncryptionAlgorithm::Aes256GcmSiv,
+            EncryptionAlgorithm::ChaCha20Poly1305,
+        ] {
+            let key = vec![7; algorithm.key_size()];
+            let encryptor = EncryptionStruct::new(algorithm.clone(), &key).unwrap();
+            let (mut encrypted, nonce) = encryptor.encrypt(b"secret");
+            assert_eq!(encryptor.decrypt(&encrypted, &nonce).unwrap(), b"secret");
+
+            let other = EncryptionStruct::new(algorithm, vec![8; key.len()]).unwrap();
+            assert!(matches!(
+                other.decrypt(&encrypted, &nonce),
+                Err(EncryptionError::DecryptionFailed)
+            ));
+
+            encrypted[0] ^= 1;
+            assert!(encryptor.decrypt(&encrypted, &nonce).is_err());
+            assert!(encryptor.decrypt(&encrypted, &nonce[1..]).is_err());
+        }
+    }
+}
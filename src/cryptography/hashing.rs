@@ -1,22 +1,28 @@
-use digest::{FixedOutputReset, Update};
+use digest::{core_api::BlockSizeUser, FixedOutputReset, Update};
 use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::{Sha256, Sha512};
+use zeroize::Zeroizing;
 
 trait HashGeneratorCore {
     fn new() -> Self
     where
         Self: Sized;
+    fn block_size(&self) -> usize;
     fn update(&mut self, data: &[u8]);
     fn finalize(&mut self) -> Vec<u8>;
 }
 
-impl<T: Update + FixedOutputReset + Default> HashGeneratorCore for T {
+impl<T: Update + FixedOutputReset + Default + BlockSizeUser> HashGeneratorCore for T {
     fn new() -> Self
     where
         Self: Sized,
     {
         Self::default()
     }
+    fn block_size(&self) -> usize {
+        <T as BlockSizeUser>::block_size()
+    }
     fn update(&mut self, data: &[u8]) {
         self.update(data);
     }
@@ -31,7 +37,7 @@ impl<T: Update + FixedOutputReset + Default> HashGeneratorCore for T {
 /// by user to select wanted hashing algorithm.
 macro_rules! hash_algorithms {
     ($($name:ident),*) => {
-        #[derive(Serialize, Deserialize, Clone)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
         pub enum HashAlgorithm {
            $(
                 $name,
@@ -42,12 +48,17 @@ macro_rules! hash_algorithms {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self {
                     $(
-                        $name => write!(f, stringify!($name)),
+                        Self::$name => write!(f, stringify!($name)),
                     )*
                 }
             }
         }
 
+        impl HashAlgorithm {
+            /// Every supported algorithm, also those which should not protect new data.
+            pub const ALL: &'static [Self] = &[$(Self::$name),*];
+        }
+
         pub struct HashStruct {
             hash_machine: Box<dyn HashGeneratorCore>,
             algorithm: HashAlgorithm
@@ -57,7 +68,7 @@ macro_rules! hash_algorithms {
             pub fn new(algorithm: HashAlgorithm) -> Self {
                 let hash_machine: Box<dyn HashGeneratorCore> = match algorithm.clone() {
                     $(
-                        $name => Box::new($name::new()),
+                        HashAlgorithm::$name => Box::new($name::new()),
                     )*
                 };
 
@@ -72,6 +83,11 @@ macro_rules! hash_algorithms {
                 self.hash_machine.finalize()
             }
 
+            /// Returns size (in bytes) of blocks processed by the algorithm.
+            pub fn block_size(&self) -> usize {
+                self.hash_machine.block_size()
+            }
+
             pub fn algorithm(&self) -> HashAlgorithm {
                 self.algorithm.clone()
             }
@@ -79,4 +95,83 @@ macro_rules! hash_algorithms {
     };
 }
 
-hash_algorithms! {Sha256, Sha512}
+// Variants are serialized by index, so new algorithms have to be appended.
+hash_algorithms! {Sha256, Sha512, Sha1}
+
+impl HashAlgorithm {
+    /// Computes HMAC (RFC 2104) of `message` using this algorithm.
+    pub fn hmac(&self, key: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut hash = HashStruct::new(self.clone());
+        let block_size = hash.block_size();
+
+        let mut padded_key = Zeroizing::new(vec![0u8; block_size]);
+        if key.len() > block_size {
+            hash.update(key);
+            let hashed_key = Zeroizing::new(hash.finalize());
+            padded_key[..hashed_key.len()].copy_from_slice(&hashed_key);
+        } else {
+            padded_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut pad = Zeroizing::new(padded_key.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+        hash.update(&pad);
+        hash.update(message);
+        let inner = Zeroizing::new(hash.finalize());
+
+        pad.iter_mut()
+            .zip(padded_key.iter())
+            .for_each(|(pad, key)| *pad = key ^ 0x5c);
+        hash.update(&pad);
+        hash.update(&inner);
+
+        hash.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let key = [0x0b; 20];
+        let message = b"Hi There";
+
+        assert_eq!(
+            hex(&HashAlgorithm::Sha256.hmac(&key, message)),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+        assert_eq!(
+            hex(&HashAlgorithm::Sha512.hmac(&key, message)),
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+             daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+        );
+
+        // Key longer than block size is hashed first.
+        let key = [0xaa; 131];
+        let message = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        assert_eq!(
+            hex(&HashAlgorithm::Sha256.hmac(&key, message)),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn serialized_indexes_are_stable() {
+        // Vault headers store the algorithm by its index.
+        assert_eq!(postcard::to_allocvec(&HashAlgorithm::Sha256).unwrap(), [0]);
+        assert_eq!(postcard::to_allocvec(&HashAlgorithm::Sha512).unwrap(), [1]);
+    }
+
+    #[test]
+    fn hmac_matches_rfc_2202() {
+        assert_eq!(
+            hex(&HashAlgorithm::Sha1.hmac(b"Jefe", b"what do ya want for nothing?")),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+    }
+}
@@ -1,74 +1,133 @@
 use super::{
-    DynPasswordHasher, KeyDerivationAlgorithm, KeyDerivationError, PasswordHasher,
-    PasswordHasherBuilder,
+    DynPasswordHasher, KeyDerivationAlgorithm, KeyDerivationError, OptionDescriptor, OptionType,
+    OptionValue, PasswordHasher, PasswordHasherBuilder, ATTACKER_MEMORY_BANDWIDTH,
+    OUTPUT_LENGTH_OPTION,
 };
 use argon2::Argon2;
 use serde::{Deserialize, Serialize};
-
-const POSSIBLE_OPTIONS: &'static [(&'static str, &'static str)] = &[
-    ("Memory size", "Determines RAM usage of algorithm"),
-    ("Iterations", "Sets how many passes algorithm does - the more iterations it does, the more time it takes to save/load database"),
-    ("Parallelism", "Configures parallelism of the algorithm")
+use zeroize::Zeroizing;
+
+const MEMORY_SIZE: &'static str = "Memory size";
+const ITERATIONS: &'static str = "Iterations";
+const PARALLELISM: &'static str = "Parallelism";
+const VERSION: &'static str = "Version";
+const SECRET_KEY_FILE: &'static str = "Secret key file";
+
+/// Longest derived key accepted. Argon2 itself allows up to 4 GiB, which would be
+/// allocated while unlocking, and no cipher needs more than a few dozen bytes.
+const MAX_OUTPUT_LENGTH: u32 = 1024;
+
+const POSSIBLE_OPTIONS: &'static [OptionDescriptor] = &[
+    OptionDescriptor {
+        name: MEMORY_SIZE,
+        description: "Determines RAM usage of algorithm",
+        option_type: OptionType::Unsigned,
+        unit: Some("KiB"),
+        min: argon2::Params::MIN_M_COST,
+        max: argon2::Params::MAX_M_COST,
+        default: argon2::Params::DEFAULT_M_COST,
+        validate: None,
+    },
+    OptionDescriptor {
+        name: ITERATIONS,
+        description: "Sets how many passes algorithm does - the more iterations it does, the more time it takes to save/load database",
+        option_type: OptionType::Unsigned,
+        unit: None,
+        min: argon2::Params::MIN_T_COST,
+        max: argon2::Params::MAX_T_COST,
+        default: argon2::Params::DEFAULT_T_COST,
+        validate: None,
+    },
+    OptionDescriptor {
+        name: PARALLELISM,
+        description: "Configures parallelism of the algorithm",
+        option_type: OptionType::Unsigned,
+        unit: Some("lanes"),
+        min: argon2::Params::MIN_P_COST,
+        max: argon2::Params::MAX_P_COST,
+        default: argon2::Params::DEFAULT_P_COST,
+        validate: None,
+    },
+    OptionDescriptor {
+        name: OUTPUT_LENGTH_OPTION,
+        description: "Length of derived key - it should be equal to key size of selected cipher",
+        option_type: OptionType::Unsigned,
+        unit: Some("bytes"),
+        min: argon2::Params::MIN_OUTPUT_LEN as u32,
+        max: MAX_OUTPUT_LENGTH,
+        default: argon2::Params::DEFAULT_OUTPUT_LEN as u32,
+        validate: None,
+    },
+    OptionDescriptor {
+        name: VERSION,
+        description: "Revision of Argon2 algorithm - 16 (0x10) or 19 (0x13)",
+        option_type: OptionType::Unsigned,
+        unit: None,
+        min: argon2::Version::V0x10 as u32,
+        max: argon2::Version::V0x13 as u32,
+        default: argon2::Version::V0x13 as u32,
+        validate: Some(validate_version),
+    },
+    OptionDescriptor {
+        name: SECRET_KEY_FILE,
+        description: "Path to file containing secret key (pepper) mixed into the hash - leave empty to disable",
+        option_type: OptionType::Text,
+        unit: None,
+        min: 0,
+        max: 4096,
+        default: 0,
+        validate: None,
+    },
 ];
 
+fn validate_version(value: &OptionValue) -> Result<(), KeyDerivationError> {
+    match value {
+        OptionValue::Unsigned(version) => {
+            argon2::Version::try_from(*version)
+                .map(|_| ())
+                .map_err(|_| KeyDerivationError::InvalidValue {
+                    description: format!("{} is not a valid Argon2 version.", version),
+                })
+        }
+        _ => unreachable!(),
+    }
+}
+
 #[derive(Clone)]
 struct Argon2Options {
     argon_version: argon2::Algorithm,
-    argon_params: argon2::ParamsBuilder,
+    version: argon2::Version,
     memory_size: u32,
     iterations: u32,
     parallelism: u32,
+    output_length: u32,
+    secret_key_file: String,
 }
 
 fn deserialize_options(bytes: &[u8]) -> Result<Argon2Options, KeyDerivationError> {
-    match postcard::from_bytes::<Argon2OptionsSerialization>(bytes) {
-        Ok(structure) => {
-            let algorithm = match structure.algorithm {
-                KeyDerivationAlgorithm::Argon2i => argon2::Algorithm::Argon2i,
-                KeyDerivationAlgorithm::Argon2id => argon2::Algorithm::Argon2id,
-            };
-
-            let mut builder = argon2::ParamsBuilder::default();
-            builder.m_cost(structure.memory_size).map_err(|e| {
-                KeyDerivationError::InvalidValue {
-                    description: format!(
-                        "Value of memory size ({}) was not accepted. Error = {}",
-                        structure.memory_size,
-                        e.to_string()
-                    ),
-                }
-            })?;
-
-            builder
-                .t_cost(structure.iterations)
-                .map_err(|e| KeyDerivationError::InvalidValue {
-                    description: format!(
-                        "Value of iterations ({}) was not accepted. Error = {}",
-                        structure.memory_size,
-                        e.to_string()
-                    ),
-                })?;
-
-            builder.p_cost(structure.parallelism).map_err(|e| {
-                KeyDerivationError::InvalidValue {
-                    description: format!(
-                        "Value of parallelism ({}) was not accepted. Error = {}",
-                        structure.memory_size,
-                        e.to_string()
-                    ),
-                }
-            })?;
-
-            Ok(Argon2Options {
-                argon_version: algorithm,
-                argon_params: builder,
-                memory_size: structure.memory_size,
-                iterations: structure.iterations,
-                parallelism: structure.parallelism,
-            })
-        }
-        Err(_e) => Err(KeyDerivationError::InvalidConfigFormat),
-    }
+    let structure = match postcard::from_bytes::<Argon2OptionsSerialization>(bytes) {
+        Ok(structure) => structure,
+        Err(_) => postcard::from_bytes::<LegacyArgon2OptionsSerialization>(bytes)
+            .map_err(|_| KeyDerivationError::InvalidConfigFormat)?
+            .into(),
+    };
+
+    let options = Argon2Options {
+        argon_version: argon_algorithm(&structure.algorithm),
+        version: argon2::Version::try_from(structure.version).map_err(|_| {
+            KeyDerivationError::InvalidValue {
+                description: format!("{} is not a valid Argon2 version.", structure.version),
+            }
+        })?,
+        memory_size: structure.memory_size,
+        iterations: structure.iterations,
+        parallelism: structure.parallelism,
+        output_length: structure.output_length,
+        secret_key_file: structure.secret_key_file.unwrap_or_default(),
+    };
+    options.params_builder()?;
+
+    Ok(options)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -77,6 +136,33 @@ struct Argon2OptionsSerialization {
     iterations: u32,
     parallelism: u32,
     algorithm: super::KeyDerivationAlgorithm,
+    version: u32,
+    output_length: u32,
+    secret_key_file: Option<String>,
+}
+
+/// Format of options saved before version, output length and secret key
+/// became configurable. Vaults using it are read with default values of those settings.
+#[derive(Serialize, Deserialize)]
+struct LegacyArgon2OptionsSerialization {
+    memory_size: u32,
+    iterations: u32,
+    parallelism: u32,
+    algorithm: super::KeyDerivationAlgorithm,
+}
+
+impl From<LegacyArgon2OptionsSerialization> for Argon2OptionsSerialization {
+    fn from(legacy: LegacyArgon2OptionsSerialization) -> Self {
+        Self {
+            memory_size: legacy.memory_size,
+            iterations: legacy.iterations,
+            parallelism: legacy.parallelism,
+            algorithm: legacy.algorithm,
+            version: argon2::Version::V0x13 as u32,
+            output_length: argon2::Params::DEFAULT_OUTPUT_LEN as u32,
+            secret_key_file: None,
+        }
+    }
 }
 
 impl Serialize for Argon2Options {
@@ -84,17 +170,19 @@ impl Serialize for Argon2Options {
     where
         S: serde::Serializer,
     {
-        let algorithm = match self.argon_version {
-            argon2::Algorithm::Argon2id => KeyDerivationAlgorithm::Argon2id,
-            argon2::Algorithm::Argon2i => KeyDerivationAlgorithm::Argon2i,
-            _ => unimplemented!(),
+        let secret_key_file = match self.secret_key_file.is_empty() {
+            true => None,
+            false => Some(self.secret_key_file.clone()),
         };
 
         Argon2OptionsSerialization {
             memory_size: self.memory_size,
             iterations: self.iterations,
             parallelism: self.parallelism,
-            algorithm,
+            algorithm: self.argon_version.into(),
+            version: self.version as u32,
+            output_length: self.output_length,
+            secret_key_file,
         }
         .serialize(serializer)
     }
@@ -110,76 +198,48 @@ impl From<argon2::Error> for KeyDerivationError {
 
 impl PasswordHasherBuilder for Argon2Options {
     fn build(&self) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError> {
-        let argon_params = self.argon_params.clone().params()?;
+        let argon_params = self.params_builder()?.params()?;
+        let secret = self.load_secret()?;
 
         Ok(Box::new(Argon2Wrapper::new(
-            Argon2::new(
-                self.argon_version.clone(),
-                argon2::Version::V0x13,
-                argon_params,
-            ),
+            argon_params,
             self.clone(),
+            secret,
         )))
     }
 
-    fn get_option(&self, option: &str) -> Result<String, KeyDerivationError> {
-        match option.to_ascii_lowercase().as_str() {
-            "memory size" => Ok(self.memory_size.to_string()),
-            "iterations" => Ok(self.memory_size.to_string()),
-            "parallelism" => Ok(self.parallelism.to_string()),
-            _ => Err(KeyDerivationError::InvalidOptions {
-                description: format!("Field {} was not found.", option),
-            }),
+    fn get_value(&self, option: &str) -> Result<OptionValue, KeyDerivationError> {
+        match self.descriptor(option)?.name {
+            MEMORY_SIZE => Ok(OptionValue::Unsigned(self.memory_size)),
+            ITERATIONS => Ok(OptionValue::Unsigned(self.iterations)),
+            PARALLELISM => Ok(OptionValue::Unsigned(self.parallelism)),
+            OUTPUT_LENGTH_OPTION => Ok(OptionValue::Unsigned(self.output_length)),
+            VERSION => Ok(OptionValue::Unsigned(self.version as u32)),
+            SECRET_KEY_FILE => Ok(OptionValue::Text(self.secret_key_file.clone())),
+            _ => unreachable!(),
         }
     }
 
-    fn set_option(&mut self, option: &str, value: &str) -> Result<(), KeyDerivationError> {
-        match option {
-            "memory size" => {
-                let memory_size: u32 =
-                    value
-                        .parse()
-                        .map_err(|_| KeyDerivationError::InvalidValue {
-                            description: format!(
-                                "Unable to parse {} into unsigned 32-bit number.",
-                                value
-                            ),
-                        })?;
-                self.memory_size = memory_size;
+    fn set_value(&mut self, option: &str, value: OptionValue) -> Result<(), KeyDerivationError> {
+        let descriptor = self.descriptor(option)?;
+        descriptor.validate(&value)?;
+
+        match (descriptor.name, value) {
+            (MEMORY_SIZE, OptionValue::Unsigned(number)) => self.memory_size = number,
+            (ITERATIONS, OptionValue::Unsigned(number)) => self.iterations = number,
+            (PARALLELISM, OptionValue::Unsigned(number)) => self.parallelism = number,
+            (OUTPUT_LENGTH_OPTION, OptionValue::Unsigned(number)) => self.output_length = number,
+            (VERSION, OptionValue::Unsigned(number)) => {
+                self.version = argon2::Version::try_from(number)?
             }
-            "iterations" => {
-                let iterations: u32 =
-                    value
-                        .parse()
-                        .map_err(|_| KeyDerivationError::InvalidValue {
-                            description: format!(
-                                "Unable to parse {} into unsigned 32-bit number.",
-                                value
-                            ),
-                        })?;
-                self.iterations = iterations;
-            }
-            "parallelism" => {
-                let parallelism: u32 =
-                    value
-                        .parse()
-                        .map_err(|_| KeyDerivationError::InvalidValue {
-                            description: format!(
-                                "Unable to parse {} into unsigned 32-bit number.",
-                                value
-                            ),
-                        })?;
-                self.parallelism = parallelism;
-            }
-            _ => Err(KeyDerivationError::InvalidOptions {
-                description: format!("Field {} was not found.", option),
-            })?,
+            (SECRET_KEY_FILE, OptionValue::Text(path)) => self.secret_key_file = path,
+            _ => unreachable!(),
         }
 
         Ok(())
     }
 
-    fn options(&self) -> &'static [(&'static str, &'static str)] {
+    fn options(&self) -> &'static [OptionDescriptor] {
         POSSIBLE_OPTIONS
     }
 }
@@ -189,39 +249,149 @@ impl From<argon2::Algorithm> for KeyDerivationAlgorithm {
         match algo {
             argon2::Algorithm::Argon2i => KeyDerivationAlgorithm::Argon2i,
             argon2::Algorithm::Argon2id => KeyDerivationAlgorithm::Argon2id,
-            _ => unreachable!(),
+            argon2::Algorithm::Argon2d => KeyDerivationAlgorithm::Argon2d,
         }
     }
 }
 
+fn argon_algorithm(algorithm: &KeyDerivationAlgorithm) -> argon2::Algorithm {
+    match algorithm {
+        KeyDerivationAlgorithm::Argon2i => argon2::Algorithm::Argon2i,
+        KeyDerivationAlgorithm::Argon2id => argon2::Algorithm::Argon2id,
+        KeyDerivationAlgorithm::Argon2d => argon2::Algorithm::Argon2d,
+    }
+}
+
 impl Argon2Options {
     fn new(argon_version: argon2::Algorithm) -> Self {
         Self {
             argon_version,
-            argon_params: argon2::ParamsBuilder::default(),
+            version: argon2::Version::V0x13,
             memory_size: argon2::Params::DEFAULT_M_COST,
             iterations: argon2::Params::DEFAULT_T_COST,
             parallelism: argon2::Params::DEFAULT_P_COST,
+            output_length: argon2::Params::DEFAULT_OUTPUT_LEN as u32,
+            secret_key_file: String::new(),
+        }
+    }
+
+    /// Creates argon2 parameters from current options. Values are checked
+    /// by argon2 itself, so invalid combinations are reported here. Options read
+    /// from a vault header do not pass descriptors, so output length is bounded here too.
+    fn params_builder(&self) -> Result<argon2::ParamsBuilder, KeyDerivationError> {
+        if self.output_length > MAX_OUTPUT_LENGTH {
+            return Err(KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Value of output length ({}) must not exceed {} bytes.",
+                    self.output_length, MAX_OUTPUT_LENGTH
+                ),
+            });
+        }
+
+        let mut builder = argon2::ParamsBuilder::default();
+        builder
+            .m_cost(self.memory_size)
+            .map_err(|e| KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Value of memory size ({}) was not accepted. Error = {}",
+                    self.memory_size,
+                    e.to_string()
+                ),
+            })?;
+
+        builder
+            .t_cost(self.iterations)
+            .map_err(|e| KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Value of iterations ({}) was not accepted. Error = {}",
+                    self.iterations,
+                    e.to_string()
+                ),
+            })?;
+
+        builder
+            .p_cost(self.parallelism)
+            .map_err(|e| KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Value of parallelism ({}) was not accepted. Error = {}",
+                    self.parallelism,
+                    e.to_string()
+                ),
+            })?;
+
+        builder
+            .output_len(self.output_length as usize)
+            .map_err(|e| KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Value of output length ({}) was not accepted. Error = {}",
+                    self.output_length,
+                    e.to_string()
+                ),
+            })?;
+
+        Ok(builder)
+    }
+
+    /// Reads secret key (pepper) from configured file. Secret is never saved
+    /// together with options - only path to it is.
+    fn load_secret(&self) -> Result<Option<Zeroizing<Vec<u8>>>, KeyDerivationError> {
+        if self.secret_key_file.is_empty() {
+            return Ok(None);
         }
+
+        let secret = Zeroizing::new(std::fs::read(&self.secret_key_file).map_err(|e| {
+            KeyDerivationError::SecretKeyUnavailable {
+                description: format!(
+                    "Unable to read secret key from {}. Error = {}",
+                    self.secret_key_file, e
+                ),
+            }
+        })?);
+
+        if secret.is_empty() {
+            return Err(KeyDerivationError::SecretKeyUnavailable {
+                description: format!("Secret key file {} is empty.", self.secret_key_file),
+            });
+        }
+
+        Ok(Some(secret))
     }
 }
 
 struct Argon2Wrapper {
-    machine: Argon2<'static>,
+    argon_params: argon2::Params,
     params: Box<Argon2Options>,
+    secret: Option<Zeroizing<Vec<u8>>>,
 }
 
 impl Argon2Wrapper {
-    fn new(argon2: Argon2<'static>, options: Argon2Options) -> Self {
+    fn new(
+        argon_params: argon2::Params,
+        options: Argon2Options,
+        secret: Option<Zeroizing<Vec<u8>>>,
+    ) -> Self {
         Self {
-            machine: argon2,
+            argon_params,
             params: Box::new(options),
+            secret,
+        }
+    }
+
+    fn machine(&self) -> Result<Argon2<'_>, KeyDerivationError> {
+        let algorithm = self.params.argon_version;
+        let version = self.params.version;
+        let params = self.argon_params.clone();
+
+        match &self.secret {
+            Some(secret) => Ok(Argon2::new_with_secret(secret, algorithm, version, params)?),
+            None => Ok(Argon2::new(algorithm, version, params)),
         }
     }
 }
 
 pub struct Argon2id;
 pub struct Argon2i;
+pub struct Argon2d;
 
 impl DynPasswordHasher for Argon2Wrapper {
     fn option_bytes(&self) -> Vec<u8> {
@@ -234,51 +404,55 @@ impl DynPasswordHasher for Argon2Wrapper {
         salt: &[u8],
         hash_place: &mut [u8],
     ) -> Result<(), KeyDerivationError> {
-        self.machine
+        self.machine()?
             .hash_password_into(password, salt, hash_place)
             .map_err(|error| KeyDerivationError::HashingError {
                 description: error.to_string(),
             })
     }
 
+    /// Every pass writes each block of memory once and reads two blocks to compute it.
+    fn guesses_per_second(&self) -> f64 {
+        let bytes_per_pass = 3.0 * 1024.0 * self.argon_params.m_cost() as f64;
+        let bytes_per_guess = bytes_per_pass * self.argon_params.t_cost() as f64;
+
+        ATTACKER_MEMORY_BANDWIDTH / bytes_per_guess
+    }
+
     fn hash_size(&self) -> usize {
-        self.machine
-            .params()
+        self.argon_params
             .output_len()
             .unwrap_or(argon2::Params::DEFAULT_OUTPUT_LEN)
     }
 
     fn algorithm(&self) -> KeyDerivationAlgorithm {
-        match self.params.argon_version {
-            argon2::Algorithm::Argon2i => KeyDerivationAlgorithm::Argon2i,
-            argon2::Algorithm::Argon2id => KeyDerivationAlgorithm::Argon2id,
-            _ => unimplemented!(),
-        }
+        self.params.argon_version.into()
     }
 }
 
-impl PasswordHasher for Argon2id {
-    fn options_builder() -> Box<dyn PasswordHasherBuilder> {
-        Box::new(Argon2Options::new(argon2::Algorithm::Argon2id))
-    }
-
-    fn build(options: &[u8]) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError> {
-        let options = deserialize_options(options)?;
-        options.build()
-    }
-}
+macro_rules! argon_hashers {
+    ($($name:ident),*) => {
+        $(
+            impl PasswordHasher for $name {
+                fn options_builder() -> Box<dyn PasswordHasherBuilder> {
+                    Box::new(Argon2Options::new(argon2::Algorithm::$name))
+                }
 
-impl PasswordHasher for Argon2i {
-    fn options_builder() -> Box<dyn PasswordHasherBuilder> {
-        Box::new(Argon2Options::new(argon2::Algorithm::Argon2i))
-    }
+                fn build(options: &[u8]) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError> {
+                    let options = deserialize_options(options)?;
+                    options.build()
+                }
 
-    fn build(options: &[u8]) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError> {
-        let options = deserialize_options(options)?;
-        options.build()
-    }
+                fn restore_builder(options: &[u8]) -> Result<Box<dyn PasswordHasherBuilder>, KeyDerivationError> {
+                    Ok(Box::new(deserialize_options(options)?))
+                }
+            }
+        )*
+    };
 }
 
+argon_hashers! {Argon2id, Argon2i, Argon2d}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -289,22 +463,251 @@ mod tests {
         params.t_cost(123).unwrap();
         params.m_cost(567).unwrap();
         params.p_cost(2).unwrap();
+        params.output_len(32).unwrap();
 
         let config = Argon2Options {
             argon_version: argon2::Algorithm::Argon2i,
-            argon_params: params.clone(),
+            version: argon2::Version::V0x13,
             memory_size: 567,
             iterations: 123,
             parallelism: 2,
+            output_length: 32,
+            secret_key_file: String::new(),
         };
 
         let bytes = postcard::to_allocvec(&config).unwrap();
 
         let options = deserialize_options(&bytes).unwrap();
         assert_eq!(options.argon_version, argon2::Algorithm::Argon2i);
-        assert_eq!(options.argon_params, params);
+        assert_eq!(options.params_builder().unwrap(), params);
         assert_eq!(options.memory_size, 567);
         assert_eq!(options.iterations, 123);
         assert_eq!(options.parallelism, 2);
     }
+
+    #[test]
+    fn options_are_case_insensitive() {
+        let mut builder = Argon2id::options_builder();
+        builder.set_option("ITERATIONS", "7").unwrap();
+        builder.set_option("memory size", "2048").unwrap();
+
+        assert_eq!(builder.get_option("Iterations").unwrap(), "7");
+        assert_eq!(builder.get_option("MEMORY SIZE").unwrap(), "2048");
+        assert_eq!(
+            builder.get_value("parallelism").unwrap(),
+            OptionValue::Unsigned(argon2::Params::DEFAULT_P_COST)
+        );
+    }
+
+    #[test]
+    fn invalid_options_are_rejected() {
+        let mut builder = Argon2id::options_builder();
+
+        assert!(builder.set_option("iterations", "0").is_err());
+        assert!(builder.set_option("iterations", "many").is_err());
+        assert!(builder.set_option("salt", "1").is_err());
+        assert!(builder.get_option("salt").is_err());
+        assert_eq!(
+            builder.get_option("iterations").unwrap(),
+            argon2::Params::DEFAULT_T_COST.to_string()
+        );
+    }
+
+    #[test]
+    fn builder_settings_survive_round_trip() {
+        let mut builder = Argon2i::options_builder();
+        builder.set_option("memory size", "1024").unwrap();
+        builder.set_option("iterations", "2").unwrap();
+        builder
+            .set_value("parallelism", OptionValue::Unsigned(2))
+            .unwrap();
+
+        let hasher = builder.build().unwrap();
+        let bytes = hasher.option_bytes();
+
+        let options = deserialize_options(&bytes).unwrap();
+        assert_eq!(options.memory_size, 1024);
+        assert_eq!(options.iterations, 2);
+        assert_eq!(options.parallelism, 2);
+
+        let restored = Argon2i::restore_builder(&bytes).unwrap();
+        assert_eq!(restored.get_option("memory size").unwrap(), "1024");
+
+        let restored = Argon2i::build(&bytes).unwrap();
+        assert_eq!(restored.option_bytes(), bytes);
+        assert_eq!(
+            restored.hash_password(b"password", b"saltsalt").unwrap(),
+            hasher.hash_password(b"password", b"saltsalt").unwrap()
+        );
+
+        let defaults = Argon2i::options_builder().build().unwrap();
+        assert_ne!(
+            defaults.hash_password(b"password", b"saltsalt").unwrap(),
+            hasher.hash_password(b"password", b"saltsalt").unwrap()
+        );
+    }
+
+    #[test]
+    fn algorithm_hasher_uses_option_bytes() {
+        let mut builder = KeyDerivationAlgorithm::Argon2id.builder();
+        builder.set_option("iterations", "4").unwrap();
+        let bytes = builder.build().unwrap().option_bytes();
+
+        let hasher = KeyDerivationAlgorithm::Argon2id.hasher(&bytes).unwrap();
+        assert_eq!(hasher.algorithm(), KeyDerivationAlgorithm::Argon2id);
+        assert_eq!(
+            deserialize_options(&hasher.option_bytes())
+                .unwrap()
+                .iterations,
+            4
+        );
+    }
+
+    #[test]
+    fn argon2d_and_version_round_trip() {
+        let mut builder = Argon2d::options_builder();
+        builder.set_option("memory size", "64").unwrap();
+        builder.set_option("iterations", "1").unwrap();
+        builder.set_option("version", "16").unwrap();
+        assert!(builder.set_option("version", "17").is_err());
+
+        let hasher = builder.build().unwrap();
+        let bytes = hasher.option_bytes();
+        let restored = KeyDerivationAlgorithm::Argon2d.hasher(&bytes).unwrap();
+
+        assert_eq!(restored.algorithm(), KeyDerivationAlgorithm::Argon2d);
+        assert_eq!(
+            deserialize_options(&bytes).unwrap().version,
+            argon2::Version::V0x10
+        );
+        assert_eq!(
+            restored.hash_password(b"password", b"saltsalt").unwrap(),
+            hasher.hash_password(b"password", b"saltsalt").unwrap()
+        );
+    }
+
+    #[test]
+    fn output_length_matches_cipher() {
+        let algorithm = crate::cryptography::EncryptionAlgorithm::Aes256GcmSiv;
+        let builder = KeyDerivationAlgorithm::Argon2id
+            .builder_for(&algorithm)
+            .unwrap();
+        let hasher = builder.build().unwrap();
+
+        assert_eq!(hasher.hash_size(), algorithm.key_size());
+
+        let mut builder = Argon2id::options_builder();
+        builder.set_option("output length", "64").unwrap();
+        let hasher = builder.build().unwrap();
+        let restored = Argon2id::build(&hasher.option_bytes()).unwrap();
+
+        assert_eq!(restored.hash_size(), 64);
+        assert_eq!(
+            restored
+                .hash_password(b"password", b"saltsalt")
+                .unwrap()
+                .len(),
+            64
+        );
+    }
+
+    #[test]
+    fn output_length_is_bounded() {
+        let mut builder = Argon2id::options_builder();
+        builder.set_option("output length", "1024").unwrap();
+        assert!(builder.set_option("output length", "1025").is_err());
+        assert!(builder.set_option("output length", "4294967295").is_err());
+
+        // Crafted header cannot request huge key either.
+        let mut options = Argon2Options::new(argon2::Algorithm::Argon2id);
+        options.output_length = u32::MAX;
+        let bytes = postcard::to_allocvec(&options).unwrap();
+        assert!(matches!(
+            Argon2id::build(&bytes),
+            Err(KeyDerivationError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn attacker_speed_depends_on_cost() {
+        let mut builder = Argon2id::options_builder();
+        builder.set_option("memory size", "4096").unwrap();
+        builder.set_option("iterations", "3").unwrap();
+        let cheap = builder.build().unwrap().guesses_per_second();
+
+        builder.set_option("memory size", "65536").unwrap();
+        let expensive = builder.build().unwrap().guesses_per_second();
+
+        assert!((cheap / expensive - 16.0).abs() < 1e-9);
+        assert!(expensive < 1e6);
+    }
+
+    #[test]
+    fn secret_key_is_read_from_file() {
+        let path = std::env::temp_dir().join(format!("rustypass-pepper-{}", std::process::id()));
+        std::fs::write(&path, b"very secret pepper").unwrap();
+
+        let mut builder = Argon2id::options_builder();
+        builder
+            .set_value(
+                "secret key file",
+                OptionValue::Text(path.to_string_lossy().into_owned()),
+            )
+            .unwrap();
+        let peppered = builder.build().unwrap();
+        let plain = Argon2id::options_builder().build().unwrap();
+
+        let bytes = peppered.option_bytes();
+        assert!(!bytes
+            .windows(b"very secret pepper".len())
+            .any(|window| window == b"very secret pepper"));
+
+        let restored = Argon2id::build(&bytes).unwrap();
+        assert_eq!(
+            restored.hash_password(b"password", b"saltsalt").unwrap(),
+            peppered.hash_password(b"password", b"saltsalt").unwrap()
+        );
+        assert_ne!(
+            plain.hash_password(b"password", b"saltsalt").unwrap(),
+            peppered.hash_password(b"password", b"saltsalt").unwrap()
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            Argon2id::build(&bytes),
+            Err(KeyDerivationError::SecretKeyUnavailable { .. })
+        ));
+    }
+
+    #[test]
+    fn legacy_options_are_migrated() {
+        let legacy = LegacyArgon2OptionsSerialization {
+            memory_size: 1024,
+            iterations: 2,
+            parallelism: 1,
+            algorithm: KeyDerivationAlgorithm::Argon2i,
+        };
+        let bytes = postcard::to_allocvec(&legacy).unwrap();
+
+        let options = deserialize_options(&bytes).unwrap();
+        assert_eq!(options.argon_version, argon2::Algorithm::Argon2i);
+        assert_eq!(options.version, argon2::Version::V0x13);
+        assert_eq!(
+            options.output_length as usize,
+            argon2::Params::DEFAULT_OUTPUT_LEN
+        );
+        assert!(options.secret_key_file.is_empty());
+
+        let mut builder = Argon2i::options_builder();
+        builder.set_option("memory size", "1024").unwrap();
+        builder.set_option("iterations", "2").unwrap();
+        let current = builder.build().unwrap();
+        let migrated = Argon2i::build(&bytes).unwrap();
+
+        assert_eq!(migrated.option_bytes(), current.option_bytes());
+        assert_eq!(
+            migrated.hash_password(b"password", b"saltsalt").unwrap(),
+            current.hash_password(b"password", b"saltsalt").unwrap()
+        );
+    }
 }
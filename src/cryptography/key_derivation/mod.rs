@@ -6,12 +6,28 @@ use chacha20poly1305::Key;
 use erased_serde::Serialize as DynSerialize;
 use serde::{Deserialize, Serialize};
 
+use super::EncryptionAlgorithm;
+
 #[derive(Debug)]
 pub enum KeyDerivationError {
     HashingError { description: String },
     InvalidOptions { description: String },
     InvalidValue { description: String },
     InvalidConfigFormat,
+    SecretKeyUnavailable { description: String },
+}
+
+impl std::fmt::Display for KeyDerivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HashingError { description } => write!(f, "key derivation failed: {description}"),
+            Self::InvalidOptions { description } | Self::InvalidValue { description } => {
+                write!(f, "invalid key derivation option: {description}")
+            }
+            Self::InvalidConfigFormat => write!(f, "key derivation options cannot be decoded"),
+            Self::SecretKeyUnavailable { description } => write!(f, "{description}"),
+        }
+    }
 }
 
 pub trait DynPasswordHasher {
@@ -30,26 +46,187 @@ pub trait DynPasswordHasher {
         Ok(hash)
     }
 
+    /// Estimated number of passwords per second which well-funded offline attacker
+    /// (see [`ATTACKER_MEMORY_BANDWIDTH`]) can check against key derived with current options.
+    fn guesses_per_second(&self) -> f64;
+
     // PVC String
     fn option_bytes(&self) -> Vec<u8>;
     fn algorithm(&self) -> KeyDerivationAlgorithm;
 }
 
+/// Memory bandwidth (bytes per second) of hardware assumed for offline attacks - roughly
+/// a rack of high-end GPUs. Memory-hard algorithms are limited by bandwidth rather
+/// than by computing power, so crack times are estimated from it.
+pub const ATTACKER_MEMORY_BANDWIDTH: f64 = 1e13;
+
+/// Type of value accepted by an option of [`PasswordHasherBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionType {
+    /// Unsigned 32-bit integer.
+    Unsigned,
+    /// UTF-8 text (e.g. path to a file).
+    Text,
+}
+
+/// Typed value of an option of [`PasswordHasherBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Unsigned(u32),
+    Text(String),
+}
+
+impl OptionValue {
+    pub fn option_type(&self) -> OptionType {
+        match self {
+            Self::Unsigned(_) => OptionType::Unsigned,
+            Self::Text(_) => OptionType::Text,
+        }
+    }
+}
+
+impl std::fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsigned(value) => write!(f, "{}", value),
+            Self::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Describes single option of key derivation algorithm - its type, accepted range
+/// and default value. Descriptors are used both to present options to the user
+/// and to validate values before they reach the algorithm.
+pub struct OptionDescriptor {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub option_type: OptionType,
+    /// Unit in which value is expressed (e.g. `KiB`), if any.
+    pub unit: Option<&'static str>,
+    /// Lower bound of value. For text options it bounds length of the text.
+    pub min: u32,
+    /// Upper bound of value. For text options it bounds length of the text.
+    pub max: u32,
+    /// Default value. Text options always default to empty text.
+    pub default: u32,
+    /// Additional check run after type and range validation.
+    pub validate: Option<fn(&OptionValue) -> Result<(), KeyDerivationError>>,
+}
+
+impl OptionDescriptor {
+    /// Returns true if `name` refers to this option. Comparison is case-insensitive.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name.trim())
+    }
+
+    pub fn default_value(&self) -> OptionValue {
+        match self.option_type {
+            OptionType::Unsigned => OptionValue::Unsigned(self.default),
+            OptionType::Text => OptionValue::Text(String::new()),
+        }
+    }
+
+    /// Parses textual representation of value and validates it.
+    pub fn parse(&self, value: &str) -> Result<OptionValue, KeyDerivationError> {
+        let value = match self.option_type {
+            OptionType::Unsigned => OptionValue::Unsigned(value.trim().parse().map_err(|_| {
+                KeyDerivationError::InvalidValue {
+                    description: format!("Unable to parse {} into unsigned 32-bit number.", value),
+                }
+            })?),
+            OptionType::Text => OptionValue::Text(value.to_string()),
+        };
+
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    /// Checks type, range and custom validation hook of supplied value.
+    pub fn validate(&self, value: &OptionValue) -> Result<(), KeyDerivationError> {
+        if value.option_type() != self.option_type {
+            return Err(KeyDerivationError::InvalidValue {
+                description: format!(
+                    "Option {} expects value of type {:?}, got {:?}.",
+                    self.name,
+                    self.option_type,
+                    value.option_type()
+                ),
+            });
+        }
+
+        match value {
+            OptionValue::Unsigned(number) if *number < self.min || *number > self.max => {
+                return Err(KeyDerivationError::InvalidValue {
+                    description: format!(
+                        "Value of {} ({}) must be between {} and {}.",
+                        self.name, number, self.min, self.max
+                    ),
+                });
+            }
+            OptionValue::Text(text)
+                if text.len() < self.min as usize || text.len() > self.max as usize =>
+            {
+                return Err(KeyDerivationError::InvalidValue {
+                    description: format!(
+                        "Length of {} ({}) must be between {} and {}.",
+                        self.name,
+                        text.len(),
+                        self.min,
+                        self.max
+                    ),
+                });
+            }
+            _ => (),
+        }
+
+        match self.validate {
+            Some(hook) => hook(value),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Name of option which sets length of derived key (in bytes). Every algorithm
+/// should provide it, so the key can be matched to the selected cipher.
+pub const OUTPUT_LENGTH_OPTION: &'static str = "Output length";
+
 pub trait PasswordHasherBuilder {
     fn build(&self) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError>;
-    fn options(&self) -> &'static [(&'static str, &'static str)];
-    fn set_option(&mut self, option: &str, value: &str) -> Result<(), KeyDerivationError>;
-    fn get_option(&self, option: &str) -> Result<String, KeyDerivationError>;
+    fn options(&self) -> &'static [OptionDescriptor];
+    fn set_value(&mut self, option: &str, value: OptionValue) -> Result<(), KeyDerivationError>;
+    fn get_value(&self, option: &str) -> Result<OptionValue, KeyDerivationError>;
+
+    fn descriptor(&self, option: &str) -> Result<&'static OptionDescriptor, KeyDerivationError> {
+        self.options()
+            .iter()
+            .find(|descriptor| descriptor.matches(option))
+            .ok_or_else(|| KeyDerivationError::InvalidOptions {
+                description: format!("Field {} was not found.", option),
+            })
+    }
+
+    fn set_option(&mut self, option: &str, value: &str) -> Result<(), KeyDerivationError> {
+        let descriptor = self.descriptor(option)?;
+        let value = descriptor.parse(value)?;
+        self.set_value(descriptor.name, value)
+    }
+
+    fn get_option(&self, option: &str) -> Result<String, KeyDerivationError> {
+        self.get_value(option).map(|value| value.to_string())
+    }
 }
 
 trait PasswordHasher {
     fn options_builder() -> Box<dyn PasswordHasherBuilder>;
     fn build(options: &[u8]) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError>;
+    fn restore_builder(
+        options: &[u8],
+    ) -> Result<Box<dyn PasswordHasherBuilder>, KeyDerivationError>;
 }
 
 macro_rules! key_derivation_algorithms {
     ($($name:ident),*) => {
-        #[derive(Serialize, Deserialize)]
+        #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
         pub enum KeyDerivationAlgorithm {
             $(
                 $name,
@@ -91,6 +268,39 @@ macro_rules! key_derivation_algorithms {
                 }
             }
 
+            /// Creates builder whose output length matches key size of `encryption` algorithm.
+            pub fn builder_for(
+                &self,
+                encryption: &EncryptionAlgorithm,
+            ) -> Result<Box<dyn PasswordHasherBuilder>, KeyDerivationError> {
+                let mut builder = self.builder();
+                builder.set_value(
+                    OUTPUT_LENGTH_OPTION,
+                    OptionValue::Unsigned(encryption.key_size() as u32),
+                )?;
+
+                Ok(builder)
+            }
+
+            /// Creates password hasher from options saved by [`DynPasswordHasher::option_bytes`].
+            pub fn hasher(&self, options: &[u8]) -> Result<Box<dyn DynPasswordHasher>, KeyDerivationError> {
+                match self {
+                    $(
+                        Self::$name => $name::build(options),
+                    )*
+                }
+            }
+
+            /// Creates builder initialized with options saved by [`DynPasswordHasher::option_bytes`],
+            /// so saved settings can be inspected or adjusted.
+            pub fn builder_from(&self, options: &[u8]) -> Result<Box<dyn PasswordHasherBuilder>, KeyDerivationError> {
+                match self {
+                    $(
+                        Self::$name => $name::restore_builder(options),
+                    )*
+                }
+            }
+
             fn variants() -> Vec<Self> {
                 vec![
                     $(
@@ -102,8 +312,8 @@ macro_rules! key_derivation_algorithms {
     };
 }
 
-use argon::{Argon2i, Argon2id};
-key_derivation_algorithms! {Argon2id, Argon2i}
+use argon::{Argon2d, Argon2i, Argon2id};
+key_derivation_algorithms! {Argon2id, Argon2i, Argon2d}
 
 #[cfg(test)]
 mod tests {
@@ -11,3 +11,6 @@ pub use key_derivation::KeyDerivationAlgorithm;
 
 pub use key_derivation::DynPasswordHasher;
 pub use key_derivation::KeyDerivationError;
+pub use key_derivation::OptionValue;
+pub use key_derivation::PasswordHasherBuilder;
+pub use key_derivation::OUTPUT_LENGTH_OPTION;
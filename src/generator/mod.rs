@@ -0,0 +1,49 @@
+mod passphrase;
+mod password;
+mod policy;
+
+pub use passphrase::Capitalization;
+pub use passphrase::wordlist;
+pub use passphrase::PassphraseGenerator;
+pub use password::CharacterClass;
+pub use password::PasswordGenerator;
+pub use policy::PasswordPolicy;
+
+use crate::storage::SafeBuffer;
+
+#[derive(Debug)]
+pub enum GeneratorError {
+    InvalidLength { description: String },
+    EmptyCharacterSet { description: String },
+    UnsatisfiableRequirements { description: String },
+    InvalidTemplate { description: String },
+    PolicyViolation { description: String },
+}
+
+/// Secret produced by one of generators together with estimate of its strength.
+#[derive(Debug, Clone)]
+pub struct GeneratedSecret {
+    secret: SafeBuffer,
+    entropy: f64,
+}
+
+impl GeneratedSecret {
+    fn new(secret: SafeBuffer, entropy: f64) -> Self {
+        Self { secret, entropy }
+    }
+
+    pub fn secret(&self) -> &SafeBuffer {
+        &self.secret
+    }
+
+    pub fn into_secret(self) -> SafeBuffer {
+        self.secret
+    }
+
+    /// Entropy of the generator in bits - logarithm of number of equally
+    /// probable secrets it can produce with current settings. It is an estimate for
+    /// some password policies, see [`PasswordPolicy::generate`].
+    pub fn entropy(&self) -> f64 {
+        self.entropy
+    }
+}
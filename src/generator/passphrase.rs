@@ -0,0 +1,297 @@
+use super::{GeneratedSecret, GeneratorError};
+use crate::configuration::ProgramConfiguration;
+use crate::language::Language;
+use crate::storage::SafeBuffer;
+use once_cell::sync::Lazy;
+use rand::{rngs::OsRng, Rng};
+
+/// EFF large word list (CC BY 3.0 US, Electronic Frontier Foundation).
+const ENGLISH_WORDLIST: &'static str = include_str!("wordlists/en-US.txt");
+/// Common Polish words in base form, none of which starts another, numbered like EFF
+/// short word list (four dice per word).
+const POLISH_WORDLIST: &'static str = include_str!("wordlists/pl-PL.txt");
+
+static ENGLISH_WORDS: Lazy<Vec<&'static str>> = Lazy::new(|| parse_wordlist(ENGLISH_WORDLIST));
+static POLISH_WORDS: Lazy<Vec<&'static str>> = Lazy::new(|| parse_wordlist(POLISH_WORDLIST));
+
+const DIGITS: &'static str = "0123456789";
+const SYMBOLS: &'static str = "!#$%&*+-=?@^_~";
+
+/// Passphrases created with default settings have at least this many bits of entropy.
+const DEFAULT_ENTROPY: f64 = 72.0;
+
+pub const MAX_WORD_COUNT: usize = 64;
+
+/// Every line of word list has format `<dice rolls> <word>`.
+fn parse_wordlist(content: &'static str) -> Vec<&'static str> {
+    content
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect()
+}
+
+/// Returns word list used for passphrases in given language.
+pub fn wordlist(language: &Language) -> &'static [&'static str] {
+    match language {
+        Language::Polish => &POLISH_WORDS,
+        Language::USEnglish => &ENGLISH_WORDS,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capitalization {
+    Lowercase,
+    Uppercase,
+    /// First letter of every word is uppercase.
+    Capitalized,
+    /// Every word is either lowercase or capitalized, chosen at random.
+    Random,
+}
+
+/// Diceware-style passphrase generator. Words are drawn uniformly from
+/// word list of selected language.
+#[derive(Debug, Clone)]
+pub struct PassphraseGenerator {
+    language: Language,
+    word_count: usize,
+    separator: String,
+    capitalization: Capitalization,
+    /// Appends random digit to randomly chosen word.
+    insert_digit: bool,
+    /// Appends random symbol to randomly chosen word.
+    insert_symbol: bool,
+}
+
+impl Default for PassphraseGenerator {
+    fn default() -> Self {
+        Self::new(Language::default())
+    }
+}
+
+impl PassphraseGenerator {
+    /// Creates generator using word list of `language`. Number of words is chosen so
+    /// passphrase has at least 72 bits of entropy.
+    pub fn new(language: Language) -> Self {
+        let bits_per_word = (wordlist(&language).len() as f64).log2();
+
+        Self {
+            language,
+            word_count: (DEFAULT_ENTROPY / bits_per_word).ceil() as usize,
+            separator: "-".into(),
+            capitalization: Capitalization::Lowercase,
+            insert_digit: false,
+            insert_symbol: false,
+        }
+    }
+
+    /// Creates generator using language configured by the user.
+    pub fn from_configuration(configuration: &ProgramConfiguration) -> Self {
+        Self::new(*configuration.get_language())
+    }
+
+    pub fn word_count(mut self, word_count: usize) -> Self {
+        self.word_count = word_count;
+        self
+    }
+
+    /// Sets text placed between words. Separator should not be a letter - otherwise
+    /// words cannot be told apart and real entropy is lower than reported.
+    pub fn separator<T: Into<String>>(mut self, separator: T) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    pub fn capitalization(mut self, capitalization: Capitalization) -> Self {
+        self.capitalization = capitalization;
+        self
+    }
+
+    pub fn insert_digit(mut self, insert: bool) -> Self {
+        self.insert_digit = insert;
+        self
+    }
+
+    pub fn insert_symbol(mut self, insert: bool) -> Self {
+        self.insert_symbol = insert;
+        self
+    }
+
+    pub fn language(&self) -> &Language {
+        &self.language
+    }
+
+    /// Returns entropy (in bits) of passphrases created with current settings.
+    pub fn entropy(&self) -> Result<f64, GeneratorError> {
+        self.check_word_count()?;
+
+        let words = self.word_count as f64;
+        let mut entropy = words * (wordlist(&self.language).len() as f64).log2();
+        if self.capitalization == Capitalization::Random {
+            entropy += words;
+        }
+        if self.insert_digit {
+            entropy += (DIGITS.len() as f64 * words).log2();
+        }
+        if self.insert_symbol {
+            entropy += (SYMBOLS.len() as f64 * words).log2();
+        }
+
+        Ok(entropy)
+    }
+
+    pub fn generate(&self) -> Result<GeneratedSecret, GeneratorError> {
+        let entropy = self.entropy()?;
+        let words = wordlist(&self.language);
+
+        let digit_position = OsRng.gen_range(0..self.word_count);
+        let symbol_position = OsRng.gen_range(0..self.word_count);
+
+        let longest_word = words.iter().map(|word| word.len()).max().unwrap_or(0);
+        let mut passphrase = SafeBuffer::with_capacity(
+            self.word_count * (longest_word * 2 + self.separator.len() + 2),
+        );
+
+        for position in 0..self.word_count {
+            if position > 0 {
+                passphrase.extend_from_slice(self.separator.as_bytes());
+            }
+
+            let word = words[OsRng.gen_range(0..words.len())];
+            let capitalize = match self.capitalization {
+                Capitalization::Random => OsRng.gen(),
+                other => other == Capitalization::Capitalized,
+            };
+
+            for (index, character) in word.chars().enumerate() {
+                if self.capitalization == Capitalization::Uppercase || (capitalize && index == 0) {
+                    character
+                        .to_uppercase()
+                        .for_each(|upper| passphrase.push_char(upper));
+                } else {
+                    passphrase.push_char(character);
+                }
+            }
+
+            if self.insert_digit && position == digit_position {
+                passphrase.push_char(random_character(DIGITS));
+            }
+            if self.insert_symbol && position == symbol_position {
+                passphrase.push_char(random_character(SYMBOLS));
+            }
+        }
+
+        Ok(GeneratedSecret::new(passphrase, entropy))
+    }
+
+    fn check_word_count(&self) -> Result<(), GeneratorError> {
+        match self.word_count {
+            1..=MAX_WORD_COUNT => Ok(()),
+            _ => Err(GeneratorError::InvalidLength {
+                description: format!(
+                    "Number of words must be between 1 and {}, got {}.",
+                    MAX_WORD_COUNT, self.word_count
+                ),
+            }),
+        }
+    }
+}
+
+fn random_character(characters: &str) -> char {
+    let characters = characters.as_bytes();
+    characters[OsRng.gen_range(0..characters.len())] as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wordlists_are_complete() {
+        for (language, size) in [(Language::USEnglish, 7776), (Language::Polish, 1296)] {
+            let words = wordlist(&language);
+            let mut unique = words.to_vec();
+            unique.sort();
+            unique.dedup();
+
+            assert_eq!(words.len(), size);
+            assert_eq!(unique.len(), size);
+            assert!(words.iter().all(
+                |word| !word.is_empty() && word.chars().all(|c| c.is_alphabetic() || c == '-')
+            ));
+        }
+
+        // In sorted order a word would be directly followed by one it starts.
+        let mut polish = wordlist(&Language::Polish).to_vec();
+        polish.sort();
+        assert!(polish.windows(2).all(|pair| !pair[1].starts_with(pair[0])));
+    }
+
+    #[test]
+    fn default_entropy_is_sufficient() {
+        let english = PassphraseGenerator::new(Language::USEnglish);
+        let polish = PassphraseGenerator::new(Language::Polish);
+
+        assert_eq!(english.word_count, 6);
+        assert_eq!(polish.word_count, 7);
+        assert!(english.entropy().unwrap() >= DEFAULT_ENTROPY);
+        assert!(polish.entropy().unwrap() >= DEFAULT_ENTROPY);
+    }
+
+    #[test]
+    fn passphrase_uses_wordlist_of_language() {
+        let configuration: ProgramConfiguration =
+            serde_json::from_str(r#"{"language": "Polish"}"#).unwrap();
+        let generator = PassphraseGenerator::from_configuration(&configuration).separator(" ");
+        assert_eq!(*generator.language(), Language::Polish);
+
+        let generated = generator.generate().unwrap();
+        let words: Vec<&str> = generated.secret().as_str().unwrap().split(' ').collect();
+
+        assert_eq!(words.len(), 7);
+        assert!(words
+            .iter()
+            .all(|word| wordlist(&Language::Polish).contains(word)));
+    }
+
+    #[test]
+    fn capitalization_and_insertions() {
+        let generator = PassphraseGenerator::new(Language::USEnglish)
+            .word_count(4)
+            .separator(".")
+            .capitalization(Capitalization::Capitalized)
+            .insert_digit(true)
+            .insert_symbol(true);
+
+        let generated = generator.generate().unwrap();
+        let passphrase = generated.secret().as_str().unwrap();
+        let words: Vec<&str> = passphrase.split('.').collect();
+
+        assert_eq!(words.len(), 4);
+        assert!(words
+            .iter()
+            .all(|word| word.chars().next().unwrap().is_uppercase()));
+        assert_eq!(
+            passphrase.chars().filter(|c| DIGITS.contains(*c)).count(),
+            1
+        );
+        assert_eq!(
+            passphrase.chars().filter(|c| SYMBOLS.contains(*c)).count(),
+            1
+        );
+
+        let expected = 4.0 * 7776f64.log2() + 40f64.log2() + 56f64.log2();
+        assert!((generated.entropy() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_word_count_is_rejected() {
+        assert!(matches!(
+            PassphraseGenerator::default().word_count(0).generate(),
+            Err(GeneratorError::InvalidLength { .. })
+        ));
+        assert!(PassphraseGenerator::default()
+            .word_count(MAX_WORD_COUNT + 1)
+            .entropy()
+            .is_err());
+    }
+}
@@ -0,0 +1,654 @@
+use super::{GeneratedSecret, GeneratorError};
+use crate::storage::SafeBuffer;
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+const UPPERCASE: &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWERCASE: &'static str = "abcdefghijklmnopqrstuvwxyz";
+const DIGITS: &'static str = "0123456789";
+const SYMBOLS: &'static str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Characters which are easily confused with each other in many fonts.
+const SIMILAR_CHARACTERS: &'static str = "0OoIl1|";
+
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CharacterClass {
+    Uppercase,
+    Lowercase,
+    Digits,
+    Symbols,
+    /// Set of characters supplied by the user.
+    Custom(String),
+}
+
+impl CharacterClass {
+    pub fn characters(&self) -> &str {
+        match self {
+            Self::Uppercase => UPPERCASE,
+            Self::Lowercase => LOWERCASE,
+            Self::Digits => DIGITS,
+            Self::Symbols => SYMBOLS,
+            Self::Custom(characters) => characters,
+        }
+    }
+}
+
+/// Generator of random passwords. Every password satisfying configured
+/// requirements is equally probable, so [`GeneratedSecret::entropy`] is exact.
+#[derive(Debug, Clone)]
+pub struct PasswordGenerator {
+    length: usize,
+    /// Character classes with minimal number of their characters in password.
+    classes: Vec<(CharacterClass, usize)>,
+    exclude_similar: bool,
+    /// Characters which can never appear in password.
+    excluded: String,
+}
+
+impl Default for PasswordGenerator {
+    fn default() -> Self {
+        Self::new(20)
+            .with_class(CharacterClass::Uppercase, 1)
+            .with_class(CharacterClass::Lowercase, 1)
+            .with_class(CharacterClass::Digits, 1)
+            .with_class(CharacterClass::Symbols, 1)
+    }
+}
+
+impl PasswordGenerator {
+    /// Creates generator without any character class.
+    pub fn new(length: usize) -> Self {
+        Self {
+            length,
+            classes: Vec::new(),
+            exclude_similar: false,
+            excluded: String::new(),
+        }
+    }
+
+    /// Adds character class to the generator. Password will contain at least `minimum`
+    /// characters of this class. Adding class second time replaces its minimum.
+    pub fn with_class(mut self, class: CharacterClass, minimum: usize) -> Self {
+        match self.classes.iter_mut().find(|(added, _)| *added == class) {
+            Some((_, added_minimum)) => *added_minimum = minimum,
+            None => self.classes.push((class, minimum)),
+        }
+
+        self
+    }
+
+    /// Excludes characters which look alike (e.g. `0` and `O`).
+    pub fn exclude_similar(mut self, exclude: bool) -> Self {
+        self.exclude_similar = exclude;
+        self
+    }
+
+    /// Excludes every character of `characters` from password.
+    pub fn exclude_characters<T: AsRef<str>>(mut self, characters: T) -> Self {
+        self.excluded.push_str(characters.as_ref());
+        self
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+    }
+
+    pub fn classes(&self) -> &[(CharacterClass, usize)] {
+        &self.classes
+    }
+
+    /// Returns entropy (in bits) of passwords created with current settings.
+    pub fn entropy(&self) -> Result<f64, GeneratorError> {
+        Ok(self.prepare()?.entropy())
+    }
+
+    pub fn generate(&self) -> Result<GeneratedSecret, GeneratorError> {
+        let prepared = self.prepare()?;
+        Ok(GeneratedSecret::new(prepared.draw(), prepared.entropy()))
+    }
+
+    /// Computes tables of the generator, so many passwords can be drawn with them.
+    pub(super) fn prepare(&self) -> Result<PreparedGenerator, GeneratorError> {
+        let components = self.components()?;
+        let table = CompositionTable::new(&components, self.length);
+
+        Ok(PreparedGenerator {
+            components,
+            table,
+            length: self.length,
+        })
+    }
+
+    /// Groups characters of all classes by classes they belong to, and classes sharing
+    /// characters into components. Minimum of every class applies to all its characters,
+    /// also to those present in other classes.
+    fn components(&self) -> Result<Vec<ClassComponent>, GeneratorError> {
+        if self.length == 0 || self.length > MAX_PASSWORD_LENGTH {
+            return Err(GeneratorError::InvalidLength {
+                description: format!(
+                    "Password length must be between 1 and {}, got {}.",
+                    MAX_PASSWORD_LENGTH, self.length
+                ),
+            });
+        }
+
+        let mut classes: Vec<(Vec<char>, usize)> = Vec::new();
+        for (class, minimum) in self.classes.iter() {
+            let mut characters: Vec<char> = Vec::new();
+            for character in class.characters().chars() {
+                if characters.contains(&character)
+                    || self.excluded.contains(character)
+                    || (self.exclude_similar && SIMILAR_CHARACTERS.contains(character))
+                {
+                    continue;
+                }
+
+                characters.push(character);
+            }
+
+            match (characters.is_empty(), *minimum) {
+                (true, 0) => continue,
+                (true, _) => {
+                    return Err(GeneratorError::EmptyCharacterSet {
+                        description: format!(
+                            "Class {:?} has no characters left, but {} of them are required.",
+                            class, minimum
+                        ),
+                    })
+                }
+                _ => classes.push((characters, *minimum)),
+            }
+        }
+
+        if classes.is_empty() {
+            return Err(GeneratorError::EmptyCharacterSet {
+                description: "No characters are available for password.".into(),
+            });
+        }
+
+        // Every character of the combined alphabet once, with classes containing it.
+        let mut groups: Vec<(Vec<usize>, Vec<char>)> = Vec::new();
+        let mut seen = Vec::new();
+        for (characters, _) in classes.iter() {
+            for character in characters {
+                if seen.contains(character) {
+                    continue;
+                }
+                seen.push(*character);
+
+                let members: Vec<usize> = (0..classes.len())
+                    .filter(|class| classes[*class].0.contains(character))
+                    .collect();
+                match groups.iter_mut().find(|(classes, _)| *classes == members) {
+                    Some((_, group)) => group.push(*character),
+                    None => groups.push((members, vec![*character])),
+                }
+            }
+        }
+
+        // Component of every class is the lowest class it shares characters with.
+        let mut component: Vec<usize> = (0..classes.len()).collect();
+        let mut merged = true;
+        while merged {
+            merged = false;
+            for (members, _) in groups.iter() {
+                let lowest = members.iter().map(|class| component[*class]).min();
+                for class in members {
+                    if Some(component[*class]) != lowest {
+                        component[*class] = lowest.expect("group has a class");
+                        merged = true;
+                    }
+                }
+            }
+        }
+
+        let mut components = Vec::new();
+        for root in 0..classes.len() {
+            let members: Vec<usize> = (0..classes.len())
+                .filter(|class| component[*class] == root)
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+
+            let minimums: Vec<usize> = members.iter().map(|class| classes[*class].1).collect();
+            let local_groups = groups
+                .iter()
+                .filter(|(classes, _)| component[classes[0]] == root)
+                .map(|(classes, characters)| {
+                    let local = classes
+                        .iter()
+                        .map(|class| members.iter().position(|member| member == class))
+                        .map(|index| index.expect("class is in its component"))
+                        .collect();
+                    (local, characters.clone())
+                })
+                .collect();
+            components.push(ClassComponent::new(&minimums, local_groups, self.length)?);
+        }
+
+        let required: usize = components
+            .iter()
+            .map(|component| component.shortest().unwrap_or(self.length + 1))
+            .sum();
+        if required > self.length {
+            return Err(GeneratorError::UnsatisfiableRequirements {
+                description: format!(
+                    "Requirements need {} characters, but password has only {}.",
+                    required, self.length
+                ),
+            });
+        }
+
+        Ok(components)
+    }
+}
+
+/// Password generator with its tables computed.
+pub(super) struct PreparedGenerator {
+    components: Vec<ClassComponent>,
+    table: CompositionTable,
+    length: usize,
+}
+
+impl PreparedGenerator {
+    pub(super) fn entropy(&self) -> f64 {
+        self.table.total()
+    }
+
+    /// Draws password, every one satisfying requirements is equally likely.
+    pub(super) fn draw(&self) -> SafeBuffer {
+        let mut positions = Vec::with_capacity(self.length);
+        // Characters of every component fill its positions in the order they were drawn.
+        let mut characters = Vec::with_capacity(self.components.len());
+        let mut remaining = self.length;
+        for (index, component) in self.components.iter().enumerate() {
+            let count = self.table.sample_count(index, component, remaining);
+            positions.extend(std::iter::repeat_n(index, count));
+            characters.push(component.sample(count).into_iter());
+            remaining -= count;
+        }
+        positions.shuffle(&mut OsRng);
+
+        let mut password = SafeBuffer::with_capacity(self.length * 4);
+        for index in positions {
+            password.push_char(characters[index].next().expect("character was drawn"));
+        }
+
+        password
+    }
+}
+
+/// Largest table of [`ClassComponent`] counts, overlapping classes with higher minimums
+/// are rejected instead of taking too much memory.
+const MAX_TABLE_SIZE: usize = 1 << 22;
+
+/// Classes sharing characters with each other, directly or through other classes, and
+/// groups of their characters. Group holds characters belonging to exactly the same
+/// classes, e.g. `Digits` together with `Custom("0123")` split digits into groups `0123`
+/// and `456789`. Classes of different components have no characters in common, so
+/// their minimums can be met independently.
+///
+/// Counts of characters of every class, capped at its minimum, are encoded into one
+/// state number. Entry `counts[r][s]` holds base-2 logarithm of number of ways to
+/// append `r` characters in state `s` so that all minimums are met at the end.
+struct ClassComponent {
+    groups: Vec<Vec<char>>,
+    /// `transitions[g][s]` is state after character of group `g` is appended in state `s`.
+    transitions: Vec<Vec<usize>>,
+    counts: Vec<Vec<f64>>,
+}
+
+impl ClassComponent {
+    /// Creates component of classes with given minimums, `groups` hold indexes of the
+    /// classes containing their characters.
+    fn new(
+        minimums: &[usize],
+        groups: Vec<(Vec<usize>, Vec<char>)>,
+        length: usize,
+    ) -> Result<Self, GeneratorError> {
+        let mut strides = Vec::with_capacity(minimums.len());
+        let mut states = 1usize;
+        for minimum in minimums {
+            strides.push(states);
+            states = states.saturating_mul(minimum + 1);
+        }
+        if states.saturating_mul(length + 1) > MAX_TABLE_SIZE {
+            return Err(GeneratorError::UnsatisfiableRequirements {
+                description: format!(
+                    "Overlapping classes require too many characters ({}).",
+                    minimums.iter().sum::<usize>()
+                ),
+            });
+        }
+
+        let transitions: Vec<Vec<usize>> = groups
+            .iter()
+            .map(|(classes, _)| {
+                (0..states)
+                    .map(|state| {
+                        classes.iter().fold(state, |next, class| {
+                            match state / strides[*class] % (minimums[*class] + 1) {
+                                count if count < minimums[*class] => next + strides[*class],
+                                _ => next,
+                            }
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let groups: Vec<Vec<char>> = groups.into_iter().map(|(_, group)| group).collect();
+
+        // Only the last state has all minimums met.
+        let mut counts = vec![vec![f64::NEG_INFINITY; states]; length + 1];
+        counts[0][states - 1] = 0.0;
+        for remaining in 1..=length {
+            for state in 0..states {
+                let mut total = f64::NEG_INFINITY;
+                for (group, transitions) in groups.iter().zip(&transitions) {
+                    let rest = counts[remaining - 1][transitions[state]];
+                    total = log2_add(total, (group.len() as f64).log2() + rest);
+                }
+                counts[remaining][state] = total;
+            }
+        }
+
+        Ok(Self {
+            groups,
+            transitions,
+            counts,
+        })
+    }
+
+    /// Logarithm of number of strings of `length` characters meeting the minimums.
+    fn ways(&self, length: usize) -> f64 {
+        self.counts[length][0]
+    }
+
+    /// Shortest string meeting the minimums, if there is one within the table.
+    fn shortest(&self) -> Option<usize> {
+        (0..self.counts.len()).find(|length| self.ways(*length) != f64::NEG_INFINITY)
+    }
+
+    /// Draws string of `length` characters, every one meeting the minimums is
+    /// equally likely.
+    fn sample(&self, length: usize) -> Vec<char> {
+        let mut characters = Vec::with_capacity(length);
+        let mut state = 0;
+        for remaining in (1..=length).rev() {
+            let all = self.counts[remaining][state];
+            let threshold: f64 = OsRng.gen();
+
+            let mut cumulative = 0.0;
+            let mut chosen = None;
+            for (index, group) in self.groups.iter().enumerate() {
+                let rest = self.counts[remaining - 1][self.transitions[index][state]];
+                if rest == f64::NEG_INFINITY {
+                    continue;
+                }
+
+                // Floating point rounding can leave cumulative probability slightly below 1.
+                chosen = Some(index);
+                cumulative += ((group.len() as f64).log2() + rest - all).exp2();
+                if threshold < cumulative {
+                    break;
+                }
+            }
+
+            let index = chosen.expect("string of remaining length can be completed");
+            let group = &self.groups[index];
+            characters.push(group[OsRng.gen_range(0..group.len())]);
+            state = self.transitions[index][state];
+        }
+
+        characters
+    }
+}
+
+/// Table of base-2 logarithms of numbers of valid passwords. Entry `[i][r]` holds
+/// number of ways to fill `r` positions with characters of components `i..`, respecting
+/// minimums of their classes. It is used to draw how many characters of every component
+/// password contains with exactly the probability of uniform choice among all valid
+/// passwords.
+struct CompositionTable {
+    counts: Vec<Vec<f64>>,
+    log_factorials: Vec<f64>,
+}
+
+impl CompositionTable {
+    fn new(components: &[ClassComponent], length: usize) -> Self {
+        let mut log_factorials = vec![0.0; length + 1];
+        for n in 1..=length {
+            log_factorials[n] = log_factorials[n - 1] + (n as f64).log2();
+        }
+
+        let mut counts = vec![vec![f64::NEG_INFINITY; length + 1]; components.len() + 1];
+        counts[components.len()][0] = 0.0;
+
+        let mut table = Self {
+            counts: Vec::new(),
+            log_factorials,
+        };
+
+        for (index, component) in components.iter().enumerate().rev() {
+            for remaining in 0..=length {
+                let mut total = f64::NEG_INFINITY;
+                for count in 0..=remaining {
+                    let ways = table.term(component, remaining, count)
+                        + counts[index + 1][remaining - count];
+                    total = log2_add(total, ways);
+                }
+                counts[index][remaining] = total;
+            }
+        }
+
+        table.counts = counts;
+        table
+    }
+
+    /// Logarithm of number of ways to place `count` characters of `component`
+    /// among `remaining` positions.
+    fn term(&self, component: &ClassComponent, remaining: usize, count: usize) -> f64 {
+        self.log_factorials[remaining]
+            - self.log_factorials[count]
+            - self.log_factorials[remaining - count]
+            + component.ways(count)
+    }
+
+    fn total(&self) -> f64 {
+        self.counts[0][self.counts[0].len() - 1]
+    }
+
+    fn sample_count(&self, index: usize, component: &ClassComponent, remaining: usize) -> usize {
+        let all = self.counts[index][remaining];
+        let threshold: f64 = OsRng.gen();
+
+        let mut cumulative = 0.0;
+        let mut last_possible = 0;
+        for count in 0..=remaining {
+            let ways = self.term(component, remaining, count);
+            let rest = self.counts[index + 1][remaining - count];
+            if ways == f64::NEG_INFINITY || rest == f64::NEG_INFINITY {
+                continue;
+            }
+
+            last_possible = count;
+            cumulative += (ways + rest - all).exp2();
+            if threshold < cumulative {
+                return count;
+            }
+        }
+
+        // Floating point rounding can leave cumulative probability slightly below 1.
+        last_possible
+    }
+}
+
+pub(super) fn log2_add(a: f64, b: f64) -> f64 {
+    let (high, low) = if a > b { (a, b) } else { (b, a) };
+
+    match low == f64::NEG_INFINITY {
+        true => high,
+        false => high + (low - high).exp2().ln_1p() / std::f64::consts::LN_2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_in(password: &str, characters: &str) -> usize {
+        password.chars().filter(|c| characters.contains(*c)).count()
+    }
+
+    #[test]
+    fn generated_password_meets_requirements() {
+        let generator = PasswordGenerator::new(12)
+            .with_class(CharacterClass::Uppercase, 2)
+            .with_class(CharacterClass::Lowercase, 0)
+            .with_class(CharacterClass::Digits, 3)
+            .with_class(CharacterClass::Symbols, 4);
+
+        for _ in 0..200 {
+            let generated = generator.generate().unwrap();
+            let password = generated.secret().as_str().unwrap();
+
+            assert_eq!(password.chars().count(), 12);
+            assert!(count_in(password, UPPERCASE) >= 2);
+            assert!(count_in(password, DIGITS) >= 3);
+            assert!(count_in(password, SYMBOLS) >= 4);
+        }
+    }
+
+    #[test]
+    fn similar_characters_are_excluded() {
+        let generator = PasswordGenerator::new(64)
+            .with_class(CharacterClass::Uppercase, 0)
+            .with_class(CharacterClass::Digits, 0)
+            .with_class(CharacterClass::Custom("|Il".into()), 0)
+            .exclude_similar(true);
+
+        for _ in 0..50 {
+            let generated = generator.generate().unwrap();
+            assert_eq!(
+                count_in(generated.secret().as_str().unwrap(), SIMILAR_CHARACTERS),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn excluded_characters_are_not_used() {
+        let generator = PasswordGenerator::new(32)
+            .with_class(CharacterClass::Symbols, 0)
+            .exclude_characters("&<>");
+
+        let generated = generator.generate().unwrap();
+        assert_eq!(count_in(generated.secret().as_str().unwrap(), "&<>"), 0);
+        assert!((generated.entropy() - 32.0 * 29f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn custom_characters_are_used() {
+        let generator =
+            PasswordGenerator::new(16).with_class(CharacterClass::Custom("ąb".into()), 1);
+        let generated = generator.generate().unwrap();
+        let password = generated.secret().as_str().unwrap();
+
+        assert_eq!(password.chars().count(), 16);
+        assert!(password.chars().all(|c| c == 'ą' || c == 'b'));
+        assert!((generated.entropy() - 16.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entropy_counts_valid_passwords() {
+        let digits = PasswordGenerator::new(8).with_class(CharacterClass::Digits, 0);
+        assert!((digits.entropy().unwrap() - 8.0 * 10f64.log2()).abs() < 1e-9);
+
+        // Either digit followed by letter or the other way round.
+        let mixed = PasswordGenerator::new(2)
+            .with_class(CharacterClass::Digits, 1)
+            .with_class(CharacterClass::Uppercase, 1);
+        assert!((mixed.entropy().unwrap() - 520f64.log2()).abs() < 1e-9);
+
+        // Overlapping custom class does not add characters.
+        let overlapping = digits.with_class(CharacterClass::Custom("0123".into()), 0);
+        assert!((overlapping.entropy().unwrap() - 8.0 * 10f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn overlapping_classes_keep_their_minimums() {
+        let generator = PasswordGenerator::new(4)
+            .with_class(CharacterClass::Digits, 1)
+            .with_class(CharacterClass::Custom("0123".into()), 2);
+
+        for _ in 0..200 {
+            let generated = generator.generate().unwrap();
+            let password = generated.secret().as_str().unwrap();
+
+            assert_eq!(password.chars().count(), 4);
+            assert!(password.chars().all(|c| c.is_ascii_digit()));
+            assert!(count_in(password, "0123") >= 2);
+        }
+
+        // Four digits with at least two of 0-3: 6 * 16 * 36 + 4 * 64 * 6 + 256.
+        assert!((generator.entropy().unwrap() - 5248f64.log2()).abs() < 1e-9);
+
+        // Minimum of the custom class is met by digits counted for `Digits` too.
+        let shared = PasswordGenerator::new(3)
+            .with_class(CharacterClass::Digits, 3)
+            .with_class(CharacterClass::Custom("0123".into()), 2)
+            .with_class(CharacterClass::Uppercase, 0);
+        assert!((shared.entropy().unwrap() - 352f64.log2()).abs() < 1e-9);
+        assert!(matches!(
+            PasswordGenerator::new(3)
+                .with_class(CharacterClass::Custom("0123".into()), 2)
+                .with_class(CharacterClass::Custom("3456".into()), 2)
+                .with_class(CharacterClass::Uppercase, 0)
+                .entropy(),
+            Ok(entropy) if entropy > 0.0
+        ));
+        assert!(matches!(
+            PasswordGenerator::new(3)
+                .with_class(CharacterClass::Custom("01".into()), 2)
+                .with_class(CharacterClass::Custom("23".into()), 2)
+                .with_class(CharacterClass::Digits, 0)
+                .generate(),
+            Err(GeneratorError::UnsatisfiableRequirements { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        assert!(matches!(
+            PasswordGenerator::new(0)
+                .with_class(CharacterClass::Digits, 0)
+                .generate(),
+            Err(GeneratorError::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            PasswordGenerator::new(8).generate(),
+            Err(GeneratorError::EmptyCharacterSet { .. })
+        ));
+        assert!(matches!(
+            PasswordGenerator::new(8)
+                .with_class(CharacterClass::Custom("O0".into()), 1)
+                .exclude_similar(true)
+                .generate(),
+            Err(GeneratorError::EmptyCharacterSet { .. })
+        ));
+        assert!(matches!(
+            PasswordGenerator::new(3)
+                .with_class(CharacterClass::Digits, 2)
+                .with_class(CharacterClass::Symbols, 2)
+                .generate(),
+            Err(GeneratorError::UnsatisfiableRequirements { .. })
+        ));
+    }
+}
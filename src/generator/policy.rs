@@ -0,0 +1,794 @@
+use super::password::{log2_add, MAX_PASSWORD_LENGTH};
+use super::{CharacterClass, GeneratedSecret, GeneratorError, PasswordGenerator};
+use crate::storage::SafeBuffer;
+use rand::{rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
+
+/// Number of candidates drawn before generator gives up on requirements
+/// which can be checked only after password is generated.
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Number of candidates drawn to estimate which part of them follows requirements
+/// checked only after password is generated.
+const ESTIMATE_SAMPLES: usize = 1000;
+
+const DEFAULT_CLASSES: [CharacterClass; 4] = [
+    CharacterClass::Uppercase,
+    CharacterClass::Lowercase,
+    CharacterClass::Digits,
+    CharacterClass::Symbols,
+];
+
+/// Set of rules which passwords of a site have to follow (e.g. maximal length
+/// of 16 characters, no `&`, must start with a letter). Policies are stored in the
+/// vault under a name and can be attached to entries.
+///
+/// Template is a sequence of tokens, each optionally followed by quantifier
+/// `{n}` or `{n,m}`:
+/// * `[...]` - any character of the set, ranges like `a-z` are supported,
+/// * `.` - any allowed character,
+/// * `\u`, `\l`, `\d`, `\s` - uppercase letter, lowercase letter, digit, symbol,
+/// * any other character (or character escaped with `\`) - that exact character.
+///
+/// For example policy requiring first character to be a letter can use template
+/// `[A-Za-z].{11,15}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    min_length: usize,
+    max_length: usize,
+    /// Classes making up alphabet of password. Empty list means all standard classes.
+    allowed: Vec<CharacterClass>,
+    required: Vec<(CharacterClass, usize)>,
+    /// Forbidden characters (single-character patterns) and substrings. Substrings
+    /// are compared case-insensitively.
+    forbidden: Vec<String>,
+    template: Option<String>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self::new(20, 20)
+    }
+}
+
+/// Single token of template with characters it accepts and how many times it repeats.
+struct TemplateToken {
+    characters: Vec<char>,
+    min: usize,
+    max: usize,
+}
+
+impl PasswordPolicy {
+    pub fn new(min_length: usize, max_length: usize) -> Self {
+        Self {
+            min_length,
+            max_length,
+            allowed: Vec::new(),
+            required: Vec::new(),
+            forbidden: Vec::new(),
+            template: None,
+        }
+    }
+
+    pub fn allow(mut self, class: CharacterClass) -> Self {
+        if !self.allowed.contains(&class) {
+            self.allowed.push(class);
+        }
+        self
+    }
+
+    /// Requires at least `minimum` characters of `class` in password.
+    pub fn require(mut self, class: CharacterClass, minimum: usize) -> Self {
+        match self.required.iter_mut().find(|(added, _)| *added == class) {
+            Some((_, added_minimum)) => *added_minimum = minimum,
+            None => self.required.push((class, minimum)),
+        }
+        self
+    }
+
+    pub fn forbid<T: Into<String>>(mut self, pattern: T) -> Self {
+        self.forbidden.push(pattern.into());
+        self
+    }
+
+    pub fn template<T: Into<String>>(mut self, template: T) -> Self {
+        self.template = Some(template.into());
+        self
+    }
+
+    pub fn length_range(&self) -> (usize, usize) {
+        (self.min_length, self.max_length)
+    }
+
+    /// Classes making up alphabet of password, all standard classes when empty.
+    pub fn allowed(&self) -> &[CharacterClass] {
+        &self.allowed
+    }
+
+    pub fn required(&self) -> &[(CharacterClass, usize)] {
+        &self.required
+    }
+
+    pub fn forbidden(&self) -> &[String] {
+        &self.forbidden
+    }
+
+    pub fn template_pattern(&self) -> Option<&str> {
+        self.template.as_deref()
+    }
+
+    /// Generates password following the policy. Without template password has
+    /// maximal allowed length.
+    ///
+    /// Entropy is exact unless forbidden substrings, or required classes together with
+    /// template, reject some of the generated candidates. It is estimated from the part
+    /// of candidates which were accepted then.
+    pub fn generate(&self) -> Result<GeneratedSecret, GeneratorError> {
+        self.check_length()?;
+
+        match &self.template {
+            Some(template) => self.generate_from_template(&self.parse_template(template)?),
+            None => self.generate_from_classes(),
+        }
+    }
+
+    /// Checks if password follows the policy.
+    pub fn validate(&self, password: &str) -> Result<(), GeneratorError> {
+        self.check_length()?;
+        let alphabet = self.alphabet();
+        let length = password.chars().count();
+
+        if length < self.min_length || length > self.max_length {
+            return Err(violation(format!(
+                "Password has {} characters, policy allows {} to {}.",
+                length, self.min_length, self.max_length
+            )));
+        }
+
+        match &self.template {
+            Some(template) => {
+                let tokens = self.parse_template(template)?;
+                let characters: Vec<char> = password.chars().collect();
+                if !matches_template(&tokens, &characters) {
+                    return Err(violation(format!(
+                        "Password does not match template {}.",
+                        template
+                    )));
+                }
+            }
+            None => {
+                if let Some(character) = password.chars().find(|c| !alphabet.contains(c)) {
+                    return Err(violation(format!(
+                        "Character {:?} is not allowed by policy.",
+                        character
+                    )));
+                }
+            }
+        }
+
+        self.check_candidate(password, &alphabet)
+    }
+
+    fn generate_from_classes(&self) -> Result<GeneratedSecret, GeneratorError> {
+        let alphabet = self.alphabet();
+        let mut generator = PasswordGenerator::new(self.max_length);
+
+        for (class, minimum) in self.required.iter() {
+            let class = CharacterClass::Custom(class_in(class, &alphabet).into_iter().collect());
+            // Classes can become the same within the alphabet, the higher minimum applies.
+            let minimum = generator
+                .classes()
+                .iter()
+                .find(|(added, _)| *added == class)
+                .map_or(*minimum, |(_, added)| (*added).max(*minimum));
+            generator = generator.with_class(class, minimum);
+        }
+        generator = generator.with_class(CharacterClass::Custom(alphabet.iter().collect()), 0);
+
+        // Forbidden characters are left out of the alphabet, only substrings are checked.
+        let prepared = generator.prepare()?;
+        let rejecting = self
+            .forbidden
+            .iter()
+            .any(|pattern| pattern.chars().count() > 1);
+        self.draw_candidate(prepared.entropy(), rejecting, &alphabet, || prepared.draw())
+    }
+
+    fn generate_from_template(
+        &self,
+        tokens: &[TemplateToken],
+    ) -> Result<GeneratedSecret, GeneratorError> {
+        let alphabet = self.alphabet();
+        let table = TemplateTable::new(tokens, self.max_length);
+        let entropy = table.total(self.min_length);
+        if entropy == f64::NEG_INFINITY {
+            return Err(unsatisfiable());
+        }
+
+        let rejecting = self.required.iter().any(|(_, minimum)| *minimum > 0)
+            || self
+                .forbidden
+                .iter()
+                .any(|pattern| pattern.chars().count() > 1);
+        self.draw_candidate(entropy, rejecting, &alphabet, || {
+            let mut password = SafeBuffer::with_capacity(self.max_length * 4);
+            let mut remaining = table.sample_length(self.min_length);
+
+            for (index, token) in tokens.iter().enumerate() {
+                let count = table.sample_count(index, token, remaining);
+                for _ in 0..count {
+                    let character = OsRng.gen_range(0..token.characters.len());
+                    password.push_char(token.characters[character]);
+                }
+                remaining -= count;
+            }
+
+            password
+        })
+    }
+
+    /// Draws candidates until one follows rules checked only after generation and
+    /// returns the first such one. Candidates are equally likely, so the accepted one
+    /// is equally likely among passwords following the rules. When the rules can reject
+    /// candidates, at least [`ESTIMATE_SAMPLES`] are drawn and `entropy` of candidates is
+    /// lowered by the part of them which was accepted, making it an estimate.
+    fn draw_candidate(
+        &self,
+        entropy: f64,
+        rejecting: bool,
+        alphabet: &[char],
+        mut draw: impl FnMut() -> SafeBuffer,
+    ) -> Result<GeneratedSecret, GeneratorError> {
+        let mut first = None;
+        let mut accepted = 0;
+        let mut drawn = 0;
+
+        while drawn < MAX_ATTEMPTS && (first.is_none() || (rejecting && drawn < ESTIMATE_SAMPLES)) {
+            let candidate = draw();
+            drawn += 1;
+            if self
+                .check_candidate(candidate.as_str().unwrap_or_default(), alphabet)
+                .is_ok()
+            {
+                accepted += 1;
+                first.get_or_insert(candidate);
+            }
+        }
+
+        match first {
+            Some(password) => Ok(GeneratedSecret::new(
+                password,
+                entropy + (accepted as f64 / drawn as f64).log2(),
+            )),
+            None => Err(unsatisfiable()),
+        }
+    }
+
+    /// Checks rules which cannot be enforced while password is being generated.
+    fn check_candidate(&self, password: &str, alphabet: &[char]) -> Result<(), GeneratorError> {
+        for (class, minimum) in self.required.iter() {
+            let count = password
+                .chars()
+                .filter(|c| class.characters().contains(*c) && alphabet.contains(c))
+                .count();
+
+            if count < *minimum {
+                return Err(violation(format!(
+                    "Password needs at least {} characters of class {:?}, has {}.",
+                    minimum, class, count
+                )));
+            }
+        }
+
+        match self.forbidden_substring(password) {
+            Some(pattern) => Err(violation(format!(
+                "Password contains forbidden pattern {:?}.",
+                pattern
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn forbidden_substring<T: AsRef<[u8]>>(&self, password: T) -> Option<&str> {
+        let password = std::str::from_utf8(password.as_ref()).unwrap_or_default();
+
+        self.forbidden
+            .iter()
+            .find(|pattern| contains_ignore_case(password, pattern))
+            .map(|pattern| pattern.as_str())
+    }
+
+    /// Returns allowed characters without forbidden ones.
+    fn alphabet(&self) -> Vec<char> {
+        let classes = match self.allowed.is_empty() {
+            true => &DEFAULT_CLASSES[..],
+            false => &self.allowed[..],
+        };
+
+        let mut alphabet: Vec<char> = Vec::new();
+        for character in classes.iter().flat_map(|class| class.characters().chars()) {
+            if !self.is_forbidden_char(character) && !alphabet.contains(&character) {
+                alphabet.push(character);
+            }
+        }
+
+        alphabet
+    }
+
+    fn check_length(&self) -> Result<(), GeneratorError> {
+        if self.min_length == 0
+            || self.min_length > self.max_length
+            || self.max_length > MAX_PASSWORD_LENGTH
+        {
+            return Err(GeneratorError::InvalidLength {
+                description: format!(
+                    "Length range {}-{} is invalid - it must be within 1-{}.",
+                    self.min_length, self.max_length, MAX_PASSWORD_LENGTH
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn parse_template(&self, template: &str) -> Result<Vec<TemplateToken>, GeneratorError> {
+        let alphabet = self.alphabet();
+        let mut characters = template.chars().peekable();
+        let mut tokens = Vec::new();
+
+        while let Some(character) = characters.next() {
+            let set: Vec<char> = match character {
+                '[' => {
+                    let mut set = Vec::new();
+                    let mut previous = None;
+                    loop {
+                        let character = match characters.next() {
+                            Some(']') => break,
+                            Some('\\') => characters.next(),
+                            Some('-')
+                                if previous.is_some()
+                                    && !matches!(characters.peek(), None | Some(']')) =>
+                            {
+                                let start: char = previous.unwrap();
+                                let end = characters.next().unwrap();
+                                set.extend((start..=end).skip(1));
+                                previous = None;
+                                continue;
+                            }
+                            other => other,
+                        }
+                        .ok_or_else(|| template_error(template, "unclosed character set"))?;
+
+                        set.push(character);
+                        previous = Some(character);
+                    }
+
+                    set.into_iter().filter(|c| alphabet.contains(c)).collect()
+                }
+                '.' => alphabet.clone(),
+                '\\' => match characters.next() {
+                    Some('u') => class_in(&CharacterClass::Uppercase, &alphabet),
+                    Some('l') => class_in(&CharacterClass::Lowercase, &alphabet),
+                    Some('d') => class_in(&CharacterClass::Digits, &alphabet),
+                    Some('s') => class_in(&CharacterClass::Symbols, &alphabet),
+                    Some(literal) => self.literal(literal)?,
+                    None => return Err(template_error(template, "dangling escape")),
+                },
+                '{' | '}' | ']' => {
+                    return Err(template_error(
+                        template,
+                        &format!("unexpected {:?}", character),
+                    ))
+                }
+                literal => self.literal(literal)?,
+            };
+
+            let mut set = set;
+            set.sort_unstable();
+            set.dedup();
+            if set.is_empty() {
+                return Err(GeneratorError::EmptyCharacterSet {
+                    description: format!(
+                        "Token {} of template {} has no allowed characters.",
+                        tokens.len() + 1,
+                        template
+                    ),
+                });
+            }
+
+            let (min, max) = match characters.peek() {
+                Some('{') => {
+                    characters.next();
+                    let mut quantifier = String::new();
+                    loop {
+                        match characters.next() {
+                            Some('}') => break,
+                            Some(character) => quantifier.push(character),
+                            None => return Err(template_error(template, "unclosed quantifier")),
+                        }
+                    }
+
+                    parse_quantifier(&quantifier)
+                        .ok_or_else(|| template_error(template, "invalid quantifier"))?
+                }
+                _ => (1, 1),
+            };
+
+            tokens.push(TemplateToken {
+                characters: set,
+                min,
+                max,
+            });
+        }
+
+        let shortest: usize = tokens.iter().map(|token| token.min).sum();
+        let longest: usize = tokens.iter().map(|token| token.max).sum();
+        if longest < self.min_length || shortest > self.max_length {
+            return Err(GeneratorError::UnsatisfiableRequirements {
+                description: format!(
+                    "Template {} creates passwords of length {}-{}, policy requires {}-{}.",
+                    template, shortest, longest, self.min_length, self.max_length
+                ),
+            });
+        }
+
+        Ok(tokens)
+    }
+
+    fn literal(&self, character: char) -> Result<Vec<char>, GeneratorError> {
+        match self.is_forbidden_char(character) {
+            false => Ok(vec![character]),
+            true => Err(GeneratorError::UnsatisfiableRequirements {
+                description: format!("Template uses forbidden character {:?}.", character),
+            }),
+        }
+    }
+
+    fn is_forbidden_char(&self, character: char) -> bool {
+        self.forbidden
+            .iter()
+            .any(|pattern| pattern.chars().eq(std::iter::once(character)))
+    }
+}
+
+/// Table of base-2 logarithms of numbers of passwords the template produces. Entry
+/// `[i][l]` holds number of ways tokens `i..` produce `l` characters. It is used to draw
+/// password length and repeats of every token with exactly the probability of uniform
+/// choice among all passwords of allowed length.
+struct TemplateTable {
+    counts: Vec<Vec<f64>>,
+}
+
+impl TemplateTable {
+    fn new(tokens: &[TemplateToken], max_length: usize) -> Self {
+        let mut counts = vec![vec![f64::NEG_INFINITY; max_length + 1]; tokens.len() + 1];
+        counts[tokens.len()][0] = 0.0;
+
+        for (index, token) in tokens.iter().enumerate().rev() {
+            for length in 0..=max_length {
+                let mut total = f64::NEG_INFINITY;
+                for count in token.min..=token.max.min(length) {
+                    total = log2_add(
+                        total,
+                        Self::term(token, count) + counts[index + 1][length - count],
+                    );
+                }
+                counts[index][length] = total;
+            }
+        }
+
+        Self { counts }
+    }
+
+    /// Logarithm of number of strings made of `count` characters of `token`.
+    fn term(token: &TemplateToken, count: usize) -> f64 {
+        match count {
+            0 => 0.0,
+            _ => count as f64 * (token.characters.len() as f64).log2(),
+        }
+    }
+
+    /// Logarithm of number of passwords at least `min_length` characters long.
+    fn total(&self, min_length: usize) -> f64 {
+        self.counts[0]
+            .iter()
+            .skip(min_length)
+            .fold(f64::NEG_INFINITY, |total, count| log2_add(total, *count))
+    }
+
+    fn sample_length(&self, min_length: usize) -> usize {
+        let all = self.total(min_length);
+        let threshold: f64 = OsRng.gen();
+
+        let mut cumulative = 0.0;
+        let mut last_possible = min_length;
+        for (length, count) in self.counts[0].iter().enumerate().skip(min_length) {
+            if *count == f64::NEG_INFINITY {
+                continue;
+            }
+
+            last_possible = length;
+            cumulative += (count - all).exp2();
+            if threshold < cumulative {
+                return length;
+            }
+        }
+
+        // Floating point rounding can leave cumulative probability slightly below 1.
+        last_possible
+    }
+
+    /// Draws how many characters `token` at `index` produces, out of `remaining` ones.
+    fn sample_count(&self, index: usize, token: &TemplateToken, remaining: usize) -> usize {
+        let all = self.counts[index][remaining];
+        let threshold: f64 = OsRng.gen();
+
+        let mut cumulative = 0.0;
+        let mut last_possible = token.min;
+        for count in token.min..=token.max.min(remaining) {
+            let rest = self.counts[index + 1][remaining - count];
+            if rest == f64::NEG_INFINITY {
+                continue;
+            }
+
+            last_possible = count;
+            cumulative += (Self::term(token, count) + rest - all).exp2();
+            if threshold < cumulative {
+                return count;
+            }
+        }
+
+        last_possible
+    }
+}
+
+fn class_in(class: &CharacterClass, alphabet: &[char]) -> Vec<char> {
+    class
+        .characters()
+        .chars()
+        .filter(|c| alphabet.contains(c))
+        .collect()
+}
+
+fn parse_quantifier(quantifier: &str) -> Option<(usize, usize)> {
+    let (min, max) = match quantifier.split_once(',') {
+        Some((min, max)) => (min.trim().parse().ok()?, max.trim().parse().ok()?),
+        None => {
+            let count = quantifier.trim().parse().ok()?;
+            (count, count)
+        }
+    };
+
+    match min <= max && max <= MAX_PASSWORD_LENGTH {
+        true => Some((min, max)),
+        false => None,
+    }
+}
+
+/// Checks if whole password matches template tokens. Entry `[i][p]` of the table tells
+/// whether tokens `i..` match password from position `p`, so every pair is checked once.
+fn matches_template(tokens: &[TemplateToken], password: &[char]) -> bool {
+    let mut matches = vec![vec![false; password.len() + 1]; tokens.len() + 1];
+    matches[tokens.len()][password.len()] = true;
+
+    for (index, token) in tokens.iter().enumerate().rev() {
+        for start in 0..=password.len() {
+            let matching_prefix = password[start..]
+                .iter()
+                .take(token.max)
+                .take_while(|c| token.characters.contains(c))
+                .count();
+
+            matches[index][start] =
+                (token.min..=matching_prefix).any(|count| matches[index + 1][start + count]);
+        }
+    }
+
+    matches[0][0]
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return false;
+    }
+
+    haystack.char_indices().any(|(start, _)| {
+        let mut rest = haystack[start..].chars().flat_map(char::to_lowercase);
+        needle
+            .chars()
+            .flat_map(char::to_lowercase)
+            .all(|expected| rest.next() == Some(expected))
+    })
+}
+
+fn violation(description: String) -> GeneratorError {
+    GeneratorError::PolicyViolation { description }
+}
+
+fn unsatisfiable() -> GeneratorError {
+    GeneratorError::UnsatisfiableRequirements {
+        description: format!(
+            "No password following the policy was found in {} attempts.",
+            MAX_ATTEMPTS
+        ),
+    }
+}
+
+fn template_error(template: &str, reason: &str) -> GeneratorError {
+    GeneratorError::InvalidTemplate {
+        description: format!("Template {} is invalid: {}.", template, reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intranet_policy() -> PasswordPolicy {
+        PasswordPolicy::new(12, 16)
+            .forbid("&")
+            .require(CharacterClass::Digits, 2)
+            .template("[A-Za-z].{11,15}")
+    }
+
+    #[test]
+    fn template_policy_is_followed() {
+        let policy = intranet_policy();
+
+        for _ in 0..100 {
+            let generated = policy.generate().unwrap();
+            let password = generated.secret().as_str().unwrap();
+            let length = password.chars().count();
+
+            assert!((12..=16).contains(&length));
+            assert!(password.chars().next().unwrap().is_ascii_alphabetic());
+            assert!(!password.contains('&'));
+            assert!(password.chars().filter(char::is_ascii_digit).count() >= 2);
+            policy.validate(password).unwrap();
+        }
+    }
+
+    #[test]
+    fn class_policy_is_followed() {
+        let policy = PasswordPolicy::new(8, 10)
+            .allow(CharacterClass::Lowercase)
+            .allow(CharacterClass::Digits)
+            .require(CharacterClass::Digits, 3)
+            .forbid("0")
+            .forbid("abc");
+
+        for _ in 0..100 {
+            let generated = policy.generate().unwrap();
+            let password = generated.secret().as_str().unwrap();
+
+            assert_eq!(password.chars().count(), 10);
+            assert!(password
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+            assert!(!password.contains('0'));
+            policy.validate(password).unwrap();
+        }
+
+        // Passwords containing `abc` are left out of the count.
+        let unconstrained = PasswordGenerator::new(10)
+            .with_class(CharacterClass::Custom("123456789".into()), 3)
+            .with_class(CharacterClass::Lowercase, 0)
+            .entropy()
+            .unwrap();
+        assert!(policy.generate().unwrap().entropy() < unconstrained + 1e-9);
+        let without_abc = PasswordPolicy::new(8, 10)
+            .allow(CharacterClass::Lowercase)
+            .allow(CharacterClass::Digits)
+            .require(CharacterClass::Digits, 3)
+            .forbid("0");
+        assert!((without_abc.generate().unwrap().entropy() - unconstrained).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entropy_counts_only_accepted_passwords() {
+        // Half of 16 strings of `a` and `b` do not contain `aa`.
+        let policy = PasswordPolicy::new(4, 4)
+            .allow(CharacterClass::Custom("ab".into()))
+            .forbid("aa");
+        assert!((policy.generate().unwrap().entropy() - 3.0).abs() < 0.2);
+
+        // 11 of 16 strings have at least two `a`.
+        let policy = PasswordPolicy::new(4, 4)
+            .allow(CharacterClass::Custom("ab".into()))
+            .require(CharacterClass::Custom("a".into()), 2)
+            .template("[ab]{4}");
+        assert!((policy.generate().unwrap().entropy() - 11f64.log2()).abs() < 0.2);
+    }
+
+    #[test]
+    fn overlapping_requirements_are_followed() {
+        let policy = PasswordPolicy::new(6, 6)
+            .require(CharacterClass::Digits, 2)
+            .require(CharacterClass::Custom("0123".into()), 2)
+            .require(CharacterClass::Custom("0123456789".into()), 3)
+            .require(CharacterClass::Digits, 1);
+
+        for _ in 0..100 {
+            let generated = policy.generate().unwrap();
+            let password = generated.secret().as_str().unwrap();
+
+            assert!(password.chars().filter(char::is_ascii_digit).count() >= 3);
+            assert!(password.chars().filter(|c| "0123".contains(*c)).count() >= 2);
+            policy.validate(password).unwrap();
+        }
+    }
+
+    #[test]
+    fn validation_reports_violations() {
+        let policy = intranet_policy();
+
+        assert!(policy.validate("a1b2c3d4e5f6").is_ok());
+        assert!(policy.validate("1a2b3c4d5e6f").is_err());
+        assert!(policy.validate("a1b2c3d4e5f&").is_err());
+        assert!(policy.validate("abcdefghijk1").is_err());
+        assert!(policy.validate("a1b2c3d4e5f6g7h8i").is_err());
+
+        // Each way to split the letters between tokens is not tried separately.
+        let repeated = PasswordPolicy::new(1, 200).template("[a-z]{0,4}".repeat(40));
+        assert!(repeated.validate(&"a".repeat(150)).is_ok());
+        assert!(repeated.validate(&format!("{}1", "a".repeat(150))).is_err());
+
+        let no_password = PasswordPolicy::new(4, 20).forbid("password");
+        assert!(no_password.validate("myPassWord1").is_err());
+        assert!(no_password.validate("myPass1Word").is_ok());
+    }
+
+    #[test]
+    fn template_syntax() {
+        let policy = PasswordPolicy::new(6, 6).template(r"\u\l{2}-\d{2}");
+        let generated = policy.generate().unwrap();
+        let password: Vec<char> = generated.secret().as_str().unwrap().chars().collect();
+
+        assert!(password[0].is_ascii_uppercase());
+        assert!(password[1..3].iter().all(char::is_ascii_lowercase));
+        assert_eq!(password[3], '-');
+        assert!(password[4..].iter().all(char::is_ascii_digit));
+        assert!((generated.entropy() - (26f64.powi(3) * 100.0).log2()).abs() < 1e-9);
+
+        // Every password of allowed length is equally likely, `b` as often as `aa`.
+        let policy = PasswordPolicy::new(1, 2).template("[ab]{1,2}");
+        let generated = policy.generate().unwrap();
+        assert!((generated.entropy() - 6f64.log2()).abs() < 1e-9);
+        let long = (0..3000)
+            .filter(|_| policy.generate().unwrap().secret().len() == 2)
+            .count();
+        assert!((1800..2200).contains(&long));
+        let policy = PasswordPolicy::new(2, 3).template("[ab]{1,3}");
+        assert!((policy.generate().unwrap().entropy() - 12f64.log2()).abs() < 1e-9);
+
+        for template in ["[abc", r"ab\", "a{2", "a{3,1}", "}"] {
+            assert!(matches!(
+                PasswordPolicy::new(1, 10).template(template).generate(),
+                Err(GeneratorError::InvalidTemplate { .. })
+            ));
+        }
+
+        assert!(matches!(
+            PasswordPolicy::new(10, 12).template("[a-z]{3}").generate(),
+            Err(GeneratorError::UnsatisfiableRequirements { .. })
+        ));
+        assert!(matches!(
+            PasswordPolicy::new(1, 4)
+                .forbid("&")
+                .template("a&b")
+                .generate(),
+            Err(GeneratorError::UnsatisfiableRequirements { .. })
+        ));
+    }
+
+    #[test]
+    fn policy_round_trip() {
+        let policy = intranet_policy();
+        let bytes = postcard::to_allocvec(&policy).unwrap();
+
+        assert_eq!(
+            postcard::from_bytes::<PasswordPolicy>(&bytes).unwrap(),
+            policy
+        );
+    }
+}
@@ -9,10 +9,11 @@ use std::sync::RwLock;
 /// Contains program's translation (map of strings). Should be initialized by [`load_translation`]
 /// function after initializing program's configuration (see documentation of [`configuration module`]).
 /// Change in translation configuration has effect only after program restart.
+/// If translation is used before it was loaded, default (English) translation is used.
 ///
 /// [`load_translation`]: crate::language::load_translation
 /// [`configuration module`]: crate::configuration
-pub const TRANSLATION: OnceCell<Translation> = OnceCell::new();
+pub static TRANSLATION: OnceCell<Translation> = OnceCell::new();
 
 /// Static variable containing all keys which should be available in [`TRANSLATION`].
 /// It is generated at the time of compilation by using [`get_translation!`] macro.
@@ -29,7 +30,7 @@ pub const TRANSLATION: OnceCell<Translation> = OnceCell::new();
 pub static REQUIRED_FIELDS: [&'static str] = [..];
 
 /// Represent all languages available in program.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Language {
     /// Polish language
     Polish,
@@ -85,8 +86,7 @@ macro_rules! get_translation {
         static _N: &'static str = $name;
 
         crate::language::TRANSLATION
-            .get()
-            .unwrap()
+            .get_or_init(|| crate::language::Translation::default())
             .get_translation($name)
     }};
 }
@@ -96,6 +96,12 @@ macro_rules! get_translation {
 ///
 /// [`HashMap`]: std::collections::HashMap
 pub struct Translation(HashMap<String, &'static str>);
+
+impl Default for Translation {
+    fn default() -> Self {
+        default_translation().into()
+    }
+}
 impl From<HashMap<String, &'static str>> for Translation {
     fn from(map: HashMap<String, &'static str>) -> Self {
         Translation(map)
@@ -103,21 +109,27 @@ impl From<HashMap<String, &'static str>> for Translation {
 }
 
 /// Loads program's translation into [`TRANSLATION`] variable.
-/// Can be used only once thanks to [`OnceCell`] implementation.
+/// Can be used only once thanks to [`OnceCell`] implementation - subsequent calls
+/// leave already loaded translation untouched. Fields missing from translation
+/// file are taken from default translation.
 ///
 /// # Arguments
 ///
 /// * `conf` - A lock reference providing non-mutable access to program's configuration.
-pub fn load_translation(conf: &RwLock<ProgramConfiguration>) -> Translation {
+pub fn load_translation(conf: &RwLock<ProgramConfiguration>) -> &'static Translation {
     let conf = conf.read().unwrap();
 
-    match crate::configuration::read_translation(conf.get_language()) {
-        Err(_e) => {
-            // TODO: Log error
-            default_translation().into()
+    TRANSLATION.get_or_init(|| {
+        let mut translation = default_translation();
+        match crate::configuration::read_translation(conf.get_language()) {
+            Err(_e) => {
+                // TODO: Log error
+            }
+            Ok(map) => translation.extend(leak_map(map)),
         }
-        Ok(map) => leak_map(map).into(),
-    }
+
+        translation.into()
+    })
 }
 
 /// Leaks values of map so they become static strings. It significantly makes usage of
@@ -165,7 +177,91 @@ macro_rules! generate_translation {
 
 fn default_translation() -> HashMap<String, &'static str> {
     generate_translation! {
-        "TRANSLATION_NOT_FOUND": "Translation for this field was not found. Please report this bug."
+        "TRANSLATION_NOT_FOUND": "Translation for this field was not found. Please report this bug.",
+        "STRENGTH_SCORE_VERY_WEAK": "Very weak",
+        "STRENGTH_SCORE_WEAK": "Weak",
+        "STRENGTH_SCORE_FAIR": "Fair",
+        "STRENGTH_SCORE_STRONG": "Strong",
+        "STRENGTH_SCORE_VERY_STRONG": "Very strong",
+        "STRENGTH_WARNING_COMMON_PASSWORD": "This is one of the most common passwords.",
+        "STRENGTH_WARNING_PERSONAL_DATA": "Password contains your personal data.",
+        "STRENGTH_WARNING_SINGLE_WORD": "A word by itself is easy to guess.",
+        "STRENGTH_WARNING_DICTIONARY_WORD": "Dictionary words are easy to guess.",
+        "STRENGTH_WARNING_KEYBOARD_PATTERN": "Neighbouring keys on the keyboard are easy to guess.",
+        "STRENGTH_WARNING_REPEAT": "Repeated characters and words are easy to guess.",
+        "STRENGTH_WARNING_SEQUENCE": "Sequences like abc or 6543 are easy to guess.",
+        "STRENGTH_WARNING_DATE": "Dates are often easy to guess.",
+        "STRENGTH_SUGGESTION_USE_WORDS": "Use a few words, avoid common phrases.",
+        "STRENGTH_SUGGESTION_NO_SYMBOLS_NEEDED": "No need for symbols, digits or uppercase letters.",
+        "STRENGTH_SUGGESTION_ADD_WORDS": "Add another word or two. Uncommon words are better.",
+        "STRENGTH_SUGGESTION_CAPITALIZATION": "Capitalization doesn't help very much.",
+        "STRENGTH_SUGGESTION_ALL_UPPERCASE": "All-uppercase is almost as easy to guess as all-lowercase.",
+        "STRENGTH_SUGGESTION_REVERSED": "Reversed words aren't much harder to guess.",
+        "STRENGTH_SUGGESTION_L33T": "Predictable substitutions like '@' instead of 'a' don't help very much.",
+        "STRENGTH_SUGGESTION_LONGER_KEYBOARD_PATTERN": "Use a longer keyboard pattern with more turns.",
+        "STRENGTH_SUGGESTION_AVOID_REPEATS": "Avoid repeated words and characters.",
+        "STRENGTH_SUGGESTION_AVOID_SEQUENCES": "Avoid sequences.",
+        "STRENGTH_SUGGESTION_AVOID_DATES": "Avoid dates and years that are associated with you.",
+        "HEALTH_OUTDATED_ENCRYPTION": "Encryption algorithm is not recommended anymore",
+        "HEALTH_OUTDATED_HASH": "Hash algorithm is not recommended anymore",
+        "HEALTH_OUTDATED_KEY_DERIVATION": "Key derivation algorithm is not recommended anymore",
+        "HEALTH_WEAK_KEY_DERIVATION_OPTION": "Key derivation setting is below recommended minimum",
+        "HEALTH_UPGRADE_HINT": "Run `rustypass upgrade` to re-encrypt the vault with recommended settings",
+        "AUDIT_SEVERITY_LOW": "low",
+        "AUDIT_SEVERITY_MEDIUM": "medium",
+        "AUDIT_SEVERITY_HIGH": "high",
+        "AUDIT_REUSED_PASSWORD": "Password is used by more than one entry",
+        "AUDIT_WEAK_PASSWORD": "Weak password",
+        "AUDIT_OLD_PASSWORD": "Password was not changed for a long time",
+        "AUDIT_DAYS": "days",
+        "AUDIT_DUPLICATE_CREDENTIALS": "Entries have the same address and user name",
+        "AUDIT_MISSING_PASSWORD": "Entry has no password",
+        "AUDIT_BREACHED_PASSWORD": "Password appeared in known data breaches",
+        "AUDIT_TIMES": "times",
+        "GUI_GENERATE_PASSWORD": "Generate password",
+        "GUI_ENTROPY": "Entropy",
+        "GUI_BITS": "bits",
+        "GUI_MASTER_PASSWORD": "Master password",
+        "GUI_OPEN_VAULT": "Open vault",
+        "GUI_SEARCH_PLACEHOLDER": "Search, e.g. mail tag:work -tag:old modified:<90d",
+        "GUI_AUDIT": "Check passwords",
+        "GUI_AUDIT_HEALTHY": "Entries without problems",
+        "GUI_AUDIT_NO_PROBLEMS": "No problems were found",
+        "TUI_UNLOCK_TITLE": "Unlock vault",
+        "TUI_MASTER_PASSWORD": "Master password",
+        "TUI_UNLOCKING": "Unlocking...",
+        "TUI_LOCKED_IDLE": "Vault was locked after a period of inactivity",
+        "TUI_SEARCH": "Search",
+        "TUI_GROUPS": "Groups",
+        "TUI_ALL_ENTRIES": "All entries",
+        "TUI_ENTRIES": "Entries",
+        "TUI_DETAILS": "Details",
+        "TUI_NO_ENTRY": "No entry is selected",
+        "TUI_PATH": "Path",
+        "TUI_USERNAME": "User name",
+        "TUI_PASSWORD": "Password",
+        "TUI_URLS": "Address",
+        "TUI_TAGS": "Tags",
+        "TUI_NOTES": "Notes",
+        "TUI_OTP": "One-time password",
+        "TUI_PROFILE": "Password policy",
+        "TUI_NEW_ENTRY": "New entry",
+        "TUI_EDIT_ENTRY": "Edit entry",
+        "TUI_REMOVE_ENTRY": "Remove entry?",
+        "TUI_GENERATOR": "Password generator",
+        "TUI_LENGTH": "Length",
+        "TUI_SYMBOLS": "Symbols",
+        "TUI_ENTROPY": "Entropy",
+        "TUI_YES": "yes",
+        "TUI_NO": "no",
+        "TUI_COPIED": "Copied to clipboard",
+        "TUI_PASSWORD_STRENGTH": "Password strength",
+        "TUI_HELP_LOCKED": "Enter unlock  Esc quit",
+        "TUI_HELP_BROWSE": "/ search  Tab switch list  r reveal  c copy password  u copy user name  o copy one-time password  n new  e edit  d remove  g generator  l lock  q quit",
+        "TUI_HELP_SEARCH": "Enter finish search  Esc clear search",
+        "TUI_HELP_FORM": "Tab next field  Ctrl+G generate password  Ctrl+R reveal  Ctrl+S save  Esc cancel",
+        "TUI_HELP_GENERATOR": "Left/Right length  s symbols  Space generate  c copy  Esc close",
+        "TUI_HELP_CONFIRM": "y remove  any other key cancel"
     }
 }
 
@@ -1,11 +1,18 @@
 // #![warn(missing_docs)]
 
+mod audit;
 mod cli;
+mod clipboard;
 mod configuration;
 mod cryptography;
+mod generator;
 mod language;
+mod otp;
 mod communication;
+mod search;
 mod storage;
+mod strength;
+mod tui;
 mod ui;
 mod service;
 
@@ -16,6 +23,17 @@ fn main() -> Result<(), String> {
     let config = configuration::ProgramConfiguration::load().map_err(|e| format!("{e:?}"))?;
     language::load_translation(&config);
 
+    let matches = cli::command().get_matches_from(cli::arguments());
+    match cli::run(&matches, &config) {
+        Ok(Some(0)) => return Ok(()),
+        Ok(Some(code)) => std::process::exit(code),
+        Ok(None) => (),
+        Err(error) => {
+            eprintln!("error: {error}");
+            std::process::exit(error.exit_code());
+        }
+    }
+
     let (tx, rx) = unbounded_channel();
     ui::run_ui(tx);
 
@@ -0,0 +1,740 @@
+use super::{decode_base32, encode_base32, OtpError, OtpKind, OtpSecret, DEFAULT_PERIOD};
+use crate::cryptography::HashAlgorithm;
+use crate::storage::{Entry, SafeBuffer, Vault, VaultError};
+use zeroize::Zeroizing;
+
+const OTPAUTH_SCHEME: &'static str = "otpauth://";
+const MIGRATION_SCHEME: &'static str = "otpauth-migration://";
+const BASE64_ALPHABET: &'static [u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Problem found while importing accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportIssue {
+    /// Line of input (counted from 1) the problem was found in.
+    pub line: usize,
+    /// Account concerned, if its name could be read.
+    pub account: Option<String>,
+    pub description: String,
+    /// True if account was not imported at all, false if only part of its data was ignored.
+    pub skipped: bool,
+}
+
+/// Summary of import - paths of created entries and everything which could not be mapped.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: Vec<String>,
+    pub issues: Vec<ImportIssue>,
+}
+
+/// Collects issues found while reading single line of input.
+struct IssueCollector<'a> {
+    line: usize,
+    issues: &'a mut Vec<ImportIssue>,
+}
+
+impl IssueCollector<'_> {
+    fn warn(&mut self, account: Option<&str>, description: String) {
+        self.push(account, description, false);
+    }
+
+    fn skip(&mut self, account: Option<&str>, error: OtpError) {
+        let description = match error {
+            OtpError::InvalidSecret { description }
+            | OtpError::InvalidDigits { description }
+            | OtpError::InvalidPeriod { description }
+            | OtpError::UnsupportedAlgorithm { description }
+            | OtpError::InvalidUri { description } => description,
+        };
+        self.push(account, description, true);
+    }
+
+    fn push(&mut self, account: Option<&str>, description: String, skipped: bool) {
+        self.issues.push(ImportIssue {
+            line: self.line,
+            account: account.map(String::from),
+            description,
+            skipped,
+        });
+    }
+}
+
+fn invalid_uri<T: Into<String>>(description: T) -> OtpError {
+    OtpError::InvalidUri {
+        description: description.into(),
+    }
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Decodes `%XX` escapes. In query values `+` stands for space.
+fn percent_decode(text: &str, query: bool) -> Result<Zeroizing<String>, OtpError> {
+    let bytes = text.as_bytes();
+    let mut decoded = Zeroizing::new(Vec::with_capacity(bytes.len()));
+
+    let mut position = 0;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'%' => {
+                let value = bytes
+                    .get(position + 1..position + 3)
+                    .and_then(|hex| Some(hex_value(hex[0])? << 4 | hex_value(hex[1])?))
+                    .ok_or_else(|| invalid_uri("Invalid percent-encoding."))?;
+                decoded.push(value);
+                position += 3;
+            }
+            b'+' if query => {
+                decoded.push(b' ');
+                position += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                position += 1;
+            }
+        }
+    }
+
+    let text = std::str::from_utf8(&decoded)
+        .map_err(|_| invalid_uri("Decoded text is not valid UTF-8."))?
+        .to_string();
+    Ok(Zeroizing::new(text))
+}
+
+/// Decodes standard or URL-safe base64. Padding and whitespace are ignored.
+fn decode_base64(encoded: &str) -> Result<Zeroizing<Vec<u8>>, OtpError> {
+    let mut decoded = Zeroizing::new(Vec::with_capacity(encoded.len() * 3 / 4 + 1));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for character in encoded.bytes() {
+        let character = match character {
+            b'-' => b'+',
+            b'_' => b'/',
+            b'=' => continue,
+            c if c.is_ascii_whitespace() => continue,
+            c => c,
+        };
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|symbol| *symbol == character)
+            .ok_or_else(|| invalid_uri("Payload is not valid base64."))?;
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Splits `Issuer:account` label into its parts.
+fn split_label(label: &str) -> (Option<&str>, &str) {
+    match label.split_once(':') {
+        Some((issuer, account)) => (Some(issuer.trim()), account.trim()),
+        None => (None, label.trim()),
+    }
+}
+
+fn parse_algorithm(name: &str) -> Result<HashAlgorithm, OtpError> {
+    match name.to_ascii_uppercase().as_str() {
+        "SHA1" => Ok(HashAlgorithm::Sha1),
+        "SHA256" => Ok(HashAlgorithm::Sha256),
+        "SHA512" => Ok(HashAlgorithm::Sha512),
+        _ => Err(OtpError::UnsupportedAlgorithm {
+            description: format!("Algorithm {} is not supported.", name),
+        }),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, OtpError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| invalid_uri(format!("Value of {} ({}) is not a number.", name, value)))
+}
+
+/// Parses `otpauth://TYPE/LABEL?PARAMETERS` URI (Key Uri Format).
+fn parse_otpauth(uri: &str, issues: &mut IssueCollector) -> Result<OtpSecret, OtpError> {
+    let rest = &uri[OTPAUTH_SCHEME.len()..];
+    let (kind, rest) = rest
+        .split_once('/')
+        .ok_or_else(|| invalid_uri("Type of one-time password is missing."))?;
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+    let label = percent_decode(label, false)?;
+    let (label_issuer, account) = split_label(&label);
+
+    let mut secret = None;
+    let mut issuer = None;
+    let mut algorithm = HashAlgorithm::Sha1;
+    let mut digits = None;
+    let mut period = None;
+    let mut counter = None;
+
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let value = percent_decode(value, true)?;
+
+        match name.to_ascii_lowercase().as_str() {
+            "secret" => secret = Some(decode_base32(&value)?),
+            "issuer" => issuer = Some(value.to_string()),
+            "algorithm" => algorithm = parse_algorithm(&value)?,
+            "digits" => digits = Some(parse_number::<u32>("digits", &value)?),
+            "period" => period = Some(parse_number::<u64>("period", &value)?),
+            "counter" => counter = Some(parse_number::<u64>("counter", &value)?),
+            other => issues.warn(
+                Some(account),
+                format!("Parameter {} is not supported and was ignored.", other),
+            ),
+        }
+    }
+
+    let kind = match kind.to_ascii_lowercase().as_str() {
+        "totp" => {
+            if counter.is_some() {
+                issues.warn(Some(account), "Counter of TOTP was ignored.".into());
+            }
+            OtpKind::Totp {
+                period: period.unwrap_or(DEFAULT_PERIOD),
+            }
+        }
+        "hotp" => {
+            if period.is_some() {
+                issues.warn(Some(account), "Period of HOTP was ignored.".into());
+            }
+            if counter.is_none() {
+                issues.warn(
+                    Some(account),
+                    "Counter is missing, it starts from 0.".into(),
+                );
+            }
+            OtpKind::Hotp {
+                counter: counter.unwrap_or(0),
+            }
+        }
+        other => return Err(invalid_uri(format!("Type {} is not supported.", other))),
+    };
+
+    if let (Some(issuer), Some(label_issuer)) = (&issuer, label_issuer) {
+        if issuer != label_issuer {
+            issues.warn(
+                Some(account),
+                format!(
+                    "Issuer in label ({}) differs from issuer parameter ({}), the latter was used.",
+                    label_issuer, issuer
+                ),
+            );
+        }
+    }
+    let issuer = issuer.or_else(|| label_issuer.map(String::from));
+
+    let secret = secret.ok_or_else(|| OtpError::InvalidSecret {
+        description: "Secret is missing.".into(),
+    })?;
+    let mut otp = OtpSecret::new(secret, kind)?
+        .with_algorithm(algorithm)
+        .with_issuer(issuer)
+        .with_label(account);
+    if let Some(digits) = digits {
+        otp = otp.with_digits(digits)?;
+    }
+
+    Ok(otp)
+}
+
+/// Encodes everything except unreserved characters (RFC 3986) as `%XX`.
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Formats secret as `otpauth://` URI, which can be read back by [`parse_accounts`].
+pub fn otpauth_uri(otp: &OtpSecret) -> Zeroizing<String> {
+    let (kind, moving_factor) = match otp.kind() {
+        OtpKind::Totp { period } => ("totp", format!("period={}", period)),
+        OtpKind::Hotp { counter } => ("hotp", format!("counter={}", counter)),
+    };
+    let label = match otp.issuer() {
+        Some(issuer) => format!("{}:{}", percent_encode(issuer), percent_encode(otp.label())),
+        None => percent_encode(otp.label()),
+    };
+    let algorithm = match otp.algorithm() {
+        HashAlgorithm::Sha256 => "SHA256",
+        HashAlgorithm::Sha512 => "SHA512",
+        HashAlgorithm::Sha1 => "SHA1",
+    };
+
+    let mut uri = Zeroizing::new(format!(
+        "{}{}/{}?secret={}&algorithm={}&digits={}&{}",
+        OTPAUTH_SCHEME,
+        kind,
+        label,
+        encode_base32(otp.secret().as_ref()).as_str(),
+        algorithm,
+        otp.digits(),
+        moving_factor
+    ));
+    if let Some(issuer) = otp.issuer() {
+        uri.push_str("&issuer=");
+        uri.push_str(&percent_encode(issuer));
+    }
+
+    uri
+}
+
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    /// Fixed-size values are not used by the migration format.
+    Fixed,
+}
+
+/// Minimal reader of protocol buffers wire format.
+struct ProtoReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ProtoReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn varint(&mut self) -> Result<u64, OtpError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| invalid_uri("Payload is truncated."))?;
+            self.position += 1;
+
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(invalid_uri("Payload contains too long number."))
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], OtpError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid_uri("Payload is truncated."))?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u64, ProtoValue<'a>)>, OtpError> {
+        if self.position >= self.data.len() {
+            return Ok(None);
+        }
+
+        let key = self.varint()?;
+        let value = match key & 0x07 {
+            0 => ProtoValue::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                ProtoValue::Fixed
+            }
+            2 => {
+                let length = self.varint()? as usize;
+                ProtoValue::Bytes(self.take(length)?)
+            }
+            5 => {
+                self.take(4)?;
+                ProtoValue::Fixed
+            }
+            other => {
+                return Err(invalid_uri(format!(
+                    "Payload uses unknown wire type {}.",
+                    other
+                )))
+            }
+        };
+
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+fn proto_text(bytes: &[u8]) -> Result<String, OtpError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| invalid_uri("Text is not valid UTF-8."))
+}
+
+/// Reads single `OtpParameters` message of migration payload.
+fn parse_migration_account(data: &[u8], issues: &mut IssueCollector) -> Option<OtpSecret> {
+    let mut secret = None;
+    let mut name = String::new();
+    let mut issuer = String::new();
+    let mut algorithm = 0;
+    let mut digits = 0;
+    let mut kind = 0;
+    let mut counter = 0;
+
+    let mut reader = ProtoReader::new(data);
+    let result: Result<(), OtpError> = (|| {
+        while let Some((field, value)) = reader.next_field()? {
+            match (field, value) {
+                (1, ProtoValue::Bytes(bytes)) => secret = Some(SafeBuffer::from(bytes.to_vec())),
+                (2, ProtoValue::Bytes(bytes)) => name = proto_text(bytes)?,
+                (3, ProtoValue::Bytes(bytes)) => issuer = proto_text(bytes)?,
+                (4, ProtoValue::Varint(value)) => algorithm = value,
+                (5, ProtoValue::Varint(value)) => digits = value,
+                (6, ProtoValue::Varint(value)) => kind = value,
+                (7, ProtoValue::Varint(value)) => counter = value,
+                (field, _) => issues.warn(
+                    None,
+                    format!("Unknown field {} of account was ignored.", field),
+                ),
+            }
+        }
+        Ok(())
+    })();
+
+    let (label_issuer, account) = split_label(&name);
+    let account = account.to_string();
+    if let Err(error) = result {
+        issues.skip(Some(&account), error);
+        return None;
+    }
+
+    let issuer = match issuer.is_empty() {
+        true => label_issuer.map(String::from),
+        false => Some(issuer),
+    };
+
+    let algorithm = match algorithm {
+        0 | 1 => HashAlgorithm::Sha1,
+        2 => HashAlgorithm::Sha256,
+        3 => HashAlgorithm::Sha512,
+        4 => {
+            let error = OtpError::UnsupportedAlgorithm {
+                description: "Algorithm MD5 is not supported.".into(),
+            };
+            issues.skip(Some(&account), error);
+            return None;
+        }
+        other => {
+            let error = OtpError::UnsupportedAlgorithm {
+                description: format!("Unknown algorithm {}.", other),
+            };
+            issues.skip(Some(&account), error);
+            return None;
+        }
+    };
+
+    let digits = match digits {
+        0 | 1 => 6,
+        2 => 8,
+        other => {
+            let error = OtpError::InvalidDigits {
+                description: format!("Unknown number of digits {}.", other),
+            };
+            issues.skip(Some(&account), error);
+            return None;
+        }
+    };
+
+    let kind = match kind {
+        1 => OtpKind::Hotp { counter },
+        2 => OtpKind::Totp {
+            period: DEFAULT_PERIOD,
+        },
+        other => {
+            issues.warn(
+                Some(&account),
+                format!("Unknown type {}, account was imported as TOTP.", other),
+            );
+            OtpKind::Totp {
+                period: DEFAULT_PERIOD,
+            }
+        }
+    };
+
+    let otp = secret
+        .ok_or_else(|| OtpError::InvalidSecret {
+            description: "Secret is missing.".into(),
+        })
+        .and_then(|secret| OtpSecret::new(secret, kind))
+        .and_then(|otp| otp.with_algorithm(algorithm).with_digits(digits))
+        .map(|otp| otp.with_issuer(issuer).with_label(account.as_str()));
+
+    match otp {
+        Ok(otp) => Some(otp),
+        Err(error) => {
+            issues.skip(Some(&account), error);
+            None
+        }
+    }
+}
+
+/// Parses `otpauth-migration://offline?data=...` URI exported by Google Authenticator.
+fn parse_migration(uri: &str, issues: &mut IssueCollector) -> Result<Vec<OtpSecret>, OtpError> {
+    let (_, query) = uri
+        .split_once('?')
+        .ok_or_else(|| invalid_uri("Payload is missing."))?;
+    let data = query
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| *name == "data")
+        .map(|(_, value)| value)
+        .ok_or_else(|| invalid_uri("Payload is missing."))?;
+    // Exporters do not always escape `+` of base64, so it is never decoded as space.
+    let payload = decode_base64(&percent_decode(data, false)?)?;
+
+    let mut accounts = Vec::new();
+    let mut batch_size = 1;
+    let mut batch_index = 0;
+    let mut reader = ProtoReader::new(&payload);
+    while let Some((field, value)) = reader.next_field()? {
+        match (field, value) {
+            (1, ProtoValue::Bytes(account)) => {
+                accounts.extend(parse_migration_account(account, issues));
+            }
+            (3, ProtoValue::Varint(size)) => batch_size = size,
+            (4, ProtoValue::Varint(index)) => batch_index = index,
+            // Version and identifier of export do not affect accounts.
+            (2, _) | (5, _) => (),
+            (field, _) => issues.warn(
+                None,
+                format!("Unknown field {} of payload was ignored.", field),
+            ),
+        }
+    }
+
+    if batch_size > 1 {
+        issues.warn(
+            None,
+            format!(
+                "This is part {} of {} of the export - remaining parts have to be imported separately.",
+                batch_index + 1,
+                batch_size
+            ),
+        );
+    }
+
+    Ok(accounts)
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .map_or(false, |start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Reads accounts from `input` containing one `otpauth://` or `otpauth-migration://`
+/// URI per line. Empty lines are skipped.
+pub fn parse_accounts(input: &str) -> (Vec<OtpSecret>, Vec<ImportIssue>) {
+    let mut accounts = Vec::new();
+    let mut issues = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut collector = IssueCollector {
+            line: index + 1,
+            issues: &mut issues,
+        };
+        if starts_with_ignore_case(line, OTPAUTH_SCHEME) {
+            match parse_otpauth(line, &mut collector) {
+                Ok(account) => accounts.push(account),
+                Err(error) => collector.skip(None, error),
+            }
+        } else if starts_with_ignore_case(line, MIGRATION_SCHEME) {
+            match parse_migration(line, &mut collector) {
+                Ok(imported) => accounts.extend(imported),
+                Err(error) => collector.skip(None, error),
+            }
+        } else {
+            collector.skip(None, invalid_uri("Line is not an otpauth URI."));
+        }
+    }
+
+    (accounts, issues)
+}
+
+/// Path components cannot contain group separator.
+fn path_component(text: &str) -> String {
+    text.trim().replace('/', "-")
+}
+
+/// Imports accounts from `input` (see [`parse_accounts`]) into `group` of the vault.
+/// Every account becomes an entry `group/issuer/account` holding its secret.
+pub fn import(vault: &mut Vault, input: &str, group: &str) -> ImportReport {
+    let (accounts, mut issues) = parse_accounts(input);
+    let mut imported = Vec::new();
+
+    for (index, otp) in accounts.into_iter().enumerate() {
+        let account = match otp.label().trim().is_empty() {
+            true => format!("account-{}", index + 1),
+            false => path_component(otp.label()),
+        };
+        let path = match otp.issuer().filter(|issuer| !issuer.trim().is_empty()) {
+            Some(issuer) => format!("{}/{}/{}", group, path_component(issuer), account),
+            None => format!("{}/{}", group, account),
+        };
+
+        let mut entry = Entry::new();
+        entry.set_username(otp.label());
+        entry.set_otp(Some(otp));
+
+        match vault.add_entry(&path, entry) {
+            Ok(()) => imported.push(path),
+            Err(VaultError::EntryExists { path }) => issues.push(ImportIssue {
+                line: 0,
+                account: Some(account),
+                description: format!("Entry {} already exists.", path),
+                skipped: true,
+            }),
+            Err(error) => issues.push(ImportIssue {
+                line: 0,
+                account: Some(account),
+                description: format!("Entry could not be created: {:?}", error),
+                skipped: true,
+            }),
+        }
+    }
+
+    ImportReport { imported, issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GOOGLE_EXAMPLE: &'static str = "otpauth-migration://offline?data=CjEKCkhlbGxvId6tvu8SGEV4YW1wbGU6YWxpY2VAZ29vZ2xlLmNvbRoHRXhhbXBsZTAC";
+    /// Two accounts (HOTP with SHA256 and MD5 one) in first of two batches.
+    const MIXED_EXPORT: &'static str = "otpauth-migration://offline?data=CjoKIDEyMzQ1Njc4OTAxMjM0NTY3ODkwMTIzNDU2Nzg5MDEyEghBQ01FOmJvYhoEQUNNRSACKAIwATgFChgKCmFiY2RlZmdoaWoSBmxlZ2FjeSAEMAIQARgCIAAouWA%3D";
+
+    #[test]
+    fn otpauth_uris_are_parsed() {
+        let input = "otpauth://totp/Example:alice%40google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example\n\
+                     \n\
+                     otpauth://hotp/ACME%20Co:bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=7&digits=8&algorithm=sha256&image=x";
+        let (accounts, issues) = parse_accounts(input);
+
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].issuer(), Some("Example"));
+        assert_eq!(accounts[0].label(), "alice@google.com");
+        assert_eq!(accounts[0].kind(), OtpKind::Totp { period: 30 });
+        assert_eq!(accounts[0].secret().as_ref(), b"Hello!\xde\xad\xbe\xef");
+
+        assert_eq!(accounts[1].issuer(), Some("ACME Co"));
+        assert_eq!(accounts[1].kind(), OtpKind::Hotp { counter: 7 });
+        assert_eq!(accounts[1].digits(), 8);
+        assert_eq!(*accounts[1].algorithm(), HashAlgorithm::Sha256);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 3);
+        assert!(!issues[0].skipped);
+    }
+
+    #[test]
+    fn otpauth_uris_are_written() {
+        let otp = OtpSecret::hotp(SafeBuffer::from(b"Hello!\xde\xad\xbe\xef".to_vec()), 7)
+            .unwrap()
+            .with_algorithm(HashAlgorithm::Sha256)
+            .with_issuer(Some("ACME Co"))
+            .with_label("bob@example.com");
+        let uri = otpauth_uri(&otp);
+        assert_eq!(
+            uri.as_str(),
+            "otpauth://hotp/ACME%20Co:bob%40example.com?secret=JBSWY3DPEHPK3PXP\
+             &algorithm=SHA256&digits=6&counter=7&issuer=ACME%20Co"
+        );
+
+        let (accounts, issues) = parse_accounts(&uri);
+        assert!(issues.is_empty());
+        assert_eq!(accounts, [otp]);
+    }
+
+    #[test]
+    fn invalid_uris_are_reported() {
+        let input = "otpauth://totp/missing?issuer=X\n\
+                     otpauth://totp/md5?secret=JBSWY3DP&algorithm=MD5\n\
+                     otpauth://motp/x?secret=JBSWY3DP\n\
+                     https://example.com";
+        let (accounts, issues) = parse_accounts(input);
+
+        assert!(accounts.is_empty());
+        assert_eq!(issues.len(), 4);
+        assert!(issues.iter().all(|issue| issue.skipped));
+        assert_eq!(
+            issues.iter().map(|issue| issue.line).collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn migration_payload_is_parsed() {
+        let (accounts, issues) = parse_accounts(GOOGLE_EXAMPLE);
+        assert!(issues.is_empty());
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].issuer(), Some("Example"));
+        assert_eq!(accounts[0].label(), "alice@google.com");
+        assert_eq!(accounts[0].secret().as_ref(), b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(accounts[0].digits(), 6);
+
+        let (mut accounts, issues) = parse_accounts(MIXED_EXPORT);
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].kind(), OtpKind::Hotp { counter: 5 });
+        assert_eq!(accounts[0].generate(0).code, "89744399");
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].account.as_deref(), Some("legacy"));
+        assert!(issues[0].skipped);
+        assert!(!issues[1].skipped);
+    }
+
+    #[test]
+    fn plus_in_migration_payload_is_kept() {
+        let secret = b"He\x03\xe0o!\xde\xad\xbe\xef";
+        for data in [
+            "Ch8KCkhlA+BvId6tvu8SBWNhcm9sGgRQbHVzIAEoATAC",
+            "Ch8KCkhlA%2BBvId6tvu8SBWNhcm9sGgRQbHVzIAEoATAC",
+        ] {
+            let (accounts, issues) =
+                parse_accounts(&format!("otpauth-migration://offline?data={}", data));
+            assert!(issues.is_empty());
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].issuer(), Some("Plus"));
+            assert_eq!(accounts[0].secret().as_ref(), secret);
+        }
+    }
+
+    #[test]
+    fn accounts_become_entries() {
+        let mut vault = Vault::new();
+        let input = format!(
+            "{}\notpauth://totp/alice%40google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+            GOOGLE_EXAMPLE
+        );
+        let report = import(&mut vault, &input, "2fa");
+
+        assert_eq!(report.imported, ["2fa/Example/alice@google.com"]);
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].skipped);
+
+        let entry = vault.entry("2fa/Example/alice@google.com").unwrap();
+        assert_eq!(entry.username(), "alice@google.com");
+        assert_eq!(entry.otp().unwrap().issuer(), Some("Example"));
+    }
+}
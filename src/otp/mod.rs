@@ -0,0 +1,343 @@
+mod import;
+
+pub use import::{import, otpauth_uri, parse_accounts, ImportIssue, ImportReport};
+
+use crate::cryptography::HashAlgorithm;
+use crate::storage::SafeBuffer;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+const BASE32_ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub const DEFAULT_DIGITS: u32 = 6;
+pub const DEFAULT_PERIOD: u64 = 30;
+pub const MIN_DIGITS: u32 = 6;
+pub const MAX_DIGITS: u32 = 10;
+
+#[derive(Debug)]
+pub enum OtpError {
+    InvalidSecret { description: String },
+    InvalidDigits { description: String },
+    InvalidPeriod { description: String },
+    UnsupportedAlgorithm { description: String },
+    InvalidUri { description: String },
+}
+
+/// Decodes base32 text (RFC 4648). Letters are case-insensitive, whitespace,
+/// dashes and padding are ignored - secrets are often shown split into groups.
+pub fn decode_base32(encoded: &str) -> Result<SafeBuffer, OtpError> {
+    let mut decoded = SafeBuffer::with_capacity(encoded.len() * 5 / 8 + 1);
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+
+    for character in encoded.chars() {
+        if character.is_whitespace() || character == '-' || character == '=' {
+            continue;
+        }
+
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|symbol| *symbol as char == character.to_ascii_uppercase())
+            .ok_or_else(|| OtpError::InvalidSecret {
+                description: format!("Character {:?} is not valid in base32.", character),
+            })?;
+
+        buffer = (buffer << 5) | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.extend_from_slice(&[(buffer >> bits) as u8]);
+        }
+    }
+    buffer.zeroize();
+
+    if decoded.is_empty() {
+        return Err(OtpError::InvalidSecret {
+            description: "Secret is empty.".into(),
+        });
+    }
+
+    Ok(decoded)
+}
+
+/// Encodes bytes as unpadded, uppercase base32 text (RFC 4648).
+pub fn encode_base32(data: &[u8]) -> Zeroizing<String> {
+    let mut encoded = Zeroizing::new(String::with_capacity((data.len() * 8 + 4) / 5));
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u64;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    buffer.zeroize();
+
+    encoded
+}
+
+/// Way in which moving factor of one-time password is obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OtpKind {
+    /// Time-based (RFC 6238) - moving factor is number of `period`-second steps since UNIX epoch.
+    Totp { period: u64 },
+    /// Counter-based (RFC 4226) - counter is advanced every time code is generated.
+    Hotp { counter: u64 },
+}
+
+/// One-time code together with number of seconds it stays valid (TOTP only).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtpCode {
+    pub code: String,
+    pub valid_for: Option<u64>,
+}
+
+/// Seed of one-time passwords stored in an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtpSecret {
+    secret: SafeBuffer,
+    algorithm: HashAlgorithm,
+    digits: u32,
+    kind: OtpKind,
+    /// Service which issued the secret, e.g. `GitHub`.
+    issuer: Option<String>,
+    /// Account the secret belongs to, as shown by authenticator apps.
+    label: String,
+}
+
+impl OtpSecret {
+    /// Creates TOTP secret with settings used by most services (SHA1, 6 digits, 30 seconds).
+    pub fn totp(secret: SafeBuffer) -> Result<Self, OtpError> {
+        Self::new(
+            secret,
+            OtpKind::Totp {
+                period: DEFAULT_PERIOD,
+            },
+        )
+    }
+
+    pub fn hotp(secret: SafeBuffer, counter: u64) -> Result<Self, OtpError> {
+        Self::new(secret, OtpKind::Hotp { counter })
+    }
+
+    pub fn new(secret: SafeBuffer, kind: OtpKind) -> Result<Self, OtpError> {
+        if secret.is_empty() {
+            return Err(OtpError::InvalidSecret {
+                description: "Secret is empty.".into(),
+            });
+        }
+        if kind == (OtpKind::Totp { period: 0 }) {
+            return Err(OtpError::InvalidPeriod {
+                description: "Period of TOTP must be at least one second.".into(),
+            });
+        }
+
+        Ok(Self {
+            secret,
+            algorithm: HashAlgorithm::Sha1,
+            digits: DEFAULT_DIGITS,
+            kind,
+            issuer: None,
+            label: String::new(),
+        })
+    }
+
+    /// Creates secret from its base32 representation, as shown by services.
+    pub fn from_base32(encoded: &str, kind: OtpKind) -> Result<Self, OtpError> {
+        Self::new(decode_base32(encoded)?, kind)
+    }
+
+    /// Sets HMAC algorithm. Every available algorithm is defined by RFC 6238.
+    pub fn with_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_digits(mut self, digits: u32) -> Result<Self, OtpError> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&digits) {
+            return Err(OtpError::InvalidDigits {
+                description: format!(
+                    "Number of digits must be between {} and {}, got {}.",
+                    MIN_DIGITS, MAX_DIGITS, digits
+                ),
+            });
+        }
+
+        self.digits = digits;
+        Ok(self)
+    }
+
+    pub fn with_issuer<T: Into<String>>(mut self, issuer: Option<T>) -> Self {
+        self.issuer = issuer.map(Into::into);
+        self
+    }
+
+    pub fn with_label<T: Into<String>>(mut self, label: T) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn secret(&self) -> &SafeBuffer {
+        &self.secret
+    }
+
+    pub fn algorithm(&self) -> &HashAlgorithm {
+        &self.algorithm
+    }
+
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    pub fn kind(&self) -> OtpKind {
+        self.kind
+    }
+
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Computes HOTP value (RFC 4226) for given moving factor.
+    pub fn code_at(&self, counter: u64) -> String {
+        let hash = Zeroizing::new(
+            self.algorithm
+                .hmac(self.secret.as_ref(), &counter.to_be_bytes()),
+        );
+
+        // Dynamic truncation
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        let code = binary as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// Generates code valid at `time` (seconds since UNIX epoch). Counter of HOTP
+    /// secret is advanced, so the vault has to be saved afterwards.
+    pub fn generate(&mut self, time: u64) -> OtpCode {
+        match &mut self.kind {
+            OtpKind::Totp { period } => {
+                let period = (*period).max(1);
+                OtpCode {
+                    code: self.code_at(time / period),
+                    valid_for: Some(period - time % period),
+                }
+            }
+            OtpKind::Hotp { counter } => {
+                let current = *counter;
+                *counter = counter.wrapping_add(1);
+                OtpCode {
+                    code: self.code_at(current),
+                    valid_for: None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA1_SEED: &'static [u8] = b"12345678901234567890";
+    const SHA256_SEED: &'static [u8] = b"12345678901234567890123456789012";
+    const SHA512_SEED: &'static [u8] =
+        b"1234567890123456789012345678901234567890123456789012345678901234";
+
+    #[test]
+    fn hotp_matches_rfc_4226() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+
+        let mut secret = OtpSecret::hotp(SHA1_SEED.to_vec().into(), 0).unwrap();
+        for code in expected {
+            assert_eq!(
+                secret.generate(0),
+                OtpCode {
+                    code: code.into(),
+                    valid_for: None
+                }
+            );
+        }
+        assert_eq!(secret.kind(), OtpKind::Hotp { counter: 10 });
+    }
+
+    #[test]
+    fn totp_matches_rfc_6238() {
+        let vectors: [(u64, &str, &str, &str); 6] = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+
+        let secret = |seed: &[u8], algorithm| {
+            OtpSecret::totp(seed.to_vec().into())
+                .unwrap()
+                .with_algorithm(algorithm)
+                .with_digits(8)
+                .unwrap()
+        };
+        let mut sha1 = secret(SHA1_SEED, HashAlgorithm::Sha1);
+        let mut sha256 = secret(SHA256_SEED, HashAlgorithm::Sha256);
+        let mut sha512 = secret(SHA512_SEED, HashAlgorithm::Sha512);
+
+        for (time, expected_sha1, expected_sha256, expected_sha512) in vectors {
+            assert_eq!(sha1.generate(time).code, expected_sha1);
+            assert_eq!(sha256.generate(time).code, expected_sha256);
+            assert_eq!(sha512.generate(time).code, expected_sha512);
+        }
+
+        assert_eq!(sha1.generate(59).valid_for, Some(1));
+        assert_eq!(sha1.generate(60).valid_for, Some(30));
+    }
+
+    #[test]
+    fn base32_round_trip() {
+        let decoded = decode_base32("jbsw y3dp-ehpk 3pxp====").unwrap();
+        assert_eq!(decoded.as_ref(), b"Hello!\xde\xad\xbe\xef");
+        assert_eq!(encode_base32(decoded.as_ref()).as_str(), "JBSWY3DPEHPK3PXP");
+        assert_eq!(encode_base32(b"f").as_str(), "MY");
+
+        assert!(matches!(
+            decode_base32("JBSW1"),
+            Err(OtpError::InvalidSecret { .. })
+        ));
+        assert!(decode_base32("  ").is_err());
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let secret =
+            OtpSecret::from_base32("JBSWY3DPEHPK3PXP", OtpKind::Totp { period: 30 }).unwrap();
+
+        assert!(matches!(
+            secret.clone().with_digits(5),
+            Err(OtpError::InvalidDigits { .. })
+        ));
+        assert!(secret.with_digits(MAX_DIGITS).is_ok());
+        assert!(matches!(
+            OtpSecret::new(SHA1_SEED.to_vec().into(), OtpKind::Totp { period: 0 }),
+            Err(OtpError::InvalidPeriod { .. })
+        ));
+    }
+}
@@ -0,0 +1,695 @@
+mod query;
+
+pub use query::{matches_glob, Query, QueryError};
+
+use crate::storage::{timestamp, Entry, Vault};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Score of every matched character.
+const MATCH_SCORE: i32 = 16;
+/// Bonus for character following previously matched one.
+const CONSECUTIVE_BONUS: i32 = 8;
+/// Bonus for character starting a word (e.g. after `/`, `.`, `-` or space).
+const WORD_START_BONUS: i32 = 8;
+/// Additional bonus for match at the very beginning of the text.
+const TEXT_START_BONUS: i32 = 4;
+/// Bonus for query term equal to whole text.
+const EXACT_BONUS: i32 = 32;
+/// Penalty for every skipped character between matched characters.
+const GAP_PENALTY: i32 = 1;
+
+/// How much recently used entries are preferred - entry used right now has score
+/// multiplied by `1 + RECENCY_WEIGHT`, the bonus halves every [`RECENCY_HALF_LIFE`] seconds.
+const RECENCY_WEIGHT: f64 = 0.5;
+const RECENCY_HALF_LIFE: f64 = 14.0 * 24.0 * 60.0 * 60.0;
+
+/// Number of removed entries kept in the index before their content is dropped.
+const COMPACTION_THRESHOLD: usize = 64;
+
+/// Searchable part of an entry. Password, one-time password secret and other
+/// protected values are never indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Field {
+    /// Last segment of entry path.
+    Title,
+    /// Groups the entry belongs to (path without title).
+    Group,
+    Username,
+    Url,
+    Tag,
+    Notes,
+}
+
+impl Field {
+    fn weight(&self) -> f64 {
+        match self {
+            Self::Title => 1.0,
+            Self::Tag => 0.9,
+            Self::Group | Self::Username => 0.8,
+            Self::Url => 0.7,
+            Self::Notes => 0.4,
+        }
+    }
+}
+
+/// Entry matching the search query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub path: String,
+    pub score: f64,
+    /// Field which matched best, `None` if query had no free text.
+    pub field: Option<Field>,
+}
+
+struct IndexedField {
+    field: Field,
+    /// Position of the field in [`SearchIndex::text`].
+    start: u32,
+    end: u32,
+    /// Characters present in the field, see [`character_mask`].
+    mask: u64,
+}
+
+struct IndexedEntry {
+    path: String,
+    /// Position of entry fields in [`SearchIndex::fields`].
+    first_field: u32,
+    last_field: u32,
+    mask: u64,
+    last_used: Option<u64>,
+    removed: bool,
+}
+
+/// Bit set of characters present in `text` - letters and digits have their own bits,
+/// all other characters share one. Entries which lack any character of the query are
+/// skipped without scoring.
+fn character_mask(text: &[u8]) -> u64 {
+    text.iter().fold(0, |mask, byte| {
+        let bit = match byte {
+            b'a'..=b'z' => byte - b'a',
+            b'0'..=b'9' => 26 + byte - b'0',
+            _ => 36,
+        };
+        mask | 1 << bit
+    })
+}
+
+/// Texts are matched as UTF-8 bytes. Bytes of non-ASCII characters are treated
+/// as letters, so words in any script are recognized.
+fn is_separator(byte: u8) -> bool {
+    byte.is_ascii() && !byte.is_ascii_alphanumeric()
+}
+
+/// Returns true if all bytes of `pattern` appear in `text` in the same order.
+fn is_subsequence(pattern: &[u8], text: &[u8]) -> bool {
+    let mut position = 0;
+    for byte in pattern {
+        match text[position..]
+            .iter()
+            .position(|candidate| candidate == byte)
+        {
+            Some(found) => position += found + 1,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Score of impossible match. It is low enough to stay negative after bonuses are added.
+const NO_MATCH: i32 = i32::MIN / 2;
+
+/// Scores fuzzy matches. Buffers are reused between calls, so a single matcher
+/// should be used for the whole search.
+#[derive(Default)]
+struct FuzzyMatcher {
+    /// Earliest and latest position at which every byte of pattern can be matched.
+    low: Vec<usize>,
+    high: Vec<usize>,
+    previous: Vec<i32>,
+    current: Vec<i32>,
+}
+
+impl FuzzyMatcher {
+    /// Upper bound of score of pattern with `length` bytes: every byte matched right after
+    /// previous one and at word start, or whole text matched exactly.
+    fn max_score(length: usize) -> i32 {
+        let length = length as i32;
+        let fuzzy = length * (MATCH_SCORE + WORD_START_BONUS)
+            + (length - 1) * CONSECUTIVE_BONUS
+            + TEXT_START_BONUS;
+        let exact = length * (MATCH_SCORE + CONSECUTIVE_BONUS) + EXACT_BONUS;
+
+        fuzzy.max(exact)
+    }
+
+    /// Scores fuzzy match of `pattern` in `text` (both lowercase). All bytes of
+    /// `pattern` have to appear in `text` in the same order. Matches of consecutive
+    /// characters and matches at word starts score higher, gaps between matched characters
+    /// lower the score. Returns `None` if `pattern` does not match.
+    fn score(&mut self, pattern: &[u8], text: &[u8]) -> Option<i32> {
+        if pattern.is_empty() || pattern.len() > text.len() {
+            return None;
+        }
+        if pattern == text {
+            return Some(pattern.len() as i32 * (MATCH_SCORE + CONSECUTIVE_BONUS) + EXACT_BONUS);
+        }
+
+        let Self {
+            low,
+            high,
+            previous,
+            current,
+        } = self;
+
+        low.clear();
+        let mut position = 0;
+        for byte in pattern {
+            let found = position + text[position..].iter().position(|c| c == byte)?;
+            low.push(found);
+            position = found + 1;
+        }
+        high.clear();
+        high.resize(pattern.len(), 0);
+        let mut end = text.len();
+        for (i, byte) in pattern.iter().enumerate().rev() {
+            // Pattern is a subsequence of text, so the byte is always found.
+            end = text[..end].iter().rposition(|c| c == byte)?;
+            high[i] = end;
+        }
+
+        let bonus = |position: usize| match position {
+            0 => WORD_START_BONUS + TEXT_START_BONUS,
+            _ if is_separator(text[position - 1]) && !is_separator(text[position]) => {
+                WORD_START_BONUS
+            }
+            _ => 0,
+        };
+
+        // previous[j] - best score of matching pattern[..i] with pattern[i - 1] matched at text[j].
+        // Buffers hold NO_MATCH outside of rows being computed, so only touched ranges are reset.
+        if previous.len() < text.len() {
+            previous.resize(text.len(), NO_MATCH);
+            current.resize(text.len(), NO_MATCH);
+        }
+        for j in low[0]..=high[0] {
+            if text[j] == pattern[0] {
+                previous[j] = MATCH_SCORE + bonus(j);
+            }
+        }
+
+        for i in 1..pattern.len() {
+            // Best `previous[k] + k * GAP_PENALTY` over k < j - 1, so gap penalty is linear.
+            let mut best_before = NO_MATCH;
+            let mut k = low[i - 1];
+            for j in low[i]..=high[i] {
+                while k + 2 <= j {
+                    best_before = best_before.max(previous[k] + k as i32 * GAP_PENALTY);
+                    k += 1;
+                }
+                current[j] = match text[j] == pattern[i] {
+                    true => {
+                        let consecutive = previous[j - 1] + CONSECUTIVE_BONUS;
+                        let gapped = best_before - (j - 1) as i32 * GAP_PENALTY;
+                        consecutive.max(gapped) + MATCH_SCORE + bonus(j)
+                    }
+                    false => NO_MATCH,
+                };
+            }
+
+            previous[low[i - 1]..=high[i - 1]].fill(NO_MATCH);
+            std::mem::swap(previous, current);
+        }
+
+        let last = &mut previous[low[pattern.len() - 1]..=high[pattern.len() - 1]];
+        let score = last.iter().copied().max();
+        last.fill(NO_MATCH);
+
+        score.filter(|score| *score > NO_MATCH / 2)
+    }
+}
+
+fn lowercase(text: &str) -> Vec<u8> {
+    text.to_lowercase().into_bytes()
+}
+
+/// Contents of entry fields which may be searched.
+fn searchable_fields<'a>(path: &'a str, entry: &'a Entry) -> Vec<(Field, &'a str)> {
+    let (group, title) = path.rsplit_once('/').unwrap_or(("", path));
+
+    let mut fields = vec![(Field::Title, title)];
+    if !group.is_empty() {
+        fields.push((Field::Group, group));
+    }
+    fields.push((Field::Username, entry.username()));
+    fields.extend(entry.urls().iter().map(|url| (Field::Url, url.as_str())));
+    fields.extend(entry.tags().iter().map(|tag| (Field::Tag, tag.as_str())));
+    fields.push((Field::Notes, entry.notes()));
+    fields
+}
+
+/// In-memory index of searchable entry fields. It should be built once vault is
+/// unlocked and updated together with entries. Lowercase texts of all fields are kept
+/// in one buffer, so a search reads memory sequentially.
+#[derive(Default)]
+pub struct SearchIndex {
+    entries: Vec<IndexedEntry>,
+    fields: Vec<IndexedField>,
+    text: Vec<u8>,
+    /// Position of live entries in `entries`.
+    positions: HashMap<String, usize>,
+}
+
+impl SearchIndex {
+    pub fn new(vault: &Vault) -> Self {
+        let mut index = Self::default();
+        for (path, entry) in vault.entries() {
+            index.insert(path, entry);
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    fn text(&self, field: &IndexedField) -> &[u8] {
+        &self.text[field.start as usize..field.end as usize]
+    }
+
+    /// Fields of `entry` in which `term` matches, `term_mask` is its [`character_mask`].
+    fn candidates<'a>(
+        &'a self,
+        entry: &IndexedEntry,
+        term: &'a [u8],
+        term_mask: u64,
+    ) -> impl Iterator<Item = &'a IndexedField> + 'a {
+        self.fields[entry.first_field as usize..entry.last_field as usize]
+            .iter()
+            .filter(move |field| field.mask & term_mask == term_mask)
+            .filter(move |field| is_subsequence(term, self.text(field)))
+    }
+
+    /// Adds entry to the index or replaces indexed content of existing one.
+    pub fn insert(&mut self, path: &str, entry: &Entry) {
+        self.remove(path);
+        let fields = searchable_fields(path, entry);
+
+        let first_field = self.fields.len() as u32;
+        let mut mask = 0;
+        for (field, content) in fields {
+            let start = self.text.len();
+            self.text.extend(lowercase(content));
+            if self.text.len() > start {
+                let field_mask = character_mask(&self.text[start..]);
+                mask |= field_mask;
+                self.fields.push(IndexedField {
+                    field,
+                    start: start as u32,
+                    end: self.text.len() as u32,
+                    mask: field_mask,
+                });
+            }
+        }
+
+        self.positions.insert(path.to_string(), self.entries.len());
+        self.entries.push(IndexedEntry {
+            path: path.to_string(),
+            first_field,
+            last_field: self.fields.len() as u32,
+            mask,
+            last_used: entry.last_used(),
+            removed: false,
+        });
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        if let Some(position) = self.positions.remove(path) {
+            self.entries[position].removed = true;
+            if self.entries.len() > 2 * self.positions.len() + COMPACTION_THRESHOLD {
+                self.compact();
+            }
+        }
+    }
+
+    /// Drops content of removed entries.
+    fn compact(&mut self) {
+        let mut old = std::mem::take(self);
+        let entries = std::mem::take(&mut old.entries);
+        for mut entry in entries.into_iter().filter(|entry| !entry.removed) {
+            let first_field = self.fields.len() as u32;
+            for field in &old.fields[entry.first_field as usize..entry.last_field as usize] {
+                let start = self.text.len() as u32;
+                self.text.extend_from_slice(old.text(field));
+                self.fields.push(IndexedField {
+                    start,
+                    end: self.text.len() as u32,
+                    ..*field
+                });
+            }
+            entry.first_field = first_field;
+            entry.last_field = self.fields.len() as u32;
+            self.positions
+                .insert(entry.path.clone(), self.entries.len());
+            self.entries.push(entry);
+        }
+    }
+
+    /// Returns at most `limit` entries matching every whitespace-separated term of
+    /// `query`, best first. Terms match fuzzily, recently used entries rank higher.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.search_at(query, limit, timestamp())
+    }
+
+    fn search_at(&self, query: &str, limit: usize, now: u64) -> Vec<SearchResult> {
+        let terms: Vec<Vec<u8>> = query.split_whitespace().map(lowercase).collect();
+        match terms.is_empty() {
+            true => Vec::new(),
+            false => self.rank(&terms, limit, now, |_| true),
+        }
+    }
+
+    /// Returns at most `limit` entries of `vault` matching structured `query`. Entries are
+    /// ranked by free text of the query, if there is none only recent use is considered.
+    pub fn find(&self, vault: &Vault, query: &Query, limit: usize) -> Vec<SearchResult> {
+        self.find_at(vault, query, limit, timestamp())
+    }
+
+    fn find_at(&self, vault: &Vault, query: &Query, limit: usize, now: u64) -> Vec<SearchResult> {
+        let terms: Vec<Vec<u8>> = query
+            .free_text()
+            .iter()
+            .map(|term| lowercase(term))
+            .collect();
+        self.rank(&terms, limit, now, |path| {
+            vault
+                .entry(path)
+                .is_ok_and(|entry| query.filter_matches(path, entry, now))
+        })
+    }
+
+    /// Ranks entries accepted by `filter` which match all `terms`. Without terms all
+    /// accepted entries are returned.
+    fn rank(
+        &self,
+        terms: &[Vec<u8>],
+        limit: usize,
+        now: u64,
+        filter: impl Fn(&str) -> bool,
+    ) -> Vec<SearchResult> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let masks: Vec<u64> = terms.iter().map(|term| character_mask(term)).collect();
+        let query_mask = masks.iter().fold(0, |mask, term_mask| mask | term_mask);
+        // Longer terms match fewer entries, so checking them first rejects entries sooner.
+        let mut selective_first: Vec<usize> = (0..terms.len()).collect();
+        selective_first.sort_by_key(|index| std::cmp::Reverse(terms[*index].len()));
+
+        let mut matcher = FuzzyMatcher::default();
+        let mut matches: Vec<(f64, usize, Option<Field>)> = Vec::new();
+        'entries: for (position, entry) in self.entries.iter().enumerate() {
+            if entry.removed || entry.mask & query_mask != query_mask {
+                continue;
+            }
+            // Cheap check of every term first, most entries are rejected before scoring.
+            for &index in selective_first.iter() {
+                let (term, term_mask) = (&terms[index], masks[index]);
+                if self.candidates(entry, term, term_mask).next().is_none() {
+                    continue 'entries;
+                }
+            }
+            if !filter(&entry.path) {
+                continue;
+            }
+
+            let mut score = match terms.is_empty() {
+                true => 1.0,
+                false => 0.0,
+            };
+            let mut best: Option<(f64, Field)> = None;
+            for (term, term_mask) in terms.iter().zip(masks.iter()) {
+                let bound = FuzzyMatcher::max_score(term.len()) as f64;
+                let mut matched: Option<(f64, Field)> = None;
+                for field in &self.fields[entry.first_field as usize..entry.last_field as usize] {
+                    let weight = field.field.weight();
+                    // Fields which cannot beat current match are not scored at all.
+                    if field.mask & term_mask != *term_mask
+                        || matched.is_some_and(|(value, _)| weight * bound <= value)
+                    {
+                        continue;
+                    }
+                    if let Some(value) = matcher.score(term, self.text(field)) {
+                        let value = value as f64 * weight;
+                        if matched.is_none_or(|(best_value, _)| value > best_value) {
+                            matched = Some((value, field.field));
+                        }
+                    }
+                }
+
+                if let Some((value, field)) = matched {
+                    score += value;
+                    if best.is_none_or(|(best_value, _)| value > best_value) {
+                        best = Some((value, field));
+                    }
+                }
+            }
+
+            if let Some(last_used) = entry.last_used {
+                let age = now.saturating_sub(last_used) as f64;
+                score *= 1.0 + RECENCY_WEIGHT * 0.5f64.powf(age / RECENCY_HALF_LIFE);
+            }
+            matches.push((score, position, best.map(|(_, field)| field)));
+        }
+
+        let order = |a: &(f64, usize, Option<Field>), b: &(f64, usize, Option<Field>)| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| self.entries[a.1].path.cmp(&self.entries[b.1].path))
+        };
+        if matches.len() > limit {
+            matches.select_nth_unstable_by(limit - 1, order);
+            matches.truncate(limit);
+        }
+        matches.sort_by(order);
+
+        matches
+            .into_iter()
+            .map(|(score, position, field)| SearchResult {
+                path: self.entries[position].path.clone(),
+                score,
+                field,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SafeBuffer;
+
+    fn fuzzy_score(pattern: &str, text: &str) -> Option<i32> {
+        FuzzyMatcher::default().score(pattern.as_bytes(), text.as_bytes())
+    }
+
+    fn vault() -> Vault {
+        let mut vault = Vault::new();
+        for (path, username, url, tag, notes) in [
+            (
+                "work/staging/postgres",
+                "app",
+                "db.staging.internal",
+                "database",
+                "",
+            ),
+            (
+                "work/production/postgres",
+                "app",
+                "db.prod.internal",
+                "database",
+                "",
+            ),
+            (
+                "work/staging/redis",
+                "cache",
+                "redis.staging.internal",
+                "",
+                "postgres replica cache",
+            ),
+            ("personal/mail", "alice", "https://mail.example.com", "", ""),
+            (
+                "personal/bank",
+                "alice",
+                "https://bank.example.com",
+                "finance",
+                "",
+            ),
+        ] {
+            let mut entry = Entry::new();
+            entry.set_username(username);
+            entry.set_password(SafeBuffer::from(String::from("postgres-staging-secret")));
+            entry.add_url(url);
+            if !tag.is_empty() {
+                entry.add_tag(tag);
+            }
+            entry.set_notes(notes);
+            vault.add_entry(path, entry).unwrap();
+        }
+        vault
+    }
+
+    fn paths(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.path.as_str()).collect()
+    }
+
+    #[test]
+    fn fuzzy_matches_are_scored() {
+        let text = "work/staging/postgres";
+
+        assert!(fuzzy_score("pg", text).is_some());
+        assert!(fuzzy_score("gp", text).is_some());
+        assert!(fuzzy_score("xyz", text).is_none());
+        assert!(fuzzy_score("sgtagni", text).is_none());
+
+        let word = fuzzy_score("post", text).unwrap();
+        let scattered = fuzzy_score("pgrs", text).unwrap();
+        assert!(word > scattered);
+        assert!(
+            fuzzy_score("postgres", "postgres").unwrap() > fuzzy_score("postgres", text).unwrap()
+        );
+    }
+
+    #[test]
+    fn results_are_ranked() {
+        let vault = vault();
+        let index = SearchIndex::new(&vault);
+        assert_eq!(index.len(), 5);
+
+        let results = index.search("staging postgres", 10);
+        assert_eq!(
+            paths(&results),
+            ["work/staging/postgres", "work/staging/redis"]
+        );
+        assert_eq!(results[0].field, Some(Field::Title));
+
+        assert_eq!(paths(&index.search("finance", 10)), ["personal/bank"]);
+        assert_eq!(index.search("pstgrs", 1).len(), 1);
+        assert!(index.search("", 10).is_empty());
+    }
+
+    #[test]
+    fn passwords_are_not_searched() {
+        let index = SearchIndex::new(&vault());
+        assert!(index.search("secret", 10).is_empty());
+    }
+
+    #[test]
+    fn structured_queries_filter_results() {
+        let vault = vault();
+        let index = SearchIndex::new(&vault);
+        let find = |query: &str| {
+            let results = index.find(&vault, &Query::parse(query).unwrap(), 10);
+            paths(&results)
+                .iter()
+                .map(|path| path.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            find("postgres -group:production tag:database"),
+            ["work/staging/postgres"]
+        );
+        assert_eq!(
+            find("url:*.example.com"),
+            ["personal/bank", "personal/mail"]
+        );
+        assert_eq!(
+            find("tag:finance OR redis"),
+            ["personal/bank", "work/staging/redis"]
+        );
+        assert!(find("has:otp").is_empty());
+
+        let results = index.find(&vault, &Query::parse("user:cache").unwrap(), 10);
+        assert_eq!(results[0].field, None);
+    }
+
+    #[test]
+    fn recently_used_entries_rank_higher() {
+        let mut vault = vault();
+        let mut index = SearchIndex::new(&vault);
+        let before = index.search("postgres", 10);
+        assert_eq!(before[0].path, "work/production/postgres");
+        assert_eq!(before.len(), 3);
+
+        vault.mark_used("work/staging/postgres").unwrap();
+        index.insert(
+            "work/staging/postgres",
+            vault.entry("work/staging/postgres").unwrap(),
+        );
+        let now = timestamp();
+        let recent = index.search_at("postgres", 10, now);
+        assert_eq!(recent[0].path, "work/staging/postgres");
+        assert!(recent[0].score > recent[1].score * 1.4);
+
+        let month_later = index.search_at("postgres", 10, now + 30 * 24 * 60 * 60);
+        assert!(month_later[0].score < recent[0].score);
+        assert!(month_later[0].score > month_later[1].score);
+
+        index.remove("work/staging/postgres");
+        assert_eq!(index.search("postgres", 10).len(), 2);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn benchmark_10k_entries() {
+        let mut vault = Vault::new();
+        let services = [
+            "postgres", "redis", "mail", "bank", "github", "jenkins", "grafana", "vpn",
+        ];
+        let environments = ["production", "staging", "development", "testing"];
+        for number in 0..10_000 {
+            let service = services[number % services.len()];
+            let environment = environments[number / services.len() % environments.len()];
+            let mut entry = Entry::new();
+            entry.set_username(format!("user{}", number));
+            entry.add_url(format!("https://{}.{}.example.com", service, environment));
+            entry.add_tag(environment);
+            entry.set_notes(format!(
+                "Credentials of {} instance number {}",
+                service, number
+            ));
+            vault
+                .add_entry(
+                    &format!("{}/{}/{}-{}", environment, service, service, number),
+                    entry,
+                )
+                .unwrap();
+        }
+
+        let started = std::time::Instant::now();
+        let index = SearchIndex::new(&vault);
+        println!(
+            "Index of {} entries built in {:?}",
+            index.len(),
+            started.elapsed()
+        );
+
+        for query in ["staging postgres", "grfn", "user9999", "prod vpn 12", "xyz"] {
+            let iterations = 100;
+            let started = std::time::Instant::now();
+            let mut found = 0;
+            for _ in 0..iterations {
+                found = index.search(query, 20).len();
+            }
+            let average = started.elapsed() / iterations;
+            println!("{:?}: {} results in {:?}", query, found, average);
+            assert!(average < std::time::Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,949 @@
+use super::{is_subsequence, lowercase, searchable_fields};
+use crate::otp::OtpKind;
+use crate::storage::{timestamp, Entry};
+use std::str::FromStr;
+
+const SECONDS_PER_HOUR: u64 = 60 * 60;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+const FIELDS: &str =
+    "title, path, group, username, url, tag, notes, profile, created, modified, changed, used, has";
+const PROPERTIES: &str = "password, username, url, tag, notes, otp, totp, hotp, profile";
+const TIME_FORMATS: &str =
+    "expected age such as `<90d` (units: h, d, w, y) or date such as `>=2024-01-31`";
+
+/// Error in query syntax. Positions are counted in characters from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnknownField {
+        field: String,
+        position: usize,
+    },
+    MissingValue {
+        field: String,
+        position: usize,
+    },
+    InvalidValue {
+        field: String,
+        value: String,
+        position: usize,
+        description: String,
+    },
+    UnclosedQuote {
+        position: usize,
+    },
+    UnmatchedParenthesis {
+        position: usize,
+    },
+    EmptyGroup {
+        position: usize,
+    },
+    /// Operator (`OR`, `AND` or `-`) without expression on one of its sides.
+    MissingOperand {
+        operator: &'static str,
+        position: usize,
+    },
+}
+
+impl QueryError {
+    pub fn position(&self) -> usize {
+        match self {
+            Self::UnknownField { position, .. }
+            | Self::MissingValue { position, .. }
+            | Self::InvalidValue { position, .. }
+            | Self::UnclosedQuote { position }
+            | Self::UnmatchedParenthesis { position }
+            | Self::EmptyGroup { position }
+            | Self::MissingOperand { position, .. } => *position,
+        }
+    }
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let column = self.position() + 1;
+        match self {
+            Self::UnknownField { field, .. } => write!(
+                f,
+                "unknown field `{field}` at column {column}, expected one of: {FIELDS}"
+            ),
+            Self::MissingValue { field, .. } => {
+                write!(f, "field `{field}` at column {column} has no value")
+            }
+            Self::InvalidValue {
+                field,
+                value,
+                description,
+                ..
+            } => write!(
+                f,
+                "invalid value `{value}` of field `{field}` at column {column}: {description}"
+            ),
+            Self::UnclosedQuote { .. } => {
+                write!(f, "quote opened at column {column} is never closed")
+            }
+            Self::UnmatchedParenthesis { .. } => {
+                write!(f, "parenthesis at column {column} has no pair")
+            }
+            Self::EmptyGroup { .. } => write!(f, "parentheses at column {column} are empty"),
+            Self::MissingOperand { operator, .. } => write!(
+                f,
+                "`{operator}` at column {column} is not followed by an expression"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    And,
+    Not,
+    Word {
+        field: Option<String>,
+        value: String,
+        /// Position of the value, differs from position of token for `field:value`.
+        position: usize,
+    },
+}
+
+/// Reads quoted text starting at `start`, `\"` and `\\` are unescaped.
+/// Returns the text and position after the closing quote.
+fn quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let mut text = String::new();
+    let mut position = start + 1;
+    while let Some(character) = chars.get(position) {
+        match character {
+            '"' => return Ok((text, position + 1)),
+            '\\' if matches!(chars.get(position + 1), Some('"' | '\\')) => {
+                text.push(chars[position + 1]);
+                position += 2;
+            }
+            _ => {
+                text.push(*character);
+                position += 1;
+            }
+        }
+    }
+    Err(QueryError::UnclosedQuote { position: start })
+}
+
+fn tokenize(query: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while let Some(&character) = chars.get(position) {
+        let start = position;
+        match character {
+            _ if character.is_whitespace() => position += 1,
+            '(' => {
+                tokens.push((start, Token::Open));
+                position += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::Close));
+                position += 1;
+            }
+            '-' => match chars.get(position + 1) {
+                Some(next) if !next.is_whitespace() && *next != ')' => {
+                    tokens.push((start, Token::Not));
+                    position += 1;
+                }
+                _ => {
+                    return Err(QueryError::MissingOperand {
+                        operator: "-",
+                        position: start,
+                    })
+                }
+            },
+            '"' => {
+                let (value, end) = quoted(&chars, start)?;
+                tokens.push((
+                    start,
+                    Token::Word {
+                        field: None,
+                        value,
+                        position: start,
+                    },
+                ));
+                position = end;
+            }
+            _ => {
+                let end = chars[start..]
+                    .iter()
+                    .position(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+                    .map_or(chars.len(), |length| start + length);
+                let word: String = chars[start..end].iter().collect();
+                position = end;
+
+                let token = match word.split_once(':') {
+                    // Addresses like `https://example.com` are plain text, not fields.
+                    Some((field, value))
+                        if !field.is_empty()
+                            && field.chars().all(|c| c.is_ascii_alphabetic())
+                            && !value.starts_with("//") =>
+                    {
+                        let value_position = start + field.chars().count() + 1;
+                        let value = match chars.get(end) {
+                            Some('"') if value.is_empty() => {
+                                let (value, end) = quoted(&chars, end)?;
+                                position = end;
+                                value
+                            }
+                            _ if value.is_empty() => {
+                                return Err(QueryError::MissingValue {
+                                    field: field.to_string(),
+                                    position: start,
+                                })
+                            }
+                            _ => value.to_string(),
+                        };
+                        Token::Word {
+                            field: Some(field.to_string()),
+                            value,
+                            position: value_position,
+                        }
+                    }
+                    _ if word == "OR" => Token::Or,
+                    _ if word == "AND" => Token::And,
+                    _ => Token::Word {
+                        field: None,
+                        value: word,
+                        position: start,
+                    },
+                };
+                tokens.push((start, token));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Text pattern, `*` matches any number of characters and `?` a single one.
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Text(String),
+    Glob(Vec<char>),
+}
+
+impl Pattern {
+    fn new(value: &str) -> Self {
+        let value = value.to_lowercase();
+        match value.contains(['*', '?']) {
+            true => Self::Glob(value.chars().collect()),
+            false => Self::Text(value),
+        }
+    }
+
+    /// Plain patterns match any part of `text`, globs have to match whole `text`.
+    fn found_in(&self, text: &str) -> bool {
+        match self {
+            Self::Text(pattern) => text.to_lowercase().contains(pattern.as_str()),
+            Self::Glob(pattern) => glob_matches(pattern, text),
+        }
+    }
+
+    fn equals(&self, text: &str) -> bool {
+        match self {
+            Self::Text(pattern) => text.to_lowercase() == *pattern,
+            Self::Glob(pattern) => glob_matches(pattern, text),
+        }
+    }
+}
+
+/// Checks whether whole `text` matches glob `pattern`, ignoring case. `*` matches any
+/// number of characters, including `/`, and `?` a single one.
+pub fn matches_glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    glob_matches(&pattern, text)
+}
+
+fn glob_matches(pattern: &[char], text: &str) -> bool {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of last `*` and of text it started to match at, used to backtrack.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Host part of the address, e.g. `api.example.com` for `https://user@api.example.com:443/login`.
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    authority.split(':').next().unwrap_or(authority)
+}
+
+/// Returns number of days between UNIX epoch and date in `YYYY-MM-DD` format.
+fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_length = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=month_length).contains(&day) {
+        return None;
+    }
+
+    // Days from civil algorithm, years are counted from March so leap day is the last one.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    u64::try_from(days).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TimeBound {
+    /// Compares age - `<90d` matches times less than 90 days ago.
+    Age {
+        comparison: Comparison,
+        seconds: u64,
+    },
+    /// Compares with the whole day - `<2024-01-31` matches times before that day.
+    Date { comparison: Comparison, start: u64 },
+}
+
+impl TimeBound {
+    fn parse(value: &str) -> Result<Self, String> {
+        let (comparison, rest) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ]
+        .iter()
+        .find_map(|(operator, comparison)| {
+            value.strip_prefix(operator).map(|rest| (*comparison, rest))
+        })
+        .unwrap_or((Comparison::Equal, value));
+
+        if let Some(start) = parse_date(rest) {
+            return Ok(Self::Date {
+                comparison,
+                start: start * SECONDS_PER_DAY,
+            });
+        }
+
+        let unit = match rest.chars().last() {
+            Some('h') => SECONDS_PER_HOUR,
+            Some('d') => SECONDS_PER_DAY,
+            Some('w') => 7 * SECONDS_PER_DAY,
+            Some('y') => 365 * SECONDS_PER_DAY,
+            _ => return Err(TIME_FORMATS.into()),
+        };
+        let amount: u64 = rest[..rest.len() - 1]
+            .parse()
+            .map_err(|_| String::from(TIME_FORMATS))?;
+        match comparison {
+            Comparison::Equal => Err(format!(
+                "age needs `<` or `>`, e.g. `<{rest}` for less than {rest} ago"
+            )),
+            _ => Ok(Self::Age {
+                comparison,
+                seconds: amount.saturating_mul(unit),
+            }),
+        }
+    }
+
+    fn matches(&self, time: u64, now: u64) -> bool {
+        match *self {
+            Self::Age {
+                comparison,
+                seconds,
+            } => {
+                let age = now.saturating_sub(time);
+                match comparison {
+                    Comparison::Less => age < seconds,
+                    Comparison::LessOrEqual => age <= seconds,
+                    Comparison::Greater => age > seconds,
+                    Comparison::GreaterOrEqual | Comparison::Equal => age >= seconds,
+                }
+            }
+            Self::Date { comparison, start } => {
+                let end = start + SECONDS_PER_DAY;
+                match comparison {
+                    Comparison::Less => time < start,
+                    Comparison::LessOrEqual => time < end,
+                    Comparison::Greater => time >= end,
+                    Comparison::GreaterOrEqual => time >= start,
+                    Comparison::Equal => (start..end).contains(&time),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Title,
+    Path,
+    Group,
+    Username,
+    Url,
+    Tag,
+    Notes,
+    Profile,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeField {
+    Created,
+    Modified,
+    PasswordChanged,
+    Used,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    Password,
+    Username,
+    Url,
+    Tag,
+    Notes,
+    Otp,
+    Totp,
+    Hotp,
+    Profile,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    All(Vec<Expression>),
+    Any(Vec<Expression>),
+    Not(Box<Expression>),
+    /// Text without field, found in any searchable field or path.
+    Text(String),
+    Field(TextField, Pattern),
+    Time(TimeField, TimeBound),
+    Has(Property),
+}
+
+fn condition(field: &str, value: &str, position: usize) -> Result<Expression, QueryError> {
+    let invalid = |description: String| QueryError::InvalidValue {
+        field: field.to_string(),
+        value: value.to_string(),
+        position,
+        description,
+    };
+    let text = |field| Ok(Expression::Field(field, Pattern::new(value)));
+    let time = |field| {
+        TimeBound::parse(value)
+            .map(|bound| Expression::Time(field, bound))
+            .map_err(invalid)
+    };
+
+    match field.to_lowercase().as_str() {
+        "title" => text(TextField::Title),
+        "path" => text(TextField::Path),
+        "group" => text(TextField::Group),
+        "user" | "username" => text(TextField::Username),
+        "url" => text(TextField::Url),
+        "tag" => text(TextField::Tag),
+        "note" | "notes" => text(TextField::Notes),
+        "profile" => text(TextField::Profile),
+        "created" => time(TimeField::Created),
+        "modified" => time(TimeField::Modified),
+        "changed" => time(TimeField::PasswordChanged),
+        "used" => time(TimeField::Used),
+        "has" => {
+            let property = match value.to_lowercase().as_str() {
+                "password" => Property::Password,
+                "user" | "username" => Property::Username,
+                "url" => Property::Url,
+                "tag" | "tags" => Property::Tag,
+                "note" | "notes" => Property::Notes,
+                "otp" => Property::Otp,
+                "totp" => Property::Totp,
+                "hotp" => Property::Hotp,
+                "profile" => Property::Profile,
+                _ => return Err(invalid(format!("expected one of: {PROPERTIES}"))),
+            };
+            Ok(Expression::Has(property))
+        }
+        _ => Err(QueryError::UnknownField {
+            field: field.to_string(),
+            position: position - field.chars().count() - 1,
+        }),
+    }
+}
+
+/// Recursive descent parser. `OR` binds weaker than `AND` (which may be omitted),
+/// `-` negates the following term or group.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.next)
+    }
+
+    fn at_operand(&self) -> bool {
+        !matches!(
+            self.peek(),
+            None | Some((_, Token::Close | Token::Or | Token::And))
+        )
+    }
+
+    fn any(&mut self) -> Result<Expression, QueryError> {
+        let mut alternatives = vec![self.all()?];
+        while let Some(&(position, Token::Or)) = self.peek() {
+            self.next += 1;
+            if !self.at_operand() {
+                return Err(QueryError::MissingOperand {
+                    operator: "OR",
+                    position,
+                });
+            }
+            alternatives.push(self.all()?);
+        }
+
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Expression::Any(alternatives),
+        })
+    }
+
+    fn all(&mut self) -> Result<Expression, QueryError> {
+        let mut terms = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some((_, Token::Close)) => break,
+                Some(&(position, Token::Or)) if terms.is_empty() => {
+                    return Err(QueryError::MissingOperand {
+                        operator: "OR",
+                        position,
+                    })
+                }
+                Some((_, Token::Or)) => break,
+                Some(&(position, Token::And)) => {
+                    self.next += 1;
+                    if terms.is_empty() || !self.at_operand() {
+                        return Err(QueryError::MissingOperand {
+                            operator: "AND",
+                            position,
+                        });
+                    }
+                }
+                _ => terms.push(self.unary()?),
+            }
+        }
+
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Expression::All(terms),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expression, QueryError> {
+        let (position, token) = self.tokens[self.next].clone();
+        self.next += 1;
+        match token {
+            Token::Not => {
+                if !self.at_operand() {
+                    return Err(QueryError::MissingOperand {
+                        operator: "-",
+                        position,
+                    });
+                }
+                Ok(Expression::Not(Box::new(self.unary()?)))
+            }
+            Token::Open => {
+                if let Some((_, Token::Close)) = self.peek() {
+                    return Err(QueryError::EmptyGroup { position });
+                }
+                let expression = self.any()?;
+                match self.peek() {
+                    Some((_, Token::Close)) => {
+                        self.next += 1;
+                        Ok(expression)
+                    }
+                    _ => Err(QueryError::UnmatchedParenthesis { position }),
+                }
+            }
+            Token::Word {
+                field: Some(field),
+                value,
+                position,
+            } => condition(&field, &value, position),
+            Token::Word { value, .. } => Ok(Expression::Text(value.to_lowercase())),
+            Token::Close | Token::Or | Token::And => unreachable!("checked by callers"),
+        }
+    }
+}
+
+impl Expression {
+    fn matches(&self, path: &str, entry: &Entry, now: u64) -> bool {
+        match self {
+            Self::All(terms) => terms.iter().all(|term| term.matches(path, entry, now)),
+            Self::Any(terms) => terms.iter().any(|term| term.matches(path, entry, now)),
+            Self::Not(term) => !term.matches(path, entry, now),
+            Self::Text(text) => {
+                path.to_lowercase().contains(text.as_str())
+                    || searchable_fields(path, entry)
+                        .iter()
+                        .any(|(_, content)| content.to_lowercase().contains(text.as_str()))
+            }
+            Self::Field(field, pattern) => {
+                let (group, title) = path.rsplit_once('/').unwrap_or(("", path));
+                match field {
+                    TextField::Title => pattern.found_in(title),
+                    TextField::Path => pattern.found_in(path),
+                    TextField::Group => pattern.found_in(group),
+                    TextField::Username => pattern.found_in(entry.username()),
+                    TextField::Url => entry
+                        .urls()
+                        .iter()
+                        .any(|url| pattern.found_in(url) || pattern.found_in(host(url))),
+                    TextField::Tag => entry.tags().iter().any(|tag| pattern.equals(tag)),
+                    TextField::Notes => pattern.found_in(entry.notes()),
+                    TextField::Profile => entry
+                        .profile()
+                        .is_some_and(|profile| pattern.equals(profile)),
+                }
+            }
+            Self::Time(field, bound) => {
+                let time = match field {
+                    TimeField::Created => Some(entry.created()),
+                    TimeField::Modified => Some(entry.modified()),
+                    TimeField::PasswordChanged => Some(entry.password_changed()),
+                    TimeField::Used => entry.last_used(),
+                };
+                time.is_some_and(|time| bound.matches(time, now))
+            }
+            Self::Has(property) => match property {
+                Property::Password => !entry.password().is_empty(),
+                Property::Username => !entry.username().is_empty(),
+                Property::Url => !entry.urls().is_empty(),
+                Property::Tag => !entry.tags().is_empty(),
+                Property::Notes => !entry.notes().is_empty(),
+                Property::Otp => entry.otp().is_some(),
+                Property::Totp => entry
+                    .otp()
+                    .is_some_and(|otp| matches!(otp.kind(), OtpKind::Totp { .. })),
+                Property::Hotp => entry
+                    .otp()
+                    .is_some_and(|otp| matches!(otp.kind(), OtpKind::Hotp { .. })),
+                Property::Profile => entry.profile().is_some(),
+            },
+        }
+    }
+}
+
+/// Parsed search query, e.g. `tag:prod url:*.example.com -tag:deprecated modified:<90d has:totp`.
+///
+/// Words without field which are not negated nor part of `OR` are free text - they match
+/// fuzzily and decide ranking of results, like in [`SearchIndex::search`]. Other words and
+/// `field:value` conditions only filter entries:
+/// - `title`, `path`, `group`, `username`, `url` and `notes` contain the value,
+///   addresses also match by host, so `url:*.example.com` matches `https://api.example.com/`,
+/// - `tag` and `profile` are equal to the value,
+/// - `created`, `modified`, `changed` (password) and `used` compare age (`<90d`, `>1y`)
+///   or date (`<2024-01-31`, `2024-01-31`),
+/// - `has` checks that entry has `password`, `username`, `url`, `tag`, `notes`, `otp`,
+///   `totp`, `hotp` or `profile`.
+///
+/// Values are case insensitive, `*` and `?` make them match whole text like in shell.
+/// Conditions may be grouped with parentheses, joined with `OR` and negated with `-`.
+///
+/// [`SearchIndex::search`]: super::SearchIndex::search
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    free_text: Vec<String>,
+    filter: Option<Expression>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(query)?,
+            next: 0,
+        };
+        let expression = parser.any()?;
+        if let Some(&(position, _)) = parser.peek() {
+            return Err(QueryError::UnmatchedParenthesis { position });
+        }
+
+        let mut terms = match expression {
+            Expression::All(terms) => terms,
+            expression => vec![expression],
+        };
+        let mut free_text = Vec::new();
+        terms.retain(|term| match term {
+            Expression::Text(text) => {
+                free_text.push(text.clone());
+                false
+            }
+            _ => true,
+        });
+        let filter = match terms.len() {
+            0 => None,
+            1 => terms.pop(),
+            _ => Some(Expression::All(terms)),
+        };
+
+        Ok(Self { free_text, filter })
+    }
+
+    /// Words searched fuzzily.
+    pub fn free_text(&self) -> &[String] {
+        &self.free_text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free_text.is_empty() && self.filter.is_none()
+    }
+
+    pub fn matches(&self, path: &str, entry: &Entry) -> bool {
+        self.matches_at(path, entry, timestamp())
+    }
+
+    /// Checks the query against `entry`, ages are computed against `now`.
+    pub fn matches_at(&self, path: &str, entry: &Entry, now: u64) -> bool {
+        let fields = searchable_fields(path, entry);
+        let contents: Vec<Vec<u8>> = fields
+            .iter()
+            .map(|(_, content)| lowercase(content))
+            .collect();
+
+        self.free_text.iter().all(|term| {
+            contents
+                .iter()
+                .any(|content| is_subsequence(term.as_bytes(), content))
+        }) && self.filter_matches(path, entry, now)
+    }
+
+    /// Like [`Query::matches`], but free text has to be found in some field as it is,
+    /// ignoring case. Used where entries are changed, so a loose match cannot select them.
+    pub fn matches_exactly(&self, path: &str, entry: &Entry) -> bool {
+        let now = timestamp();
+        self.free_text
+            .iter()
+            .all(|term| Expression::Text(term.clone()).matches(path, entry, now))
+            && self.filter_matches(path, entry, now)
+    }
+
+    /// Checks conditions other than free text.
+    pub(super) fn filter_matches(&self, path: &str, entry: &Entry, now: u64) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(path, entry, now))
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Self::parse(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::otp::OtpSecret;
+    use crate::storage::SafeBuffer;
+
+    fn entry(urls: &[&str], tags: &[&str], totp: bool) -> Entry {
+        let mut entry = Entry::new();
+        entry.set_username("deploy");
+        entry.set_password(SafeBuffer::from(String::from("secret")));
+        for url in urls {
+            entry.add_url(*url);
+        }
+        for tag in tags {
+            entry.add_tag(*tag);
+        }
+        if totp {
+            entry.set_otp(Some(
+                OtpSecret::totp(b"12345678901234567890".to_vec().into()).unwrap(),
+            ));
+        }
+        entry
+    }
+
+    fn matches(query: &str, path: &str, entry: &Entry) -> bool {
+        Query::parse(query).unwrap().matches(path, entry)
+    }
+
+    #[test]
+    fn dates_are_parsed() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(11_017));
+        assert_eq!(parse_date("2024-02-29"), Some(19_782));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("1969-12-31"), None);
+    }
+
+    #[test]
+    fn query_is_parsed() {
+        let query = Query::parse(r#"postgres tag:prod -"old db" (user:admin OR has:otp)"#).unwrap();
+        assert_eq!(query.free_text(), ["postgres"]);
+        assert_eq!(
+            query.filter,
+            Some(Expression::All(vec![
+                Expression::Field(TextField::Tag, Pattern::Text("prod".into())),
+                Expression::Not(Box::new(Expression::Text("old db".into()))),
+                Expression::Any(vec![
+                    Expression::Field(TextField::Username, Pattern::Text("admin".into())),
+                    Expression::Has(Property::Otp),
+                ]),
+            ]))
+        );
+        assert_eq!(
+            Query::parse("https://example.com").unwrap().free_text(),
+            ["https://example.com"]
+        );
+        assert!(Query::parse("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        for (query, error) in [
+            (
+                "tag:prod colour:red",
+                QueryError::UnknownField {
+                    field: "colour".into(),
+                    position: 9,
+                },
+            ),
+            (
+                "tag: prod",
+                QueryError::MissingValue {
+                    field: "tag".into(),
+                    position: 0,
+                },
+            ),
+            ("notes:\"abc", QueryError::UnclosedQuote { position: 6 }),
+            (
+                "(tag:a OR tag:b",
+                QueryError::UnmatchedParenthesis { position: 0 },
+            ),
+            ("tag:a)", QueryError::UnmatchedParenthesis { position: 5 }),
+            ("tag:a ()", QueryError::EmptyGroup { position: 6 }),
+            (
+                "tag:a OR",
+                QueryError::MissingOperand {
+                    operator: "OR",
+                    position: 6,
+                },
+            ),
+            (
+                "OR tag:a",
+                QueryError::MissingOperand {
+                    operator: "OR",
+                    position: 0,
+                },
+            ),
+            (
+                "tag:a - tag:b",
+                QueryError::MissingOperand {
+                    operator: "-",
+                    position: 6,
+                },
+            ),
+        ] {
+            assert_eq!(Query::parse(query), Err(error), "{query}");
+        }
+
+        let error = Query::parse("modified:90d").unwrap_err();
+        assert!(matches!(
+            error,
+            QueryError::InvalidValue { position: 9, .. }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "invalid value `90d` of field `modified` at column 10: \
+             age needs `<` or `>`, e.g. `<90d` for less than 90d ago"
+        );
+        assert!(Query::parse("has:wings").is_err());
+        assert!(Query::parse("used:<2024-02-30").is_err());
+    }
+
+    #[test]
+    fn conditions_are_evaluated() {
+        let query = "tag:prod url:*.example.com -tag:deprecated modified:<90d has:totp";
+        let api = entry(&["https://api.example.com/login"], &["prod"], true);
+        assert!(matches(query, "work/api", &api));
+        assert!(!matches(
+            query,
+            "work/api",
+            &entry(&["https://example.org"], &["prod"], true)
+        ));
+        assert!(!matches(
+            query,
+            "work/api",
+            &entry(&["https://api.example.com"], &["prod"], false)
+        ));
+        let deprecated = entry(&["api.example.com"], &["prod", "deprecated"], true);
+        assert!(!matches(query, "work/api", &deprecated));
+
+        let later = timestamp() + 100 * SECONDS_PER_DAY;
+        let query = Query::parse(query).unwrap();
+        assert!(!query.matches_at("work/api", &api, later));
+        let query =
+            Query::parse("modified:>90d created:>=1970-01-02 changed:<=2999-01-01").unwrap();
+        assert!(query.matches_at("work/api", &api, later));
+
+        assert!(matches("group:work title:API", "work/api", &api));
+        assert!(matches("path:work/* -group:personal", "work/api", &api));
+        assert!(matches("deploy -used:<1y", "work/api", &api));
+        assert!(matches("tag:pr* OR notes:missing", "work/api", &api));
+        assert!(!matches("tag:pr", "work/api", &api));
+        assert!(!matches("has:profile", "work/api", &api));
+        assert!(!matches("wrk/aip", "work/api", &api));
+        assert!(matches("wrk ap", "work/api", &api));
+    }
+}
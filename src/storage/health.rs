@@ -0,0 +1,273 @@
+use super::{StorageError, VaultHeader};
+use crate::cryptography::{
+    EncryptionAlgorithm, HashAlgorithm, KeyDerivationAlgorithm, OptionValue, PasswordHasherBuilder,
+    OUTPUT_LENGTH_OPTION,
+};
+use crate::get_translation;
+use serde::Serialize;
+
+/// Ciphers which are considered secure. The first one is used when vault is upgraded.
+const RECOMMENDED_ENCRYPTION: &'static [EncryptionAlgorithm] = &[
+    EncryptionAlgorithm::ChaCha20Poly1305,
+    EncryptionAlgorithm::Aes256GcmSiv,
+];
+
+/// Hashes which may protect integrity of the vault. The first one is used when vault is upgraded.
+pub(super) const RECOMMENDED_HASHES: &'static [HashAlgorithm] =
+    &[HashAlgorithm::Sha256, HashAlgorithm::Sha512];
+
+const RECOMMENDED_KEY_DERIVATION: KeyDerivationAlgorithm = KeyDerivationAlgorithm::Argon2id;
+
+/// Minimum values of key derivation options. Argon2 values follow the second
+/// recommended option of RFC 9106 (64 MiB of memory, 3 passes) - defaults of
+/// argon2 crate are much lower. Options missing from the algorithm are skipped.
+const MINIMUM_KEY_DERIVATION_OPTIONS: &'static [(&'static str, u32)] = &[
+    ("Memory size", 64 * 1024),
+    ("Iterations", 3),
+    ("Version", 0x13),
+];
+
+/// Cryptographic setting of the vault which is below recommended minimum.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HealthIssue {
+    OutdatedEncryption {
+        current: EncryptionAlgorithm,
+        recommended: EncryptionAlgorithm,
+    },
+    OutdatedHash {
+        current: HashAlgorithm,
+        recommended: HashAlgorithm,
+    },
+    OutdatedKeyDerivation {
+        current: KeyDerivationAlgorithm,
+        recommended: KeyDerivationAlgorithm,
+    },
+    WeakKeyDerivationOption {
+        option: &'static str,
+        current: u32,
+        minimum: u32,
+    },
+}
+
+impl std::fmt::Display for HealthIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutdatedEncryption {
+                current,
+                recommended,
+            } => write!(
+                f,
+                "{} ({} -> {})",
+                get_translation!("HEALTH_OUTDATED_ENCRYPTION"),
+                current,
+                recommended
+            ),
+            Self::OutdatedHash {
+                current,
+                recommended,
+            } => write!(
+                f,
+                "{} ({} -> {})",
+                get_translation!("HEALTH_OUTDATED_HASH"),
+                current,
+                recommended
+            ),
+            Self::OutdatedKeyDerivation {
+                current,
+                recommended,
+            } => write!(
+                f,
+                "{} ({} -> {})",
+                get_translation!("HEALTH_OUTDATED_KEY_DERIVATION"),
+                current,
+                recommended
+            ),
+            Self::WeakKeyDerivationOption {
+                option,
+                current,
+                minimum,
+            } => write!(
+                f,
+                "{} - {} ({} < {})",
+                get_translation!("HEALTH_WEAK_KEY_DERIVATION_OPTION"),
+                option,
+                current,
+                minimum
+            ),
+        }
+    }
+}
+
+/// Returns options of `builder` which are below [`MINIMUM_KEY_DERIVATION_OPTIONS`].
+fn weak_options(builder: &dyn PasswordHasherBuilder) -> Vec<HealthIssue> {
+    MINIMUM_KEY_DERIVATION_OPTIONS
+        .iter()
+        .filter_map(|(option, minimum)| {
+            let descriptor = builder.descriptor(option).ok()?;
+            match builder.get_value(descriptor.name).ok()? {
+                OptionValue::Unsigned(current) if current < *minimum => {
+                    Some(HealthIssue::WeakKeyDerivationOption {
+                        option: descriptor.name,
+                        current,
+                        minimum: *minimum,
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Compares settings of the vault with built-in table of recommended minimums.
+pub fn check_health(header: &VaultHeader) -> Vec<HealthIssue> {
+    let mut issues = Vec::new();
+
+    if !RECOMMENDED_ENCRYPTION.contains(&header.encryption_algorithm) {
+        issues.push(HealthIssue::OutdatedEncryption {
+            current: header.encryption_algorithm.clone(),
+            recommended: RECOMMENDED_ENCRYPTION[0].clone(),
+        });
+    }
+    if !RECOMMENDED_HASHES.contains(&header.hash_algorithm) {
+        issues.push(HealthIssue::OutdatedHash {
+            current: header.hash_algorithm.clone(),
+            recommended: RECOMMENDED_HASHES[0].clone(),
+        });
+    }
+    if header.key_derivation_algorithm != RECOMMENDED_KEY_DERIVATION {
+        issues.push(HealthIssue::OutdatedKeyDerivation {
+            current: header.key_derivation_algorithm.clone(),
+            recommended: RECOMMENDED_KEY_DERIVATION,
+        });
+    }
+    issues.extend(weak_options(header.key_derivation.as_ref()));
+
+    issues
+}
+
+/// Returns settings meeting recommended minimums. Settings which already meet them
+/// (including options like secret key file) are kept.
+pub(super) fn upgraded_header(header: VaultHeader) -> Result<VaultHeader, StorageError> {
+    let encryption_algorithm = match RECOMMENDED_ENCRYPTION.contains(&header.encryption_algorithm) {
+        true => header.encryption_algorithm,
+        false => RECOMMENDED_ENCRYPTION[0].clone(),
+    };
+    let hash_algorithm = match RECOMMENDED_HASHES.contains(&header.hash_algorithm) {
+        true => header.hash_algorithm,
+        false => RECOMMENDED_HASHES[0].clone(),
+    };
+
+    let mut key_derivation = RECOMMENDED_KEY_DERIVATION.builder_for(&encryption_algorithm)?;
+    for descriptor in key_derivation.options() {
+        if let Ok(value) = header.key_derivation.get_value(descriptor.name) {
+            // Values invalid for the new algorithm keep their defaults.
+            let _ = key_derivation.set_value(descriptor.name, value);
+        }
+    }
+    key_derivation.set_value(
+        OUTPUT_LENGTH_OPTION,
+        OptionValue::Unsigned(encryption_algorithm.key_size() as u32),
+    )?;
+    for issue in weak_options(key_derivation.as_ref()) {
+        if let HealthIssue::WeakKeyDerivationOption {
+            option, minimum, ..
+        } = issue
+        {
+            key_derivation.set_value(option, OptionValue::Unsigned(minimum))?;
+        }
+    }
+
+    Ok(VaultHeader {
+        encryption_algorithm,
+        hash_algorithm,
+        key_derivation_algorithm: RECOMMENDED_KEY_DERIVATION,
+        key_derivation,
+    })
+}
+
+/// Settings of new vaults - recommended algorithms with options meeting minimums.
+pub(super) fn recommended_header() -> Result<VaultHeader, StorageError> {
+    let encryption_algorithm = RECOMMENDED_ENCRYPTION[0].clone();
+    let key_derivation = RECOMMENDED_KEY_DERIVATION.builder_for(&encryption_algorithm)?;
+
+    upgraded_header(VaultHeader {
+        encryption_algorithm,
+        hash_algorithm: RECOMMENDED_HASHES[0].clone(),
+        key_derivation_algorithm: RECOMMENDED_KEY_DERIVATION,
+        key_derivation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(algorithm: KeyDerivationAlgorithm, hash_algorithm: HashAlgorithm) -> VaultHeader {
+        let encryption_algorithm = EncryptionAlgorithm::Aes256GcmSiv;
+        VaultHeader {
+            key_derivation: algorithm.builder_for(&encryption_algorithm).unwrap(),
+            encryption_algorithm,
+            hash_algorithm,
+            key_derivation_algorithm: algorithm,
+        }
+    }
+
+    #[test]
+    fn default_argon_settings_are_below_policy() {
+        let issues = check_health(&header(
+            KeyDerivationAlgorithm::Argon2i,
+            HashAlgorithm::Sha1,
+        ));
+
+        assert_eq!(
+            issues,
+            [
+                HealthIssue::OutdatedHash {
+                    current: HashAlgorithm::Sha1,
+                    recommended: HashAlgorithm::Sha256
+                },
+                HealthIssue::OutdatedKeyDerivation {
+                    current: KeyDerivationAlgorithm::Argon2i,
+                    recommended: KeyDerivationAlgorithm::Argon2id
+                },
+                HealthIssue::WeakKeyDerivationOption {
+                    option: "Memory size",
+                    current: argon2::Params::DEFAULT_M_COST,
+                    minimum: 64 * 1024
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn recommended_settings_meet_policy() {
+        let header = recommended_header().unwrap();
+        assert!(check_health(&header).is_empty());
+        assert_eq!(
+            header.encryption_algorithm,
+            EncryptionAlgorithm::ChaCha20Poly1305
+        );
+    }
+
+    #[test]
+    fn upgrade_meets_policy_and_keeps_good_settings() {
+        let mut old = header(KeyDerivationAlgorithm::Argon2i, HashAlgorithm::Sha1);
+        old.key_derivation.set_option("iterations", "5").unwrap();
+        old.key_derivation.set_option("parallelism", "2").unwrap();
+
+        let upgraded = upgraded_header(old).unwrap();
+        assert!(check_health(&upgraded).is_empty());
+        assert_eq!(
+            upgraded.encryption_algorithm,
+            EncryptionAlgorithm::Aes256GcmSiv
+        );
+        assert_eq!(upgraded.hash_algorithm, HashAlgorithm::Sha256);
+
+        let options = &upgraded.key_derivation;
+        assert_eq!(options.get_option("iterations").unwrap(), "5");
+        assert_eq!(options.get_option("parallelism").unwrap(), "2");
+        assert_eq!(options.get_option("memory size").unwrap(), "65536");
+        assert_eq!(options.get_option("output length").unwrap(), "32");
+    }
+}
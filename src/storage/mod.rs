@@ -1,8 +1,92 @@
-use std::{path::Path, pin::Pin};
+mod health;
+mod vault;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
 
 use crate::cryptography::*;
+use crate::strength::{self, Estimate, Feedback, Score};
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
+
+pub use health::{check_health, HealthIssue};
+pub use vault::{normalize_path, timestamp, Entry, Vault, VaultError};
+
+/// Length of random salt used to derive key from master password.
+const SALT_LENGTH: usize = 16;
+
+#[derive(Debug)]
+pub enum StorageError {
+    KeyDerivationError(KeyDerivationError),
+    EncryptionError(EncryptionError),
+    /// Master password does not match the vault.
+    WrongPassword,
+    /// File is not a vault or it was damaged.
+    CorruptedVault {
+        description: String,
+    },
+    /// Master password is weaker than required.
+    WeakPassword {
+        score: Score,
+        minimum: Score,
+        feedback: Feedback,
+    },
+    /// Vault was saved by newer version of the program, in layout `version`.
+    UnsupportedFormat {
+        version: u32,
+    },
+    /// Hash cannot protect integrity of the vault, e.g. SHA-1.
+    UnsupportedHash {
+        algorithm: HashAlgorithm,
+    },
+    SerializationError {
+        description: String,
+    },
+    IoError {
+        description: String,
+    },
+}
+
+impl From<KeyDerivationError> for StorageError {
+    fn from(err: KeyDerivationError) -> Self {
+        Self::KeyDerivationError(err)
+    }
+}
+
+impl From<EncryptionError> for StorageError {
+    fn from(err: EncryptionError) -> Self {
+        Self::EncryptionError(err)
+    }
+}
+
+impl From<std::io::Error> for StorageError {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError {
+            description: err.to_string(),
+        }
+    }
+}
+
+impl From<postcard::Error> for StorageError {
+    fn from(err: postcard::Error) -> Self {
+        Self::SerializationError {
+            description: err.to_string(),
+        }
+    }
+}
+
+impl From<snap::Error> for StorageError {
+    fn from(err: snap::Error) -> Self {
+        Self::SerializationError {
+            description: err.to_string(),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 struct ProgramConfiguration {
@@ -10,6 +94,7 @@ struct ProgramConfiguration {
     text_hash_algorithm: HashAlgorithm,
     key_derivation_algorithm: KeyDerivationAlgorithm,
     key_derivation_options: Vec<u8>,
+    salt: Vec<u8>,
     nonce: Vec<u8>,
     cipher_hash: Vec<u8>,
 }
@@ -18,20 +103,112 @@ struct ProgramConfiguration {
 struct SaveFile {
     configuration: ProgramConfiguration,
     encrypted_data: Vec<u8>,
+    /// Layout of the decrypted vault, see [`vault::FORMAT_VERSION`].
+    format_version: u32,
+}
+
+/// Buffer for sensitive data (passwords, keys). Its content is zeroized when
+/// buffer is dropped and never shows up in debug output.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SafeBuffer {
+    data: Zeroizing<Vec<u8>>,
+}
+
+impl SafeBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            data: Zeroizing::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Appends bytes to the buffer. Capacity should be reserved up front with
+    /// [`SafeBuffer::with_capacity`], otherwise reallocation may leave copies of the data behind.
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+    }
+
+    pub fn push_char(&mut self, character: char) {
+        let mut encoded = [0; 4];
+        self.extend_from_slice(character.encode_utf8(&mut encoded).as_bytes());
+        encoded.zeroize();
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns content as text, if it is valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
 }
 
-struct SafeBuffer;
+impl From<Vec<u8>> for SafeBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        Self {
+            data: Zeroizing::new(data),
+        }
+    }
+}
+
+impl From<String> for SafeBuffer {
+    fn from(data: String) -> Self {
+        data.into_bytes().into()
+    }
+}
 
 impl AsRef<[u8]> for SafeBuffer {
     fn as_ref(&self) -> &[u8] {
-        todo!();
+        &self.data
+    }
+}
+
+impl Serialize for SafeBuffer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.data.as_slice().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SafeBuffer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Vec::<u8>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl std::fmt::Debug for SafeBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SafeBuffer({} bytes)", self.data.len())
     }
 }
 
+/// Serializes and compresses the vault. Intermediate plain data is zeroized.
+fn serialize_vault(vault: &Vault) -> Result<Pin<Box<Vec<u8>>>, StorageError> {
+    let serialized = Zeroizing::new(postcard::to_allocvec(vault)?);
+    let compressed = snap::raw::Encoder::new().compress_vec(&serialized)?;
+
+    Ok(Box::pin(compressed))
+}
+
 fn encrypt_database(
     mut database: Pin<Box<Vec<u8>>>,
     mut hash_algorithm: HashStruct,
-    key_deriver: Box<dyn DynPasswordHasher>,
+    key_derivation_algorithm: KeyDerivationAlgorithm,
+    key_derivation_options: Vec<u8>,
+    salt: Vec<u8>,
     encryptor: EncryptionStruct,
 ) -> SaveFile {
     let (encrypted_data, nonce) = encryptor.encrypt(database.as_slice());
@@ -39,8 +216,6 @@ fn encrypt_database(
     hash_algorithm.update(&encrypted_data);
 
     let cipher_hash = hash_algorithm.finalize();
-    let key_derivation_options = key_deriver.option_bytes();
-    let key_derivation_algorithm = key_deriver.algorithm();
     let encryption_algorithm = encryptor.algorithm();
     let text_hash_algorithm = hash_algorithm.algorithm();
 
@@ -49,6 +224,7 @@ fn encrypt_database(
         text_hash_algorithm,
         key_derivation_algorithm,
         key_derivation_options,
+        salt,
         nonce,
         cipher_hash,
     };
@@ -56,5 +232,696 @@ fn encrypt_database(
     SaveFile {
         configuration,
         encrypted_data,
+        format_version: vault::FORMAT_VERSION,
+    }
+}
+
+/// Creates new, empty vault protected with master password. Key derivation options
+/// decide how long attacker needs to check single password, so together with
+/// strength of master password they determine estimated crack time.
+pub struct VaultCreator {
+    encryption_algorithm: EncryptionAlgorithm,
+    hash_algorithm: HashAlgorithm,
+    key_derivation: Box<dyn PasswordHasherBuilder>,
+    minimum_score: Option<Score>,
+}
+
+impl VaultCreator {
+    pub fn new(
+        encryption_algorithm: EncryptionAlgorithm,
+        hash_algorithm: HashAlgorithm,
+        key_derivation_algorithm: KeyDerivationAlgorithm,
+    ) -> Result<Self, StorageError> {
+        check_vault_hash(&hash_algorithm)?;
+        let key_derivation = key_derivation_algorithm.builder_for(&encryption_algorithm)?;
+
+        Ok(Self {
+            encryption_algorithm,
+            hash_algorithm,
+            key_derivation,
+            minimum_score: None,
+        })
+    }
+
+    /// Creates vault with recommended algorithms and key derivation options meeting
+    /// minimums of [`check_health`].
+    pub fn recommended() -> Result<Self, StorageError> {
+        let header = health::recommended_header()?;
+
+        Ok(Self {
+            encryption_algorithm: header.encryption_algorithm,
+            hash_algorithm: header.hash_algorithm,
+            key_derivation: header.key_derivation,
+            minimum_score: None,
+        })
+    }
+
+    /// Gives access to options of key derivation algorithm. Output length should be
+    /// left equal to key size of selected cipher.
+    pub fn key_derivation(&mut self) -> &mut dyn PasswordHasherBuilder {
+        self.key_derivation.as_mut()
+    }
+
+    /// Master passwords scored below `score` are rejected.
+    pub fn minimum_score(mut self, score: Score) -> Self {
+        self.minimum_score = Some(score);
+        self
+    }
+
+    /// Estimates strength of master password against offline attack on vault created
+    /// with current settings. `user_inputs` are data related to the user (e.g. e-mail).
+    pub fn estimate(
+        &self,
+        master_password: &SafeBuffer,
+        user_inputs: &[&str],
+    ) -> Result<Estimate, StorageError> {
+        let key_deriver = self.key_derivation.build()?;
+        let password =
+            Zeroizing::new(String::from_utf8_lossy(master_password.as_ref()).into_owned());
+
+        Ok(strength::estimate(
+            &password,
+            user_inputs,
+            key_deriver.guesses_per_second(),
+        ))
+    }
+
+    /// Writes empty vault to `path`. Existing files are never overwritten.
+    pub fn create(
+        &self,
+        path: &Path,
+        master_password: &SafeBuffer,
+        user_inputs: &[&str],
+    ) -> Result<Estimate, StorageError> {
+        let estimate = self.estimate(master_password, user_inputs)?;
+        if let Some(minimum) = self.minimum_score {
+            if estimate.score() < minimum {
+                return Err(StorageError::WeakPassword {
+                    score: estimate.score(),
+                    minimum,
+                    feedback: estimate.feedback().clone(),
+                });
+            }
+        }
+
+        let key_deriver = self.key_derivation.build()?;
+        let mut salt = vec![0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        let key = Zeroizing::new(key_deriver.hash_password(master_password.as_ref(), &salt)?);
+        let encryptor = EncryptionStruct::new(self.encryption_algorithm.clone(), key.as_slice())?;
+        let save_file = encrypt_database(
+            serialize_vault(&Vault::new())?,
+            HashStruct::new(self.hash_algorithm.clone()),
+            key_deriver.algorithm(),
+            key_deriver.option_bytes(),
+            salt,
+            encryptor,
+        );
+
+        let mut file = private_file().create_new(true).open(path)?;
+        file.write_all(&postcard::to_allocvec(&save_file)?)?;
+        file.sync_all()?;
+
+        Ok(estimate)
+    }
+}
+
+/// Rejects hashes which are available only for protocols requiring them.
+fn check_vault_hash(algorithm: &HashAlgorithm) -> Result<(), StorageError> {
+    match health::RECOMMENDED_HASHES.contains(algorithm) {
+        true => Ok(()),
+        false => Err(StorageError::UnsupportedHash {
+            algorithm: algorithm.clone(),
+        }),
+    }
+}
+
+/// Settings of key derivation saved in the vault which cannot be decoded mean the file
+/// was damaged. Missing secret key or failure of hashing itself are reported as they are.
+fn header_error(error: KeyDerivationError) -> StorageError {
+    match error {
+        KeyDerivationError::InvalidOptions { .. }
+        | KeyDerivationError::InvalidValue { .. }
+        | KeyDerivationError::InvalidConfigFormat => StorageError::CorruptedVault {
+            description: format!("Key derivation settings cannot be read. Error = {error}"),
+        },
+        error => error.into(),
+    }
+}
+
+/// Checks integrity of encrypted data, then decrypts and decompresses the vault.
+fn decrypt_database(save_file: &SaveFile, key: &[u8]) -> Result<Vault, StorageError> {
+    let configuration = &save_file.configuration;
+
+    let mut hash_algorithm = HashStruct::new(configuration.text_hash_algorithm.clone());
+    hash_algorithm.update(&save_file.encrypted_data);
+    if hash_algorithm.finalize() != configuration.cipher_hash {
+        return Err(StorageError::CorruptedVault {
+            description: "Checksum of encrypted data does not match.".into(),
+        });
+    }
+
+    // Key length follows from saved settings, so it differs from the cipher's only in damaged files.
+    let decryptor = EncryptionStruct::new(configuration.encryption_algorithm.clone(), key)
+        .map_err(|error| StorageError::CorruptedVault {
+            description: format!("Key does not fit the cipher. Error = {error}"),
+        })?;
+    let compressed = Zeroizing::new(
+        decryptor
+            .decrypt(&save_file.encrypted_data, &configuration.nonce)
+            .map_err(|_| StorageError::WrongPassword)?,
+    );
+    let serialized = Zeroizing::new(
+        snap::raw::Decoder::new()
+            .decompress_vec(&compressed)
+            .map_err(|error| StorageError::CorruptedVault {
+                description: format!("Decrypted vault cannot be decompressed. Error = {error}"),
+            })?,
+    );
+
+    Vault::from_bytes(&serialized, save_file.format_version)
+}
+
+fn read_save_file(path: &Path) -> Result<SaveFile, StorageError> {
+    postcard::from_bytes(&std::fs::read(path)?).map_err(|err| StorageError::CorruptedVault {
+        description: format!("{} is not a vault. Error = {}", path.display(), err),
+    })
+}
+
+/// Returns path of file placed next to `path`, with `suffix` appended to its name.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Options opening file for writing, created readable only by its owner. Vault is
+/// encrypted, but other users should not be able to attack it offline.
+fn private_file() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+}
+
+/// Makes existing file readable only by its owner, see [`private_file`].
+fn make_private(file: &File) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
+
+/// Writes `data` to `path` and flushes it to disk. File left over by interrupted
+/// write may have other permissions, so they are set again.
+fn write_synced(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = private_file().create(true).truncate(true).open(path)?;
+    make_private(&file)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Replaces `path` with `data` so that either old or new content is on disk even if
+/// program is interrupted - data is written to temporary file which is then renamed.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), StorageError> {
+    let temporary_path = sibling_path(path, ".tmp");
+
+    match write_synced(&temporary_path, data).and_then(|_| std::fs::rename(&temporary_path, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = std::fs::remove_file(&temporary_path);
+            Err(err.into())
+        }
+    }
+}
+
+/// Cryptographic settings of the vault. They are stored unencrypted, so they can be
+/// read without master password.
+pub struct VaultHeader {
+    pub encryption_algorithm: EncryptionAlgorithm,
+    pub hash_algorithm: HashAlgorithm,
+    pub key_derivation_algorithm: KeyDerivationAlgorithm,
+    /// Decoded options of key derivation algorithm.
+    pub key_derivation: Box<dyn PasswordHasherBuilder>,
+}
+
+impl VaultHeader {
+    fn from_configuration(configuration: &ProgramConfiguration) -> Result<Self, StorageError> {
+        let key_derivation = configuration
+            .key_derivation_algorithm
+            .builder_from(&configuration.key_derivation_options)
+            .map_err(header_error)?;
+
+        Ok(Self {
+            encryption_algorithm: configuration.encryption_algorithm.clone(),
+            hash_algorithm: configuration.text_hash_algorithm.clone(),
+            key_derivation_algorithm: configuration.key_derivation_algorithm.clone(),
+            key_derivation,
+        })
+    }
+
+    /// Reads settings of vault saved at `path`.
+    pub fn read(path: &Path) -> Result<Self, StorageError> {
+        Self::from_configuration(&read_save_file(path)?.configuration)
+    }
+}
+
+/// Decrypted vault together with key needed to save it back.
+pub struct OpenVault {
+    path: PathBuf,
+    vault: Vault,
+    configuration: ProgramConfiguration,
+    key: Zeroizing<Vec<u8>>,
+    health_issues: Vec<HealthIssue>,
+}
+
+impl OpenVault {
+    /// Decrypts vault saved at `path`. Settings below recommended minimums are
+    /// reported by [`OpenVault::health_issues`].
+    pub fn open(path: &Path, master_password: &SafeBuffer) -> Result<Self, StorageError> {
+        let save_file = read_save_file(path)?;
+        let configuration = &save_file.configuration;
+        let header = VaultHeader::from_configuration(configuration)?;
+
+        let key_deriver = configuration
+            .key_derivation_algorithm
+            .hasher(&configuration.key_derivation_options)
+            .map_err(header_error)?;
+        let key = Zeroizing::new(
+            key_deriver.hash_password(master_password.as_ref(), &configuration.salt)?,
+        );
+        let vault = decrypt_database(&save_file, &key)?;
+
+        Ok(Self {
+            path: path.into(),
+            vault,
+            configuration: save_file.configuration,
+            key,
+            health_issues: check_health(&header),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn vault(&self) -> &Vault {
+        &self.vault
+    }
+
+    pub fn vault_mut(&mut self) -> &mut Vault {
+        &mut self.vault
+    }
+
+    pub fn header(&self) -> Result<VaultHeader, StorageError> {
+        VaultHeader::from_configuration(&self.configuration)
+    }
+
+    /// Problems with cryptographic settings found when vault was opened. They can
+    /// be fixed with [`OpenVault::upgrade`].
+    pub fn health_issues(&self) -> &[HealthIssue] {
+        &self.health_issues
+    }
+
+    /// Encrypts vault with the same key and writes it back atomically.
+    pub fn save(&self) -> Result<(), StorageError> {
+        let encryptor = EncryptionStruct::new(
+            self.configuration.encryption_algorithm.clone(),
+            self.key.as_slice(),
+        )?;
+        let save_file = encrypt_database(
+            serialize_vault(&self.vault)?,
+            HashStruct::new(self.configuration.text_hash_algorithm.clone()),
+            self.configuration.key_derivation_algorithm.clone(),
+            self.configuration.key_derivation_options.clone(),
+            self.configuration.salt.clone(),
+            encryptor,
+        );
+
+        write_atomically(&self.path, &postcard::to_allocvec(&save_file)?)
+    }
+
+    /// Checks `master_password` against settings the vault was opened with.
+    fn verify_password(&self, master_password: &SafeBuffer) -> Result<(), StorageError> {
+        let key_deriver = self
+            .configuration
+            .key_derivation_algorithm
+            .hasher(&self.configuration.key_derivation_options)?;
+        let key = Zeroizing::new(
+            key_deriver.hash_password(master_password.as_ref(), &self.configuration.salt)?,
+        );
+
+        match key.as_slice() == self.key.as_slice() {
+            true => Ok(()),
+            false => Err(StorageError::WrongPassword),
+        }
+    }
+
+    /// Re-encrypts vault with settings meeting recommended minimums, keeping every
+    /// setting which already meets them. Works like [`OpenVault::rekey`] and returns fixed issues.
+    pub fn upgrade(
+        &mut self,
+        master_password: &SafeBuffer,
+    ) -> Result<Vec<HealthIssue>, StorageError> {
+        if self.health_issues.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let fixed = self.health_issues.clone();
+        let header = health::upgraded_header(self.header()?)?;
+        self.rekey(master_password, header)?;
+        Ok(fixed)
+    }
+
+    /// Decrypts and re-encrypts vault with cipher, hash and key derivation settings from
+    /// `header`, using new salt. Output length of key derivation has to match key size of
+    /// the cipher. New file is verified by decrypting it before it replaces the original,
+    /// which is kept as a backup - its path is returned.
+    pub fn rekey(
+        &mut self,
+        master_password: &SafeBuffer,
+        header: VaultHeader,
+    ) -> Result<PathBuf, StorageError> {
+        check_vault_hash(&header.hash_algorithm)?;
+        self.verify_password(master_password)?;
+
+        let key_deriver = header.key_derivation.build()?;
+        let mut salt = vec![0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let key = Zeroizing::new(key_deriver.hash_password(master_password.as_ref(), &salt)?);
+
+        let encryptor = EncryptionStruct::new(header.encryption_algorithm.clone(), key.as_slice())?;
+        let save_file = encrypt_database(
+            serialize_vault(&self.vault)?,
+            HashStruct::new(header.hash_algorithm.clone()),
+            key_deriver.algorithm(),
+            key_deriver.option_bytes(),
+            salt,
+            encryptor,
+        );
+
+        let temporary_path = sibling_path(&self.path, ".rekey");
+        let result = write_synced(&temporary_path, &postcard::to_allocvec(&save_file)?)
+            .map_err(StorageError::from)
+            .and_then(|_| self.verify_file(&temporary_path, master_password))
+            .and_then(|_| self.replace_with_backup(&temporary_path));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temporary_path);
+        }
+        let backup_path = result?;
+
+        self.configuration = save_file.configuration;
+        self.key = key;
+        self.health_issues = check_health(&self.header()?);
+        Ok(backup_path)
+    }
+
+    /// Opens vault saved at `path` the same way [`OpenVault::open`] does and checks
+    /// that its content matches the vault in memory.
+    fn verify_file(&self, path: &Path, master_password: &SafeBuffer) -> Result<(), StorageError> {
+        let written = Self::open(path, master_password)?;
+        let expected = Zeroizing::new(postcard::to_allocvec(&self.vault)?);
+        let actual = Zeroizing::new(postcard::to_allocvec(&written.vault)?);
+
+        match expected == actual {
+            true => Ok(()),
+            false => Err(StorageError::CorruptedVault {
+                description: format!("Content of {} does not match the vault.", path.display()),
+            }),
+        }
+    }
+
+    /// Copies current file to timestamped backup and moves `replacement` in its place.
+    fn replace_with_backup(&self, replacement: &Path) -> Result<PathBuf, StorageError> {
+        let backup_path = sibling_path(&self.path, &format!(".{}.bak", timestamp()));
+        std::fs::copy(&self.path, &backup_path)?;
+        let backup = OpenOptions::new().write(true).open(&backup_path)?;
+        make_private(&backup)?;
+        backup.sync_all()?;
+        std::fs::rename(replacement, &self.path)?;
+
+        Ok(backup_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creator() -> VaultCreator {
+        let mut creator = VaultCreator::new(
+            EncryptionAlgorithm::ChaCha20Poly1305,
+            HashAlgorithm::Sha256,
+            KeyDerivationAlgorithm::Argon2id,
+        )
+        .unwrap();
+        creator
+            .key_derivation()
+            .set_option("memory size", "64")
+            .unwrap();
+        creator.minimum_score(Score::Strong)
+    }
+
+    #[test]
+    fn weak_master_password_is_rejected() {
+        let path = std::env::temp_dir().join(format!("rustypass-weak-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("password1"));
+
+        assert!(matches!(
+            creator().create(&path, &password, &[]),
+            Err(StorageError::WeakPassword {
+                score: Score::VeryWeak,
+                minimum: Score::Strong,
+                ..
+            })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn sha1_does_not_protect_vault() {
+        assert!(matches!(
+            VaultCreator::new(
+                EncryptionAlgorithm::ChaCha20Poly1305,
+                HashAlgorithm::Sha1,
+                KeyDerivationAlgorithm::Argon2id,
+            ),
+            Err(StorageError::UnsupportedHash {
+                algorithm: HashAlgorithm::Sha1
+            })
+        ));
+    }
+
+    #[test]
+    fn vault_is_created_once() {
+        let path = std::env::temp_dir().join(format!("rustypass-create-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+
+        let estimate = creator().create(&path, &password, &[]).unwrap();
+        assert!(estimate.score() >= Score::Strong);
+        assert!(estimate.crack_time() > 0.0);
+
+        let save_file: SaveFile = postcard::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(save_file.configuration.salt.len(), SALT_LENGTH);
+        assert!(matches!(
+            creator().create(&path, &password, &[]),
+            Err(StorageError::IoError { .. })
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vault_is_saved_and_upgraded() {
+        let path = std::env::temp_dir().join(format!("rustypass-open-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        let wrong_password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outrage"));
+        creator().create(&path, &password, &[]).unwrap();
+
+        assert!(matches!(
+            OpenVault::open(&path, &wrong_password),
+            Err(StorageError::WrongPassword)
+        ));
+
+        let mut opened = OpenVault::open(&path, &password).unwrap();
+        assert!(opened
+            .health_issues()
+            .contains(&HealthIssue::WeakKeyDerivationOption {
+                option: "Memory size",
+                current: 64,
+                minimum: 64 * 1024
+            }));
+        opened.vault_mut().add_entry("mail", Entry::new()).unwrap();
+        opened.save().unwrap();
+
+        let mut opened = OpenVault::open(&path, &password).unwrap();
+        assert!(opened.vault().entry("mail").is_ok());
+        assert!(matches!(
+            opened.upgrade(&wrong_password),
+            Err(StorageError::WrongPassword)
+        ));
+        assert!(!opened.upgrade(&password).unwrap().is_empty());
+        assert!(opened.health_issues().is_empty());
+        for backup in std::fs::read_dir(std::env::temp_dir()).unwrap() {
+            let backup = backup.unwrap().path();
+            if backup
+                .to_string_lossy()
+                .contains(&format!("rustypass-open-{}.", std::process::id()))
+            {
+                std::fs::remove_file(backup).unwrap();
+            }
+        }
+
+        let header = VaultHeader::read(&path).unwrap();
+        assert!(check_health(&header).is_empty());
+        let opened = OpenVault::open(&path, &password).unwrap();
+        assert!(opened.health_issues().is_empty());
+        assert!(opened.vault().entry("mail").is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn vault_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let path = std::env::temp_dir().join(format!("rustypass-private-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        creator().create(&path, &password, &[]).unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        // Vault created by older version may be readable by others.
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let temporary_path = sibling_path(&path, ".tmp");
+        std::fs::write(&temporary_path, b"left over").unwrap();
+        std::fs::set_permissions(&temporary_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut opened = OpenVault::open(&path, &password).unwrap();
+        opened.save().unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let backup = opened.rekey(&password, opened.header().unwrap()).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&backup), 0o600);
+
+        std::fs::remove_file(&backup).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn damaged_header_is_reported_as_corruption() {
+        let path = std::env::temp_dir().join(format!("rustypass-header-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        creator().create(&path, &password, &[]).unwrap();
+
+        let mut save_file = read_save_file(&path).unwrap();
+        save_file.configuration.key_derivation_options = vec![0xff; 3];
+        std::fs::write(&path, postcard::to_allocvec(&save_file).unwrap()).unwrap();
+        assert!(matches!(
+            VaultHeader::read(&path),
+            Err(StorageError::CorruptedVault { .. })
+        ));
+        assert!(matches!(
+            OpenVault::open(&path, &password),
+            Err(StorageError::CorruptedVault { .. })
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn newer_layout_is_rejected() {
+        let path = std::env::temp_dir().join(format!("rustypass-newer-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        creator().create(&path, &password, &[]).unwrap();
+
+        let mut save_file = read_save_file(&path).unwrap();
+        save_file.format_version = vault::FORMAT_VERSION + 1;
+        std::fs::write(&path, postcard::to_allocvec(&save_file).unwrap()).unwrap();
+        assert!(matches!(
+            OpenVault::open(&path, &password),
+            Err(StorageError::UnsupportedFormat { .. })
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vault_is_rekeyed_with_backup() {
+        let path = std::env::temp_dir().join(format!("rustypass-rekey-{}", std::process::id()));
+        let password = SafeBuffer::from(String::from("gravel-unlatch-mothproof-outage"));
+        creator().create(&path, &password, &[]).unwrap();
+        let original = std::fs::read(&path).unwrap();
+
+        let mut opened = OpenVault::open(&path, &password).unwrap();
+        opened.vault_mut().add_entry("mail", Entry::new()).unwrap();
+        opened.save().unwrap();
+        let saved = std::fs::read(&path).unwrap();
+
+        let encryption_algorithm = EncryptionAlgorithm::Aes256GcmSiv;
+        let header = || {
+            let mut key_derivation = KeyDerivationAlgorithm::Argon2i
+                .builder_for(&encryption_algorithm)
+                .unwrap();
+            key_derivation.set_option("memory size", "128").unwrap();
+            VaultHeader {
+                encryption_algorithm: encryption_algorithm.clone(),
+                hash_algorithm: HashAlgorithm::Sha512,
+                key_derivation_algorithm: KeyDerivationAlgorithm::Argon2i,
+                key_derivation,
+            }
+        };
+
+        let wrong_password = SafeBuffer::from(String::from("password"));
+        assert!(matches!(
+            opened.rekey(&wrong_password, header()),
+            Err(StorageError::WrongPassword)
+        ));
+        let sha1_header = VaultHeader {
+            hash_algorithm: HashAlgorithm::Sha1,
+            ..header()
+        };
+        assert!(matches!(
+            opened.rekey(&password, sha1_header),
+            Err(StorageError::UnsupportedHash { .. })
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), saved);
+
+        let backup = opened.rekey(&password, header()).unwrap();
+        assert_eq!(std::fs::read(&backup).unwrap(), saved);
+        assert_ne!(std::fs::read(&path).unwrap(), original);
+        assert!(!sibling_path(&path, ".rekey").exists());
+
+        let header = VaultHeader::read(&path).unwrap();
+        assert_eq!(header.encryption_algorithm, encryption_algorithm);
+        assert_eq!(header.hash_algorithm, HashAlgorithm::Sha512);
+        assert_eq!(
+            header.key_derivation_algorithm,
+            KeyDerivationAlgorithm::Argon2i
+        );
+        assert_eq!(
+            header.key_derivation.get_option("memory size").unwrap(),
+            "128"
+        );
+        assert!(OpenVault::open(&path, &password)
+            .unwrap()
+            .vault()
+            .entry("mail")
+            .is_ok());
+
+        std::fs::remove_file(&backup).unwrap();
+        std::fs::remove_file(&path).unwrap();
     }
 }
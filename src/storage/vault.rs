@@ -0,0 +1,579 @@
+use super::{SafeBuffer, StorageError};
+use crate::generator::{GeneratorError, PasswordGenerator, PasswordPolicy};
+use crate::otp::{OtpCode, OtpSecret};
+use crate::search::{Query, QueryError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub enum VaultError {
+    InvalidPath { path: String },
+    EntryNotFound { path: String },
+    EntryExists { path: String },
+    ProfileNotFound { name: String },
+    OtpNotConfigured { path: String },
+    SavedSearchNotFound { name: String },
+    GeneratorError(GeneratorError),
+    QueryError(QueryError),
+}
+
+impl From<GeneratorError> for VaultError {
+    fn from(err: GeneratorError) -> Self {
+        Self::GeneratorError(err)
+    }
+}
+
+impl From<QueryError> for VaultError {
+    fn from(err: QueryError) -> Self {
+        Self::QueryError(err)
+    }
+}
+
+/// Version of serialized [`Vault`] layout, stored in the vault file. It has to be increased
+/// whenever fields of [`Vault`] or [`Entry`] change, and layouts of released versions
+/// have to be migrated in [`Vault::from_bytes`].
+pub(super) const FORMAT_VERSION: u32 = 1;
+
+/// Returns current time as number of seconds since UNIX epoch.
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Normalizes path of entry - groups are separated with `/`, empty components are dropped.
+pub fn normalize_path(path: &str) -> Result<String, VaultError> {
+    let normalized = path
+        .split('/')
+        .map(str::trim)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    match normalized.is_empty() {
+        true => Err(VaultError::InvalidPath { path: path.into() }),
+        false => Ok(normalized),
+    }
+}
+
+/// Single set of credentials stored in the vault.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    username: String,
+    password: SafeBuffer,
+    urls: Vec<String>,
+    notes: String,
+    tags: BTreeSet<String>,
+    /// Name of password policy used when password is regenerated.
+    profile: Option<String>,
+    otp: Option<OtpSecret>,
+    created: u64,
+    modified: u64,
+    password_changed: u64,
+    /// Time when credentials were last copied or used, if ever.
+    last_used: Option<u64>,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Entry {
+    pub fn new() -> Self {
+        let now = timestamp();
+
+        Self {
+            username: String::new(),
+            password: SafeBuffer::new(),
+            urls: Vec::new(),
+            notes: String::new(),
+            tags: BTreeSet::new(),
+            profile: None,
+            otp: None,
+            created: now,
+            modified: now,
+            password_changed: now,
+            last_used: None,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn set_username<T: Into<String>>(&mut self, username: T) {
+        self.username = username.into();
+        self.touch();
+    }
+
+    pub fn password(&self) -> &SafeBuffer {
+        &self.password
+    }
+
+    pub fn set_password(&mut self, password: SafeBuffer) {
+        self.password = password;
+        self.touch();
+        self.password_changed = self.modified;
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub fn add_url<T: Into<String>>(&mut self, url: T) {
+        self.urls.push(url.into());
+        self.touch();
+    }
+
+    pub fn remove_url(&mut self, url: &str) -> bool {
+        let count = self.urls.len();
+        self.urls.retain(|existing| existing != url);
+        let removed = self.urls.len() != count;
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    pub fn notes(&self) -> &str {
+        &self.notes
+    }
+
+    pub fn set_notes<T: Into<String>>(&mut self, notes: T) {
+        self.notes = notes.into();
+        self.touch();
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn add_tag<T: Into<String>>(&mut self, tag: T) {
+        self.tags.insert(tag.into());
+        self.touch();
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let removed = self.tags.remove(tag);
+        if removed {
+            self.touch();
+        }
+        removed
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    pub fn otp(&self) -> Option<&OtpSecret> {
+        self.otp.as_ref()
+    }
+
+    pub fn set_otp(&mut self, otp: Option<OtpSecret>) {
+        self.otp = otp;
+        self.touch();
+    }
+
+    pub fn created(&self) -> u64 {
+        self.created
+    }
+
+    pub fn modified(&self) -> u64 {
+        self.modified
+    }
+
+    /// Time of last password change, used to find credentials which were not rotated.
+    pub fn password_changed(&self) -> u64 {
+        self.password_changed
+    }
+
+    pub fn last_used(&self) -> Option<u64> {
+        self.last_used
+    }
+
+    /// Records use of the entry. It is not a modification, so modification time is kept.
+    pub fn mark_used(&mut self) {
+        self.last_used = Some(timestamp());
+    }
+
+    fn touch(&mut self) {
+        self.modified = timestamp();
+    }
+}
+
+/// Decrypted content of the database - entries indexed by their paths,
+/// named password policies which entries can use and saved search queries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Vault {
+    entries: BTreeMap<String, Entry>,
+    profiles: BTreeMap<String, PasswordPolicy>,
+    /// Queries in [`Query`] syntax by their names. They are kept in the vault,
+    /// so everyone sharing it sees the same smart folders.
+    saved_searches: BTreeMap<String, String>,
+}
+
+impl Vault {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads vault serialized with layout `version`.
+    pub(super) fn from_bytes(bytes: &[u8], version: u32) -> Result<Self, StorageError> {
+        match version {
+            FORMAT_VERSION => {
+                postcard::from_bytes(bytes).map_err(|error| StorageError::CorruptedVault {
+                    description: format!("Decrypted vault cannot be read. Error = {error}"),
+                })
+            }
+            version => Err(StorageError::UnsupportedFormat { version }),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.entries.iter()
+    }
+
+    pub fn entry(&self, path: &str) -> Result<&Entry, VaultError> {
+        let path = normalize_path(path)?;
+        self.entries
+            .get(&path)
+            .ok_or(VaultError::EntryNotFound { path })
+    }
+
+    pub fn entry_mut(&mut self, path: &str) -> Result<&mut Entry, VaultError> {
+        let path = normalize_path(path)?;
+        self.entries
+            .get_mut(&path)
+            .ok_or(VaultError::EntryNotFound { path })
+    }
+
+    pub fn add_entry(&mut self, path: &str, entry: Entry) -> Result<(), VaultError> {
+        let path = normalize_path(path)?;
+        if self.entries.contains_key(&path) {
+            return Err(VaultError::EntryExists { path });
+        }
+
+        self.entries.insert(path, entry);
+        Ok(())
+    }
+
+    pub fn remove_entry(&mut self, path: &str) -> Result<Entry, VaultError> {
+        let path = normalize_path(path)?;
+        self.entries
+            .remove(&path)
+            .ok_or(VaultError::EntryNotFound { path })
+    }
+
+    /// Moves entry to another path. Content of the entry is not modified.
+    pub fn move_entry(&mut self, from: &str, to: &str) -> Result<(), VaultError> {
+        let from = normalize_path(from)?;
+        let to = normalize_path(to)?;
+        if self.entries.contains_key(&to) {
+            return Err(VaultError::EntryExists { path: to });
+        }
+
+        let entry = self
+            .entries
+            .remove(&from)
+            .ok_or(VaultError::EntryNotFound { path: from })?;
+        self.entries.insert(to, entry);
+        Ok(())
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = (&String, &PasswordPolicy)> {
+        self.profiles.iter()
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&PasswordPolicy, VaultError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| VaultError::ProfileNotFound { name: name.into() })
+    }
+
+    /// Saves policy under `name`, replacing previous one.
+    pub fn set_profile<T: Into<String>>(&mut self, name: T, policy: PasswordPolicy) {
+        self.profiles.insert(name.into(), policy);
+    }
+
+    /// Removes policy. Entries using it fall back to default generator.
+    pub fn remove_profile(&mut self, name: &str) -> Result<PasswordPolicy, VaultError> {
+        let policy = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| VaultError::ProfileNotFound { name: name.into() })?;
+
+        for entry in self.entries.values_mut() {
+            if entry.profile.as_deref() == Some(name) {
+                entry.profile = None;
+            }
+        }
+
+        Ok(policy)
+    }
+
+    /// Attaches policy to entry, or detaches it when `name` is `None`.
+    pub fn attach_profile(&mut self, path: &str, name: Option<&str>) -> Result<(), VaultError> {
+        if let Some(name) = name {
+            self.profile(name)?;
+        }
+
+        let entry = self.entry_mut(path)?;
+        entry.profile = name.map(String::from);
+        entry.touch();
+
+        Ok(())
+    }
+
+    /// Replaces password of entry with newly generated one. Policy attached to entry
+    /// is followed, entries without policy use default generator. Returns entropy
+    /// of new password.
+    pub fn regenerate_password(&mut self, path: &str) -> Result<f64, VaultError> {
+        let generated = match self.entry(path)?.profile() {
+            Some(name) => self.profile(name)?.generate()?,
+            None => PasswordGenerator::default().generate()?,
+        };
+
+        let entropy = generated.entropy();
+        self.entry_mut(path)?.set_password(generated.into_secret());
+
+        Ok(entropy)
+    }
+
+    /// Generates one-time code of entry. Counter of HOTP secret is advanced,
+    /// so the vault should be saved afterwards even if nothing else changed.
+    pub fn otp_code(&mut self, path: &str) -> Result<OtpCode, VaultError> {
+        let path = normalize_path(path)?;
+        let entry = self
+            .entries
+            .get_mut(&path)
+            .ok_or_else(|| VaultError::EntryNotFound { path: path.clone() })?;
+
+        match entry.otp.as_mut() {
+            Some(otp) => Ok(otp.generate(timestamp())),
+            None => Err(VaultError::OtpNotConfigured { path }),
+        }
+    }
+
+    /// Records that credentials of entry were used, so it ranks higher in search.
+    pub fn mark_used(&mut self, path: &str) -> Result<(), VaultError> {
+        self.entry_mut(path)?.mark_used();
+        Ok(())
+    }
+
+    /// Names and query texts of saved searches.
+    pub fn saved_searches(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.saved_searches.iter()
+    }
+
+    pub fn saved_search(&self, name: &str) -> Result<Query, VaultError> {
+        let query = self
+            .saved_searches
+            .get(name)
+            .ok_or_else(|| VaultError::SavedSearchNotFound { name: name.into() })?;
+
+        Ok(Query::parse(query)?)
+    }
+
+    /// Saves query under `name`, replacing previous one. Invalid queries are rejected.
+    pub fn set_saved_search<T: Into<String>>(
+        &mut self,
+        name: T,
+        query: &str,
+    ) -> Result<(), VaultError> {
+        Query::parse(query)?;
+        self.saved_searches.insert(name.into(), query.trim().into());
+
+        Ok(())
+    }
+
+    /// Removes saved search and returns its query.
+    pub fn remove_saved_search(&mut self, name: &str) -> Result<String, VaultError> {
+        self.saved_searches
+            .remove(name)
+            .ok_or_else(|| VaultError::SavedSearchNotFound { name: name.into() })
+    }
+
+    /// Paths of entries matching saved search. Saved searches act as virtual groups,
+    /// so their content is computed again on every call.
+    pub fn saved_search_entries(&self, name: &str) -> Result<Vec<&String>, VaultError> {
+        let query = self.saved_search(name)?;
+        let now = timestamp();
+
+        Ok(self
+            .entries
+            .iter()
+            .filter(|(path, entry)| query.matches_at(path, entry, now))
+            .map(|(path, _)| path)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::CharacterClass;
+
+    #[test]
+    fn paths_are_normalized() {
+        let mut vault = Vault::new();
+        vault.add_entry("/db//prod/ ", Entry::new()).unwrap();
+
+        assert!(vault.entry("db/prod").is_ok());
+        assert!(matches!(
+            vault.add_entry("db/prod/", Entry::new()),
+            Err(VaultError::EntryExists { .. })
+        ));
+        assert!(matches!(
+            vault.entry("//"),
+            Err(VaultError::InvalidPath { .. })
+        ));
+        vault.add_entry("db/staging", Entry::new()).unwrap();
+        assert!(matches!(
+            vault.move_entry("db/prod", "db/staging"),
+            Err(VaultError::EntryExists { .. })
+        ));
+        vault.move_entry("db/prod", "/old/db/prod").unwrap();
+        assert!(vault.entry("db/prod").is_err());
+        assert!(vault.remove_entry("old/db/prod").is_ok());
+        assert!(matches!(
+            vault.entry("db/prod"),
+            Err(VaultError::EntryNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn saved_searches_are_computed_live() {
+        let mut vault = Vault::new();
+        vault
+            .set_saved_search("Shared with ops", "tag:ops -tag:deprecated")
+            .unwrap();
+        assert!(matches!(
+            vault.set_saved_search("Broken", "tag:ops OR"),
+            Err(VaultError::QueryError(_))
+        ));
+        assert!(vault
+            .saved_search_entries("Shared with ops")
+            .unwrap()
+            .is_empty());
+
+        let mut entry = Entry::new();
+        entry.add_tag("ops");
+        vault.add_entry("servers/bastion", entry).unwrap();
+        assert_eq!(
+            vault.saved_search_entries("Shared with ops").unwrap(),
+            ["servers/bastion"]
+        );
+
+        vault
+            .entry_mut("servers/bastion")
+            .unwrap()
+            .add_tag("deprecated");
+        assert!(vault
+            .saved_search_entries("Shared with ops")
+            .unwrap()
+            .is_empty());
+
+        // Definitions are stored together with entries.
+        let saved: Vault = postcard::from_bytes(&postcard::to_allocvec(&vault).unwrap()).unwrap();
+        assert_eq!(
+            saved.saved_searches().collect::<Vec<_>>(),
+            [(
+                &"Shared with ops".to_string(),
+                &"tag:ops -tag:deprecated".to_string()
+            )]
+        );
+        assert_eq!(
+            vault.remove_saved_search("Shared with ops").unwrap(),
+            "tag:ops -tag:deprecated"
+        );
+        assert!(matches!(
+            vault.saved_search_entries("Shared with ops"),
+            Err(VaultError::SavedSearchNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn regenerated_password_follows_profile() {
+        let mut vault = Vault::new();
+        let policy = PasswordPolicy::new(12, 16)
+            .forbid("&")
+            .require(CharacterClass::Digits, 1)
+            .template("[A-Za-z].{11,15}");
+        vault.set_profile("intranet", policy.clone());
+        vault.add_entry("work/intranet", Entry::new()).unwrap();
+        vault.add_entry("personal/mail", Entry::new()).unwrap();
+
+        assert!(matches!(
+            vault.attach_profile("personal/mail", Some("missing")),
+            Err(VaultError::ProfileNotFound { .. })
+        ));
+        vault
+            .attach_profile("work/intranet", Some("intranet"))
+            .unwrap();
+
+        for _ in 0..20 {
+            vault.regenerate_password("work/intranet").unwrap();
+            let entry = vault.entry("work/intranet").unwrap();
+            policy.validate(entry.password().as_str().unwrap()).unwrap();
+        }
+
+        vault.regenerate_password("personal/mail").unwrap();
+        assert_eq!(
+            vault.entry("personal/mail").unwrap().password().len(),
+            PasswordGenerator::default().length()
+        );
+
+        vault.remove_profile("intranet").unwrap();
+        assert_eq!(vault.entry("work/intranet").unwrap().profile(), None);
+    }
+
+    #[test]
+    fn hotp_counter_is_persisted() {
+        let mut vault = Vault::new();
+        let mut entry = Entry::new();
+        entry.set_otp(Some(
+            OtpSecret::hotp(b"12345678901234567890".to_vec().into(), 0).unwrap(),
+        ));
+        vault.add_entry("mail", entry).unwrap();
+        vault.add_entry("forum", Entry::new()).unwrap();
+
+        assert_eq!(vault.otp_code("mail").unwrap().code, "755224");
+        assert_eq!(vault.otp_code("mail").unwrap().code, "287082");
+        assert!(matches!(
+            vault.otp_code("forum"),
+            Err(VaultError::OtpNotConfigured { .. })
+        ));
+
+        let bytes = postcard::to_allocvec(&vault).unwrap();
+        let mut restored: Vault = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.otp_code("mail").unwrap().code, "359152");
+    }
+
+    #[test]
+    fn vault_round_trip() {
+        let mut vault = Vault::new();
+        let mut entry = Entry::new();
+        entry.set_username("admin");
+        entry.set_password(SafeBuffer::from(String::from("secret")));
+        entry.add_url("https://example.com");
+        entry.add_tag("prod");
+        vault.add_entry("db/prod", entry).unwrap();
+        vault.set_profile("default", PasswordPolicy::default());
+        vault.attach_profile("db/prod", Some("default")).unwrap();
+
+        let bytes = postcard::to_allocvec(&vault).unwrap();
+        let restored: Vault = postcard::from_bytes(&bytes).unwrap();
+        let entry = restored.entry("db/prod").unwrap();
+
+        assert_eq!(entry.username(), "admin");
+        assert_eq!(entry.password().as_str(), Some("secret"));
+        assert_eq!(entry.urls(), ["https://example.com"]);
+        assert!(entry.tags().contains("prod"));
+        assert_eq!(entry.profile(), Some("default"));
+        assert!(restored.profile("default").is_ok());
+    }
+}
@@ -0,0 +1,812 @@
+use crate::generator::wordlist;
+use crate::language::Language;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+/// Most popular leaked passwords, most frequent first.
+const COMMON_PASSWORDS: &'static str = include_str!("common-passwords.txt");
+
+/// Longer candidates are not looked up in dictionaries.
+const MAX_WORD_LENGTH: usize = 32;
+const MIN_WORD_LENGTH: usize = 3;
+/// Limit of de-l33ted variants checked for single substring.
+const MAX_L33T_CANDIDATES: usize = 32;
+/// Sequences with larger difference between characters are not considered.
+const MAX_SEQUENCE_DELTA: i64 = 5;
+/// Years further from current year are not guessed earlier than this.
+const MIN_YEAR_SPACE: f64 = 20.0;
+const MIN_YEAR: u32 = 1900;
+const MAX_YEAR: u32 = 2099;
+
+/// Characters commonly used instead of letters, with letters they can stand for.
+const L33T_TABLE: &'static [(char, &'static [char])] = &[
+    ('4', &['a']),
+    ('@', &['a']),
+    ('8', &['b']),
+    ('(', &['c']),
+    ('{', &['c']),
+    ('[', &['c']),
+    ('<', &['c']),
+    ('3', &['e']),
+    ('6', &['g']),
+    ('9', &['g']),
+    ('1', &['i', 'l']),
+    ('!', &['i']),
+    ('|', &['i', 'l']),
+    ('0', &['o']),
+    ('$', &['s']),
+    ('5', &['s']),
+    ('+', &['t']),
+    ('7', &['t', 'l']),
+    ('%', &['x']),
+    ('2', &['z']),
+];
+
+/// US QWERTY layout. Every row is shifted half a key to the right relative to the row above.
+const KEYBOARD_ROWS: &'static [&'static str] = &[
+    "1234567890-=",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+];
+const SHIFTED_KEYBOARD_ROWS: &'static [&'static str] = &[
+    "!@#$%^&*()_+",
+    "QWERTYUIOP{}|",
+    "ASDFGHJKL:\"",
+    "ZXCVBNM<>?",
+];
+/// Offsets (row, column) of neighbouring keys.
+const KEYBOARD_DIRECTIONS: &'static [(i64, i64)] =
+    &[(0, -1), (0, 1), (-1, 0), (-1, 1), (1, -1), (1, 0)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dictionary {
+    CommonPasswords,
+    English,
+    Polish,
+    /// Data related to the user or the entry (e.g. username, address of the site).
+    UserInputs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Dictionary {
+        dictionary: Dictionary,
+        reversed: bool,
+        l33t: bool,
+    },
+    Spatial {
+        turns: usize,
+        shifted: usize,
+    },
+    Repeat {
+        base_length: usize,
+        count: usize,
+    },
+    Sequence {
+        ascending: bool,
+    },
+    Date {
+        year: u32,
+        separator: bool,
+    },
+    Bruteforce,
+}
+
+/// Part of the password (characters `start..end`) recognized as a pattern, together with
+/// number of guesses attacker needs to find it. Matched text itself is never stored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub pattern: Pattern,
+    pub guesses: f64,
+}
+
+impl Match {
+    fn new(start: usize, end: usize, pattern: Pattern, guesses: f64) -> Self {
+        Self {
+            start,
+            end,
+            pattern,
+            guesses,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+struct RankedDictionary {
+    dictionary: Dictionary,
+    /// Word lists without frequency information are searched in random order.
+    ranked: bool,
+    words: HashMap<String, usize>,
+}
+
+impl RankedDictionary {
+    fn new<'a, I: Iterator<Item = &'a str>>(
+        dictionary: Dictionary,
+        ranked: bool,
+        words: I,
+    ) -> Self {
+        let mut map = HashMap::new();
+        for (rank, word) in words.enumerate() {
+            map.entry(word.to_lowercase()).or_insert(rank + 1);
+        }
+
+        Self {
+            dictionary,
+            ranked,
+            words: map,
+        }
+    }
+
+    fn guesses(&self, word: &str) -> Option<f64> {
+        let rank = *self.words.get(word)?;
+        match self.ranked {
+            true => Some(rank as f64),
+            false => Some((self.words.len() as f64 / 2.0).max(1.0)),
+        }
+    }
+}
+
+static DICTIONARIES: Lazy<Vec<RankedDictionary>> = Lazy::new(|| {
+    vec![
+        RankedDictionary::new(Dictionary::CommonPasswords, true, COMMON_PASSWORDS.lines()),
+        RankedDictionary::new(
+            Dictionary::English,
+            false,
+            wordlist(&Language::USEnglish).iter().copied(),
+        ),
+        RankedDictionary::new(
+            Dictionary::Polish,
+            false,
+            wordlist(&Language::Polish).iter().copied(),
+        ),
+    ]
+});
+
+/// Position of every key: (row, column, shifted).
+static KEYBOARD: Lazy<HashMap<char, (i64, i64, bool)>> = Lazy::new(|| {
+    let mut keyboard = HashMap::new();
+    for (rows, shifted) in [(KEYBOARD_ROWS, false), (SHIFTED_KEYBOARD_ROWS, true)] {
+        for (row, keys) in rows.iter().enumerate() {
+            for (column, key) in keys.chars().enumerate() {
+                keyboard.insert(key, (row as i64, column as i64, shifted));
+            }
+        }
+    }
+    keyboard
+});
+
+/// Number of keys and their average number of neighbours.
+static KEYBOARD_STATISTICS: Lazy<(f64, f64)> = Lazy::new(|| {
+    let keys: Vec<(i64, i64)> = KEYBOARD
+        .values()
+        .filter(|(_, _, shifted)| !shifted)
+        .map(|(row, column, _)| (*row, *column))
+        .collect();
+
+    let neighbours: usize = keys
+        .iter()
+        .map(|(row, column)| {
+            KEYBOARD_DIRECTIONS
+                .iter()
+                .filter(|(dr, dc)| keys.contains(&(row + dr, column + dc)))
+                .count()
+        })
+        .sum();
+
+    (keys.len() as f64, neighbours as f64 / keys.len() as f64)
+});
+
+fn n_choose_k(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+
+    (1..=k.min(n - k)).fold(1.0, |result, i| result * (n + 1 - i) as f64 / i as f64)
+}
+
+/// Number of ways `special` characters can be mixed with `normal` ones, assuming
+/// attacker tries variants with few special characters first.
+fn variations(special: usize, normal: usize) -> f64 {
+    match (special, normal) {
+        (0, _) => 1.0,
+        (_, 0) => 2.0,
+        _ => (1..=special.min(normal))
+            .map(|i| n_choose_k(special + normal, i))
+            .sum(),
+    }
+}
+
+fn uppercase_variations(token: &[char]) -> f64 {
+    let upper = token.iter().filter(|c| c.is_uppercase()).count();
+    let lower = token.iter().filter(|c| c.is_lowercase()).count();
+
+    if upper == 0 {
+        return 1.0;
+    }
+
+    // Capitalized, last letter uppercase and all uppercase are tried first.
+    let first_only = token[0].is_uppercase() && upper == 1;
+    let last_only = token[token.len() - 1].is_uppercase() && upper == 1;
+    if first_only || last_only || lower == 0 {
+        return 2.0;
+    }
+
+    variations(upper, lower)
+}
+
+/// Finds all patterns in `password`. `user_inputs` are treated as additional dictionary.
+pub fn omnimatch(password: &[char], user_inputs: &[String]) -> Vec<Match> {
+    let lower: Zeroizing<Vec<char>> = Zeroizing::new(
+        password
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect(),
+    );
+    let user_dictionary = RankedDictionary::new(
+        Dictionary::UserInputs,
+        true,
+        user_inputs.iter().map(String::as_str),
+    );
+
+    let mut matches = Vec::new();
+    dictionary_match(password, &lower, &user_dictionary, &mut matches);
+    reverse_dictionary_match(password, &lower, &user_dictionary, &mut matches);
+    l33t_match(password, &lower, &user_dictionary, &mut matches);
+    spatial_match(password, &mut matches);
+    repeat_match(password, user_inputs, &mut matches);
+    sequence_match(password, &mut matches);
+    date_match(password, &mut matches);
+
+    matches
+}
+
+fn dictionaries<'a>(
+    user_dictionary: &'a RankedDictionary,
+) -> impl Iterator<Item = &'a RankedDictionary> {
+    DICTIONARIES.iter().chain(std::iter::once(user_dictionary))
+}
+
+fn dictionary_match(
+    password: &[char],
+    lower: &[char],
+    user_dictionary: &RankedDictionary,
+    matches: &mut Vec<Match>,
+) {
+    let mut candidate = Zeroizing::new(String::with_capacity(MAX_WORD_LENGTH * 4));
+
+    for start in 0..lower.len() {
+        let last = lower.len().min(start + MAX_WORD_LENGTH);
+        for end in start + MIN_WORD_LENGTH..=last {
+            candidate.clear();
+            candidate.extend(&lower[start..end]);
+
+            for dictionary in dictionaries(user_dictionary) {
+                if let Some(guesses) = dictionary.guesses(&candidate) {
+                    let pattern = Pattern::Dictionary {
+                        dictionary: dictionary.dictionary,
+                        reversed: false,
+                        l33t: false,
+                    };
+                    let guesses = guesses * uppercase_variations(&password[start..end]);
+                    matches.push(Match::new(start, end, pattern, guesses));
+                }
+            }
+        }
+    }
+}
+
+fn reverse_dictionary_match(
+    password: &[char],
+    lower: &[char],
+    user_dictionary: &RankedDictionary,
+    matches: &mut Vec<Match>,
+) {
+    let reversed_password: Zeroizing<Vec<char>> =
+        Zeroizing::new(password.iter().rev().copied().collect());
+    let reversed_lower: Zeroizing<Vec<char>> =
+        Zeroizing::new(lower.iter().rev().copied().collect());
+
+    let mut reversed = Vec::new();
+    dictionary_match(
+        &reversed_password,
+        &reversed_lower,
+        user_dictionary,
+        &mut reversed,
+    );
+
+    let length = password.len();
+    matches.extend(reversed.into_iter().map(|found| {
+        let pattern = match found.pattern {
+            Pattern::Dictionary { dictionary, .. } => Pattern::Dictionary {
+                dictionary,
+                reversed: true,
+                l33t: false,
+            },
+            other => other,
+        };
+        Match::new(
+            length - found.end,
+            length - found.start,
+            pattern,
+            found.guesses * 2.0,
+        )
+    }));
+}
+
+fn l33t_substitutes(character: char) -> Option<&'static [char]> {
+    L33T_TABLE
+        .iter()
+        .find(|(l33t, _)| *l33t == character)
+        .map(|(_, letters)| *letters)
+}
+
+/// Fills `candidates` with every way `token` can be read when l33t characters are
+/// replaced by letters. Each candidate remembers which substitutions were made.
+fn l33t_candidates(
+    token: &[char],
+    prefix: &mut Vec<char>,
+    substitutions: &mut Vec<(char, char)>,
+    candidates: &mut Vec<(Zeroizing<String>, Vec<(char, char)>)>,
+) {
+    if candidates.len() >= MAX_L33T_CANDIDATES {
+        return;
+    }
+
+    let position = prefix.len();
+    if position == token.len() {
+        candidates.push((
+            Zeroizing::new(prefix.iter().collect()),
+            substitutions.clone(),
+        ));
+        return;
+    }
+
+    let character = token[position];
+    match l33t_substitutes(character) {
+        Some(letters) => {
+            for letter in letters {
+                prefix.push(*letter);
+                substitutions.push((character, *letter));
+                l33t_candidates(token, prefix, substitutions, candidates);
+                substitutions.pop();
+                prefix.pop();
+            }
+        }
+        None => {
+            prefix.push(character);
+            l33t_candidates(token, prefix, substitutions, candidates);
+            prefix.pop();
+        }
+    }
+}
+
+fn l33t_variations(token: &[char], substitutions: &[(char, char)]) -> f64 {
+    let mut unique = substitutions.to_vec();
+    unique.sort();
+    unique.dedup();
+
+    unique
+        .iter()
+        .map(|(l33t, letter)| {
+            let special = token.iter().filter(|c| *c == l33t).count();
+            let normal = token.iter().filter(|c| *c == letter).count();
+            variations(special, normal)
+        })
+        .product()
+}
+
+fn l33t_match(
+    password: &[char],
+    lower: &[char],
+    user_dictionary: &RankedDictionary,
+    matches: &mut Vec<Match>,
+) {
+    let mut prefix = Zeroizing::new(Vec::with_capacity(MAX_WORD_LENGTH));
+    let mut substitutions = Vec::new();
+    let mut candidates = Vec::new();
+
+    for start in 0..lower.len() {
+        let last = lower.len().min(start + MAX_WORD_LENGTH);
+        for end in start + MIN_WORD_LENGTH..=last {
+            let token = &lower[start..end];
+            if !token.iter().any(|c| l33t_substitutes(*c).is_some()) {
+                continue;
+            }
+
+            candidates.clear();
+            l33t_candidates(token, &mut prefix, &mut substitutions, &mut candidates);
+
+            let mut best: Option<(Dictionary, f64)> = None;
+            for (candidate, substitutions) in candidates.iter() {
+                for dictionary in dictionaries(user_dictionary) {
+                    if let Some(guesses) = dictionary.guesses(candidate) {
+                        let guesses = guesses * l33t_variations(token, substitutions);
+                        if best.is_none_or(|(_, best)| guesses < best) {
+                            best = Some((dictionary.dictionary, guesses));
+                        }
+                    }
+                }
+            }
+
+            if let Some((dictionary, guesses)) = best {
+                let pattern = Pattern::Dictionary {
+                    dictionary,
+                    reversed: false,
+                    l33t: true,
+                };
+                let guesses = guesses * uppercase_variations(&password[start..end]);
+                matches.push(Match::new(start, end, pattern, guesses));
+            }
+        }
+    }
+}
+
+fn keyboard_direction(from: char, to: char) -> Option<usize> {
+    let (from_row, from_column, _) = KEYBOARD.get(&from)?;
+    let (to_row, to_column, _) = KEYBOARD.get(&to)?;
+    let offset = (to_row - from_row, to_column - from_column);
+
+    KEYBOARD_DIRECTIONS
+        .iter()
+        .position(|direction| *direction == offset)
+}
+
+fn spatial_guesses(length: usize, turns: usize, shifted: usize) -> f64 {
+    let (keys, degree) = *KEYBOARD_STATISTICS;
+
+    let mut guesses = 0.0;
+    for i in 2..=length {
+        for j in 1..=turns.min(i - 1) {
+            guesses += n_choose_k(i - 1, j - 1) * keys * degree.powi(j as i32);
+        }
+    }
+
+    guesses * variations(shifted, length - shifted)
+}
+
+fn spatial_match(password: &[char], matches: &mut Vec<Match>) {
+    let mut start = 0;
+    while start + 1 < password.len() {
+        let mut end = start + 1;
+        let mut turns = 0;
+        let mut last_direction = None;
+
+        while end < password.len() {
+            match keyboard_direction(password[end - 1], password[end]) {
+                Some(direction) => {
+                    if last_direction != Some(direction) {
+                        turns += 1;
+                        last_direction = Some(direction);
+                    }
+                    end += 1;
+                }
+                None => break,
+            }
+        }
+
+        if end - start >= 3 {
+            let shifted = password[start..end]
+                .iter()
+                .filter(|c| matches!(KEYBOARD.get(c), Some((_, _, true))))
+                .count();
+            let pattern = Pattern::Spatial { turns, shifted };
+            let guesses = spatial_guesses(end - start, turns, shifted);
+            matches.push(Match::new(start, end, pattern, guesses));
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+}
+
+fn repeat_match(password: &[char], user_inputs: &[String], matches: &mut Vec<Match>) {
+    let mut start = 0;
+    while start < password.len() {
+        let remaining = password.len() - start;
+        let mut best: Option<(usize, usize)> = None;
+
+        for base_length in 1..=remaining / 2 {
+            let base = &password[start..start + base_length];
+            let count = password[start..]
+                .chunks(base_length)
+                .take_while(|chunk| *chunk == base)
+                .count();
+
+            let span = base_length * count;
+            if count >= 2 && span >= 3 && best.is_none_or(|(l, c)| span > l * c) {
+                best = Some((base_length, count));
+            }
+        }
+
+        match best {
+            Some((base_length, count)) => {
+                let base = &password[start..start + base_length];
+                let base_guesses = super::most_guessable(base, user_inputs).guesses();
+                let end = start + base_length * count;
+                let pattern = Pattern::Repeat { base_length, count };
+                matches.push(Match::new(start, end, pattern, base_guesses * count as f64));
+                start = end;
+            }
+            None => start += 1,
+        }
+    }
+}
+
+fn sequence_guesses(token: &[char], ascending: bool) -> f64 {
+    let first = token[0];
+    let mut base = match first {
+        'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9' => 4.0,
+        c if c.is_ascii_digit() => 10.0,
+        _ => 26.0,
+    };
+    if !ascending {
+        base *= 2.0;
+    }
+
+    base * token.len() as f64
+}
+
+fn sequence_match(password: &[char], matches: &mut Vec<Match>) {
+    let push = |start: usize, end: usize, delta: i64, matches: &mut Vec<Match>| {
+        if end - start >= 3 && delta != 0 && delta.abs() <= MAX_SEQUENCE_DELTA {
+            let ascending = delta > 0;
+            let guesses = sequence_guesses(&password[start..end], ascending);
+            matches.push(Match::new(
+                start,
+                end,
+                Pattern::Sequence { ascending },
+                guesses,
+            ));
+        }
+    };
+
+    if password.len() < 2 {
+        return;
+    }
+
+    let delta_at = |i: usize| password[i] as i64 - password[i - 1] as i64;
+
+    let mut start = 0;
+    let mut last_delta = delta_at(1);
+    for i in 2..password.len() {
+        let delta = delta_at(i);
+        if delta != last_delta {
+            push(start, i, last_delta, matches);
+            start = i - 1;
+            last_delta = delta;
+        }
+    }
+    push(start, password.len(), last_delta, matches);
+}
+
+fn year_guesses(year: u32) -> f64 {
+    let current = 1970 + crate::storage::timestamp() / 31_556_952;
+    (current as f64 - year as f64).abs().max(MIN_YEAR_SPACE)
+}
+
+/// Expands two digit year the way people usually mean it.
+fn full_year(year: u32, digits: usize) -> Option<u32> {
+    match digits {
+        2 if year > 50 => Some(1900 + year),
+        2 => Some(2000 + year),
+        4 if (MIN_YEAR..=MAX_YEAR).contains(&year) => Some(year),
+        _ => None,
+    }
+}
+
+/// Interprets three numbers (with their number of digits) as a date in one of
+/// popular orders. Returns year of the most recent interpretation.
+fn parse_date(parts: [(u32, usize); 3]) -> Option<u32> {
+    let [a, b, c] = parts;
+    let orders = [(a, b, c), (c, b, a), (c, a, b)];
+
+    orders
+        .iter()
+        .filter_map(|(year, month, day)| {
+            let valid_day = (1..=31).contains(&day.0) && day.1 <= 2;
+            let valid_month = (1..=12).contains(&month.0) && month.1 <= 2;
+            match valid_day && valid_month {
+                true => full_year(year.0, year.1),
+                false => None,
+            }
+        })
+        .min_by(|x, y| year_guesses(*x).total_cmp(&year_guesses(*y)))
+}
+
+fn number(digits: &[char]) -> u32 {
+    digits.iter().fold(0, |result, digit| {
+        result * 10 + digit.to_digit(10).unwrap_or(0)
+    })
+}
+
+fn date_match(password: &[char], matches: &mut Vec<Match>) {
+    let is_digit = |i: usize| password.get(i).map_or(false, |c| c.is_ascii_digit());
+
+    // Years and dates written without separators.
+    for start in 0..password.len() {
+        for end in start + 4..=password.len().min(start + 8) {
+            if !(start..end).all(is_digit) {
+                break;
+            }
+
+            let token = &password[start..end];
+            if end - start == 4 {
+                let year = number(token);
+                if (MIN_YEAR..=MAX_YEAR).contains(&year) {
+                    let pattern = Pattern::Date {
+                        year,
+                        separator: false,
+                    };
+                    matches.push(Match::new(start, end, pattern, year_guesses(year)));
+                }
+            }
+
+            let mut best: Option<u32> = None;
+            for first in 1..token.len() - 1 {
+                for second in first + 1..token.len() {
+                    let parts = [
+                        (number(&token[..first]), first),
+                        (number(&token[first..second]), second - first),
+                        (number(&token[second..]), token.len() - second),
+                    ];
+                    if let Some(year) = parse_date(parts) {
+                        if best.is_none_or(|best| year_guesses(year) < year_guesses(best)) {
+                            best = Some(year);
+                        }
+                    }
+                }
+            }
+
+            if let Some(year) = best {
+                let pattern = Pattern::Date {
+                    year,
+                    separator: false,
+                };
+                matches.push(Match::new(start, end, pattern, 365.0 * year_guesses(year)));
+            }
+        }
+    }
+
+    // Dates with separators, e.g. 13.05.1999 or 1999-05-13.
+    let digits_from = |start: usize, max: usize| {
+        (start..password.len().min(start + max))
+            .take_while(|i| is_digit(*i))
+            .count()
+    };
+    for start in 0..password.len() {
+        let first = digits_from(start, 4);
+        if first == 0 || (start > 0 && is_digit(start - 1)) {
+            continue;
+        }
+
+        let separator = match password.get(start + first) {
+            Some(c) if " /\\_.-".contains(*c) => *c,
+            _ => continue,
+        };
+        let second_start = start + first + 1;
+        let second = digits_from(second_start, 2);
+        if second == 0 || password.get(second_start + second) != Some(&separator) {
+            continue;
+        }
+        let third_start = second_start + second + 1;
+        let third = digits_from(third_start, 4);
+        if third == 0 {
+            continue;
+        }
+
+        let parts = [
+            (number(&password[start..start + first]), first),
+            (
+                number(&password[second_start..second_start + second]),
+                second,
+            ),
+            (number(&password[third_start..third_start + third]), third),
+        ];
+        if let Some(year) = parse_date(parts) {
+            let pattern = Pattern::Date {
+                year,
+                separator: true,
+            };
+            let guesses = 365.0 * 4.0 * year_guesses(year);
+            matches.push(Match::new(start, third_start + third, pattern, guesses));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(password: &str) -> Vec<Match> {
+        let password: Vec<char> = password.chars().collect();
+        omnimatch(&password, &[])
+    }
+
+    fn has(matches: &[Match], start: usize, end: usize, check: fn(&Pattern) -> bool) -> bool {
+        matches
+            .iter()
+            .any(|found| found.start == start && found.end == end && check(&found.pattern))
+    }
+
+    #[test]
+    fn dictionary_words_are_found() {
+        let matches = find("xxPassword");
+        assert!(has(&matches, 2, 10, |pattern| matches!(
+            pattern,
+            Pattern::Dictionary {
+                dictionary: Dictionary::CommonPasswords,
+                reversed: false,
+                l33t: false
+            }
+        )));
+
+        let matches = find("drowssap");
+        assert!(has(&matches, 0, 8, |pattern| matches!(
+            pattern,
+            Pattern::Dictionary { reversed: true, .. }
+        )));
+
+        let matches = find("p4$$w0rd");
+        assert!(has(&matches, 0, 8, |pattern| matches!(
+            pattern,
+            Pattern::Dictionary { l33t: true, .. }
+        )));
+
+        let matches = find("kochanie");
+        assert!(has(&matches, 0, 8, |pattern| matches!(
+            pattern,
+            Pattern::Dictionary { .. }
+        )));
+    }
+
+    #[test]
+    fn patterns_are_found() {
+        assert!(has(&find("zxcvfr"), 0, 6, |pattern| matches!(
+            pattern,
+            Pattern::Spatial { turns: 2, .. }
+        )));
+        assert!(has(&find("abcabcabc"), 0, 9, |pattern| matches!(
+            pattern,
+            Pattern::Repeat {
+                base_length: 3,
+                count: 3
+            }
+        )));
+        assert!(has(&find("x97531"), 1, 6, |pattern| matches!(
+            pattern,
+            Pattern::Sequence { ascending: false }
+        )));
+        assert!(has(&find("born13.05.1999"), 4, 14, |pattern| matches!(
+            pattern,
+            Pattern::Date {
+                year: 1999,
+                separator: true
+            }
+        )));
+        assert!(has(&find("x19990513"), 1, 9, |pattern| matches!(
+            pattern,
+            Pattern::Date {
+                year: 1999,
+                separator: false
+            }
+        )));
+    }
+
+    #[test]
+    fn variations_follow_capitalization() {
+        let token = |text: &str| text.chars().collect::<Vec<char>>();
+
+        assert_eq!(uppercase_variations(&token("password")), 1.0);
+        assert_eq!(uppercase_variations(&token("Password")), 2.0);
+        assert_eq!(uppercase_variations(&token("PASSWORD")), 2.0);
+        assert_eq!(uppercase_variations(&token("PaSsword")), 8.0 + 28.0);
+        assert_eq!(n_choose_k(5, 2), 10.0);
+    }
+}
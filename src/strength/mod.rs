@@ -0,0 +1,466 @@
+mod matching;
+
+pub use matching::{Dictionary, Match, Pattern};
+
+use crate::get_translation;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// Only this many first characters are analysed, the rest is treated as random.
+pub const MAX_ANALYSED_LENGTH: usize = 100;
+
+/// Guesses of single unrecognized character.
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+/// Attacker is assumed to try sequences of fewer patterns first - every additional
+/// pattern costs at least this many guesses.
+const PATTERN_PENALTY: f64 = 10000.0;
+const MIN_SUBMATCH_GUESSES_SINGLE_CHAR: f64 = 10.0;
+const MIN_SUBMATCH_GUESSES_MULTI_CHAR: f64 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Score {
+    /// Less than 10^3 guesses - guessable within seconds even online.
+    VeryWeak,
+    /// Less than 10^6 guesses.
+    Weak,
+    /// Less than 10^8 guesses.
+    Fair,
+    /// Less than 10^10 guesses.
+    Strong,
+    VeryStrong,
+}
+
+impl Score {
+    fn from_guesses_log10(guesses_log10: f64) -> Self {
+        match guesses_log10 {
+            g if g < 3.0 => Self::VeryWeak,
+            g if g < 6.0 => Self::Weak,
+            g if g < 8.0 => Self::Fair,
+            g if g < 10.0 => Self::Strong,
+            _ => Self::VeryStrong,
+        }
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::VeryWeak => get_translation!("STRENGTH_SCORE_VERY_WEAK"),
+            Self::Weak => get_translation!("STRENGTH_SCORE_WEAK"),
+            Self::Fair => get_translation!("STRENGTH_SCORE_FAIR"),
+            Self::Strong => get_translation!("STRENGTH_SCORE_STRONG"),
+            Self::VeryStrong => get_translation!("STRENGTH_SCORE_VERY_STRONG"),
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Localized hints telling the user why password is weak and how to improve it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Feedback {
+    pub warning: Option<&'static str>,
+    pub suggestions: Vec<&'static str>,
+}
+
+/// Patterns which together make the password easiest to guess.
+pub struct Guessability {
+    guesses_log10: f64,
+    sequence: Vec<Match>,
+}
+
+impl Guessability {
+    pub fn guesses(&self) -> f64 {
+        10f64.powf(self.guesses_log10)
+    }
+
+    pub fn guesses_log10(&self) -> f64 {
+        self.guesses_log10
+    }
+
+    pub fn sequence(&self) -> &[Match] {
+        &self.sequence
+    }
+}
+
+/// Result of password strength estimation.
+pub struct Estimate {
+    guessability: Guessability,
+    score: Score,
+    crack_time: f64,
+    feedback: Feedback,
+}
+
+impl Estimate {
+    /// Number of guesses attacker needs to find the password.
+    pub fn guesses(&self) -> f64 {
+        self.guessability.guesses()
+    }
+
+    pub fn guesses_log10(&self) -> f64 {
+        self.guessability.guesses_log10()
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// Estimated time (in seconds) of offline attack against the vault.
+    pub fn crack_time(&self) -> f64 {
+        self.crack_time
+    }
+
+    pub fn feedback(&self) -> &Feedback {
+        &self.feedback
+    }
+
+    /// Patterns found in the password, in order.
+    pub fn sequence(&self) -> &[Match] {
+        self.guessability.sequence()
+    }
+}
+
+fn log10_add(a: f64, b: f64) -> f64 {
+    let (high, low) = if a > b { (a, b) } else { (b, a) };
+    high + (1.0 + 10f64.powf(low - high)).log10()
+}
+
+fn log10_factorial(n: usize) -> f64 {
+    (2..=n).map(|i| (i as f64).log10()).sum()
+}
+
+/// Guesses of a match, raised to minimum which prevents password made of many tiny
+/// matches from looking weaker than it is.
+fn match_guesses_log10(found: &Match, password_length: usize) -> f64 {
+    let minimum = match found.len() {
+        length if length == password_length => 1.0,
+        1 => MIN_SUBMATCH_GUESSES_SINGLE_CHAR,
+        _ => MIN_SUBMATCH_GUESSES_MULTI_CHAR,
+    };
+    found.guesses.max(minimum).log10()
+}
+
+fn bruteforce(start: usize, end: usize) -> Match {
+    let minimum = match end - start {
+        1 => MIN_SUBMATCH_GUESSES_SINGLE_CHAR + 1.0,
+        _ => MIN_SUBMATCH_GUESSES_MULTI_CHAR + 1.0,
+    };
+    let guesses = BRUTEFORCE_CARDINALITY
+        .powi((end - start) as i32)
+        .max(minimum);
+
+    Match {
+        start,
+        end,
+        pattern: Pattern::Bruteforce,
+        guesses,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    /// Logarithm of product of guesses of all matches in sequence.
+    product: f64,
+    /// Logarithm of guesses of whole sequence (including ordering and penalty).
+    total: f64,
+    match_index: usize,
+}
+
+/// Finds sequence of non-overlapping matches covering the whole password which
+/// requires the fewest guesses. Characters not covered by any pattern are bruteforced.
+pub fn most_guessable(password: &[char], user_inputs: &[String]) -> Guessability {
+    let length = password.len();
+    if length == 0 {
+        return Guessability {
+            guesses_log10: 0.0,
+            sequence: Vec::new(),
+        };
+    }
+
+    let mut matches = matching::omnimatch(password, user_inputs);
+    for start in 0..length {
+        for end in start + 1..=length {
+            matches.push(bruteforce(start, end));
+        }
+    }
+
+    let mut ending_at: Vec<Vec<usize>> = vec![Vec::new(); length];
+    for (index, found) in matches.iter().enumerate() {
+        ending_at[found.end - 1].push(index);
+    }
+
+    // best[k][l] - best sequence of l matches covering characters 0..=k.
+    let mut best: Vec<Vec<Option<Step>>> = vec![vec![None; length + 1]; length];
+    for k in 0..length {
+        for &index in ending_at[k].iter() {
+            let found = &matches[index];
+            let guesses = match_guesses_log10(found, length);
+
+            let previous: Vec<(usize, f64)> = match found.start {
+                0 => vec![(0, 0.0)],
+                start => best[start - 1]
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(count, step)| {
+                        let step = (*step)?;
+                        // Two consecutive bruteforce matches are never better than one.
+                        let previous = &matches[step.match_index].pattern;
+                        match (previous, &found.pattern) {
+                            (Pattern::Bruteforce, Pattern::Bruteforce) => None,
+                            _ => Some((count, step.product)),
+                        }
+                    })
+                    .collect(),
+            };
+
+            for (count, product) in previous {
+                let count = count + 1;
+                let product = product + guesses;
+                let mut total = log10_factorial(count) + product;
+                if count > 1 {
+                    total = log10_add(total, PATTERN_PENALTY.log10() * (count - 1) as f64);
+                }
+
+                if best[k][count].is_none_or(|step| total < step.total) {
+                    best[k][count] = Some(Step {
+                        product,
+                        total,
+                        match_index: index,
+                    });
+                }
+            }
+        }
+    }
+
+    let (mut count, last) = best[length - 1]
+        .iter()
+        .enumerate()
+        .filter_map(|(count, step)| Some((count, (*step)?)))
+        .min_by(|(_, a), (_, b)| a.total.total_cmp(&b.total))
+        .expect("Bruteforce match always covers whole password.");
+
+    let guesses_log10 = last.total;
+    let mut sequence = Vec::with_capacity(count);
+    let mut k = length;
+    while count > 0 {
+        let step = best[k - 1][count].expect("Sequence is complete.");
+        let found = matches[step.match_index].clone();
+        k = found.start;
+        count -= 1;
+        sequence.push(found);
+    }
+    sequence.reverse();
+
+    Guessability {
+        guesses_log10,
+        sequence,
+    }
+}
+
+/// Splits data related to the user (e.g. username, e-mail, site address) into words
+/// which attacker would try first.
+fn user_dictionary(user_inputs: &[&str]) -> Vec<String> {
+    let mut words = Vec::new();
+    for input in user_inputs {
+        let input = input.to_lowercase();
+        words.extend(
+            input
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(String::from),
+        );
+        words.push(input);
+    }
+    words
+}
+
+/// Estimates strength of `password` against offline attack performed with
+/// `guesses_per_second` speed (see [`DynPasswordHasher::guesses_per_second`]).
+/// `user_inputs` contain data related to the user, which attacker would try first.
+///
+/// [`DynPasswordHasher::guesses_per_second`]: crate::cryptography::DynPasswordHasher::guesses_per_second
+pub fn estimate(password: &str, user_inputs: &[&str], guesses_per_second: f64) -> Estimate {
+    let characters: Zeroizing<Vec<char>> = Zeroizing::new(password.chars().collect());
+    let analysed = characters.len().min(MAX_ANALYSED_LENGTH);
+    let user_inputs = user_dictionary(user_inputs);
+
+    let mut guessability = most_guessable(&characters[..analysed], &user_inputs);
+    guessability.guesses_log10 +=
+        BRUTEFORCE_CARDINALITY.log10() * (characters.len() - analysed) as f64;
+
+    let score = Score::from_guesses_log10(guessability.guesses_log10);
+    let crack_time = 10f64.powf(guessability.guesses_log10) / guesses_per_second;
+    let feedback = feedback(&characters[..analysed], &guessability.sequence, score);
+
+    Estimate {
+        guessability,
+        score,
+        crack_time,
+        feedback,
+    }
+}
+
+fn feedback(password: &[char], sequence: &[Match], score: Score) -> Feedback {
+    if password.is_empty() {
+        return Feedback {
+            warning: None,
+            suggestions: vec![
+                get_translation!("STRENGTH_SUGGESTION_USE_WORDS"),
+                get_translation!("STRENGTH_SUGGESTION_NO_SYMBOLS_NEEDED"),
+            ],
+        };
+    }
+
+    if score > Score::Fair {
+        return Feedback::default();
+    }
+
+    let mut feedback = Feedback {
+        warning: None,
+        suggestions: vec![get_translation!("STRENGTH_SUGGESTION_ADD_WORDS")],
+    };
+
+    let longest = match sequence.iter().max_by_key(|found| found.len()) {
+        Some(longest) => longest,
+        None => return feedback,
+    };
+    let token = &password[longest.start..longest.end];
+
+    match &longest.pattern {
+        Pattern::Dictionary {
+            dictionary,
+            reversed,
+            l33t,
+        } => {
+            feedback.warning = match dictionary {
+                Dictionary::CommonPasswords => {
+                    Some(get_translation!("STRENGTH_WARNING_COMMON_PASSWORD"))
+                }
+                Dictionary::UserInputs => Some(get_translation!("STRENGTH_WARNING_PERSONAL_DATA")),
+                _ if sequence.len() == 1 => Some(get_translation!("STRENGTH_WARNING_SINGLE_WORD")),
+                _ => Some(get_translation!("STRENGTH_WARNING_DICTIONARY_WORD")),
+            };
+
+            let upper = token.iter().filter(|c| c.is_uppercase()).count();
+            let lower = token.iter().filter(|c| c.is_lowercase()).count();
+            if upper == 1 && token[0].is_uppercase() {
+                feedback
+                    .suggestions
+                    .push(get_translation!("STRENGTH_SUGGESTION_CAPITALIZATION"));
+            } else if upper > 0 && lower == 0 {
+                feedback
+                    .suggestions
+                    .push(get_translation!("STRENGTH_SUGGESTION_ALL_UPPERCASE"));
+            }
+            if *reversed {
+                feedback
+                    .suggestions
+                    .push(get_translation!("STRENGTH_SUGGESTION_REVERSED"));
+            }
+            if *l33t {
+                feedback
+                    .suggestions
+                    .push(get_translation!("STRENGTH_SUGGESTION_L33T"));
+            }
+        }
+        Pattern::Spatial { turns, .. } => {
+            feedback.warning = Some(get_translation!("STRENGTH_WARNING_KEYBOARD_PATTERN"));
+            if *turns == 1 {
+                feedback.suggestions.push(get_translation!(
+                    "STRENGTH_SUGGESTION_LONGER_KEYBOARD_PATTERN"
+                ));
+            }
+        }
+        Pattern::Repeat { .. } => {
+            feedback.warning = Some(get_translation!("STRENGTH_WARNING_REPEAT"));
+            feedback
+                .suggestions
+                .push(get_translation!("STRENGTH_SUGGESTION_AVOID_REPEATS"));
+        }
+        Pattern::Sequence { .. } => {
+            feedback.warning = Some(get_translation!("STRENGTH_WARNING_SEQUENCE"));
+            feedback
+                .suggestions
+                .push(get_translation!("STRENGTH_SUGGESTION_AVOID_SEQUENCES"));
+        }
+        Pattern::Date { .. } => {
+            feedback.warning = Some(get_translation!("STRENGTH_WARNING_DATE"));
+            feedback
+                .suggestions
+                .push(get_translation!("STRENGTH_SUGGESTION_AVOID_DATES"));
+        }
+        Pattern::Bruteforce => (),
+    }
+
+    feedback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_passwords_are_very_weak() {
+        for password in ["password", "123456", "qwerty", "P@ssw0rd", "kochanie"] {
+            let weak = estimate(password, &[], 1e4);
+            assert_eq!(weak.score(), Score::VeryWeak, "{}", password);
+            assert!(weak.feedback().warning.is_some());
+        }
+    }
+
+    #[test]
+    fn patterns_lower_the_score() {
+        for password in [
+            "qwertyuiopasdfgh",
+            "abcdefghijklmnop",
+            "aaaaaaaaaaaaaaaa",
+            "13.05.1999",
+        ] {
+            let weak = estimate(password, &[], 1e4);
+            assert!(weak.score() <= Score::Weak, "{}", password);
+        }
+    }
+
+    #[test]
+    fn random_passwords_are_strong() {
+        let random = estimate("k8#Tq!vR2m$Lw9zX", &[], 1e4);
+        assert_eq!(random.score(), Score::VeryStrong);
+        assert_eq!(*random.feedback(), Feedback::default());
+
+        let passphrase = estimate("correct-horse-battery-staple", &[], 1e4);
+        assert!(passphrase.score() >= Score::Strong);
+    }
+
+    #[test]
+    fn user_inputs_are_guessed_first() {
+        let without = estimate("jsmith1987", &[], 1e4);
+        let with = estimate("jsmith1987", &["jsmith@example.com"], 1e4);
+
+        assert!(with.guesses() < without.guesses());
+        assert!(with.sequence().iter().any(|found| matches!(
+            found.pattern,
+            Pattern::Dictionary {
+                dictionary: Dictionary::UserInputs,
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn crack_time_follows_attacker_speed() {
+        let fast = estimate("Tr0ub4dour&3", &[], 1e10);
+        let slow = estimate("Tr0ub4dour&3", &[], 1e4);
+
+        assert!((slow.crack_time() / fast.crack_time() - 1e6).abs() < 1e-3);
+        assert!((fast.crack_time() - fast.guesses() / 1e10).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sequence_covers_password() {
+        let result = estimate("Password1987!", &[], 1e4);
+        let sequence = result.sequence();
+
+        assert_eq!(sequence.first().unwrap().start, 0);
+        assert_eq!(sequence.last().unwrap().end, 13);
+        assert!(sequence.windows(2).all(|pair| pair[0].end == pair[1].start));
+    }
+}
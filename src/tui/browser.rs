@@ -0,0 +1,614 @@
+use super::describe;
+use super::form::{EntryForm, GeneratorPanel, Input};
+use crate::audit::{estimate_password, AuditOptions};
+use crate::get_translation;
+use crate::search::{Query, SearchIndex};
+use crate::storage::{Entry, SafeBuffer, Vault};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Part of the screen receiving keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Groups,
+    Entries,
+    Search,
+}
+
+/// Item of the group tree, saved searches are shown after groups as smart folders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Group {
+    All,
+    Folder { path: String, depth: usize },
+    Saved { name: String },
+}
+
+impl Group {
+    /// Checks whether the entry belongs to the group. `saved` is the query of saved search.
+    fn contains(&self, path: &str, entry: &Entry, saved: Option<&Query>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Folder { path: folder, .. } => path
+                .strip_prefix(folder.as_str())
+                .is_some_and(|rest| rest.starts_with('/')),
+            Self::Saved { .. } => saved.is_some_and(|query| query.matches(path, entry)),
+        }
+    }
+}
+
+/// Describes strength of the password of the entry, with the main hint how to improve it.
+fn strength_status(path: &str, entry: &Entry) -> String {
+    let estimate = estimate_password(path, entry, AuditOptions::default().guesses_per_second);
+    let feedback = estimate.feedback();
+    let mut status = format!(
+        "{}: {}.",
+        get_translation!("TUI_PASSWORD_STRENGTH"),
+        estimate.score()
+    );
+    for line in feedback.warning.iter().chain(feedback.suggestions.first()) {
+        status.push(' ');
+        status.push_str(line);
+    }
+    status
+}
+
+/// Returns groups of the vault, every prefix of an entry path is a group. Subgroups
+/// follow their parent.
+fn groups(vault: &Vault) -> Vec<Group> {
+    let mut folders: Vec<&str> = vault
+        .entries()
+        .flat_map(|(path, _)| {
+            path.match_indices('/')
+                .map(move |(separator, _)| &path[..separator])
+        })
+        .collect();
+    folders.sort_by(|a, b| a.split('/').cmp(b.split('/')));
+    folders.dedup();
+
+    std::iter::once(Group::All)
+        .chain(folders.into_iter().map(|path| Group::Folder {
+            path: path.to_string(),
+            depth: path.matches('/').count(),
+        }))
+        .chain(
+            vault
+                .saved_searches()
+                .map(|(name, _)| Group::Saved { name: name.clone() }),
+        )
+        .collect()
+}
+
+/// Window shown over the browser.
+pub enum Popup {
+    Form(EntryForm),
+    Generator(GeneratorPanel),
+    /// Asks whether the entry with the path should be removed.
+    ConfirmRemoval(String),
+}
+
+/// What should be done after a key was handled.
+pub enum Outcome {
+    Nothing,
+    /// Vault was modified and should be saved.
+    Changed,
+    /// Value should be copied to the clipboard, `what` names it for the user. `used` is
+    /// path of the entry whose password or one-time code is copied, `counter_advanced`
+    /// tells that HOTP counter changed and the vault has to be saved.
+    Copy {
+        value: SafeBuffer,
+        what: &'static str,
+        used: Option<String>,
+        counter_advanced: bool,
+    },
+    Lock,
+    Quit,
+}
+
+/// Contents of the unlocked vault - group tree, entries of the chosen group matching
+/// the search and details of the chosen entry.
+pub struct Browser {
+    pub(super) groups: Vec<Group>,
+    pub(super) selected_group: usize,
+    /// Paths of entries in the chosen group which match the search.
+    pub(super) entries: Vec<String>,
+    pub(super) selected_entry: usize,
+    pub(super) search: Input,
+    pub(super) focus: Focus,
+    /// Secrets of the chosen entry are shown instead of being masked.
+    pub(super) revealed: bool,
+    pub(super) popup: Option<Popup>,
+    /// Result of the last action shown to the user.
+    pub(super) status: Option<String>,
+    index: SearchIndex,
+}
+
+impl Browser {
+    pub fn new(vault: &Vault) -> Self {
+        Self {
+            groups: groups(vault),
+            selected_group: 0,
+            entries: vault.entries().map(|(path, _)| path.clone()).collect(),
+            selected_entry: 0,
+            search: Input::default(),
+            focus: Focus::Entries,
+            revealed: false,
+            popup: None,
+            status: None,
+            index: SearchIndex::new(vault),
+        }
+    }
+
+    pub fn group(&self) -> &Group {
+        &self.groups[self.selected_group]
+    }
+
+    pub fn selected_path(&self) -> Option<&str> {
+        self.entries.get(self.selected_entry).map(String::as_str)
+    }
+
+    /// Rebuilds groups and search index after the vault was modified.
+    fn refresh(&mut self, vault: &Vault) {
+        let group = self.group().clone();
+        self.groups = groups(vault);
+        self.selected_group = self
+            .groups
+            .iter()
+            .position(|candidate| *candidate == group)
+            .unwrap_or_default();
+        self.index = SearchIndex::new(vault);
+        self.refresh_entries(vault);
+    }
+
+    /// Lists entries of the chosen group matching the search, the chosen entry stays
+    /// selected if it is still listed.
+    fn refresh_entries(&mut self, vault: &Vault) {
+        let selected = self.selected_path().map(String::from);
+        let group = self.group().clone();
+        let saved = match &group {
+            Group::Saved { name } => vault.saved_search(name).ok(),
+            _ => None,
+        };
+        let in_group = |path: &str| {
+            vault
+                .entry(path)
+                .is_ok_and(|entry| group.contains(path, entry, saved.as_ref()))
+        };
+
+        let search = self.search.text().trim();
+        self.entries = match Query::parse(search) {
+            _ if search.is_empty() => vault
+                .entries()
+                .map(|(path, _)| path.clone())
+                .filter(|path| in_group(path))
+                .collect(),
+            Ok(query) => self
+                .index
+                .find(vault, &query, usize::MAX)
+                .into_iter()
+                .map(|result| result.path)
+                .filter(|path| in_group(path))
+                .collect(),
+            Err(error) => {
+                self.status = Some(error.to_string());
+                Vec::new()
+            }
+        };
+
+        self.selected_entry = selected
+            .and_then(|selected| self.entries.iter().position(|path| *path == selected))
+            .unwrap_or_default();
+    }
+
+    /// Moves selection in the focused list by `step` items.
+    fn move_selection(&mut self, step: isize, vault: &Vault) {
+        let select = |selected: usize, count: usize| {
+            selected
+                .saturating_add_signed(step)
+                .min(count.saturating_sub(1))
+        };
+        match self.focus {
+            Focus::Groups => {
+                self.selected_group = select(self.selected_group, self.groups.len());
+                self.selected_entry = 0;
+                self.refresh_entries(vault);
+            }
+            _ => self.selected_entry = select(self.selected_entry, self.entries.len()),
+        }
+        self.revealed = false;
+    }
+
+    /// Returns the chosen entry, or tells the user there is none.
+    fn selected(&mut self) -> Option<String> {
+        let path = self.selected_path().map(String::from);
+        if path.is_none() {
+            self.status = Some(get_translation!("TUI_NO_ENTRY").to_string());
+        }
+
+        path
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, vault: &mut Vault) -> Outcome {
+        self.status = None;
+        if let Some(popup) = self.popup.take() {
+            return self.handle_popup_key(popup, key, vault);
+        }
+
+        match self.focus {
+            Focus::Search => {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.search.clear();
+                        self.focus = Focus::Entries;
+                        self.refresh_entries(vault);
+                    }
+                    KeyCode::Enter | KeyCode::Tab | KeyCode::Down => self.focus = Focus::Entries,
+                    _ => {
+                        if self.search.handle_key(key) {
+                            self.selected_entry = 0;
+                            self.refresh_entries(vault);
+                        }
+                    }
+                }
+                Outcome::Nothing
+            }
+            _ => self.handle_browse_key(key, vault),
+        }
+    }
+
+    fn handle_browse_key(&mut self, key: KeyEvent, vault: &mut Vault) -> Outcome {
+        match key.code {
+            KeyCode::Char('q') => return Outcome::Quit,
+            KeyCode::Char('l') => return Outcome::Lock,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Groups => Focus::Entries,
+                    _ => Focus::Groups,
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Groups,
+            KeyCode::Right => self.focus = Focus::Entries,
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1, vault),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1, vault),
+            KeyCode::PageUp => self.move_selection(-10, vault),
+            KeyCode::PageDown => self.move_selection(10, vault),
+            KeyCode::Enter if self.focus == Focus::Groups => self.focus = Focus::Entries,
+            KeyCode::Char('r') => self.revealed = !self.revealed,
+            KeyCode::Char(field @ ('c' | 'u' | 'o')) => {
+                if let Some(path) = self.selected() {
+                    return self.copy(&path, field, vault);
+                }
+            }
+            KeyCode::Char('n') => {
+                let group = match self.group() {
+                    Group::Folder { path, .. } => path.as_str(),
+                    _ => "",
+                };
+                self.popup = Some(Popup::Form(EntryForm::new(group)));
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(path) = self.selected() {
+                    match vault.entry(&path) {
+                        Ok(entry) => self.popup = Some(Popup::Form(EntryForm::edit(&path, entry))),
+                        Err(error) => self.status = Some(describe(error)),
+                    }
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(path) = self.selected() {
+                    self.popup = Some(Popup::ConfirmRemoval(path));
+                }
+            }
+            KeyCode::Char('g') => self.popup = Some(Popup::Generator(GeneratorPanel::default())),
+            _ => (),
+        }
+
+        Outcome::Nothing
+    }
+
+    /// Copies password (`c`), user name (`u`) or one-time password (`o`) of the entry.
+    fn copy(&mut self, path: &str, field: char, vault: &mut Vault) -> Outcome {
+        let copied = match field {
+            'c' => vault.entry(path).map(|entry| {
+                (
+                    entry.password().clone(),
+                    get_translation!("TUI_PASSWORD"),
+                    true,
+                    false,
+                )
+            }),
+            'u' => vault.entry(path).map(|entry| {
+                (
+                    SafeBuffer::from(entry.username().to_string()),
+                    get_translation!("TUI_USERNAME"),
+                    false,
+                    false,
+                )
+            }),
+            _ => vault.otp_code(path).map(|code| {
+                (
+                    SafeBuffer::from(code.code),
+                    get_translation!("TUI_OTP"),
+                    true,
+                    // Only HOTP codes are valid until used.
+                    code.valid_for.is_none(),
+                )
+            }),
+        };
+
+        match copied {
+            Ok((value, what, secret, counter_advanced)) => Outcome::Copy {
+                value,
+                what,
+                used: secret.then(|| path.to_string()),
+                counter_advanced,
+            },
+            Err(error) => {
+                self.status = Some(describe(error));
+                Outcome::Nothing
+            }
+        }
+    }
+
+    fn handle_popup_key(&mut self, popup: Popup, key: KeyEvent, vault: &mut Vault) -> Outcome {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match popup {
+            Popup::Form(mut form) => {
+                match key.code {
+                    KeyCode::Esc => return Outcome::Nothing,
+                    KeyCode::Char('s') if control => {
+                        let password_changed = form.password_changed(vault);
+                        match form.save(vault) {
+                            Ok(path) => {
+                                if password_changed {
+                                    self.status = vault
+                                        .entry(&path)
+                                        .ok()
+                                        .map(|entry| strength_status(&path, entry));
+                                }
+                                self.refresh(vault);
+                                if let Some(position) =
+                                    self.entries.iter().position(|listed| *listed == path)
+                                {
+                                    self.selected_entry = position;
+                                }
+                                return Outcome::Changed;
+                            }
+                            Err(error) => self.status = Some(describe(error)),
+                        }
+                    }
+                    KeyCode::Char('g') if control => {
+                        if let Err(error) = form.generate_password(vault) {
+                            self.status = Some(describe(error));
+                        }
+                    }
+                    KeyCode::Char('r') if control => form.revealed = !form.revealed,
+                    KeyCode::Tab | KeyCode::Down => form.next_field(),
+                    KeyCode::BackTab | KeyCode::Up => form.previous_field(),
+                    _ => {
+                        if !form.handle_key(key) && key.code == KeyCode::Enter {
+                            form.next_field();
+                        }
+                    }
+                }
+                self.popup = Some(Popup::Form(form));
+            }
+            Popup::Generator(mut panel) => {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => return Outcome::Nothing,
+                    KeyCode::Left | KeyCode::Char('-') => panel.resize(-1),
+                    KeyCode::Right | KeyCode::Char('+') => panel.resize(1),
+                    KeyCode::Char('s') => panel.toggle_symbols(),
+                    KeyCode::Char(' ') => panel.generate(),
+                    KeyCode::Char('c') | KeyCode::Enter => {
+                        if let Some(generated) = panel.generated() {
+                            let value = generated.secret().clone();
+                            self.popup = Some(Popup::Generator(panel));
+                            return Outcome::Copy {
+                                value,
+                                what: get_translation!("TUI_PASSWORD"),
+                                used: None,
+                                counter_advanced: false,
+                            };
+                        }
+                    }
+                    _ => (),
+                }
+                self.popup = Some(Popup::Generator(panel));
+            }
+            Popup::ConfirmRemoval(path) => {
+                if key.code == KeyCode::Char('y') {
+                    return match vault.remove_entry(&path) {
+                        Ok(_) => {
+                            self.refresh(vault);
+                            Outcome::Changed
+                        }
+                        Err(error) => {
+                            self.status = Some(describe(error));
+                            Outcome::Nothing
+                        }
+                    };
+                }
+            }
+        }
+
+        Outcome::Nothing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strength::Score;
+
+    fn vault() -> Vault {
+        let mut vault = Vault::new();
+        for path in [
+            "mail/work",
+            "mail/personal",
+            "db/prod/main",
+            "dns",
+            "db-backup/s3",
+        ] {
+            let mut entry = Entry::new();
+            entry.set_username(format!("user of {path}"));
+            entry.set_password(SafeBuffer::from(format!("password of {path}")));
+            vault.add_entry(path, entry).unwrap();
+        }
+        vault.entry_mut("dns").unwrap().add_tag("ops");
+        vault.set_saved_search("Operations", "tag:ops").unwrap();
+
+        vault
+    }
+
+    fn press(browser: &mut Browser, vault: &mut Vault, keys: &str) -> Outcome {
+        keys.chars().fold(Outcome::Nothing, |_, key| {
+            browser.handle_key(KeyEvent::from(KeyCode::Char(key)), vault)
+        })
+    }
+
+    fn press_code(browser: &mut Browser, vault: &mut Vault, code: KeyCode) -> Outcome {
+        browser.handle_key(KeyEvent::from(code), vault)
+    }
+
+    #[test]
+    fn groups_follow_paths() {
+        let folder = |path: &str, depth| Group::Folder {
+            path: path.to_string(),
+            depth,
+        };
+        assert_eq!(
+            groups(&vault()),
+            [
+                Group::All,
+                folder("db", 0),
+                folder("db/prod", 1),
+                folder("db-backup", 0),
+                folder("mail", 0),
+                Group::Saved {
+                    name: "Operations".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn entries_are_filtered_by_group_and_search() {
+        let mut vault = vault();
+        let mut browser = Browser::new(&vault);
+        assert_eq!(browser.entries.len(), 5);
+
+        press(&mut browser, &mut vault, "hj");
+        assert_eq!(
+            browser.group(),
+            &Group::Folder {
+                path: "db".to_string(),
+                depth: 0
+            }
+        );
+        assert_eq!(browser.entries, ["db/prod/main"]);
+        press(&mut browser, &mut vault, "jjjj");
+        assert_eq!(browser.entries, ["dns"]);
+
+        press(&mut browser, &mut vault, "kkkkkk/");
+        assert_eq!(browser.focus, Focus::Search);
+        press(&mut browser, &mut vault, "wrk");
+        assert_eq!(browser.entries, ["mail/work"]);
+        press(&mut browser, &mut vault, " tag:");
+        assert!(browser.entries.is_empty());
+        assert!(browser.status.is_some());
+
+        press_code(&mut browser, &mut vault, KeyCode::Esc);
+        assert_eq!(browser.focus, Focus::Entries);
+        assert_eq!(browser.entries.len(), 5);
+    }
+
+    #[test]
+    fn entries_are_edited() {
+        let mut vault = vault();
+        let mut browser = Browser::new(&vault);
+        press(&mut browser, &mut vault, "hjjjjn");
+        assert!(matches!(browser.popup, Some(Popup::Form(_))));
+        press(&mut browser, &mut vault, "home");
+        press_code(&mut browser, &mut vault, KeyCode::Enter);
+        press(&mut browser, &mut vault, "bob");
+        let save = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert!(matches!(
+            browser.handle_key(save, &mut vault),
+            Outcome::Changed
+        ));
+        assert!(browser.popup.is_none());
+        assert_eq!(vault.entry("mail/home").unwrap().username(), "bob");
+        assert_eq!(browser.selected_path(), Some("mail/home"));
+        assert!(browser.status.is_none());
+
+        press(&mut browser, &mut vault, "e");
+        press_code(&mut browser, &mut vault, KeyCode::Tab);
+        press(&mut browser, &mut vault, "!");
+        press_code(&mut browser, &mut vault, KeyCode::Esc);
+        assert!(browser.popup.is_none());
+        assert_eq!(
+            vault.entry("mail/home").unwrap().password().as_str(),
+            Some("")
+        );
+
+        // Typed password is estimated, user name counts as personal data.
+        press(&mut browser, &mut vault, "e");
+        press_code(&mut browser, &mut vault, KeyCode::Tab);
+        press(&mut browser, &mut vault, "bob");
+        browser.handle_key(save, &mut vault);
+        let status = browser.status.clone().unwrap();
+        assert!(status.starts_with(get_translation!("TUI_PASSWORD_STRENGTH")));
+        assert!(status.contains(&Score::VeryWeak.to_string()));
+
+        press(&mut browser, &mut vault, "dn");
+        assert!(vault.entry("mail/home").is_ok());
+        assert!(matches!(
+            press(&mut browser, &mut vault, "dy"),
+            Outcome::Changed
+        ));
+        assert!(vault.entry("mail/home").is_err());
+        assert_eq!(browser.entries, ["mail/personal", "mail/work"]);
+    }
+
+    #[test]
+    fn secrets_are_copied() {
+        let mut vault = vault();
+        let mut browser = Browser::new(&vault);
+        press(&mut browser, &mut vault, "jj");
+        assert_eq!(browser.selected_path(), Some("dns"));
+
+        match press(&mut browser, &mut vault, "c") {
+            Outcome::Copy { value, used, .. } => {
+                assert_eq!(value.as_str(), Some("password of dns"));
+                assert_eq!(used.as_deref(), Some("dns"));
+            }
+            _ => panic!("password is not copied"),
+        }
+        match press(&mut browser, &mut vault, "u") {
+            Outcome::Copy { value, used, .. } => {
+                assert_eq!(value.as_str(), Some("user of dns"));
+                assert_eq!(used, None);
+            }
+            _ => panic!("user name is not copied"),
+        }
+        assert!(matches!(
+            press(&mut browser, &mut vault, "o"),
+            Outcome::Nothing
+        ));
+        assert!(browser.status.is_some());
+
+        assert!(matches!(
+            press(&mut browser, &mut vault, "g"),
+            Outcome::Nothing
+        ));
+        assert!(matches!(
+            press(&mut browser, &mut vault, "c"),
+            Outcome::Copy { .. }
+        ));
+        assert!(matches!(browser.popup, Some(Popup::Generator(_))));
+        assert!(matches!(
+            press(&mut browser, &mut vault, "ql"),
+            Outcome::Lock
+        ));
+    }
+}
@@ -0,0 +1,457 @@
+use super::describe;
+use crate::generator::{CharacterClass, GeneratedSecret, PasswordGenerator};
+use crate::get_translation;
+use crate::storage::{normalize_path, Entry, SafeBuffer, Vault, VaultError};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use zeroize::Zeroizing;
+
+/// Character shown instead of each character of hidden text.
+const MASK: char = '*';
+
+/// Line of text typed by the user, it is erased from memory when dropped.
+#[derive(Default)]
+pub struct Input {
+    text: Zeroizing<String>,
+}
+
+impl Input {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: Zeroizing::new(text.to_string()),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+    }
+
+    pub fn push(&mut self, character: char) {
+        self.text.push(character);
+    }
+
+    /// Text as shown on screen, `masked` text has every character replaced.
+    pub fn display(&self, masked: bool) -> Cow<'_, str> {
+        match masked {
+            true => Cow::Owned(
+                self.text
+                    .chars()
+                    .map(|character| if character == '\n' { '\n' } else { MASK })
+                    .collect(),
+            ),
+            false => Cow::Borrowed(&self.text),
+        }
+    }
+
+    /// Edits the text - characters are appended, Backspace removes the last one and
+    /// Ctrl+U clears everything. Returns whether the key was used.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('u') if control => self.clear(),
+            KeyCode::Char(character) if !control && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.push(character)
+            }
+            KeyCode::Backspace => {
+                self.text.pop();
+            }
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormField {
+    Path,
+    Username,
+    Password,
+    Urls,
+    Tags,
+    Notes,
+}
+
+impl FormField {
+    /// Fields in order in which they are shown and visited.
+    pub const ALL: [FormField; 6] = [
+        Self::Path,
+        Self::Username,
+        Self::Password,
+        Self::Urls,
+        Self::Tags,
+        Self::Notes,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Path => get_translation!("TUI_PATH"),
+            Self::Username => get_translation!("TUI_USERNAME"),
+            Self::Password => get_translation!("TUI_PASSWORD"),
+            Self::Urls => get_translation!("TUI_URLS"),
+            Self::Tags => get_translation!("TUI_TAGS"),
+            Self::Notes => get_translation!("TUI_NOTES"),
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|field| *field == self)
+            .expect("field is listed")
+    }
+}
+
+/// Splits comma-separated list, empty items are dropped.
+fn split_list(text: &str) -> impl Iterator<Item = &str> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Form creating a new entry or editing an existing one. Addresses and tags are
+/// edited as comma-separated lists.
+pub struct EntryForm {
+    /// Path of the edited entry, `None` if a new one is created.
+    original: Option<String>,
+    /// Values of [`FormField::ALL`].
+    inputs: [Input; 6],
+    focus: FormField,
+    /// Password is shown instead of being masked.
+    pub revealed: bool,
+}
+
+impl EntryForm {
+    /// Creates form of a new entry in `group`, empty for the top level.
+    pub fn new(group: &str) -> Self {
+        let mut form = Self {
+            original: None,
+            inputs: Default::default(),
+            focus: FormField::Path,
+            revealed: false,
+        };
+        if !group.is_empty() {
+            form.inputs[FormField::Path.index()].set(&format!("{group}/"));
+        }
+
+        form
+    }
+
+    pub fn edit(path: &str, entry: &Entry) -> Self {
+        let urls = entry.urls().join(", ");
+        let tags = entry.tags().iter().cloned().collect::<Vec<_>>().join(", ");
+        Self {
+            original: Some(path.to_string()),
+            inputs: [
+                Input::new(path),
+                Input::new(entry.username()),
+                Input::new(entry.password().as_str().unwrap_or_default()),
+                Input::new(&urls),
+                Input::new(&tags),
+                Input::new(entry.notes()),
+            ],
+            focus: FormField::Username,
+            revealed: false,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.original {
+            Some(_) => get_translation!("TUI_EDIT_ENTRY"),
+            None => get_translation!("TUI_NEW_ENTRY"),
+        }
+    }
+
+    pub fn focus(&self) -> FormField {
+        self.focus
+    }
+
+    pub fn input(&self, field: FormField) -> &Input {
+        &self.inputs[field.index()]
+    }
+
+    pub fn next_field(&mut self) {
+        self.focus = FormField::ALL[(self.focus.index() + 1) % FormField::ALL.len()];
+    }
+
+    pub fn previous_field(&mut self) {
+        let count = FormField::ALL.len();
+        self.focus = FormField::ALL[(self.focus.index() + count - 1) % count];
+    }
+
+    /// Edits the focused field, Enter starts a new line of notes.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let input = &mut self.inputs[self.focus.index()];
+        match (key.code, self.focus) {
+            (KeyCode::Enter, FormField::Notes) => {
+                input.push('\n');
+                true
+            }
+            _ => input.handle_key(key),
+        }
+    }
+
+    /// Replaces the password with a generated one. Password policy of the edited entry
+    /// is followed, otherwise the default generator is used. Returns entropy of it.
+    pub fn generate_password(&mut self, vault: &Vault) -> Result<f64, VaultError> {
+        let profile = match &self.original {
+            Some(path) => vault.entry(path)?.profile(),
+            None => None,
+        };
+        let generated = match profile {
+            Some(name) => vault.profile(name)?.generate()?,
+            None => PasswordGenerator::default().generate()?,
+        };
+
+        self.inputs[FormField::Password.index()]
+            .set(generated.secret().as_str().unwrap_or_default());
+        Ok(generated.entropy())
+    }
+
+    /// Whether saving the form sets new password of the entry.
+    pub fn password_changed(&self, vault: &Vault) -> bool {
+        let password = self.input(FormField::Password).text();
+        let original = self
+            .original
+            .as_ref()
+            .and_then(|original| vault.entry(original).ok());
+        !password.is_empty()
+            && original.is_none_or(|entry| entry.password().as_ref() != password.as_bytes())
+    }
+
+    /// Stores the entry in the vault, it is moved if its path was changed. Only changed
+    /// fields are set, so time of the last password change is kept otherwise.
+    /// Returns path of the entry.
+    pub fn save(&self, vault: &mut Vault) -> Result<String, VaultError> {
+        let path = normalize_path(self.input(FormField::Path).text())?;
+        let entry = match &self.original {
+            Some(original) => {
+                if normalize_path(original)? != path {
+                    vault.move_entry(original, &path)?;
+                }
+                vault.entry_mut(&path)?
+            }
+            None => {
+                vault.add_entry(&path, Entry::new())?;
+                vault.entry_mut(&path)?
+            }
+        };
+
+        let username = self.input(FormField::Username).text();
+        if entry.username() != username {
+            entry.set_username(username);
+        }
+        let password = self.input(FormField::Password).text();
+        if entry.password().as_ref() != password.as_bytes() {
+            entry.set_password(SafeBuffer::from(password.to_string()));
+        }
+
+        let urls: Vec<&str> = split_list(self.input(FormField::Urls).text()).collect();
+        if entry.urls() != urls.as_slice() {
+            for url in entry.urls().to_vec() {
+                entry.remove_url(&url);
+            }
+            urls.into_iter().for_each(|url| entry.add_url(url));
+        }
+        let tags: BTreeSet<String> = split_list(self.input(FormField::Tags).text())
+            .map(String::from)
+            .collect();
+        if *entry.tags() != tags {
+            for tag in entry.tags().clone() {
+                entry.remove_tag(&tag);
+            }
+            tags.into_iter().for_each(|tag| entry.add_tag(tag));
+        }
+
+        let notes = self.input(FormField::Notes).text();
+        if entry.notes() != notes {
+            entry.set_notes(notes);
+        }
+
+        Ok(path)
+    }
+}
+
+/// Panel generating random passwords of chosen length.
+pub struct GeneratorPanel {
+    length: usize,
+    symbols: bool,
+    generated: Option<GeneratedSecret>,
+    error: Option<String>,
+}
+
+impl Default for GeneratorPanel {
+    fn default() -> Self {
+        let mut panel = Self {
+            length: GeneratorPanel::DEFAULT_LENGTH,
+            symbols: true,
+            generated: None,
+            error: None,
+        };
+        panel.generate();
+
+        panel
+    }
+}
+
+impl GeneratorPanel {
+    const DEFAULT_LENGTH: usize = 20;
+    const MIN_LENGTH: usize = 8;
+    const MAX_LENGTH: usize = 128;
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn symbols(&self) -> bool {
+        self.symbols
+    }
+
+    pub fn generated(&self) -> Option<&GeneratedSecret> {
+        self.generated.as_ref()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Generates a new password with current settings.
+    pub fn generate(&mut self) {
+        let mut generator = PasswordGenerator::new(self.length)
+            .with_class(CharacterClass::Uppercase, 1)
+            .with_class(CharacterClass::Lowercase, 1)
+            .with_class(CharacterClass::Digits, 1);
+        if self.symbols {
+            generator = generator.with_class(CharacterClass::Symbols, 1);
+        }
+
+        match generator.generate() {
+            Ok(generated) => {
+                self.generated = Some(generated);
+                self.error = None;
+            }
+            Err(error) => {
+                self.generated = None;
+                self.error = Some(describe(error));
+            }
+        }
+    }
+
+    /// Changes length by `change` characters, within supported range.
+    pub fn resize(&mut self, change: isize) {
+        self.length = self
+            .length
+            .saturating_add_signed(change)
+            .clamp(Self::MIN_LENGTH, Self::MAX_LENGTH);
+        self.generate();
+    }
+
+    pub fn toggle_symbols(&mut self) {
+        self.symbols = !self.symbols;
+        self.generate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(form: &mut EntryForm, text: &str) {
+        for character in text.chars() {
+            form.handle_key(KeyEvent::from(KeyCode::Char(character)));
+        }
+    }
+
+    #[test]
+    fn input_is_edited_and_masked() {
+        let mut input = Input::new("pin");
+        assert!(input.handle_key(KeyEvent::from(KeyCode::Char('!'))));
+        assert!(input.handle_key(KeyEvent::from(KeyCode::Backspace)));
+        assert!(!input.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL)));
+        assert!(!input.handle_key(KeyEvent::from(KeyCode::Esc)));
+        assert_eq!(input.text(), "pin");
+        assert_eq!(input.display(true), "***");
+        assert_eq!(input.display(false), "pin");
+
+        assert!(input.handle_key(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL)));
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn entries_are_saved_from_form() {
+        let mut vault = Vault::new();
+        let mut form = EntryForm::new("mail");
+        assert_eq!(form.input(FormField::Path).text(), "mail/");
+        type_text(&mut form, "work");
+        form.next_field();
+        type_text(&mut form, "alice");
+        form.next_field();
+        type_text(&mut form, "hunter2");
+        form.next_field();
+        type_text(&mut form, "https://mail.example.com, ,imap.example.com");
+        form.next_field();
+        type_text(&mut form, "work,mail");
+        form.next_field();
+        type_text(&mut form, "first");
+        form.handle_key(KeyEvent::from(KeyCode::Enter));
+        type_text(&mut form, "second");
+        assert_eq!(form.save(&mut vault).unwrap(), "mail/work");
+
+        let entry = vault.entry("mail/work").unwrap();
+        assert_eq!(entry.username(), "alice");
+        assert_eq!(entry.password().as_str(), Some("hunter2"));
+        assert_eq!(
+            entry.urls(),
+            ["https://mail.example.com", "imap.example.com"]
+        );
+        assert_eq!(entry.tags().iter().collect::<Vec<_>>(), ["mail", "work"]);
+        assert_eq!(entry.notes(), "first\nsecond");
+        // New entry cannot replace an existing one.
+        assert!(matches!(
+            form.save(&mut vault),
+            Err(VaultError::EntryExists { .. })
+        ));
+
+        let mut form = EntryForm::edit("mail/work", vault.entry("mail/work").unwrap());
+        assert_eq!(form.focus(), FormField::Username);
+        form.previous_field();
+        type_text(&mut form, "-old");
+        form.save(&mut vault).unwrap();
+        assert!(vault.entry("mail/work").is_err());
+        let entry = vault.entry("mail/work-old").unwrap();
+        assert_eq!(entry.password().as_str(), Some("hunter2"));
+        assert_eq!(entry.notes(), "first\nsecond");
+
+        let mut form = EntryForm::edit("mail/work-old", entry);
+        let entropy = form.generate_password(&vault).unwrap();
+        assert!(entropy > 100.0);
+        assert_ne!(form.input(FormField::Password).text(), "hunter2");
+    }
+
+    #[test]
+    fn generator_length_is_limited() {
+        let mut panel = GeneratorPanel::default();
+        assert_eq!(panel.generated().unwrap().secret().len(), 20);
+
+        panel.resize(-100);
+        assert_eq!(panel.length(), GeneratorPanel::MIN_LENGTH);
+        panel.resize(1000);
+        assert_eq!(panel.length(), GeneratorPanel::MAX_LENGTH);
+        assert_eq!(panel.generated().unwrap().secret().len(), 128);
+
+        panel.toggle_symbols();
+        let password = panel.generated().unwrap().secret().as_str().unwrap();
+        assert!(password.chars().all(char::is_alphanumeric));
+        assert!(panel.error().is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Full-screen terminal interface, usable where the graphical one cannot run,
+//! e.g. over SSH.
+
+mod browser;
+mod form;
+mod view;
+
+use crate::cli::{copy_value, CliError};
+use crate::configuration::ProgramConfiguration;
+use crate::get_translation;
+use crate::storage::{OpenVault, SafeBuffer};
+use browser::{Browser, Outcome};
+use form::Input;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::DefaultTerminal;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a key before checking whether the vault should be locked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Describes the error like the command line does.
+fn describe(error: impl Into<CliError>) -> String {
+    error.into().to_string()
+}
+
+struct Unlocked {
+    open_vault: OpenVault,
+    browser: Browser,
+}
+
+enum Screen {
+    /// Master password is typed, `message` tells why the vault is locked.
+    Locked {
+        password: Input,
+        message: Option<String>,
+    },
+    Unlocked(Box<Unlocked>),
+}
+
+struct App<'a> {
+    path: &'a Path,
+    configuration: &'a RwLock<ProgramConfiguration>,
+    screen: Screen,
+    /// Master password was entered, the vault is unlocked after the screen shows it,
+    /// as key derivation takes a while.
+    unlocking: bool,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(path: &'a Path, configuration: &'a RwLock<ProgramConfiguration>) -> Self {
+        Self {
+            path,
+            configuration,
+            screen: Screen::Locked {
+                password: Input::default(),
+                message: None,
+            },
+            unlocking: false,
+            quit: false,
+        }
+    }
+
+    /// Drops the unlocked vault, which erases the key and entries from memory.
+    fn lock(&mut self, message: Option<String>) {
+        self.screen = Screen::Locked {
+            password: Input::default(),
+            message,
+        };
+    }
+
+    fn unlock(&mut self) {
+        self.unlocking = false;
+        let Screen::Locked { password, message } = &mut self.screen else {
+            return;
+        };
+
+        match OpenVault::open(self.path, &SafeBuffer::from(password.text().to_string())) {
+            Ok(open_vault) => {
+                let mut browser = Browser::new(open_vault.vault());
+                if !open_vault.health_issues().is_empty() {
+                    let issues: Vec<String> = open_vault
+                        .health_issues()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    browser.status = Some(format!(
+                        "{}. {}",
+                        issues.join("; "),
+                        get_translation!("HEALTH_UPGRADE_HINT")
+                    ));
+                }
+                self.screen = Screen::Unlocked(Box::new(Unlocked {
+                    open_vault,
+                    browser,
+                }));
+            }
+            Err(error) => {
+                password.clear();
+                *message = Some(describe(error));
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+
+        let (open_vault, browser) = match &mut self.screen {
+            Screen::Locked { password, .. } => {
+                match key.code {
+                    KeyCode::Esc => self.quit = true,
+                    KeyCode::Enter => self.unlocking = !password.text().is_empty(),
+                    _ => {
+                        password.handle_key(key);
+                    }
+                }
+                return;
+            }
+            Screen::Unlocked(unlocked) => (&mut unlocked.open_vault, &mut unlocked.browser),
+        };
+
+        match browser.handle_key(key, open_vault.vault_mut()) {
+            Outcome::Nothing => (),
+            Outcome::Changed => {
+                if let Err(error) = open_vault.save() {
+                    browser.status = Some(describe(error));
+                }
+            }
+            Outcome::Copy {
+                value,
+                what,
+                used,
+                counter_advanced,
+            } => {
+                let configuration = self.configuration.read().unwrap();
+                browser.status = Some(match copy_value(value.as_ref(), &configuration) {
+                    Ok(Some(timeout)) => format!(
+                        "{}: {what} ({} s)",
+                        get_translation!("TUI_COPIED"),
+                        timeout.as_secs()
+                    ),
+                    Ok(None) => format!("{}: {what}", get_translation!("TUI_COPIED")),
+                    Err(error) => describe(error),
+                });
+                let recorded = match used {
+                    Some(path) if configuration.get_record_use() => {
+                        open_vault.vault_mut().mark_used(&path).is_ok()
+                    }
+                    _ => false,
+                };
+                if counter_advanced || recorded {
+                    if let Err(error) = open_vault.save() {
+                        browser.status = Some(describe(error));
+                    }
+                }
+            }
+            Outcome::Lock => self.lock(None),
+            Outcome::Quit => self.quit = true,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        let lock_timeout = self.configuration.read().unwrap().get_lock_timeout();
+        let mut last_key = Instant::now();
+
+        while !self.quit {
+            terminal.draw(|frame| view::draw(frame, self))?;
+            if self.unlocking {
+                self.unlock();
+                last_key = Instant::now();
+                continue;
+            }
+
+            let idle = lock_timeout.is_some_and(|timeout| last_key.elapsed() >= timeout);
+            if idle && matches!(self.screen, Screen::Unlocked(_)) {
+                self.lock(Some(get_translation!("TUI_LOCKED_IDLE").to_string()));
+            }
+
+            if event::poll(POLL_INTERVAL)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        last_key = Instant::now();
+                        self.handle_key(key);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs terminal interface for the vault until the user quits. Terminal is restored
+/// afterwards, also after a panic.
+pub fn run(path: &Path, configuration: &RwLock<ProgramConfiguration>) -> std::io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = App::new(path, configuration).run(&mut terminal);
+    ratatui::try_restore()?;
+
+    result
+}
@@ -0,0 +1,431 @@
+use super::browser::{Browser, Focus, Group, Popup};
+use super::form::{EntryForm, FormField, GeneratorPanel, Input};
+use super::{App, Screen};
+use crate::get_translation;
+use crate::otp::OtpKind;
+use crate::storage::Vault;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Frame;
+use std::path::Path;
+
+/// Shown instead of hidden secrets, it does not tell their length.
+const HIDDEN: &str = "********";
+
+pub(super) fn draw(frame: &mut Frame, app: &App) {
+    match &app.screen {
+        Screen::Locked { password, message } => {
+            draw_lock_screen(frame, app.path, password, message.as_deref(), app.unlocking)
+        }
+        Screen::Unlocked(unlocked) => {
+            draw_browser(frame, &unlocked.browser, unlocked.open_vault.vault())
+        }
+    }
+}
+
+/// Returns area of given size in the middle of `area`, it is shrunk if it does not fit.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    area
+}
+
+fn block(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(format!(" {title} "));
+    match focused {
+        true => block.border_style(Style::new().fg(Color::Cyan)),
+        false => block,
+    }
+}
+
+fn help(frame: &mut Frame, area: Rect, text: &str) {
+    frame.render_widget(Paragraph::new(text).style(Style::new().dim()), area);
+}
+
+fn label(text: &str) -> Span<'_> {
+    Span::styled(format!("{text}: "), Style::new().bold())
+}
+
+fn draw_lock_screen(
+    frame: &mut Frame,
+    path: &Path,
+    password: &Input,
+    message: Option<&str>,
+    unlocking: bool,
+) {
+    let [main_area, help_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let area = centered(main_area, 70, 7);
+    let block = block(get_translation!("TUI_UNLOCK_TITLE"), true);
+    let inner = block.inner(area);
+
+    let prompt = label(get_translation!("TUI_MASTER_PASSWORD"));
+    let prompt_width = prompt.width() as u16;
+    let mut lines = vec![
+        Line::from(path.display().to_string()),
+        Line::default(),
+        Line::from(vec![prompt, Span::raw(password.display(true))]),
+        Line::default(),
+    ];
+    match (unlocking, message) {
+        (true, _) => lines.push(Line::from(get_translation!("TUI_UNLOCKING"))),
+        (false, Some(message)) => lines.push(Line::from(message).fg(Color::Red)),
+        (false, None) => (),
+    }
+
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+    frame.set_cursor_position((
+        inner.x + prompt_width + password.text().chars().count() as u16,
+        inner.y + 2,
+    ));
+    help(frame, help_area, get_translation!("TUI_HELP_LOCKED"));
+}
+
+fn group_name(group: &Group) -> String {
+    match group {
+        Group::All => get_translation!("TUI_ALL_ENTRIES").to_string(),
+        Group::Folder { path, depth } => format!(
+            "{}{}",
+            "  ".repeat(*depth),
+            path.rsplit('/').next().unwrap_or_default()
+        ),
+        Group::Saved { name } => format!("» {name}"),
+    }
+}
+
+fn list<'a>(items: Vec<ListItem<'a>>, title: &'a str, focused: bool) -> List<'a> {
+    List::new(items)
+        .block(block(title, focused))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+}
+
+/// Lines describing the entry, secrets are masked unless `revealed`.
+fn details<'a>(vault: &'a Vault, path: &'a str, revealed: bool) -> Vec<Line<'a>> {
+    let entry = match vault.entry(path) {
+        Ok(entry) => entry,
+        Err(_) => return Vec::new(),
+    };
+    let secret = |value: &'a str| match revealed {
+        true => Span::raw(value),
+        false => Span::raw(HIDDEN).dim(),
+    };
+
+    let mut lines = vec![
+        Line::from(path).bold(),
+        Line::default(),
+        Line::from(vec![
+            label(get_translation!("TUI_USERNAME")),
+            Span::raw(entry.username()),
+        ]),
+        Line::from(vec![
+            label(get_translation!("TUI_PASSWORD")),
+            secret(entry.password().as_str().unwrap_or_default()),
+        ]),
+    ];
+    lines.extend(entry.urls().iter().map(|url| {
+        Line::from(vec![
+            label(get_translation!("TUI_URLS")),
+            Span::raw(url.as_str()),
+        ])
+    }));
+    if !entry.tags().is_empty() {
+        let tags: Vec<&str> = entry.tags().iter().map(String::as_str).collect();
+        lines.push(Line::from(vec![
+            label(get_translation!("TUI_TAGS")),
+            Span::raw(tags.join(", ")),
+        ]));
+    }
+    if let Some(otp) = entry.otp() {
+        let kind = match otp.kind() {
+            OtpKind::Totp { period } => format!("TOTP, {period} s"),
+            OtpKind::Hotp { counter } => format!("HOTP, {counter}"),
+        };
+        lines.push(Line::from(vec![
+            label(get_translation!("TUI_OTP")),
+            Span::raw(kind),
+        ]));
+    }
+    if let Some(profile) = entry.profile() {
+        lines.push(Line::from(vec![
+            label(get_translation!("TUI_PROFILE")),
+            Span::raw(profile),
+        ]));
+    }
+    if !entry.notes().is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from(label(get_translation!("TUI_NOTES"))));
+        match revealed {
+            true => lines.extend(entry.notes().lines().map(Line::from)),
+            false => lines.push(Line::from(HIDDEN).dim()),
+        }
+    }
+
+    lines
+}
+
+fn draw_browser(frame: &mut Frame, browser: &Browser, vault: &Vault) {
+    let [search_area, main_area, status_area, help_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [groups_area, entries_area, details_area] = Layout::horizontal([
+        Constraint::Percentage(25),
+        Constraint::Percentage(35),
+        Constraint::Percentage(40),
+    ])
+    .areas(main_area);
+
+    let searching = browser.focus == Focus::Search;
+    let search_block = block(get_translation!("TUI_SEARCH"), searching);
+    let search_inner = search_block.inner(search_area);
+    frame.render_widget(
+        Paragraph::new(browser.search.text()).block(search_block),
+        search_area,
+    );
+    if searching && browser.popup.is_none() {
+        frame.set_cursor_position((
+            search_inner.x + browser.search.text().chars().count() as u16,
+            search_inner.y,
+        ));
+    }
+
+    let groups = browser
+        .groups
+        .iter()
+        .map(|group| match group {
+            Group::Saved { .. } => ListItem::new(group_name(group)).italic(),
+            _ => ListItem::new(group_name(group)),
+        })
+        .collect();
+    frame.render_stateful_widget(
+        list(
+            groups,
+            get_translation!("TUI_GROUPS"),
+            browser.focus == Focus::Groups,
+        ),
+        groups_area,
+        &mut ListState::default().with_selected(Some(browser.selected_group)),
+    );
+
+    // Paths are shown relative to the chosen group.
+    let prefix = match browser.group() {
+        Group::Folder { path, .. } => format!("{path}/"),
+        _ => String::new(),
+    };
+    let entries = browser
+        .entries
+        .iter()
+        .map(|path| ListItem::new(path.strip_prefix(&prefix).unwrap_or(path)))
+        .collect();
+    frame.render_stateful_widget(
+        list(
+            entries,
+            get_translation!("TUI_ENTRIES"),
+            browser.focus == Focus::Entries,
+        ),
+        entries_area,
+        &mut ListState::default().with_selected(Some(browser.selected_entry)),
+    );
+
+    let details = match browser.selected_path() {
+        Some(path) => details(vault, path, browser.revealed),
+        None => vec![Line::from(get_translation!("TUI_NO_ENTRY")).dim()],
+    };
+    frame.render_widget(
+        Paragraph::new(details)
+            .wrap(Wrap { trim: false })
+            .block(block(get_translation!("TUI_DETAILS"), false)),
+        details_area,
+    );
+
+    if let Some(status) = &browser.status {
+        frame.render_widget(
+            Paragraph::new(status.as_str()).fg(Color::Yellow),
+            status_area,
+        );
+    }
+
+    let help_text = match &browser.popup {
+        Some(Popup::Form(form)) => {
+            draw_form(frame, form);
+            get_translation!("TUI_HELP_FORM")
+        }
+        Some(Popup::Generator(panel)) => {
+            draw_generator(frame, panel);
+            get_translation!("TUI_HELP_GENERATOR")
+        }
+        Some(Popup::ConfirmRemoval(path)) => {
+            let area = centered(frame.area(), 60, 5);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(vec![Line::from(path.as_str()).bold(), Line::default()])
+                    .block(block(get_translation!("TUI_REMOVE_ENTRY"), true)),
+                area,
+            );
+            get_translation!("TUI_HELP_CONFIRM")
+        }
+        None if searching => get_translation!("TUI_HELP_SEARCH"),
+        None => get_translation!("TUI_HELP_BROWSE"),
+    };
+    help(frame, help_area, help_text);
+}
+
+/// Most lines of notes shown in the form at once.
+const NOTES_HEIGHT: u16 = 8;
+
+fn draw_form(frame: &mut Frame, form: &EntryForm) {
+    let label_width = FormField::ALL
+        .iter()
+        .map(|field| field.label().chars().count() + 2)
+        .max()
+        .unwrap_or_default();
+    let mut lines = Vec::new();
+    let mut cursor = (0, 0);
+    for field in FormField::ALL {
+        let input = form.input(field);
+        let masked = field == FormField::Password && !form.revealed;
+        let value = input.display(masked);
+        let style = match form.focus() == field {
+            true => Style::new().bold().fg(Color::Cyan),
+            false => Style::new().bold(),
+        };
+
+        let start = lines.len();
+        let mut rows = value.split('\n');
+        let first = rows.next().unwrap_or_default().to_string();
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:label_width$}", format!("{}:", field.label())),
+                style,
+            ),
+            Span::raw(first),
+        ]));
+        lines.extend(rows.map(|row| Line::from(format!("{:label_width$}{row}", ""))));
+        if form.focus() == field {
+            let last = value.rsplit('\n').next().unwrap_or_default();
+            cursor = (
+                (label_width + last.chars().count()) as u16,
+                (lines.len() - 1) as u16,
+            );
+            // Only the end of long notes is shown, where text is typed.
+            if lines.len() - start > NOTES_HEIGHT as usize {
+                lines.drain(start..lines.len() - NOTES_HEIGHT as usize);
+                cursor.1 = (lines.len() - 1) as u16;
+            }
+        }
+    }
+
+    let height = lines
+        .len()
+        .max(FormField::ALL.len() + NOTES_HEIGHT as usize - 1) as u16
+        + 2;
+    let area = centered(frame.area(), 80, height);
+    let block = block(form.title(), true);
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(Text::from(lines)).block(block), area);
+    frame.set_cursor_position((inner.x + cursor.0, inner.y + cursor.1));
+}
+
+fn draw_generator(frame: &mut Frame, panel: &GeneratorPanel) {
+    let password = match (panel.generated(), panel.error()) {
+        (Some(generated), _) => {
+            Line::from(generated.secret().as_str().unwrap_or_default().to_string()).bold()
+        }
+        (None, error) => Line::from(error.unwrap_or_default().to_string()).fg(Color::Red),
+    };
+    let symbols = match panel.symbols() {
+        true => get_translation!("TUI_YES"),
+        false => get_translation!("TUI_NO"),
+    };
+    let mut lines = vec![
+        password,
+        Line::default(),
+        Line::from(vec![
+            label(get_translation!("TUI_LENGTH")),
+            Span::raw(panel.length().to_string()),
+        ]),
+        Line::from(vec![
+            label(get_translation!("TUI_SYMBOLS")),
+            Span::raw(symbols),
+        ]),
+    ];
+    if let Some(generated) = panel.generated() {
+        lines.push(Line::from(vec![
+            label(get_translation!("TUI_ENTROPY")),
+            Span::raw(format!("{:.0} bit", generated.entropy())),
+        ]));
+    }
+
+    let area = centered(frame.area(), 80, lines.len() as u16 + 2);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block(get_translation!("TUI_GENERATOR"), true)),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Entry, SafeBuffer};
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use ratatui::Terminal;
+
+    fn screen(browser: &Browser, vault: &Vault) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal
+            .draw(|frame| draw_browser(frame, browser, vault))
+            .unwrap();
+
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn secrets_are_masked() {
+        let mut vault = Vault::new();
+        let mut entry = Entry::new();
+        entry.set_username("alice");
+        entry.set_password(SafeBuffer::from("hunter2".to_string()));
+        entry.set_notes("pin 8642");
+        vault.add_entry("mail/work", entry).unwrap();
+        let mut browser = Browser::new(&vault);
+
+        let shown = screen(&browser, &vault);
+        assert!(shown.contains("mail/work"));
+        assert!(shown.contains("alice"));
+        assert!(!shown.contains("hunter2"));
+        assert!(!shown.contains("8642"));
+
+        browser.handle_key(KeyEvent::from(KeyCode::Char('r')), &mut vault);
+        let shown = screen(&browser, &vault);
+        assert!(shown.contains("hunter2"));
+        assert!(shown.contains("pin 8642"));
+
+        // Form masks the password too.
+        browser.handle_key(KeyEvent::from(KeyCode::Char('r')), &mut vault);
+        browser.handle_key(KeyEvent::from(KeyCode::Char('e')), &mut vault);
+        let shown = screen(&browser, &vault);
+        assert!(shown.contains(get_translation!("TUI_EDIT_ENTRY")));
+        assert!(!shown.contains("hunter2"));
+    }
+}
@@ -1,25 +1,116 @@
-
-use iced::{Sandbox, Text};
 use iced::Settings;
+use iced::{button, text_input, Button, Color, Column, Row, Sandbox, Text, TextInput};
 use rfd::FileDialog;
 use tokio::sync::mpsc::UnboundedSender;
+use zeroize::Zeroize;
 
+use crate::audit::{AuditOptions, AuditReport, Severity};
+use crate::cli::CliError;
 use crate::communication::Task;
+use crate::generator::{GeneratedSecret, PasswordGenerator};
+use crate::get_translation;
+use crate::search::{Query, SearchIndex, SearchResult};
+use crate::storage::{OpenVault, SafeBuffer};
 use std::{
     sync::{mpsc::Receiver, mpsc::Sender},
     thread::JoinHandle,
 };
 
-struct Rustypass {}
+/// Number of search results shown at once.
+const RESULT_LIMIT: usize = 50;
 
-#[derive(Debug)]
-enum UserMessage {}
+struct Rustypass {
+    generate_button: button::State,
+    generated: Option<GeneratedSecret>,
+    password_input: text_input::State,
+    password: String,
+    open_button: button::State,
+    vault: Option<(OpenVault, SearchIndex)>,
+    /// Names and queries of searches saved in the vault, shown as virtual groups.
+    smart_folders: Vec<(String, String, button::State)>,
+    search_input: text_input::State,
+    query: String,
+    results: Vec<SearchResult>,
+    audit_button: button::State,
+    /// Health of passwords in the vault, shown after the audit button is pressed.
+    audit: Option<AuditReport>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum UserMessage {
+    GeneratePassword,
+    PasswordChanged(String),
+    OpenVault,
+    QueryChanged(String),
+    SmartFolderSelected(usize),
+    AuditVault,
+}
+
+impl Rustypass {
+    fn open_vault(&mut self) {
+        let path = match FileDialog::new().pick_file() {
+            Some(path) => path,
+            None => return,
+        };
+        let password = SafeBuffer::from(std::mem::take(&mut self.password));
+
+        match OpenVault::open(&path, &password) {
+            Ok(open_vault) => {
+                let index = SearchIndex::new(open_vault.vault());
+                self.smart_folders = open_vault
+                    .vault()
+                    .saved_searches()
+                    .map(|(name, query)| (name.clone(), query.clone(), button::State::new()))
+                    .collect();
+                self.vault = Some((open_vault, index));
+                self.audit = None;
+                self.error = None;
+                self.search();
+            }
+            Err(error) => self.error = Some(CliError::from(error).to_string()),
+        }
+    }
+
+    /// Search box accepts the same queries as `find` command, e.g. `tag:prod -tag:old mail`.
+    fn search(&mut self) {
+        self.results.clear();
+        self.error = None;
+        let query = match Query::parse(&self.query) {
+            Ok(query) => query,
+            Err(error) => {
+                self.error = Some(error.to_string());
+                return;
+            }
+        };
+
+        if let Some((open_vault, index)) = &self.vault {
+            if !query.is_empty() {
+                self.results = index.find(open_vault.vault(), &query, RESULT_LIMIT);
+            }
+        }
+    }
+}
 
 impl Sandbox for Rustypass {
     type Message = UserMessage;
 
     fn new() -> Self {
-        Self {  }
+        Self {
+            generate_button: button::State::new(),
+            generated: None,
+            password_input: text_input::State::new(),
+            password: String::new(),
+            open_button: button::State::new(),
+            vault: None,
+            smart_folders: Vec::new(),
+            search_input: text_input::State::new(),
+            query: String::new(),
+            results: Vec::new(),
+            audit_button: button::State::new(),
+            audit: None,
+            error: None,
+        }
     }
 
     fn title(&self) -> String {
@@ -27,11 +118,137 @@ impl Sandbox for Rustypass {
     }
 
     fn update(&mut self, message: Self::Message) {
-        
+        match message {
+            UserMessage::GeneratePassword => {
+                self.generated = PasswordGenerator::default().generate().ok();
+            }
+            UserMessage::PasswordChanged(mut password) => {
+                self.password.zeroize();
+                std::mem::swap(&mut self.password, &mut password);
+            }
+            UserMessage::OpenVault => self.open_vault(),
+            UserMessage::QueryChanged(query) => {
+                self.query = query;
+                self.search();
+            }
+            UserMessage::SmartFolderSelected(index) => {
+                self.query = self.smart_folders[index].1.clone();
+                self.search();
+            }
+            UserMessage::AuditVault => {
+                self.audit = self
+                    .vault
+                    .as_ref()
+                    .map(|(open_vault, _)| open_vault.vault().audit(&AuditOptions::default()));
+            }
+        }
     }
 
     fn view(&mut self) -> iced::Element<'_, Self::Message> {
-        Text::new("Rustypass").into()
+        let mut content = Column::new().push(Text::new("Rustypass")).push(
+            Button::new(
+                &mut self.generate_button,
+                Text::new(get_translation!("GUI_GENERATE_PASSWORD")),
+            )
+            .on_press(UserMessage::GeneratePassword),
+        );
+
+        content = match &self.vault {
+            None => content
+                .push(
+                    TextInput::new(
+                        &mut self.password_input,
+                        get_translation!("GUI_MASTER_PASSWORD"),
+                        &self.password,
+                        UserMessage::PasswordChanged,
+                    )
+                    .password()
+                    .on_submit(UserMessage::OpenVault),
+                )
+                .push(
+                    Button::new(
+                        &mut self.open_button,
+                        Text::new(get_translation!("GUI_OPEN_VAULT")),
+                    )
+                    .on_press(UserMessage::OpenVault),
+                ),
+            Some(_) => content
+                .push(TextInput::new(
+                    &mut self.search_input,
+                    get_translation!("GUI_SEARCH_PLACEHOLDER"),
+                    &self.query,
+                    UserMessage::QueryChanged,
+                ))
+                .push(
+                    Button::new(
+                        &mut self.audit_button,
+                        Text::new(get_translation!("GUI_AUDIT")),
+                    )
+                    .on_press(UserMessage::AuditVault),
+                ),
+        };
+        if !self.smart_folders.is_empty() {
+            let folders = self.smart_folders.iter_mut().enumerate().fold(
+                Row::new().spacing(5),
+                |row, (index, (name, _, state))| {
+                    row.push(
+                        Button::new(state, Text::new(name.as_str()))
+                            .on_press(UserMessage::SmartFolderSelected(index)),
+                    )
+                },
+            );
+            content = content.push(folders);
+        }
+        if let Some(error) = &self.error {
+            content = content.push(Text::new(error.as_str()).color(Color::from_rgb(0.8, 0.1, 0.1)));
+        }
+        if let Some((open_vault, _)) = &self.vault {
+            let warning = Color::from_rgb(0.8, 0.5, 0.0);
+            for issue in open_vault.health_issues() {
+                content = content.push(Text::new(issue.to_string()).color(warning));
+            }
+            if !open_vault.health_issues().is_empty() {
+                content =
+                    content.push(Text::new(get_translation!("HEALTH_UPGRADE_HINT")).color(warning));
+            }
+        }
+        if let Some(report) = &self.audit {
+            content = content.push(Text::new(format!(
+                "{}: {}/{}",
+                get_translation!("GUI_AUDIT_HEALTHY"),
+                report.summary.healthy,
+                report.summary.entries
+            )));
+            if report.findings.is_empty() {
+                content = content.push(Text::new(get_translation!("GUI_AUDIT_NO_PROBLEMS")));
+            }
+            for finding in &report.findings {
+                let color = match finding.severity() {
+                    Severity::High => Color::from_rgb(0.8, 0.1, 0.1),
+                    Severity::Medium => Color::from_rgb(0.8, 0.5, 0.0),
+                    Severity::Low => Color::from_rgb(0.4, 0.4, 0.4),
+                };
+                content = content.push(
+                    Text::new(format!("{}: {}", finding.paths().join(", "), finding)).color(color),
+                );
+            }
+        }
+        for result in &self.results {
+            content = content.push(Text::new(result.path.as_str()));
+        }
+
+        if let Some(generated) = &self.generated {
+            content = content
+                .push(Text::new(generated.secret().as_str().unwrap_or_default()))
+                .push(Text::new(format!(
+                    "{}: {:.1} {}",
+                    get_translation!("GUI_ENTROPY"),
+                    generated.entropy(),
+                    get_translation!("GUI_BITS")
+                )));
+        }
+
+        content.into()
     }
 }
 
@@ -50,4 +267,4 @@ impl From<std::io::Error> for UIError {
 /// This should be non thread blocking function.
 pub fn run_ui(backend_connector: UnboundedSender<Task>) {
     Rustypass::run(Settings::default()).unwrap()
-}
\ No newline at end of file
+}